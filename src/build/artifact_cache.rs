@@ -0,0 +1,469 @@
+//! Content-addressed cache of built firmware.
+//!
+//! Each target is keyed by a hash of everything its firmware is built
+//! from: board, shield, snippets and CMake arguments, the config files it
+//! uses, `boards/` and `shields/`, the commit of every west project and the
+//! build image. A target whose key is in the cache is copied from there
+//! instead of being rebuilt.
+//!
+//! ```text
+//! ~/.cache/lfz/artifacts/<key>/
+//!   zmk.uf2
+//!   .config
+//!   entry.json
+//! ```
+//!
+//! A remote store (see [`super::remote_cache`]) can fill in entries the
+//! local cache doesn't have.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::incremental;
+use super::kconfig;
+use super::memory::MemoryRegion;
+use super::orchestrator::BuildResult;
+use super::target::BuildTarget;
+use crate::paths;
+use crate::workspace::{collect_files, hash_file, BuildHashes, WestLock};
+
+/// Bump when the key's inputs change, so old entries are not mistaken for new ones
+const KEY_VERSION: &str = "1";
+
+/// Entries kept after pruning, most recently used first
+const MAX_ENTRIES: usize = 100;
+
+pub(crate) const FIRMWARE_FILE: &str = "zmk.uf2";
+pub(crate) const KCONFIG_FILE: &str = ".config";
+pub(crate) const ENTRY_FILE: &str = "entry.json";
+
+/// What the cache keeps beside a firmware file
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    target: String,
+    #[serde(default)]
+    memory: Vec<MemoryRegion>,
+}
+
+/// Inputs shared by all targets of a build
+#[derive(Debug)]
+pub struct CacheInputs {
+    image_digest: String,
+    /// `name=revision` of every west project
+    modules: Vec<String>,
+    boards_dir: Option<String>,
+    shields_dir: Option<String>,
+    /// Files of the config directory (relative path, hash)
+    config_files: Vec<(String, String)>,
+    /// Boards and shields of all targets, to tell which files are whose
+    names: Vec<String>,
+}
+
+impl CacheInputs {
+    /// Hash the config directory and gather the other inputs
+    pub fn collect(
+        config_dir: &Path,
+        hashes: &BuildHashes,
+        lock: &WestLock,
+        image_digest: String,
+        targets: &[BuildTarget],
+    ) -> Result<Self> {
+        let mut config_files = Vec::new();
+        for path in collect_files(config_dir)? {
+            let relative = path.strip_prefix(config_dir).unwrap_or(&path);
+            config_files.push((relative.to_string_lossy().to_string(), hash_file(&path)?));
+        }
+        config_files.sort();
+
+        Ok(Self {
+            image_digest,
+            modules: lock
+                .projects
+                .iter()
+                .map(|(name, project)| format!("{}={}", name, project.revision))
+                .collect(),
+            boards_dir: hashes.boards_dir.clone(),
+            shields_dir: hashes.shields_dir.clone(),
+            config_files,
+            names: incremental::names(targets),
+        })
+    }
+
+    /// The key of a target's firmware, or `None` if it can't be cached
+    /// (extra modules are mounted from directories that aren't tracked)
+    pub fn key(&self, target: &BuildTarget) -> Option<String> {
+        if !target.extra_modules.is_empty() {
+            return None;
+        }
+
+        let mut hasher = Sha256::new();
+        let mut add = |field: &str, value: &str| {
+            hasher.update(field.as_bytes());
+            hasher.update(b"=");
+            hasher.update(value.as_bytes());
+            hasher.update(b"\0");
+        };
+        add("version", KEY_VERSION);
+        add("image", &self.image_digest);
+        add("board", &target.board);
+        add("shield", target.shield.as_deref().unwrap_or_default());
+        add("snippet", target.snippet.as_deref().unwrap_or_default());
+        for arg in &target.cmake_args {
+            add("cmake", arg);
+        }
+        for (name, value) in &target.env {
+            add("env", &format!("{}={}", name, value));
+        }
+        for module in &self.modules {
+            add("module", module);
+        }
+        add("boards", self.boards_dir.as_deref().unwrap_or_default());
+        add("shields", self.shields_dir.as_deref().unwrap_or_default());
+        for (path, hash) in &self.config_files {
+            if incremental::uses_file(target, path, &self.names) {
+                add(path, hash);
+            }
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Built firmware, by key
+pub struct ArtifactCache {
+    dir: PathBuf,
+}
+
+impl ArtifactCache {
+    pub fn open() -> Result<Self> {
+        Ok(Self::at(paths::artifacts_dir()?))
+    }
+
+    fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Whether there is a firmware under a key
+    pub fn contains(&self, key: &str) -> bool {
+        self.dir.join(key).join(FIRMWARE_FILE).is_file()
+    }
+
+    /// The files of the entry under a key, by name, or `None` if there is no
+    /// firmware under it
+    pub fn entry_files(&self, key: &str) -> Option<Vec<(&'static str, PathBuf)>> {
+        if !self.contains(key) {
+            return None;
+        }
+        let entry_dir = self.dir.join(key);
+        Some(
+            [FIRMWARE_FILE, KCONFIG_FILE, ENTRY_FILE]
+                .into_iter()
+                .map(|name| (name, entry_dir.join(name)))
+                .filter(|(_, path)| path.is_file())
+                .collect(),
+        )
+    }
+
+    /// Add an entry from the contents of its files, by name
+    pub fn insert(&self, key: &str, files: &[(&str, Vec<u8>)]) -> Result<()> {
+        self.write_entry(key, |staging| {
+            for (name, contents) in files {
+                let path = staging.join(name);
+                fs::write(&path, contents)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Copy a cached firmware (and its Kconfig) into the output directory,
+    /// as the result of a build of `target`. Firmware over the target's
+    /// memory limits (which may have been tightened since) isn't reused.
+    pub fn restore(
+        &self,
+        key: &str,
+        target: &BuildTarget,
+        output_dir: &Path,
+    ) -> Result<Option<BuildResult>> {
+        let entry_dir = self.dir.join(key);
+        let firmware = entry_dir.join(FIRMWARE_FILE);
+        if !firmware.is_file() {
+            return Ok(None);
+        }
+        let entry: Entry = fs::read_to_string(entry_dir.join(ENTRY_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or(Entry {
+                target: target.artifact_name.clone(),
+                memory: Vec::new(),
+            });
+        if target.memory_limits.check(&entry.memory).is_some() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+        let dest = output_dir.join(format!("{}.uf2", target.artifact_name));
+        fs::copy(&firmware, &dest).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                firmware.display(),
+                dest.display()
+            )
+        })?;
+        let config = entry_dir.join(KCONFIG_FILE);
+        if config.is_file() {
+            let config_dest = kconfig::output_path(output_dir, &target.artifact_name);
+            if let Some(parent) = config_dest.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            fs::copy(&config, &config_dest).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    config.display(),
+                    config_dest.display()
+                )
+            })?;
+        }
+        // Recently used entries survive pruning
+        let _ = fs::File::options()
+            .write(true)
+            .open(&firmware)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+
+        Ok(Some(BuildResult {
+            target_name: target.artifact_name.clone(),
+            success: true,
+            artifact_path: Some(dest),
+            memory: entry.memory,
+            ..Default::default()
+        }))
+    }
+
+    /// Keep a built firmware (and the Kconfig it was built with) under its key
+    pub fn store(
+        &self,
+        key: &str,
+        target: &BuildTarget,
+        artifact: &Path,
+        kconfig: Option<&Path>,
+        memory: &[MemoryRegion],
+    ) -> Result<()> {
+        self.write_entry(key, |staging| {
+            fs::copy(artifact, staging.join(FIRMWARE_FILE))
+                .with_context(|| format!("Failed to copy {}", artifact.display()))?;
+            if let Some(kconfig) = kconfig {
+                fs::copy(kconfig, staging.join(KCONFIG_FILE))
+                    .with_context(|| format!("Failed to copy {}", kconfig.display()))?;
+            }
+            let entry = Entry {
+                target: target.artifact_name.clone(),
+                memory: memory.to_vec(),
+            };
+            fs::write(
+                staging.join(ENTRY_FILE),
+                serde_json::to_string_pretty(&entry)?,
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Write an entry with `fill`, which puts its files into the directory
+    /// it is given. The files are written beside the entry and renamed into
+    /// place, so a concurrent build never sees half an entry.
+    fn write_entry(&self, key: &str, fill: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        let entry_dir = self.dir.join(key);
+        let staging = self.dir.join(format!(".{}.{}", key, std::process::id()));
+        fs::create_dir_all(&staging)
+            .with_context(|| format!("Failed to create {}", staging.display()))?;
+        if let Err(e) = fill(&staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        let _ = fs::remove_dir_all(&entry_dir);
+        if let Err(e) = fs::rename(&staging, &entry_dir) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e).with_context(|| format!("Failed to write {}", entry_dir.display()));
+        }
+        Ok(())
+    }
+
+    /// Drop all but the most recently used entries
+    pub fn prune(&self) {
+        let mut entries = self.entries();
+        entries.sort_by_key(|(used, _)| std::cmp::Reverse(*used));
+        for (_, path) in entries.into_iter().skip(MAX_ENTRIES) {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+
+    /// The entries last used before `cutoff`
+    pub fn used_before(&self, cutoff: SystemTime) -> Vec<PathBuf> {
+        self.entries()
+            .into_iter()
+            .filter(|(used, _)| *used < cutoff)
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    /// Every complete entry, with when it was last used
+    fn entries(&self) -> Vec<(SystemTime, PathBuf)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|path| {
+                path.is_dir()
+                    && !path
+                        .file_name()
+                        .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            })
+            .map(|path| {
+                let used = fs::metadata(path.join(FIRMWARE_FILE))
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (used, path)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn target(board: &str, shield: &str) -> BuildTarget {
+        BuildTarget::from_args(board.to_string(), Some(shield.to_string())).unwrap()
+    }
+
+    fn inputs(config_files: &[(&str, &str)], targets: &[BuildTarget]) -> CacheInputs {
+        let dir = tempdir().unwrap();
+        for (name, contents) in config_files {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        let hashes = BuildHashes {
+            build_yaml: String::new(),
+            west_yml: String::new(),
+            boards_dir: None,
+            shields_dir: None,
+            config_files: Default::default(),
+        };
+        CacheInputs::collect(
+            dir.path(),
+            &hashes,
+            &WestLock::default(),
+            "sha256:abc".to_string(),
+            targets,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_key() {
+        let targets = [
+            target("nice_nano_v2", "corne_left"),
+            target("nice_nano_v2", "corne_right"),
+        ];
+        let before = inputs(
+            &[
+                ("corne.keymap", "a"),
+                ("corne_left.conf", "a"),
+                ("corne_right.conf", "a"),
+                ("west.yml", "a"),
+            ],
+            &targets,
+        );
+        let after = inputs(
+            &[
+                ("corne.keymap", "a"),
+                ("corne_left.conf", "b"),
+                ("corne_right.conf", "a"),
+                ("west.yml", "a"),
+            ],
+            &targets,
+        );
+
+        assert_ne!(before.key(&targets[0]), after.key(&targets[0]));
+        assert_eq!(before.key(&targets[1]), after.key(&targets[1]));
+        assert_ne!(before.key(&targets[0]), before.key(&targets[1]));
+
+        let mut with_env = targets[0].clone();
+        with_env
+            .env
+            .insert("ZMK_EXTRA".to_string(), "1".to_string());
+        assert_ne!(before.key(&with_env), before.key(&targets[0]));
+
+        let mut with_module = targets[0].clone();
+        with_module.extra_modules.push(PathBuf::from("/modules/x"));
+        assert_eq!(before.key(&with_module), None);
+    }
+
+    #[test]
+    fn test_store_and_restore() {
+        let cache_dir = tempdir().unwrap();
+        let output = tempdir().unwrap();
+        let cache = ArtifactCache::at(cache_dir.path().to_path_buf());
+        let target = target("nice_nano_v2", "corne_left");
+
+        assert!(cache
+            .restore("abc", &target, output.path())
+            .unwrap()
+            .is_none());
+
+        let artifact = cache_dir.path().join("built.uf2");
+        fs::write(&artifact, "firmware").unwrap();
+        let memory = [MemoryRegion {
+            name: "FLASH".to_string(),
+            used: 100,
+            size: 1000,
+        }];
+        cache
+            .store("abc", &target, &artifact, None, &memory)
+            .unwrap();
+
+        let result = cache
+            .restore("abc", &target, output.path())
+            .unwrap()
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.memory, memory);
+        let restored = result.artifact_path.unwrap();
+        assert_eq!(
+            restored,
+            output.path().join("corne_left-nice_nano_v2-zmk.uf2")
+        );
+        assert_eq!(fs::read_to_string(restored).unwrap(), "firmware");
+
+        // An entry copied file by file (as from a remote store) restores the same
+        let files: Vec<(&str, Vec<u8>)> = cache
+            .entry_files("abc")
+            .unwrap()
+            .into_iter()
+            .map(|(name, path)| (name, fs::read(path).unwrap()))
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert!(!cache.contains("def"));
+        cache.insert("def", &files).unwrap();
+        let copy = cache
+            .restore("def", &target, output.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.memory, memory);
+
+        // Limits tightened since the firmware was built
+        let mut limited = target.clone();
+        limited.memory_limits.max_flash = Some(5);
+        assert!(cache
+            .restore("abc", &limited, output.path())
+            .unwrap()
+            .is_none());
+    }
+}
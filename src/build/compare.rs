@@ -0,0 +1,183 @@
+//! Differences between two builds of the same targets (`lfz compare`)
+
+use serde::Serialize;
+
+use super::history::{Run, TargetRun};
+use crate::workspace::BuildHashes;
+
+/// How two runs differ
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Comparison {
+    /// Config files whose contents differ; unknown if a run has no hashes
+    pub changed_config: Option<Vec<&'static str>>,
+    pub targets: Vec<TargetComparison>,
+}
+
+/// How a target's firmware differs between two runs
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TargetComparison {
+    pub target: String,
+    /// Firmware size in bytes
+    pub size: Change,
+    pub memory: Vec<RegionComparison>,
+}
+
+/// Bytes a memory region uses in each run
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RegionComparison {
+    pub name: String,
+    pub used: Change,
+    /// Size of the region in the later run (or the earlier one)
+    pub size: u64,
+}
+
+/// A value before and after; missing where the run didn't have it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+impl Change {
+    /// The difference, if both values are known
+    pub fn delta(&self) -> Option<i64> {
+        Some(self.after? as i64 - self.before? as i64)
+    }
+}
+
+impl Comparison {
+    /// Compare the successful builds of each target in two runs
+    pub fn new(before: &Run, after: &Run) -> Self {
+        let built = |run: &'_ Run, target: &str| -> Option<TargetRun> {
+            run.targets
+                .iter()
+                .find(|t| t.target == target && t.success)
+                .cloned()
+        };
+        let mut names: Vec<&str> = before
+            .targets
+            .iter()
+            .chain(&after.targets)
+            .filter(|t| t.success)
+            .map(|t| t.target.as_str())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let targets = names
+            .into_iter()
+            .map(|name| {
+                let (old, new) = (built(before, name), built(after, name));
+                TargetComparison {
+                    target: name.to_string(),
+                    size: Change {
+                        before: old.as_ref().and_then(|t| t.size),
+                        after: new.as_ref().and_then(|t| t.size),
+                    },
+                    memory: compare_memory(old.as_ref(), new.as_ref()),
+                }
+            })
+            .collect();
+
+        Self {
+            changed_config: match (&before.hashes, &after.hashes) {
+                (Some(old), Some(new)) => Some(changed_config(old, new)),
+                _ => None,
+            },
+            targets,
+        }
+    }
+}
+
+fn compare_memory(before: Option<&TargetRun>, after: Option<&TargetRun>) -> Vec<RegionComparison> {
+    let regions = |run: Option<&TargetRun>| run.map(|t| t.memory.clone()).unwrap_or_default();
+    let (before, after) = (regions(before), regions(after));
+    let mut names: Vec<&str> = Vec::new();
+    for region in after.iter().chain(&before) {
+        if !names.contains(&region.name.as_str()) {
+            names.push(&region.name);
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let old = before.iter().find(|r| r.name == name);
+            let new = after.iter().find(|r| r.name == name);
+            RegionComparison {
+                name: name.to_string(),
+                used: Change {
+                    before: old.map(|r| r.used),
+                    after: new.map(|r| r.used),
+                },
+                size: new.or(old).map_or(0, |r| r.size),
+            }
+        })
+        .collect()
+}
+
+fn changed_config(before: &BuildHashes, after: &BuildHashes) -> Vec<&'static str> {
+    [
+        ("build config", before.build_yaml != after.build_yaml),
+        ("west.yml", before.west_yml != after.west_yml),
+        ("boards/", before.boards_dir != after.boards_dir),
+        ("shields/", before.shields_dir != after.shields_dir),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::memory::MemoryRegion;
+
+    fn run(west_yml: &str, targets: &[(&str, u64, u64)]) -> Run {
+        Run {
+            time: 0,
+            pristine: true,
+            duration_ms: 0,
+            targets: targets
+                .iter()
+                .map(|(target, size, flash)| TargetRun {
+                    target: target.to_string(),
+                    success: true,
+                    duration_ms: 0,
+                    size: Some(*size),
+                    retries: 0,
+                    memory: vec![MemoryRegion {
+                        name: "FLASH".to_string(),
+                        used: *flash,
+                        size: 1000,
+                    }],
+                })
+                .collect(),
+            hashes: Some(BuildHashes {
+                build_yaml: "a".to_string(),
+                west_yml: west_yml.to_string(),
+                boards_dir: None,
+                shields_dir: None,
+                config_files: Default::default(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = run("v1", &[("left", 300, 100), ("right", 300, 100)]);
+        let after = run("v2", &[("left", 350, 120), ("dongle", 200, 80)]);
+        let comparison = Comparison::new(&before, &after);
+
+        assert_eq!(comparison.changed_config, Some(vec!["west.yml"]));
+        let targets: Vec<_> = comparison.targets.iter().map(|t| &t.target).collect();
+        assert_eq!(targets, ["dongle", "left", "right"]);
+
+        let left = &comparison.targets[1];
+        assert_eq!(left.size.delta(), Some(50));
+        assert_eq!(left.memory[0].used.delta(), Some(20));
+        assert_eq!(comparison.targets[0].size.before, None);
+        assert_eq!(comparison.targets[2].size.after, None);
+    }
+}
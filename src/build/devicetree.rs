@@ -0,0 +1,151 @@
+//! The final devicetree of a target (`zephyr/zephyr.dts` in a build
+//! directory), with every board file, shield and overlay merged in.
+//!
+//! ```text
+//! / {
+//!     chosen {
+//!         zmk,kscan = &kscan0;
+//!     };
+//!     kscan0: kscan {
+//!         compatible = "zmk,kscan-gpio-matrix";
+//!     };
+//! };
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use super::target::BuildTarget;
+
+/// Load the devicetree generated by the last configure of a target (for
+/// sysbuild, the zmk image's)
+pub fn load(workspace: &Path, target: &BuildTarget) -> Result<String> {
+    let build_dir = workspace.join(&target.build_dir);
+    let path = [
+        build_dir.join("zephyr/zephyr.dts"),
+        build_dir.join("zmk/zephyr/zephyr.dts"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+    .with_context(|| {
+        format!(
+            "No zephyr.dts in {}'s build directory",
+            target.artifact_name
+        )
+    })?;
+    fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// The nodes matching `query`, each with its properties and children and
+/// dedented to the left. A query is a path (`/soc/i2c@40003000`), a node
+/// name with or without unit address (`kscan`, `i2c@40003000`) or a label
+/// (`kscan0` or `&kscan0`).
+pub fn select(dts: &str, query: &str) -> Vec<String> {
+    let mut nodes = Vec::new();
+    // Names of the open nodes, and where the matching one started
+    let mut path: Vec<String> = Vec::new();
+    let mut matched: Option<(usize, String)> = None;
+
+    for line in dts.lines() {
+        let trimmed = line.trim();
+        if let Some((_, indent)) = &matched {
+            let text = nodes.last_mut().unwrap();
+            *text += line.strip_prefix(indent.as_str()).unwrap_or(trimmed);
+            *text += "\n";
+        }
+
+        if let Some(header) = trimmed.strip_suffix('{') {
+            let (labels, name) = parse_header(header);
+            path.push(name.to_string());
+            if matched.is_none() && matches(query, &path, &labels) {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                matched = Some((path.len(), indent.to_string()));
+                nodes.push(format!("{}\n", trimmed));
+            }
+        } else if trimmed == "};" {
+            if matched
+                .as_ref()
+                .is_some_and(|(depth, _)| *depth == path.len())
+            {
+                matched = None;
+            }
+            path.pop();
+        }
+    }
+    nodes
+}
+
+/// Labels and name of a node header such as `kscan0: kscan `
+fn parse_header(header: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = header.split_whitespace().collect();
+    let name = parts.pop().unwrap_or("/");
+    let labels = parts.iter().map(|l| l.trim_end_matches(':')).collect();
+    (labels, name)
+}
+
+fn matches(query: &str, path: &[String], labels: &[&str]) -> bool {
+    let name_matches =
+        |query: &str, name: &str| query == name || name.split('@').next() == Some(query);
+
+    if let Some(label) = query.strip_prefix('&') {
+        labels.contains(&label)
+    } else if query.starts_with('/') {
+        let components: Vec<&str> = query.split('/').filter(|c| !c.is_empty()).collect();
+        // path[0] is the root
+        components.len() == path.len() - 1
+            && components
+                .iter()
+                .zip(&path[1..])
+                .all(|(query, name)| name_matches(query, name))
+    } else {
+        labels.contains(&query) || path.last().is_some_and(|name| name_matches(query, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DTS: &str = "\
+/dts-v1/;
+
+/ {
+\tmodel = \"nice!nano\";
+\tchosen {
+\t\tzmk,kscan = &kscan0;
+\t};
+\tsoc {
+\t\ti2c0: arduino_i2c: i2c@40003000 {
+\t\t\tstatus = \"okay\";
+\t\t\toled@3c {
+\t\t\t\treg = < 0x3c >;
+\t\t\t};
+\t\t};
+\t};
+\tkscan0: kscan {
+\t\tcompatible = \"zmk,kscan-gpio-matrix\";
+\t};
+};
+";
+
+    #[test]
+    fn test_select() {
+        assert_eq!(
+            select(DTS, "&kscan0"),
+            ["kscan0: kscan {\n\tcompatible = \"zmk,kscan-gpio-matrix\";\n};\n"]
+        );
+        assert_eq!(select(DTS, "kscan"), select(DTS, "kscan0"));
+
+        let i2c = select(DTS, "arduino_i2c");
+        assert_eq!(i2c.len(), 1);
+        assert!(i2c[0].contains("\toled@3c {\n\t\treg = < 0x3c >;\n\t};\n"));
+        assert_eq!(select(DTS, "/soc/i2c"), i2c);
+        assert_eq!(select(DTS, "i2c@40003000"), i2c);
+
+        assert_eq!(select(DTS, "oled").len(), 1);
+        assert_eq!(select(DTS, "/").len(), 1);
+        assert!(select(DTS, "/oled").is_empty());
+        assert!(select(DTS, "spi").is_empty());
+    }
+}
@@ -0,0 +1,467 @@
+//! Compiler diagnostics parsed from captured build output.
+//!
+//! Understands the `file:line[:column]: error: message` form GCC, ld and
+//! the devicetree/Kconfig scripts print, and CMake's `CMake Error at` blocks.
+//! Container paths are mapped back to the host files they were mounted from.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One error or warning from a build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// File as printed in the container (e.g. `/workspace/config/corne.keymap`)
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// The file on the host, if it was mounted into the build container
+    pub fn host_file(&self, paths: &PathMap) -> Option<PathBuf> {
+        paths.host_path(self.file.as_deref()?)
+    }
+
+    /// Whether this comes from Kconfig (`.conf` files and symbol checks)
+    pub fn is_kconfig(&self) -> bool {
+        self.file
+            .as_deref()
+            .is_some_and(|f| f.ends_with(".conf") || f.ends_with("_defconfig"))
+            || self.message.contains("(defined at")
+            || self.message.contains("undefined symbol")
+            || self.message.contains("Kconfig")
+    }
+
+    /// The file for display: relative to `root` when inside it, else the
+    /// (anonymized) host path, else the container path
+    pub fn display_file(&self, paths: &PathMap, root: &Path) -> Option<String> {
+        match self.host_file(paths) {
+            Some(host) => Some(match host.strip_prefix(root) {
+                Ok(relative) => relative.display().to_string(),
+                Err(_) => crate::paths::anonymize_path(&host),
+            }),
+            None => self.file.clone(),
+        }
+    }
+
+    /// One-line summary like `config/corne.keymap:42: unknown behavior`,
+    /// with host files shown relative to `root` when inside it
+    pub fn summary(&self, paths: &PathMap, root: &Path) -> String {
+        let Some(file) = self.display_file(paths, root) else {
+            return self.message.clone();
+        };
+        match self.line {
+            Some(line) => format!("{}:{}: {}", file, line, self.message),
+            None => format!("{}: {}", file, self.message),
+        }
+    }
+}
+
+/// Host directories behind container paths (the build's mounts)
+#[derive(Debug, Default, Clone)]
+pub struct PathMap {
+    mounts: Vec<(String, PathBuf)>,
+}
+
+impl PathMap {
+    /// Map a container directory to a host directory
+    pub fn mount(mut self, container: &str, host: &Path) -> Self {
+        self.mounts.push((
+            container.trim_end_matches('/').to_string(),
+            host.to_path_buf(),
+        ));
+        self
+    }
+
+    /// The host path of a container path (the most specific mount wins)
+    pub fn host_path(&self, container: &str) -> Option<PathBuf> {
+        self.mounts
+            .iter()
+            .filter_map(|(prefix, host)| {
+                let rest = container.strip_prefix(prefix.as_str())?;
+                let rest = match rest.strip_prefix('/') {
+                    Some(rest) => rest,
+                    None if rest.is_empty() => rest,
+                    None => return None,
+                };
+                Some((prefix.len(), host.join(rest)))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, path)| path)
+    }
+}
+
+/// All diagnostics in build output, in order, without duplicates
+pub fn parse(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut lines = output.lines().peekable();
+    while let Some(line) = lines.next() {
+        let diagnostic = match line.trim().strip_prefix("CMake Error at ") {
+            // The message follows on indented lines
+            Some(location) => {
+                let mut message = Vec::new();
+                while let Some(next) = lines.peek() {
+                    if !next.is_empty() && !next.starts_with(' ') {
+                        break;
+                    }
+                    if !next.trim().is_empty() {
+                        message.push(next.trim());
+                    }
+                    lines.next();
+                }
+                parse_cmake_error(location, &message.join(" "))
+            }
+            None => parse_line(line),
+        };
+        if let Some(diagnostic) = diagnostic {
+            if !diagnostics.contains(&diagnostic) {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The error that caused a build to fail: the first one pointing at a
+/// source file, as later ones (`ld returned 1 exit status`, ninja giving
+/// up) are consequences
+pub fn first_error(diagnostics: &[Diagnostic]) -> Option<&Diagnostic> {
+    let mut errors = diagnostics.iter().filter(|d| d.severity == Severity::Error);
+    errors
+        .clone()
+        .find(|d| d.file.is_some())
+        .or_else(|| errors.next())
+}
+
+/// The lines of output starting at a diagnostic
+pub fn context<'a>(output: &'a str, diagnostic: &Diagnostic) -> Vec<&'a str> {
+    let lines: Vec<&str> = output.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.contains(&diagnostic.message))
+        .unwrap_or(0);
+    lines[start..].to_vec()
+}
+
+/// Errors from dtc and Zephyr's devicetree scripts, which don't use the
+/// compiler format:
+///
+/// - `Error: /path/corne.keymap:42.13-14 syntax error` (dtc)
+/// - `ERROR (phandle_references): /keymap/base: Reference to non-existent node or label "mtt"` (dtc)
+/// - `devicetree error: /path/corne.keymap:42 (column 13): parse error: undefined node label 'mtt'`
+fn parse_devicetree_line(line: &str) -> Option<Diagnostic> {
+    let error = |file: Option<&str>, line: Option<u32>, column: Option<u32>, message: &str| {
+        Some(Diagnostic {
+            severity: Severity::Error,
+            file: file.map(String::from),
+            line,
+            column,
+            message: message.trim().to_string(),
+        })
+    };
+
+    if let Some(rest) = line.strip_prefix("Error: ") {
+        let (location, message) = rest.split_once(' ')?;
+        let (file, position) = location.rsplit_once(':')?;
+        let (line, column) = dtc_position(position);
+        return error(Some(file), line, column, message);
+    }
+
+    // `/path/corne.dtsi:12.3-20: Warning (unit_address_vs_reg): /soc/x: node has a reg`
+    if let Some((location, rest)) = line.split_once(": Warning (") {
+        let (_, message) = rest.split_once("): ")?;
+        let (file, position) = location.rsplit_once(':')?;
+        let (line, column) = dtc_position(position);
+        return Some(Diagnostic {
+            severity: Severity::Warning,
+            file: Some(file.to_string()),
+            line,
+            column,
+            message: message.trim().to_string(),
+        });
+    }
+
+    if line.starts_with("ERROR (") {
+        let (_, rest) = line.split_once("): ")?;
+        // `rest` starts with the node path, not a file
+        let message = rest.split_once(": ").map_or(rest, |(_, message)| message);
+        return error(None, None, None, message);
+    }
+
+    let rest = line.strip_prefix("devicetree error: ")?;
+    let Some((location, message)) = rest.split_once(": ") else {
+        return error(None, None, None, rest);
+    };
+    // `file:line (column N)`
+    let (location, column) = match location.split_once(" (column ") {
+        Some((location, column)) => (location, column.trim_end_matches(')').parse().ok()),
+        None => (location, None),
+    };
+    match location.rsplit_once(':') {
+        Some((file, line)) if line.parse::<u32>().is_ok() => {
+            let message = message.strip_prefix("parse error: ").unwrap_or(message);
+            error(Some(file), line.parse().ok(), column, message)
+        }
+        _ => error(None, None, None, rest),
+    }
+}
+
+/// dtc's `line.column-column` position
+fn dtc_position(position: &str) -> (Option<u32>, Option<u32>) {
+    match position.split_once('.') {
+        Some((line, columns)) => (
+            line.parse().ok(),
+            columns.split('-').next().and_then(|c| c.parse().ok()),
+        ),
+        None => (position.parse().ok(), None),
+    }
+}
+
+/// `CMake Error at /path/CMakeLists.txt:12 (message):`
+fn parse_cmake_error(location: &str, message: &str) -> Option<Diagnostic> {
+    let location = location.split(" (").next()?.trim_end_matches(':');
+    let (file, line) = match location.rsplit_once(':') {
+        Some((file, line)) => (file, line.parse().ok()),
+        None => (location, None),
+    };
+    Some(Diagnostic {
+        severity: Severity::Error,
+        file: Some(file.to_string()),
+        line,
+        column: None,
+        message: message.to_string(),
+    })
+}
+
+fn parse_line(line: &str) -> Option<Diagnostic> {
+    let line = line.trim();
+    if let Some(diagnostic) = parse_devicetree_line(line) {
+        return Some(diagnostic);
+    }
+
+    // Kconfig prints bare `warning: ...` lines, then `error: Aborting due to Kconfig warnings`
+    for (prefix, severity) in [
+        ("warning: ", Severity::Warning),
+        ("error: ", Severity::Error),
+    ] {
+        if let Some(message) = line.strip_prefix(prefix) {
+            return Some(Diagnostic {
+                severity,
+                file: None,
+                line: None,
+                column: None,
+                message: message.trim().to_string(),
+            });
+        }
+    }
+
+    let (location, severity, message) = [
+        (": fatal error: ", Severity::Error),
+        (": error: ", Severity::Error),
+        (": warning: ", Severity::Warning),
+    ]
+    .iter()
+    .find_map(|(marker, severity)| {
+        let (location, message) = line.split_once(marker)?;
+        Some((location, *severity, message))
+    })?;
+
+    // `file:line:column`, `file:line` or just `file`
+    let (mut file, mut line_number, mut column) = (location, None, None);
+    for _ in 0..2 {
+        let Some((rest, number)) = file.rsplit_once(':') else {
+            break;
+        };
+        let Ok(number) = number.parse::<u32>() else {
+            break;
+        };
+        column = line_number;
+        line_number = Some(number);
+        file = rest;
+    }
+
+    // Tool prefixes like `collect2` or `make[2]` are not files
+    let is_file = (file.contains('/') || file.contains('.')) && !file.contains(' ');
+
+    Some(Diagnostic {
+        severity,
+        file: is_file.then(|| file.to_string()),
+        line: line_number,
+        column,
+        message: message.trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compiler_error() {
+        let output = "\
+[12/200] Building C object
+/workspace/config/corne.keymap:42:13: error: 'mtt' undeclared here (not in a function)
+/workspace/zmk/app/src/main.c:7: warning: unused variable 'x'
+collect2: error: ld returned 1 exit status
+/workspace/config/corne.keymap:42:13: error: 'mtt' undeclared here (not in a function)
+";
+        let diagnostics = parse(output);
+        assert_eq!(diagnostics.len(), 3);
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some("/workspace/config/corne.keymap")
+        );
+        assert_eq!(diagnostics[0].line, Some(42));
+        assert_eq!(diagnostics[0].column, Some(13));
+        assert_eq!(
+            diagnostics[0].message,
+            "'mtt' undeclared here (not in a function)"
+        );
+
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[1].file.as_deref(),
+            Some("/workspace/zmk/app/src/main.c")
+        );
+        assert_eq!(diagnostics[1].line, Some(7));
+        assert_eq!(diagnostics[1].column, None);
+
+        assert_eq!(diagnostics[2].file, None);
+        assert_eq!(diagnostics[2].message, "ld returned 1 exit status");
+    }
+
+    #[test]
+    fn test_parse_cmake_error() {
+        let output = "\
+-- Found devicetree overlay
+CMake Error at /workspace/zephyr/cmake/modules/dts.cmake:295 (message):
+  dtc failed on overlay
+  see above
+
+
+-- Configuring incomplete, errors occurred!
+";
+        let diagnostics = parse(output);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some("/workspace/zephyr/cmake/modules/dts.cmake")
+        );
+        assert_eq!(diagnostics[0].line, Some(295));
+        assert_eq!(diagnostics[0].message, "dtc failed on overlay see above");
+    }
+
+    #[test]
+    fn test_first_error_summary() {
+        let output = "\
+collect2: error: ld returned 1 exit status
+/workspace/config/corne.keymap:42:13: error: unknown behavior &mtt
+/workspace/module_0/boards/shields/corne/corne.dtsi:3: warning: unit address
+";
+        let diagnostics = parse(output);
+        let first = first_error(&diagnostics).unwrap();
+        assert_eq!(first.line, Some(42));
+
+        let root = Path::new("/home/user/zmk-config");
+        let paths = PathMap::default()
+            .mount("/workspace/module_0", root)
+            .mount("/workspace/config", &root.join("config"));
+        assert_eq!(
+            first.summary(&paths, root),
+            "config/corne.keymap:42: unknown behavior &mtt"
+        );
+        assert_eq!(
+            diagnostics[2].host_file(&paths).unwrap(),
+            root.join("boards/shields/corne/corne.dtsi")
+        );
+        assert_eq!(
+            diagnostics[0].summary(&paths, root),
+            "ld returned 1 exit status"
+        );
+        assert_eq!(
+            context(output, first),
+            &output.lines().collect::<Vec<_>>()[1..]
+        );
+    }
+
+    #[test]
+    fn test_parse_devicetree_errors() {
+        let output = "\
+Error: /workspace/config/corne.keymap:42.22-26 Label or path mtt not found
+ERROR (phandle_references): /keymap/default_layer: Reference to non-existent node or label \"mtt\"
+devicetree error: /workspace/config/corne.keymap:42 (column 13): parse error: undefined node label 'mtt'
+devicetree error: <Node /kscan in 'corne.dts'> lacks 'compatible'
+";
+        let diagnostics = parse(output);
+        assert_eq!(diagnostics.len(), 4);
+
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some("/workspace/config/corne.keymap")
+        );
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(42), Some(22))
+        );
+        assert_eq!(diagnostics[0].message, "Label or path mtt not found");
+
+        assert_eq!(diagnostics[1].file, None);
+        assert_eq!(
+            diagnostics[1].message,
+            "Reference to non-existent node or label \"mtt\""
+        );
+
+        assert_eq!(diagnostics[2].column, Some(13));
+        assert_eq!(diagnostics[2].message, "undefined node label 'mtt'");
+
+        assert_eq!(diagnostics[3].file, None);
+        assert_eq!(
+            diagnostics[3].message,
+            "<Node /kscan in 'corne.dts'> lacks 'compatible'"
+        );
+    }
+
+    #[test]
+    fn test_parse_kconfig_warnings() {
+        let output = "\
+/workspace/config/corne.conf:3: warning: attempt to assign the value 'y' to the undefined symbol ZMK_SLEEP_X
+warning: ZMK_DISPLAY (defined at /workspace/zmk/app/Kconfig:20) was assigned the value 'y' but got the value 'n'.
+error: Aborting due to Kconfig warnings
+";
+        let diagnostics = parse(output);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.iter().all(Diagnostic::is_kconfig));
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].file, None);
+        assert_eq!(diagnostics[2].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_parse_dtc_warning() {
+        let diagnostics = parse(
+            "/workspace/module_0/boards/shields/corne/corne.dtsi:12.3-20: Warning (unit_address_vs_reg): /soc/kscan: node has a reg or ranges property, but no unit name",
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some("/workspace/module_0/boards/shields/corne/corne.dtsi")
+        );
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(12), Some(3))
+        );
+        assert!(diagnostics[0]
+            .message
+            .starts_with("/soc/kscan: node has a reg"));
+    }
+}
@@ -0,0 +1,94 @@
+//! How long each target's builds have taken, to estimate the next one.
+//!
+//! Pristine and incremental builds of a target take very different times,
+//! so they are tracked separately.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// File name for storing build durations in the workspace
+const DURATIONS_FILE: &str = ".lfz_build_durations.json";
+
+/// Typical build duration in milliseconds, by target and build mode
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Durations {
+    #[serde(flatten)]
+    builds: BTreeMap<String, u64>,
+}
+
+impl Durations {
+    /// Load the durations recorded in a workspace; none if they can't be read
+    pub fn load(workspace: &Path) -> Self {
+        fs::read_to_string(workspace.join(DURATIONS_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the durations to a workspace
+    pub fn save(&self, workspace: &Path) -> Result<()> {
+        let path = workspace.join(DURATIONS_FILE);
+
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize build durations")?;
+
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// How long a build of the target is expected to take
+    pub fn estimate(&self, target: &str, pristine: bool) -> Option<Duration> {
+        self.builds
+            .get(&key(target, pristine))
+            .map(|ms| Duration::from_millis(*ms))
+    }
+
+    /// Record a successful build. The estimate moves halfway towards each new
+    /// duration, so it follows changes without jumping on one slow build.
+    pub fn record(&mut self, target: &str, pristine: bool, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        self.builds
+            .entry(key(target, pristine))
+            .and_modify(|previous| *previous = (*previous + ms) / 2)
+            .or_insert(ms);
+    }
+}
+
+fn key(target: &str, pristine: bool) -> String {
+    let mode = if pristine { "pristine" } else { "incremental" };
+    format!("{}:{}", target, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_and_estimate() {
+        let dir = tempdir().unwrap();
+        let mut durations = Durations::load(dir.path());
+        assert_eq!(durations.estimate("corne_left", true), None);
+
+        durations.record("corne_left", true, Duration::from_secs(100));
+        durations.record("corne_left", true, Duration::from_secs(60));
+        durations.record("corne_left", false, Duration::from_secs(10));
+        durations.save(dir.path()).unwrap();
+
+        let durations = Durations::load(dir.path());
+        assert_eq!(
+            durations.estimate("corne_left", true),
+            Some(Duration::from_secs(80))
+        );
+        assert_eq!(
+            durations.estimate("corne_left", false),
+            Some(Duration::from_secs(10))
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Target filtering for partial builds.
+//!
+//! Tag expressions combine tag names with `&` (and), `|` (or), `!` (not)
+//! and parentheses, e.g. `central & !dongle` or `(left | right) & corne`.
+//! A target's group counts as one of its tags.
+//!
+//! Name globs (`corne_*`, `*_left-*`) select targets by artifact name.
+//!
+//! Group filters are comma-separated group names where a leading `!`
+//! excludes a group, e.g. `central,dongle` or `!peripheral`.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use super::target::BuildTarget;
+
+/// A parsed tag expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+impl TagExpr {
+    /// Parse a tag expression like `central & !dongle`
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            anyhow::bail!("Empty tag expression");
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!(
+                "Unexpected '{}' in tag expression '{}'",
+                token.as_str(),
+                input
+            );
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate the expression against a set of tags
+    pub fn matches(&self, tags: &[&str]) -> bool {
+        match self {
+            TagExpr::Tag(tag) => tags.contains(&tag.as_str()),
+            TagExpr::Not(inner) => !inner.matches(tags),
+            TagExpr::And(a, b) => a.matches(tags) && b.matches(tags),
+            TagExpr::Or(a, b) => a.matches(tags) || b.matches(tags),
+        }
+    }
+
+    /// Evaluate the expression against a target's tags and group
+    pub fn matches_target(&self, target: &BuildTarget) -> bool {
+        let mut tags: Vec<&str> = target.tags.iter().map(|t| t.as_str()).collect();
+        if let Some(ref group) = target.group {
+            tags.push(group);
+        }
+        self.matches(&tags)
+    }
+}
+
+/// A parsed `--group` filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupFilter {
+    /// Groups to include (empty = every group)
+    include: Vec<String>,
+    /// Groups to exclude
+    exclude: Vec<String>,
+}
+
+impl GroupFilter {
+    /// Parse a group filter like `central,dongle` or `!peripheral`,
+    /// expanding named aliases (from `lfz.toml`)
+    ///
+    /// `all` matches every target, including ungrouped ones. A negated
+    /// alias (`!daily`) excludes every group it stands for.
+    pub fn parse_with_aliases(
+        input: &str,
+        aliases: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self> {
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+
+        for item in input.split(',').map(str::trim) {
+            if item.is_empty() {
+                anyhow::bail!("Empty group name in '{}'", input);
+            }
+            match item.strip_prefix('!') {
+                Some(group) if group.trim().is_empty() => {
+                    anyhow::bail!("Empty group name in '{}'", input)
+                }
+                Some(group) => {
+                    exclude.extend(expand_alias(group.trim(), aliases, &mut Vec::new())?)
+                }
+                None => include.extend(expand_alias(item, aliases, &mut Vec::new())?),
+            }
+        }
+
+        // `all` anywhere in the includes means no include restriction
+        if include.iter().any(|g| g == "all") {
+            include.clear();
+        }
+
+        Ok(Self { include, exclude })
+    }
+
+    /// Whether this filter selects every target
+    pub fn is_all(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// All group names mentioned in the filter
+    pub fn groups(&self) -> impl Iterator<Item = &str> {
+        self.include
+            .iter()
+            .chain(self.exclude.iter())
+            .map(|g| g.as_str())
+    }
+
+    /// Check whether a target's group passes the filter
+    pub fn matches(&self, group: Option<&str>) -> bool {
+        let included =
+            self.include.is_empty() || group.is_some_and(|g| self.include.iter().any(|i| i == g));
+        let excluded = group.is_some_and(|g| self.exclude.iter().any(|e| e == g));
+        included && !excluded
+    }
+}
+
+/// Expand a group name through the alias table (aliases may nest)
+fn expand_alias(
+    name: &str,
+    aliases: &BTreeMap<String, Vec<String>>,
+    stack: &mut Vec<String>,
+) -> Result<Vec<String>> {
+    let Some(members) = aliases.get(name) else {
+        return Ok(vec![name.to_string()]);
+    };
+
+    if stack.iter().any(|s| s == name) {
+        anyhow::bail!("Group alias cycle: {} -> {}", stack.join(" -> "), name);
+    }
+
+    stack.push(name.to_string());
+    let mut groups = Vec::new();
+    for member in members {
+        groups.extend(expand_alias(member, aliases, stack)?);
+    }
+    stack.pop();
+
+    Ok(groups)
+}
+
+/// Check whether a target's artifact name matches any of the given globs
+pub fn matches_any_glob(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|p| glob_match(p, name))
+}
+
+/// Match a name against a glob supporting `*` (any run) and `?` (one char)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Iterative matcher with single-star backtracking
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the last star swallow one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Token {
+    fn as_str(&self) -> &str {
+        match self {
+            Token::Ident(s) => s,
+            Token::And => "&",
+            Token::Or => "|",
+            Token::Not => "!",
+            Token::Open => "(",
+            Token::Close => ")",
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '&' => {
+                chars.next();
+                tokens.push(Token::And);
+            }
+            '|' => {
+                chars.next();
+                tokens.push(Token::Or);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            c if is_tag_char(c) => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_tag_char(c) {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            other => anyhow::bail!("Invalid character '{}' in tag expression", other),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Recursive descent parser: or > and > not > atom
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<TagExpr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = TagExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<TagExpr> {
+        let mut expr = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = TagExpr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<TagExpr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(TagExpr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<TagExpr> {
+        match self.next() {
+            Some(Token::Ident(tag)) => Ok(TagExpr::Tag(tag)),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                if self.next() != Some(Token::Close) {
+                    anyhow::bail!("Missing ')' in tag expression");
+                }
+                Ok(expr)
+            }
+            Some(token) => anyhow::bail!("Expected a tag, found '{}'", token.as_str()),
+            None => anyhow::bail!("Tag expression ends unexpectedly"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_not() {
+        let expr = TagExpr::parse("central & !dongle").unwrap();
+        assert!(expr.matches(&["central"]));
+        assert!(!expr.matches(&["central", "dongle"]));
+        assert!(!expr.matches(&["peripheral"]));
+    }
+
+    #[test]
+    fn test_precedence_and_binds_tighter_than_or() {
+        let expr = TagExpr::parse("a | b & c").unwrap();
+        assert!(expr.matches(&["a"]));
+        assert!(!expr.matches(&["b"]));
+        assert!(expr.matches(&["b", "c"]));
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let expr = TagExpr::parse("(left | right) & corne").unwrap();
+        assert!(expr.matches(&["left", "corne"]));
+        assert!(!expr.matches(&["left", "sofle"]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(TagExpr::parse("").is_err());
+        assert!(TagExpr::parse("a &").is_err());
+        assert!(TagExpr::parse("(a | b").is_err());
+        assert!(TagExpr::parse("a b").is_err());
+        assert!(TagExpr::parse("a $ b").is_err());
+    }
+
+    #[test]
+    fn test_group_filter_multiple() {
+        let filter = GroupFilter::parse_with_aliases("central, dongle", &BTreeMap::new()).unwrap();
+        assert!(filter.matches(Some("central")));
+        assert!(filter.matches(Some("dongle")));
+        assert!(!filter.matches(Some("peripheral")));
+        assert!(!filter.matches(None));
+    }
+
+    #[test]
+    fn test_group_filter_negation() {
+        let filter = GroupFilter::parse_with_aliases("!peripheral", &BTreeMap::new()).unwrap();
+        assert!(filter.matches(Some("central")));
+        assert!(filter.matches(None));
+        assert!(!filter.matches(Some("peripheral")));
+
+        let filter =
+            GroupFilter::parse_with_aliases("central,dongle,!dongle", &BTreeMap::new()).unwrap();
+        assert!(!filter.matches(Some("dongle")));
+    }
+
+    #[test]
+    fn test_group_filter_all() {
+        let filter = GroupFilter::parse_with_aliases("all", &BTreeMap::new()).unwrap();
+        assert!(filter.is_all());
+        assert!(filter.matches(None));
+        assert!(GroupFilter::parse_with_aliases("central,,dongle", &BTreeMap::new()).is_err());
+        assert!(GroupFilter::parse_with_aliases("!", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_group_filter_aliases() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert(
+            "daily".to_string(),
+            vec!["central".to_string(), "peripheral".to_string()],
+        );
+        aliases.insert(
+            "everything".to_string(),
+            vec!["daily".to_string(), "reset".to_string()],
+        );
+
+        let filter = GroupFilter::parse_with_aliases("daily", &aliases).unwrap();
+        assert!(filter.matches(Some("central")));
+        assert!(filter.matches(Some("peripheral")));
+        assert!(!filter.matches(Some("reset")));
+
+        let filter = GroupFilter::parse_with_aliases("everything,!daily", &aliases).unwrap();
+        assert!(filter.matches(Some("reset")));
+        assert!(!filter.matches(Some("central")));
+    }
+
+    #[test]
+    fn test_group_filter_alias_cycle() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert("a".to_string(), vec!["b".to_string()]);
+        aliases.insert("b".to_string(), vec!["a".to_string()]);
+        assert!(GroupFilter::parse_with_aliases("a", &aliases).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("corne_*", "corne_left-nice_nano_v2-zmk"));
+        assert!(glob_match("*_left-*", "corne_left-nice_nano_v2-zmk"));
+        assert!(glob_match("corne_?eft*", "corne_left-nice_nano_v2-zmk"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exact-zmk"));
+        assert!(!glob_match("sofle_*", "corne_left-nice_nano_v2-zmk"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_matches_any_glob() {
+        let patterns = vec!["corne_*".to_string(), "settings_reset*".to_string()];
+        assert!(matches_any_glob(
+            &patterns,
+            "settings_reset-nice_nano_v2-zmk"
+        ));
+        assert!(!matches_any_glob(&patterns, "sofle_left-nice_nano_v2-zmk"));
+    }
+
+    #[test]
+    fn test_matches_target_includes_group() {
+        let mut target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+        target.group = Some("central".to_string());
+        target.tags = vec!["corne".to_string()];
+
+        assert!(TagExpr::parse("central & corne")
+            .unwrap()
+            .matches_target(&target));
+        assert!(!TagExpr::parse("!central").unwrap().matches_target(&target));
+    }
+}
@@ -0,0 +1,232 @@
+//! Explanations for common ZMK build failures.
+//!
+//! Devicetree and Kconfig errors are reported in dtc's and Zephyr's terms;
+//! these hints say what usually causes them in a ZMK config.
+
+use std::fs;
+use std::path::Path;
+
+use super::diagnostics::Diagnostic;
+
+/// A likely cause and fix for a diagnostic, if it is a known failure
+pub fn hint(diagnostic: &Diagnostic) -> Option<String> {
+    let message = diagnostic.message.as_str();
+    let lower = message.to_lowercase();
+
+    if let Some(symbol) = undefined_symbol(diagnostic) {
+        return Some(format!(
+            "CONFIG_{} does not exist in this build. Check its spelling; it may \
+             have been renamed or removed in a newer ZMK, or belong to a module \
+             missing from west.yml",
+            symbol
+        ));
+    }
+
+    if let Some((symbol, _)) = message
+        .split_once(" (defined at")
+        .filter(|_| lower.contains("but got the value"))
+    {
+        return Some(format!(
+            "CONFIG_{} has unmet dependencies, so the setting is ignored. Enable \
+             the dependencies Kconfig lists, or check that it applies to this \
+             board and shield (e.g. split, BLE or display options)",
+            symbol
+        ));
+    }
+
+    if lower.contains("aborting due to kconfig warnings") {
+        return Some(
+            "Zephyr treats Kconfig warnings as errors; fix the warnings it printed".to_string(),
+        );
+    }
+
+    if let Some(label) = undefined_label(message) {
+        return Some(format!(
+            "`&{}` is not defined in this build. Check its spelling, that the \
+             shield overlay or `#include` that defines it is part of the build, \
+             and that any module providing it is listed in west.yml",
+            label
+        ));
+    }
+
+    if lower.contains("gpio")
+        && ["overlap", "conflict", "already", "duplicate"]
+            .iter()
+            .any(|word| lower.contains(word))
+    {
+        return Some(
+            "Two nodes use the same GPIO pin. Compare the kscan pins with the \
+             other peripherals on the shield (display, encoders, LEDs) and the \
+             pins the board itself reserves"
+                .to_string(),
+        );
+    }
+
+    if lower.contains("compatible")
+        && ["lacks", "missing", "no "]
+            .iter()
+            .any(|w| lower.contains(w))
+    {
+        return Some(
+            "The node needs a `compatible` property (e.g. `compatible = \
+             \"zmk,behavior-hold-tap\";`) so Zephyr knows which driver it is for"
+                .to_string(),
+        );
+    }
+
+    let in_devicetree_file = diagnostic.file.as_deref().is_some_and(|file| {
+        [".keymap", ".overlay", ".dtsi", ".dts"]
+            .iter()
+            .any(|ext| file.ends_with(ext))
+    });
+    if in_devicetree_file && lower.contains("syntax error") {
+        return Some(
+            "Look for a missing `;` on or before this line: properties end \
+             with `;` and nodes with `};`"
+                .to_string(),
+        );
+    }
+
+    None
+}
+
+/// The symbol (without `CONFIG_`) a Kconfig file assigned but no Kconfig defines
+pub fn undefined_symbol(diagnostic: &Diagnostic) -> Option<&str> {
+    let (_, rest) = diagnostic.message.split_once("undefined symbol ")?;
+    let symbol = rest.split_whitespace().next()?;
+    Some(symbol.trim_start_matches("CONFIG_"))
+}
+
+/// The ZMK Kconfig symbol closest to a misspelled one, if any is close
+pub fn similar_symbol(workspace: &Path, symbol: &str) -> Option<String> {
+    let mut symbols = Vec::new();
+    kconfig_symbols(&workspace.join("zmk/app"), &mut symbols);
+
+    let max_distance = (symbol.len() / 5).max(2);
+    symbols
+        .into_iter()
+        .map(|candidate| (edit_distance(symbol, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Symbols defined by `config`/`menuconfig` in Kconfig files below a directory
+fn kconfig_symbols(dir: &Path, symbols: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            kconfig_symbols(&path, symbols);
+        } else if entry.file_name().to_string_lossy().starts_with("Kconfig") {
+            let contents = fs::read_to_string(&path).unwrap_or_default();
+            symbols.extend(contents.lines().filter_map(|line| {
+                let line = line.trim();
+                let name = line
+                    .strip_prefix("config ")
+                    .or_else(|| line.strip_prefix("menuconfig "))?;
+                Some(name.trim().to_string())
+            }));
+        }
+    }
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The label of an undefined-reference error from dtc or Zephyr's scripts
+fn undefined_label(message: &str) -> Option<&str> {
+    let label = if let Some(rest) = message.strip_prefix("Label or path ") {
+        rest.strip_suffix(" not found")?
+    } else if let Some((_, rest)) = message.split_once("undefined node label ") {
+        rest
+    } else if let Some((_, rest)) = message.split_once("non-existent node or label ") {
+        rest
+    } else {
+        return None;
+    };
+    Some(label.trim_matches(|c| c == '\'' || c == '"' || c == '&'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::diagnostics::parse;
+
+    #[test]
+    fn test_undefined_label_hint() {
+        for output in [
+            "Error: /workspace/config/corne.keymap:42.22-26 Label or path mtt not found",
+            "devicetree error: /workspace/config/corne.keymap:42 (column 13): parse error: undefined node label 'mtt'",
+            "ERROR (phandle_references): /keymap/base: Reference to non-existent node or label \"mtt\"",
+        ] {
+            let hint = hint(&parse(output)[0]).unwrap();
+            assert!(hint.starts_with("`&mtt` is not defined"), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_kconfig_hints() {
+        let diagnostics = parse(
+            "/workspace/config/corne.conf:3: warning: attempt to assign the value 'y' to the undefined symbol ZMK_SLEPE\n\
+             warning: ZMK_DISPLAY (defined at /workspace/zmk/app/Kconfig:20) was assigned the value 'y' but got the value 'n'.",
+        );
+        assert_eq!(undefined_symbol(&diagnostics[0]), Some("ZMK_SLEPE"));
+        assert!(hint(&diagnostics[0])
+            .unwrap()
+            .starts_with("CONFIG_ZMK_SLEPE does not exist"));
+        assert!(hint(&diagnostics[1])
+            .unwrap()
+            .starts_with("CONFIG_ZMK_DISPLAY has unmet dependencies"));
+    }
+
+    #[test]
+    fn test_similar_symbol() {
+        let workspace = tempfile::tempdir().unwrap();
+        let app = workspace.path().join("zmk/app");
+        fs::create_dir_all(app.join("src/display")).unwrap();
+        fs::write(app.join("Kconfig"), "config ZMK_SLEEP\n\tbool \"Sleep\"\n").unwrap();
+        fs::write(
+            app.join("src/display/Kconfig"),
+            "menuconfig ZMK_DISPLAY\n\tbool\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            similar_symbol(workspace.path(), "ZMK_SLEPE").as_deref(),
+            Some("ZMK_SLEEP")
+        );
+        assert_eq!(
+            similar_symbol(workspace.path(), "ZMK_DISPLAYS").as_deref(),
+            Some("ZMK_DISPLAY")
+        );
+        assert_eq!(similar_symbol(workspace.path(), "ZMK_RGB_UNDERGLOW"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_devicetree_hints() {
+        let missing = parse("devicetree error: <Node /kscan in 'corne.dts'> lacks 'compatible'");
+        assert!(hint(&missing[0]).unwrap().contains("`compatible`"));
+
+        let syntax = parse("Error: /workspace/config/corne.keymap:12.1-2 syntax error");
+        assert!(hint(&syntax[0]).unwrap().contains("missing `;`"));
+
+        let unrelated = parse("/workspace/zmk/app/src/main.c:3:1: error: expected ';'");
+        assert_eq!(hint(&unrelated[0]), None);
+    }
+}
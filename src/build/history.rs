@@ -0,0 +1,328 @@
+//! Statistics of past build runs, for `lfz history` and `lfz compare`.
+//!
+//! Each run of a workspace is appended as one JSON line to
+//! `<cache>/history/<workspace>.jsonl`; only the most recent runs are kept.
+//! The output directory gets `lfz-run.json`, describing the latest build of
+//! each firmware in it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::build::memory::MemoryRegion;
+use crate::build::orchestrator::BuildResult;
+use crate::paths;
+use crate::workspace::BuildHashes;
+
+/// Runs kept per workspace; older ones are dropped when the file grows past this
+const KEEP_RUNS: usize = 500;
+
+/// File in the output directory describing the firmware in it
+const OUTPUT_RUN_FILE: &str = "lfz-run.json";
+
+/// One `lfz build` run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    /// When the run finished, in seconds since the Unix epoch
+    pub time: u64,
+    pub pristine: bool,
+    pub duration_ms: u64,
+    pub targets: Vec<TargetRun>,
+    /// Hashes of the config files the run was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashes: Option<BuildHashes>,
+}
+
+/// One target's build in a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetRun {
+    pub target: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Size of the firmware in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub retries: u32,
+    /// Flash and RAM usage reported by the linker
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<MemoryRegion>,
+}
+
+/// A target's results over the recorded runs
+#[derive(Debug, PartialEq)]
+pub struct TargetStats {
+    pub target: String,
+    pub runs: usize,
+    pub failures: usize,
+    /// Average duration of successful builds
+    pub average: Option<Duration>,
+    /// Failures in a row up to the latest run
+    pub failure_streak: usize,
+    /// Firmware size of the latest successful build
+    pub size: Option<u64>,
+}
+
+impl Run {
+    pub fn new(
+        results: &[BuildResult],
+        pristine: bool,
+        duration: Duration,
+        time: SystemTime,
+        hashes: Option<BuildHashes>,
+    ) -> Self {
+        Self {
+            time: time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            pristine,
+            duration_ms: duration.as_millis() as u64,
+            targets: results
+                .iter()
+                .filter(|r| !r.cancelled)
+                .map(|r| TargetRun {
+                    target: r.target_name.clone(),
+                    success: r.success,
+                    duration_ms: r.duration.as_millis() as u64,
+                    size: r
+                        .artifact_path
+                        .as_ref()
+                        .and_then(|p| fs::metadata(p).ok())
+                        .map(|m| m.len()),
+                    retries: r.retries,
+                    memory: r.memory.clone(),
+                })
+                .collect(),
+            hashes,
+        }
+    }
+
+    pub fn failed(&self) -> usize {
+        self.targets.iter().filter(|t| !t.success).count()
+    }
+}
+
+/// History file of a workspace
+pub fn history_file(workspace: &Path) -> Result<PathBuf> {
+    let name = workspace
+        .file_name()
+        .context("Workspace path has no name")?;
+    Ok(paths::history_dir()?.join(format!("{}.jsonl", name.to_string_lossy())))
+}
+
+/// Append a run to a history file, dropping the oldest runs past the limit
+pub fn append(path: &Path, run: &Run) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(run)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let runs = load(path);
+    if runs.len() > KEEP_RUNS {
+        let kept: Vec<String> = runs[runs.len() - KEEP_RUNS..]
+            .iter()
+            .filter_map(|run| serde_json::to_string(run).ok())
+            .collect();
+        fs::write(path, kept.join("\n") + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs in a history file, oldest first; unreadable lines are skipped
+pub fn load(path: &Path) -> Vec<Run> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Record a run's firmware in the output directory's `lfz-run.json`, keeping
+/// the entries of other targets whose firmware is still there
+pub fn record_output(output_dir: &Path, run: &Run) -> Result<()> {
+    let path = output_dir.join(OUTPUT_RUN_FILE);
+    let mut targets: Vec<TargetRun> = run.targets.iter().filter(|t| t.success).cloned().collect();
+    if let Some(previous) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Run>(&contents).ok())
+    {
+        targets.extend(previous.targets.into_iter().filter(|t| {
+            !run.targets.iter().any(|r| r.target == t.target)
+                && output_dir.join(format!("{}.uf2", t.target)).is_file()
+        }));
+    }
+    targets.sort_by(|a, b| a.target.cmp(&b.target));
+
+    let output = Run {
+        targets,
+        ..run.clone()
+    };
+    fs::write(&path, serde_json::to_string_pretty(&output)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The firmware in an output directory, from its `lfz-run.json` or else
+/// (for directories built before it existed) the sizes of the `.uf2` files
+pub fn load_output(output_dir: &Path) -> Result<Run> {
+    if !output_dir.is_dir() {
+        anyhow::bail!("Output directory {} not found", output_dir.display());
+    }
+    let path = output_dir.join(OUTPUT_RUN_FILE);
+    if path.is_file() {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()));
+    }
+
+    let mut targets = Vec::new();
+    for entry in fs::read_dir(output_dir)
+        .with_context(|| format!("Failed to read {}", output_dir.display()))?
+        .flatten()
+    {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "uf2") {
+            targets.push(TargetRun {
+                target: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                success: true,
+                duration_ms: 0,
+                size: entry.metadata().ok().map(|m| m.len()),
+                retries: 0,
+                memory: Vec::new(),
+            });
+        }
+    }
+    targets.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(Run {
+        time: 0,
+        pristine: false,
+        duration_ms: 0,
+        targets,
+        hashes: None,
+    })
+}
+
+/// Per-target statistics over runs (oldest first), by target name
+pub fn stats(runs: &[Run]) -> Vec<TargetStats> {
+    let mut by_target: BTreeMap<&str, Vec<&TargetRun>> = BTreeMap::new();
+    for run in runs {
+        for target in &run.targets {
+            by_target.entry(&target.target).or_default().push(target);
+        }
+    }
+
+    by_target
+        .into_iter()
+        .map(|(target, builds)| {
+            let successes: Vec<_> = builds.iter().filter(|b| b.success).collect();
+            let average = (!successes.is_empty()).then(|| {
+                let total: u64 = successes.iter().map(|b| b.duration_ms).sum();
+                Duration::from_millis(total / successes.len() as u64)
+            });
+            TargetStats {
+                target: target.to_string(),
+                runs: builds.len(),
+                failures: builds.len() - successes.len(),
+                average,
+                failure_streak: builds.iter().rev().take_while(|b| !b.success).count(),
+                size: successes.last().and_then(|b| b.size),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn run(time: u64, results: &[(&str, bool, u64)]) -> Run {
+        Run {
+            time,
+            pristine: false,
+            duration_ms: 0,
+            targets: results
+                .iter()
+                .map(|(target, success, duration_ms)| TargetRun {
+                    target: target.to_string(),
+                    success: *success,
+                    duration_ms: *duration_ms,
+                    size: None,
+                    retries: 0,
+                    memory: Vec::new(),
+                })
+                .collect(),
+            hashes: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history").join("ws.jsonl");
+        for time in 0..(KEEP_RUNS as u64 + 2) {
+            append(&path, &run(time, &[("corne_left", true, 1000)])).unwrap();
+        }
+        let runs = load(&path);
+        assert_eq!(runs.len(), KEEP_RUNS);
+        assert_eq!(runs[0].time, 2);
+    }
+
+    #[test]
+    fn test_record_and_load_output() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("left.uf2"), "firmware").unwrap();
+        assert_eq!(load_output(dir.path()).unwrap().targets[0].size, Some(8));
+
+        fs::write(dir.path().join("right.uf2"), "firmware").unwrap();
+        record_output(
+            dir.path(),
+            &run(1, &[("left", true, 1000), ("old", true, 1000)]),
+        )
+        .unwrap();
+        record_output(
+            dir.path(),
+            &run(2, &[("right", true, 1000), ("left", false, 10)]),
+        )
+        .unwrap();
+
+        // "left" failed last and "old" has no firmware left
+        let output = load_output(dir.path()).unwrap();
+        assert_eq!(output.time, 2);
+        let targets: Vec<_> = output.targets.iter().map(|t| t.target.as_str()).collect();
+        assert_eq!(targets, ["right"]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let runs = vec![
+            run(1, &[("left", true, 1000), ("right", true, 2000)]),
+            run(2, &[("left", true, 3000), ("right", false, 500)]),
+            run(3, &[("right", false, 500)]),
+        ];
+        let stats = stats(&runs);
+        assert_eq!(stats[0].target, "left");
+        assert_eq!(stats[0].runs, 2);
+        assert_eq!(stats[0].average, Some(Duration::from_secs(2)));
+        assert_eq!(stats[0].failure_streak, 0);
+        assert_eq!(stats[1].failures, 2);
+        assert_eq!(stats[1].failure_streak, 2);
+        assert_eq!(stats[1].average, Some(Duration::from_secs(2)));
+    }
+}
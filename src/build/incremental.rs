@@ -0,0 +1,429 @@
+//! Which targets can build incrementally.
+//!
+//! CMake notices most changes to a target's config files by itself, but not
+//! a different set of modules or changed board and shield definitions; a
+//! build directory configured before those changed has to be rebuilt from
+//! scratch. After each successful build, the hashes of those inputs are
+//! saved in the target's build directory (`lfz-hashes.json`), so that only
+//! the targets whose own inputs changed build pristine next time.
+//!
+//! The hashes also cover the config files each target uses, which CMake
+//! does notice, and the module revisions and build image, to tell the
+//! targets that are up to date (`--changed-only`).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::target::BuildTarget;
+use crate::workspace::{collect_files, hash_file, is_incremental_safe, BuildHashes, WestLock};
+
+/// File in a build directory with the hashes it was last built from
+const HASH_FILE: &str = "lfz-hashes.json";
+
+/// Project directories with board and shield definitions
+const BOARD_DIRS: [&str; 2] = ["boards", "shields"];
+
+/// Hashes of the inputs a target's build directory depends on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetHashes {
+    /// west.yml, which decides the modules
+    pub west_yml: String,
+    /// The target's board, shield, snippets, CMake arguments, environment
+    /// and extra modules
+    pub target: String,
+    /// The files of `boards/` and `shields/` the target uses
+    pub board_files: String,
+    /// The files of the config directory the target uses
+    #[serde(default)]
+    pub config_files: String,
+    /// The resolved module revisions and the build image's digest
+    #[serde(default)]
+    pub sources: String,
+}
+
+/// Inputs shared by all targets of a build
+#[derive(Debug)]
+pub struct IncrementalInputs {
+    west_yml: String,
+    /// Files of `boards/` and `shields/` (path relative to the project, hash)
+    files: Vec<(String, String)>,
+    /// Files of the config directory (path relative to it, hash)
+    config_files: Vec<(String, String)>,
+    /// Boards and shields of all targets, to tell which files are whose
+    names: Vec<String>,
+    /// Hash of the module revisions and build image (`sources`)
+    sources: String,
+}
+
+impl IncrementalInputs {
+    /// Hash the project's board and shield definitions; the config files
+    /// come hashed with `hashes`
+    pub fn collect(
+        project_root: &Path,
+        hashes: &BuildHashes,
+        targets: &[BuildTarget],
+    ) -> Result<Self> {
+        let mut files = Vec::new();
+        for dir in BOARD_DIRS {
+            for path in collect_files(&project_root.join(dir))? {
+                let relative = path.strip_prefix(project_root).unwrap_or(&path);
+                files.push((relative.to_string_lossy().to_string(), hash_file(&path)?));
+            }
+        }
+        files.sort();
+
+        Ok(Self {
+            west_yml: hashes.west_yml.clone(),
+            files,
+            config_files: hashes
+                .config_files
+                .iter()
+                .map(|(path, hash)| (path.clone(), hash.clone()))
+                .collect(),
+            names: names(targets),
+            sources: String::new(),
+        })
+    }
+
+    /// Count the revisions the workspace's modules are at and the build
+    /// image among the inputs, which `west update` or a pull change
+    /// without touching the project
+    pub fn sources(mut self, lock: &WestLock, image_digest: &str) -> Self {
+        let mut hasher = Sha256::new();
+        for (name, project) in &lock.projects {
+            hasher.update(format!("{}={}\0", name, project.revision).as_bytes());
+        }
+        hasher.update(format!("image={}", image_digest).as_bytes());
+        self.sources = hex::encode(hasher.finalize());
+        self
+    }
+
+    /// The hashes of a target's inputs
+    pub fn hashes(&self, target: &BuildTarget) -> TargetHashes {
+        let mut hasher = Sha256::new();
+        let mut add = |field: &str, value: &str| {
+            hasher.update(field.as_bytes());
+            hasher.update(b"=");
+            hasher.update(value.as_bytes());
+            hasher.update(b"\0");
+        };
+        add("board", &target.board);
+        add("shield", target.shield.as_deref().unwrap_or_default());
+        add("snippet", target.snippet.as_deref().unwrap_or_default());
+        for arg in &target.cmake_args {
+            add("cmake", arg);
+        }
+        for (name, value) in &target.env {
+            add(name, value);
+        }
+        for module in &target.extra_modules {
+            add("module", &module.to_string_lossy());
+        }
+        let target_hash = hex::encode(hasher.finalize());
+
+        TargetHashes {
+            west_yml: self.west_yml.clone(),
+            target: target_hash,
+            board_files: self.hash_files(target, &self.files),
+            config_files: self.hash_files(target, &self.config_files),
+            sources: self.sources.clone(),
+        }
+    }
+
+    /// Hash of those of `files` the target uses
+    fn hash_files(&self, target: &BuildTarget, files: &[(String, String)]) -> String {
+        let mut hasher = Sha256::new();
+        for (path, hash) in files {
+            if uses_file(target, path, &self.names) {
+                hasher.update(path.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(hash.as_bytes());
+                hasher.update(b"\0");
+            }
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+impl TargetHashes {
+    fn path(workspace: &Path, target: &BuildTarget) -> PathBuf {
+        workspace.join(&target.build_dir).join(HASH_FILE)
+    }
+
+    /// The hashes a target's build directory was last built from
+    pub fn load(workspace: &Path, target: &BuildTarget) -> Option<Self> {
+        let contents = fs::read_to_string(Self::path(workspace, target)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Record that a target's build directory was built from these inputs
+    pub fn save(&self, workspace: &Path, target: &BuildTarget) -> Result<()> {
+        let path = Self::path(workspace, target);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether the target's build directory was last built successfully from
+    /// these very inputs
+    pub fn up_to_date(&self, workspace: &Path, target: &BuildTarget) -> bool {
+        Self::load(workspace, target).as_ref() == Some(self)
+    }
+
+    /// Why a target has to build pristine, or `None` if its build directory
+    /// can be built incrementally. Build directories from before per-target
+    /// hashes fall back to the workspace-wide `hashes`.
+    pub fn pristine_reason(
+        &self,
+        workspace: &Path,
+        target: &BuildTarget,
+        hashes: &BuildHashes,
+    ) -> Option<&'static str> {
+        if !workspace.join(&target.build_dir).is_dir() {
+            return Some("first build");
+        }
+        let Some(stored) = Self::load(workspace, target) else {
+            return (!is_incremental_safe(workspace, hashes)).then_some("configs changed");
+        };
+
+        if stored.west_yml != self.west_yml {
+            Some("west.yml changed")
+        } else if stored.target != self.target {
+            Some("build config changed")
+        } else if stored.board_files != self.board_files {
+            Some("board or shield files changed")
+        } else {
+            None
+        }
+    }
+}
+
+/// Boards and shields of a set of targets
+pub(crate) fn names(targets: &[BuildTarget]) -> Vec<String> {
+    let mut names: Vec<String> = targets
+        .iter()
+        .flat_map(|t| std::iter::once(board_name(t)).chain(shields(t)))
+        .map(String::from)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// The board's name without qualifiers (`nice_nano` for `nice_nano/nrf52840`)
+fn board_name(target: &BuildTarget) -> &str {
+    target.board.split('/').next().unwrap_or(&target.board)
+}
+
+/// The shields of a target (`shield` may list several)
+fn shields(target: &BuildTarget) -> impl Iterator<Item = &str> {
+    target
+        .shield
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+}
+
+/// Whether a target's build may read a file of the project. The most
+/// specific part of its path named after a board or shield of the build
+/// (`corne.keymap`, `corne_left.conf`, `shields/corne/Kconfig.shield`)
+/// decides which targets it belongs to; files named after none belong to
+/// every target.
+pub(crate) fn uses_file(target: &BuildTarget, path: &str, names: &[String]) -> bool {
+    let mut parts = path.rsplit('/');
+    let file_name = parts.next().unwrap_or(path);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+
+    for part in std::iter::once(stem).chain(parts) {
+        if names.iter().any(|name| named_after(part, name)) {
+            return named_after(part, board_name(target))
+                || shields(target).any(|shield| named_after(part, shield));
+        }
+    }
+    true
+}
+
+/// Whether `part` of a path names `name` or the family it belongs to
+/// (`corne` for `corne_left`)
+fn named_after(part: &str, name: &str) -> bool {
+    !part.is_empty()
+        && (name == part
+            || name
+                .strip_prefix(part)
+                .is_some_and(|rest| rest.starts_with('_')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn target(board: &str, shield: &str) -> BuildTarget {
+        BuildTarget::from_args(board.to_string(), Some(shield.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_uses_file() {
+        let left = target("nice_nano_v2", "corne_left nice_view");
+        let right = target("nice_nano_v2", "corne_right");
+        let names = names(&[left.clone(), right.clone()]);
+
+        assert!(uses_file(&left, "corne.keymap", &names));
+        assert!(uses_file(&left, "corne_left.conf", &names));
+        assert!(!uses_file(&right, "corne_left.conf", &names));
+        assert!(uses_file(&left, "nice_view.conf", &names));
+        assert!(!uses_file(&right, "nice_view.conf", &names));
+        assert!(uses_file(&right, "nice_nano_v2.conf", &names));
+        assert!(uses_file(&right, "west.yml", &names));
+        assert!(uses_file(&right, "includes/behaviors.dtsi", &names));
+        assert!(uses_file(&right, "shields/corne/Kconfig.shield", &names));
+        assert!(!uses_file(
+            &right,
+            "shields/corne/corne_left.overlay",
+            &names
+        ));
+    }
+
+    #[test]
+    fn test_pristine_reason() {
+        let project = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let shields_dir = project.path().join("shields/corne");
+        fs::create_dir_all(&shields_dir).unwrap();
+        fs::write(shields_dir.join("corne_left.overlay"), "a").unwrap();
+        fs::write(shields_dir.join("corne_right.overlay"), "a").unwrap();
+
+        let targets = [
+            target("nice_nano_v2", "corne_left"),
+            target("nice_nano_v2", "corne_right"),
+        ];
+        let hashes = BuildHashes {
+            build_yaml: "a".to_string(),
+            west_yml: "a".to_string(),
+            boards_dir: None,
+            shields_dir: None,
+            config_files: Default::default(),
+        };
+        let collect = || IncrementalInputs::collect(project.path(), &hashes, &targets).unwrap();
+
+        let before = collect();
+        for target in &targets {
+            assert_eq!(
+                before
+                    .hashes(target)
+                    .pristine_reason(workspace.path(), target, &hashes),
+                Some("first build")
+            );
+            fs::create_dir_all(workspace.path().join(&target.build_dir)).unwrap();
+            before
+                .hashes(target)
+                .save(workspace.path(), target)
+                .unwrap();
+        }
+
+        fs::write(shields_dir.join("corne_left.overlay"), "b").unwrap();
+        let after = collect();
+        let reasons: Vec<_> = targets
+            .iter()
+            .map(|t| {
+                after
+                    .hashes(t)
+                    .pristine_reason(workspace.path(), t, &hashes)
+            })
+            .collect();
+        assert_eq!(reasons, [Some("board or shield files changed"), None]);
+
+        let mut changed = targets[1].clone();
+        changed.cmake_args.push("-DCONFIG_ZMK_SLEEP=y".to_string());
+        assert_eq!(
+            after
+                .hashes(&changed)
+                .pristine_reason(workspace.path(), &changed, &hashes),
+            Some("build config changed")
+        );
+    }
+
+    #[test]
+    fn test_up_to_date() {
+        let project = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let targets = [
+            target("nice_nano_v2", "corne_left"),
+            target("nice_nano_v2", "corne_right"),
+        ];
+        let hashes = |left_conf: &str| BuildHashes {
+            build_yaml: "a".to_string(),
+            west_yml: "a".to_string(),
+            boards_dir: None,
+            shields_dir: None,
+            config_files: [
+                ("corne.keymap".to_string(), "a".to_string()),
+                ("corne_left.conf".to_string(), left_conf.to_string()),
+            ]
+            .into(),
+        };
+
+        let before = IncrementalInputs::collect(project.path(), &hashes("a"), &targets).unwrap();
+        assert!(!before
+            .hashes(&targets[0])
+            .up_to_date(workspace.path(), &targets[0]));
+        for target in &targets {
+            fs::create_dir_all(workspace.path().join(&target.build_dir)).unwrap();
+            before
+                .hashes(target)
+                .save(workspace.path(), target)
+                .unwrap();
+        }
+
+        let after = IncrementalInputs::collect(project.path(), &hashes("b"), &targets).unwrap();
+        let left = after.hashes(&targets[0]);
+        assert!(!left.up_to_date(workspace.path(), &targets[0]));
+        assert_eq!(
+            left.pristine_reason(workspace.path(), &targets[0], &hashes("b")),
+            None
+        );
+        assert!(after
+            .hashes(&targets[1])
+            .up_to_date(workspace.path(), &targets[1]));
+    }
+
+    #[test]
+    fn test_sources_up_to_date() {
+        let project = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let target = target("nice_nano_v2", "corne_left");
+        let hashes = BuildHashes {
+            build_yaml: "a".to_string(),
+            west_yml: "a".to_string(),
+            boards_dir: None,
+            shields_dir: None,
+            config_files: Default::default(),
+        };
+        let lock: WestLock =
+            serde_yaml::from_str("projects:\n  zmk:\n    path: zmk\n    revision: aaa\n").unwrap();
+        let inputs = |lock: &WestLock, image: &str| {
+            IncrementalInputs::collect(project.path(), &hashes, std::slice::from_ref(&target))
+                .unwrap()
+                .sources(lock, image)
+                .hashes(&target)
+        };
+        fs::create_dir_all(workspace.path().join(&target.build_dir)).unwrap();
+        inputs(&lock, "sha256:1")
+            .save(workspace.path(), &target)
+            .unwrap();
+        assert!(inputs(&lock, "sha256:1").up_to_date(workspace.path(), &target));
+        assert!(!inputs(&lock, "sha256:2").up_to_date(workspace.path(), &target));
+
+        let updated: WestLock =
+            serde_yaml::from_str("projects:\n  zmk:\n    path: zmk\n    revision: bbb\n").unwrap();
+        let moved = inputs(&updated, "sha256:1");
+        assert!(!moved.up_to_date(workspace.path(), &target));
+        // Modules moving don't make the build directory unusable
+        assert_eq!(
+            moved.pristine_reason(workspace.path(), &target, &hashes),
+            None
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! The resolved Kconfig of a target (`zephyr/.config` in a build
+//! directory): every symbol's final value after the board defaults, the
+//! shield's and the user's `.conf` files are applied.
+//!
+//! ```text
+//! CONFIG_ZMK_SLEEP=y
+//! CONFIG_ZMK_IDLE_SLEEP_TIMEOUT=900000
+//! CONFIG_ZMK_KEYBOARD_NAME="Corne"
+//! # CONFIG_ZMK_USB_LOGGING is not set
+//! ```
+//!
+//! Each build keeps a copy of its targets' `.config` in the `kconfig/`
+//! directory of the output directory, so that runs can be compared later.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::target::BuildTarget;
+
+/// Value of symbols that are not set
+pub const NOT_SET: &str = "n";
+
+/// Directory of the output directory holding each target's `.config`
+pub const OUTPUT_DIR: &str = "kconfig";
+
+/// A symbol whose value differs between two configurations; missing where
+/// the symbol doesn't exist in that configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The `.config` generated by the last configure of a target (for
+/// sysbuild, the zmk image's)
+pub fn find(workspace: &Path, target: &BuildTarget) -> Option<PathBuf> {
+    let build_dir = workspace.join(&target.build_dir);
+    [
+        build_dir.join("zephyr/.config"),
+        build_dir.join("zmk/zephyr/.config"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Load the `.config` generated by the last configure of a target
+pub fn load(workspace: &Path, target: &BuildTarget) -> Result<String> {
+    let path = find(workspace, target)
+        .with_context(|| format!("No .config in {}'s build directory", target.artifact_name))?;
+    fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Path of a target's `.config` in an output directory
+pub fn output_path(output_dir: &Path, artifact_name: &str) -> PathBuf {
+    output_dir
+        .join(OUTPUT_DIR)
+        .join(format!("{}.config", artifact_name))
+}
+
+/// Copy a built target's `.config` into the output directory
+pub fn collect(workspace: &Path, target: &BuildTarget, output_dir: &Path) -> Result<()> {
+    let source = find(workspace, target)
+        .with_context(|| format!("No .config in {}'s build directory", target.artifact_name))?;
+    let dest = output_path(output_dir, &target.artifact_name);
+    fs::create_dir_all(output_dir.join(OUTPUT_DIR))
+        .with_context(|| format!("Failed to create {}", output_dir.join(OUTPUT_DIR).display()))?;
+    fs::copy(&source, &dest)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), dest.display()))?;
+    Ok(())
+}
+
+/// The `.config` of each target collected in an output directory, by
+/// artifact name
+pub fn load_output(output_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let dir = output_dir.join(OUTPUT_DIR);
+    if !dir.is_dir() {
+        anyhow::bail!(
+            "No Kconfig recorded in {}; rebuild to record it",
+            output_dir.display()
+        );
+    }
+
+    let mut configs = BTreeMap::new();
+    for entry in fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .flatten()
+    {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "config") {
+            let target = path.file_stem().unwrap_or_default().to_string_lossy();
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            configs.insert(target.to_string(), contents);
+        }
+    }
+    Ok(configs)
+}
+
+/// Symbols (with their `CONFIG_` prefix) and values, by name. Symbols that
+/// are not set have the value `n`; string values keep their quotes.
+pub fn parse(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            if let Some(unset) = line.strip_prefix("# ") {
+                let name = unset.strip_suffix(" is not set")?;
+                return Some((name.to_string(), NOT_SET.to_string()));
+            }
+            let (name, value) = line.split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .filter(|(name, _)| name.starts_with("CONFIG_"))
+        .collect()
+}
+
+/// Whether a symbol matches a search: a case-insensitive substring of its
+/// name, with or without the `CONFIG_` prefix
+pub fn matches(name: &str, pattern: &str) -> bool {
+    name.to_ascii_lowercase()
+        .contains(&pattern.to_ascii_lowercase())
+}
+
+/// The symbols whose values differ, by name
+pub fn diff(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<Difference> {
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| Difference {
+            name: name.clone(),
+            before: before.get(name).cloned(),
+            after: after.get(name).cloned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = parse(
+            "\
+#
+# Automatically generated file; DO NOT EDIT.
+#
+CONFIG_ZMK_SLEEP=y
+CONFIG_ZMK_KEYBOARD_NAME=\"Corne\"
+# CONFIG_ZMK_USB_LOGGING is not set
+CONFIG_ZMK_IDLE_SLEEP_TIMEOUT=900000
+",
+        );
+
+        assert_eq!(config.len(), 4);
+        assert_eq!(config["CONFIG_ZMK_SLEEP"], "y");
+        assert_eq!(config["CONFIG_ZMK_KEYBOARD_NAME"], "\"Corne\"");
+        assert_eq!(config["CONFIG_ZMK_USB_LOGGING"], NOT_SET);
+
+        assert!(matches("CONFIG_ZMK_SLEEP", "zmk_sleep"));
+        assert!(matches("CONFIG_ZMK_SLEEP", "CONFIG_ZMK"));
+        assert!(!matches("CONFIG_ZMK_SLEEP", "IDLE"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let left = parse("CONFIG_ZMK_SLEEP=y\nCONFIG_ZMK_SPLIT_ROLE_CENTRAL=y\nCONFIG_ZMK_BLE=y\n");
+        let right = parse(
+            "CONFIG_ZMK_SLEEP=y\n# CONFIG_ZMK_SPLIT_ROLE_CENTRAL is not set\nCONFIG_ZMK_BLE=y\nCONFIG_ZMK_SPLIT_BLE_PERIPHERAL=y\n",
+        );
+
+        let differences = diff(&left, &right);
+        assert_eq!(
+            differences,
+            [
+                Difference {
+                    name: "CONFIG_ZMK_SPLIT_BLE_PERIPHERAL".to_string(),
+                    before: None,
+                    after: Some("y".to_string()),
+                },
+                Difference {
+                    name: "CONFIG_ZMK_SPLIT_ROLE_CENTRAL".to_string(),
+                    before: Some("y".to_string()),
+                    after: Some(NOT_SET.to_string()),
+                },
+            ]
+        );
+        assert!(diff(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn test_collect_and_load_output() {
+        let workspace = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let mut target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+        target.build_dir = "build/corne_left".to_string();
+        target.artifact_name = "corne_left".to_string();
+
+        assert!(collect(workspace.path(), &target, output.path()).is_err());
+        assert!(load_output(output.path()).is_err());
+
+        let zephyr = workspace.path().join("build/corne_left/zephyr");
+        fs::create_dir_all(&zephyr).unwrap();
+        fs::write(zephyr.join(".config"), "CONFIG_ZMK_SLEEP=y\n").unwrap();
+        collect(workspace.path(), &target, output.path()).unwrap();
+
+        let configs = load_output(output.path()).unwrap();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs["corne_left"], "CONFIG_ZMK_SLEEP=y\n");
+    }
+}
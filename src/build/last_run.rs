@@ -0,0 +1,119 @@
+//! The outcome of the most recent build run in a workspace.
+//!
+//! Saved after every run so follow-up commands can act on the targets that
+//! failed without rebuilding the whole matrix.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::build::orchestrator::BuildResult;
+
+/// File name for storing the last run in the workspace
+const LAST_RUN_FILE: &str = ".lfz_last_run.json";
+
+/// Targets of the last run, by outcome
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LastRun {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+    /// Targets skipped or stopped by `--fail-fast`
+    #[serde(default)]
+    pub cancelled: Vec<String>,
+}
+
+impl LastRun {
+    pub fn from_results(results: &[BuildResult]) -> Self {
+        let mut run = Self::default();
+        for result in results {
+            let list = if result.success {
+                &mut run.succeeded
+            } else if result.cancelled {
+                &mut run.cancelled
+            } else {
+                &mut run.failed
+            };
+            list.push(result.target_name.clone());
+        }
+        run
+    }
+
+    /// Targets that failed or were cancelled, to build again
+    pub fn unfinished(&self) -> impl Iterator<Item = &str> {
+        self.failed
+            .iter()
+            .chain(&self.cancelled)
+            .map(String::as_str)
+    }
+
+    /// Load the last run of a workspace
+    pub fn load(workspace: &Path) -> Result<Option<Self>> {
+        let path = workspace.join(LAST_RUN_FILE);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let run: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(run))
+    }
+
+    /// Save this run to a workspace
+    pub fn save(&self, workspace: &Path) -> Result<()> {
+        let path = workspace.join(LAST_RUN_FILE);
+
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize last run")?;
+
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        assert_eq!(LastRun::load(dir.path()).unwrap(), None);
+
+        let results = vec![
+            BuildResult {
+                target_name: "corne_left".to_string(),
+                success: true,
+                ..Default::default()
+            },
+            BuildResult {
+                target_name: "corne_right".to_string(),
+                ..Default::default()
+            },
+            BuildResult {
+                target_name: "settings_reset".to_string(),
+                cancelled: true,
+                ..Default::default()
+            },
+        ];
+        let run = LastRun::from_results(&results);
+        assert_eq!(run.succeeded, ["corne_left"]);
+        assert_eq!(run.failed, ["corne_right"]);
+        assert_eq!(run.cancelled, ["settings_reset"]);
+        assert_eq!(
+            run.unfinished().collect::<Vec<_>>(),
+            ["corne_right", "settings_reset"]
+        );
+
+        run.save(dir.path()).unwrap();
+        assert_eq!(LastRun::load(dir.path()).unwrap(), Some(run));
+    }
+}
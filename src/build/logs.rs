@@ -0,0 +1,189 @@
+//! Per-target build logs.
+//!
+//! Every build writes its complete output to
+//! `<cache>/logs/<workspace>/<target>-<timestamp>.log`, whatever the
+//! console shows, so `lfz logs` can show a failure from a quiet or
+//! parallel run after the fact.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::paths;
+
+/// Logs kept per target; older ones are removed when a new one starts
+const KEEP_PER_TARGET: usize = 10;
+
+/// Length of the `-20250101T120000Z.log` suffix of a log file name
+const SUFFIX_LEN: usize = "-20250101T120000Z.log".len();
+
+/// The log of one target's build, shared by its output reader threads
+#[derive(Debug)]
+pub struct BuildLog {
+    file: Mutex<Option<File>>,
+}
+
+impl BuildLog {
+    /// Start a new log for a target. Logging is best effort: if the file
+    /// can't be created, lines are dropped and the build carries on.
+    pub fn create(workspace: &Path, target: &str) -> Self {
+        let file = log_dir(workspace).ok().and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            let name = format!("{}-{}.log", file_stem(target), timestamp(SystemTime::now()));
+            let file = File::create(dir.join(name)).ok()?;
+            prune(&dir, target);
+            Some(file)
+        });
+        Self {
+            file: Mutex::new(file),
+        }
+    }
+
+    /// Append a line
+    pub fn line(&self, line: &str) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+}
+
+/// Directory holding the logs of a workspace's builds
+pub fn log_dir(workspace: &Path) -> Result<PathBuf> {
+    let name = workspace
+        .file_name()
+        .context("Workspace path has no name")?;
+    Ok(paths::logs_dir()?.join(name))
+}
+
+/// Logs in a directory, oldest first, optionally only those of one target
+pub fn list(dir: &Path, target: Option<&str>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.ends_with(".log") && target.is_none_or(|t| target_of(&name) == Some(&file_stem(t)))
+        })
+        .collect();
+    // Timestamps sort lexically; the name breaks ties between targets
+    logs.sort_by_key(|p| {
+        let name = p
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let split = name.len().saturating_sub(SUFFIX_LEN);
+        (name[split..].to_string(), name)
+    });
+    logs
+}
+
+/// Logs of every workspace's builds last written before `cutoff`
+pub fn written_before(logs_root: &Path, cutoff: SystemTime) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(logs_root) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .flat_map(|dir| list(&dir.path(), None))
+        .filter(|log| {
+            fs::metadata(log)
+                .and_then(|m| m.modified())
+                .is_ok_and(|written| written < cutoff)
+        })
+        .collect()
+}
+
+/// The target a log file name belongs to
+pub fn target_of(file_name: &str) -> Option<&str> {
+    file_name.get(..file_name.len().checked_sub(SUFFIX_LEN)?)
+}
+
+/// Remove all but the newest logs of a target
+fn prune(dir: &Path, target: &str) {
+    let logs = list(dir, Some(target));
+    for old in logs.iter().rev().skip(KEEP_PER_TARGET) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+/// Artifact names may contain `/`; logs live in one flat directory
+fn file_stem(target: &str) -> String {
+    target.replace('/', "_")
+}
+
+/// `20250101T120000Z`
+fn timestamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time)
+        .to_string()
+        .chars()
+        .filter(|c| *c != '-' && *c != ':')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_list_and_prune() {
+        let dir = tempdir().unwrap();
+        for i in 0..12 {
+            let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + i);
+            let name = format!("corne_left-nice_nano_v2-zmk-{}.log", timestamp(time));
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        fs::write(
+            dir.path()
+                .join("corne_right-nice_nano_v2-zmk-20231114T221400Z.log"),
+            "",
+        )
+        .unwrap();
+
+        let logs = list(dir.path(), None);
+        assert_eq!(logs.len(), 13);
+        assert!(logs
+            .last()
+            .unwrap()
+            .ends_with("corne_right-nice_nano_v2-zmk-20231114T221400Z.log"));
+
+        prune(dir.path(), "corne_left-nice_nano_v2-zmk");
+        let left = list(dir.path(), Some("corne_left-nice_nano_v2-zmk"));
+        assert_eq!(left.len(), KEEP_PER_TARGET);
+        assert!(left[0].ends_with("corne_left-nice_nano_v2-zmk-20231114T221322Z.log"));
+        assert_eq!(
+            target_of("corne_left-nice_nano_v2-zmk-20231114T221322Z.log"),
+            Some("corne_left-nice_nano_v2-zmk")
+        );
+    }
+
+    #[test]
+    fn test_written_before() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join("abc123");
+        fs::create_dir(&dir).unwrap();
+        let old = dir.join("corne_left-nice_nano_v2-zmk-20231114T221400Z.log");
+        let new = dir.join("corne_left-nice_nano_v2-zmk-20251114T221400Z.log");
+        fs::write(&old, "").unwrap();
+        fs::write(&new, "").unwrap();
+        let week_ago = SystemTime::now() - Duration::from_secs(7 * 86400);
+        File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(week_ago - Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(written_before(root.path(), week_ago), [old]);
+    }
+}
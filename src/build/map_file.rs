@@ -0,0 +1,263 @@
+//! GNU ld map files (`zephyr/zmk.map` in a build directory): the memory
+//! regions and where every input section of the firmware ended up.
+//!
+//! ```text
+//! Memory Configuration
+//!
+//! Name             Origin             Length             Attributes
+//! FLASH            0x0000000000026000 0x00000000000c5000 xr
+//!
+//! Linker script and memory map
+//!
+//! text            0x0000000000026100    0x1d0f4
+//!  .text.main     0x0000000000026100       0x48 app/libapp.a(main.c.obj)
+//!                 0x0000000000026100                main
+//! ```
+//!
+//! ZMK compiles with `-ffunction-sections -fdata-sections`, so most input
+//! sections hold a single function or variable, named after it.
+
+use std::path::{Path, PathBuf};
+
+use super::target::BuildTarget;
+
+/// A memory region from the map's memory configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+/// An output section of the firmware (e.g. `text`, `rodata`, `bss`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+/// An input section placed in the firmware, named after the symbol it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Output section it was placed in
+    pub section: String,
+    pub address: u64,
+    pub size: u64,
+    /// Object file it came from, e.g. `app/libapp.a(main.c.obj)`
+    pub object: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MapFile {
+    pub regions: Vec<Region>,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl MapFile {
+    /// The region an address lies in
+    pub fn region_of(&self, address: u64) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|r| address >= r.origin && address - r.origin < r.length)
+    }
+}
+
+/// The map file of a target's last build in a workspace (the zmk image's
+/// for sysbuild)
+pub fn find(workspace: &Path, target: &BuildTarget) -> Option<PathBuf> {
+    ["zephyr", "zmk/zephyr"]
+        .iter()
+        .flat_map(|dir| {
+            ["zmk.map", "zephyr.map"]
+                .iter()
+                .map(move |file| workspace.join(&target.build_dir).join(dir).join(file))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Parse the contents of a map file; lines that don't fit are skipped
+pub fn parse(contents: &str) -> MapFile {
+    let mut map = MapFile::default();
+    let mut lines = contents.lines();
+
+    for line in lines.by_ref() {
+        if line.starts_with("Memory Configuration") {
+            break;
+        }
+    }
+    for line in lines.by_ref() {
+        if line.starts_with("Linker script and memory map") {
+            break;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let [name, origin, length, ..] = fields[..] {
+            if let (Some(origin), Some(length), false) =
+                (hex(origin), hex(length), name == "*default*")
+            {
+                map.regions.push(Region {
+                    name: name.to_string(),
+                    origin,
+                    length,
+                });
+            }
+        }
+    }
+
+    // Names too long for their column are on a line of their own
+    let mut pending: Option<(bool, String)> = None;
+    let mut section = String::new();
+    let mut unnamed: Option<usize> = None;
+    for line in lines {
+        let output_section = !line.starts_with(' ');
+        let input_section = line.starts_with(' ') && !line.starts_with("  ");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let (output_section, name, rest) = match (pending.take(), &fields[..]) {
+            (Some((output, name)), rest) if rest.first().and_then(|f| hex(f)).is_some() => {
+                (output, name, rest)
+            }
+            (_, [name]) if output_section || input_section => {
+                pending = Some((output_section, name.to_string()));
+                continue;
+            }
+            (_, [name, rest @ ..]) if output_section || input_section => {
+                (output_section, name.to_string(), rest)
+            }
+            (_, [address, symbol]) => {
+                // A symbol defined in the input section above
+                if let (Some(index), Some(_)) = (unnamed.take(), hex(address)) {
+                    map.symbols[index].name = symbol.to_string();
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let (Some(address), Some(size)) = (
+            rest.first().and_then(|f| hex(f)),
+            rest.get(1).and_then(|f| hex(f)),
+        ) else {
+            continue;
+        };
+
+        if output_section {
+            section = name.clone();
+            map.sections.push(Section {
+                name,
+                address,
+                size,
+            });
+        } else if size > 0 && !name.starts_with('*') {
+            let object = rest.get(2..).unwrap_or_default().join(" ");
+            unnamed = None;
+            let symbol = match symbol_name(&name) {
+                Some(symbol) => symbol.to_string(),
+                None => {
+                    unnamed = Some(map.symbols.len());
+                    format!("{} ({})", name, object_name(&object))
+                }
+            };
+            map.symbols.push(Symbol {
+                name: symbol,
+                section: section.clone(),
+                address,
+                size,
+                object,
+            });
+        }
+    }
+    map
+}
+
+/// The function or variable an input section holds, e.g. `main` for
+/// `.text.main`
+fn symbol_name(section: &str) -> Option<&str> {
+    let (_, symbol) = section.strip_prefix('.')?.split_once('.')?;
+    Some(symbol).filter(|s| !s.is_empty())
+}
+
+/// The file name of an object, e.g. `main.c.obj` for `app/libapp.a(main.c.obj)`
+fn object_name(object: &str) -> &str {
+    match object.rsplit_once('(') {
+        Some((_, member)) => member.trim_end_matches(')'),
+        None => object.rsplit('/').next().unwrap_or(object),
+    }
+}
+
+fn hex(field: &str) -> Option<u64> {
+    u64::from_str_radix(field.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "\
+Archive member included to satisfy reference by file (symbol)
+
+Memory Configuration
+
+Name             Origin             Length             Attributes
+FLASH            0x0000000000026000 0x00000000000c5000 xr
+RAM              0x0000000020000000 0x0000000000040000 xw
+*default*        0x0000000000000000 0xffffffffffffffff
+
+Linker script and memory map
+
+text            0x0000000000026100      0x1d0
+ *(.text)
+ .text          0x0000000000026100       0x40 zephyr/kernel/libkernel.a(sched.c.obj)
+                0x0000000000026100                z_reschedule
+ .text.main     0x0000000000026140       0x48 app/libapp.a(main.c.obj)
+                0x0000000000026140                main
+ .text.zmk_keymap_apply_position_state
+                0x0000000000026188      0x100 zmk/libzmk.a(keymap.c.obj)
+ *fill*         0x0000000000026288        0x8
+bss             0x0000000020000000      0x200
+ .bss.zmk_keymap
+                0x0000000020000000      0x200 zmk/libzmk.a(keymap.c.obj)
+ .bss.empty     0x0000000020000200        0x0 app/libapp.a(main.c.obj)
+.debug_info     0x0000000000000000    0x1a2b3
+";
+
+    #[test]
+    fn test_parse() {
+        let map = parse(MAP);
+        assert_eq!(map.regions.len(), 2);
+        assert_eq!(map.regions[1].length, 0x40000);
+
+        let sections: Vec<_> = map.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(sections, ["text", "bss", ".debug_info"]);
+
+        let symbols: Vec<_> = map
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.section.as_str(), s.size))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("z_reschedule", "text", 0x40),
+                ("main", "text", 0x48),
+                ("zmk_keymap_apply_position_state", "text", 0x100),
+                ("zmk_keymap", "bss", 0x200),
+            ]
+        );
+        assert_eq!(map.symbols[1].object, "app/libapp.a(main.c.obj)");
+        assert_eq!(
+            map.region_of(map.symbols[3].address)
+                .map(|r| r.name.as_str()),
+            Some("RAM")
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(symbol_name(".rodata.keymap.str1"), Some("keymap.str1"));
+        assert_eq!(symbol_name(".text"), None);
+        assert_eq!(object_name("app/libapp.a(main.c.obj)"), "main.c.obj");
+        assert_eq!(object_name("zephyr/main.c.obj"), "main.c.obj");
+    }
+}
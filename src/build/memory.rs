@@ -0,0 +1,244 @@
+//! Flash and RAM usage, from the memory region table the linker prints at
+//! the end of a Zephyr build:
+//!
+//! ```text
+//! Memory region         Used Size  Region Size  %age Used
+//!            FLASH:      173512 B       788 KB     22.02%
+//!              RAM:       43152 B       256 KB     16.46%
+//!         IDT_LIST:          0 GB        32 KB      0.00%
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Regions worth showing; the others are linker bookkeeping
+const REGIONS: [&str; 2] = ["FLASH", "RAM"];
+
+/// The last report of a build directory, for builds where ninja doesn't
+/// relink (and so prints no table)
+const REPORT_FILE: &str = "lfz-memory.json";
+
+/// Usage of a memory region
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub name: String,
+    /// Bytes used
+    pub used: u64,
+    /// Bytes available
+    pub size: u64,
+}
+
+impl MemoryRegion {
+    /// Percentage of the region in use
+    pub fn percent(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.used as f64 * 100.0 / self.size as f64
+    }
+}
+
+/// Highest percentage of flash and RAM a target may use before its build
+/// counts as failed (`max-flash`/`max-ram` in build.yaml or `[memory]` in
+/// lfz.toml)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MemoryLimits {
+    pub max_flash: Option<u8>,
+    pub max_ram: Option<u8>,
+}
+
+impl MemoryLimits {
+    /// These limits, with the unset ones taken from `defaults`
+    pub fn or(self, defaults: Self) -> Self {
+        Self {
+            max_flash: self.max_flash.or(defaults.max_flash),
+            max_ram: self.max_ram.or(defaults.max_ram),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.max_flash.is_some() || self.max_ram.is_some()
+    }
+
+    /// Why the regions are over the limits (or can't be checked against
+    /// them), if they are
+    pub fn check(&self, regions: &[MemoryRegion]) -> Option<String> {
+        if self.is_set() && regions.is_empty() {
+            return Some(
+                "Flash and RAM usage are unknown (the firmware wasn't linked in this build \
+                 and no earlier report exists), so the memory limits can't be checked. \
+                 Build it again with --pristine."
+                    .to_string(),
+            );
+        }
+        let over: Vec<String> = regions
+            .iter()
+            .filter_map(|region| {
+                let limit = match region.name.as_str() {
+                    "FLASH" => self.max_flash,
+                    "RAM" => self.max_ram,
+                    _ => None,
+                }?;
+                (region.percent() > f64::from(limit)).then(|| {
+                    format!(
+                        "{} is {:.1}% full ({} of {} bytes), over the {}% limit",
+                        region.name,
+                        region.percent(),
+                        region.used,
+                        region.size,
+                        limit
+                    )
+                })
+            })
+            .collect();
+        (!over.is_empty()).then(|| over.join("; "))
+    }
+}
+
+/// The FLASH and RAM rows of the last memory region table in a build's
+/// output (with sysbuild, bootloader images come first)
+pub fn parse(output: &str) -> Vec<MemoryRegion> {
+    let lines: Vec<&str> = output.lines().collect();
+    let Some(header) = lines
+        .iter()
+        .rposition(|line| line.trim_start().starts_with("Memory region"))
+    else {
+        return Vec::new();
+    };
+
+    lines[header + 1..]
+        .iter()
+        .map_while(|line| parse_row(line))
+        .filter(|region| REGIONS.contains(&region.name.as_str()))
+        .collect()
+}
+
+/// The usage of the firmware in a build directory: `parsed` from this
+/// build's output, which is stored for later builds, or else the stored
+/// report of the build that last linked it
+pub fn measured(build_dir: &Path, parsed: Vec<MemoryRegion>) -> Vec<MemoryRegion> {
+    let report = build_dir.join(REPORT_FILE);
+    if parsed.is_empty() {
+        return fs::read_to_string(report)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+    }
+    if let Ok(content) = serde_json::to_string(&parsed) {
+        // Only saves a later build from --pristine
+        let _ = fs::write(report, content);
+    }
+    parsed
+}
+
+/// A row like "FLASH:  173512 B  788 KB  22.02%"
+fn parse_row(line: &str) -> Option<MemoryRegion> {
+    let (name, rest) = line.trim().split_once(':')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let [used, used_unit, size, size_unit, ..] = fields[..] else {
+        return None;
+    };
+    Some(MemoryRegion {
+        name: name.trim().to_string(),
+        used: bytes(used, used_unit)?,
+        size: bytes(size, size_unit)?,
+    })
+}
+
+fn bytes(number: &str, unit: &str) -> Option<u64> {
+    let multiplier = match unit {
+        "B" => 1,
+        "KB" => 1024,
+        "MB" => 1024 * 1024,
+        "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some(number.parse::<u64>().ok()? * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "\
+[250/251] Linking C executable zephyr/zephyr.elf
+Memory region         Used Size  Region Size  %age Used
+           FLASH:      173512 B       788 KB     21.50%
+             RAM:       43152 B       256 KB     16.46%
+        IDT_LIST:          0 GB        32 KB      0.00%
+Generating files from zephyr/zephyr.elf for board: nice_nano_v2
+[251/251] Generating zephyr/zmk.uf2";
+
+        let regions = parse(output);
+        assert_eq!(
+            regions,
+            vec![
+                MemoryRegion {
+                    name: "FLASH".to_string(),
+                    used: 173512,
+                    size: 788 * 1024,
+                },
+                MemoryRegion {
+                    name: "RAM".to_string(),
+                    used: 43152,
+                    size: 256 * 1024,
+                },
+            ]
+        );
+        assert_eq!(format!("{:.1}", regions[1].percent()), "16.5");
+        assert!(parse("[1/1] Linking C executable zephyr/zephyr.elf").is_empty());
+    }
+
+    #[test]
+    fn test_limits() {
+        let regions = [
+            MemoryRegion {
+                name: "FLASH".to_string(),
+                used: 960,
+                size: 1000,
+            },
+            MemoryRegion {
+                name: "RAM".to_string(),
+                used: 500,
+                size: 1000,
+            },
+        ];
+        let defaults = MemoryLimits {
+            max_flash: Some(95),
+            max_ram: Some(90),
+        };
+        let limits = MemoryLimits {
+            max_flash: None,
+            max_ram: Some(40),
+        }
+        .or(defaults);
+
+        assert_eq!(
+            limits.check(&regions).unwrap(),
+            "FLASH is 96.0% full (960 of 1000 bytes), over the 95% limit; \
+             RAM is 50.0% full (500 of 1000 bytes), over the 40% limit"
+        );
+        assert_eq!(defaults.check(&regions[1..]), None);
+        assert_eq!(MemoryLimits::default().check(&regions), None);
+        assert!(defaults.check(&[]).is_some());
+        assert_eq!(MemoryLimits::default().check(&[]), None);
+    }
+
+    #[test]
+    fn test_measured() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(measured(tmp.path(), Vec::new()).is_empty());
+        let regions = vec![MemoryRegion {
+            name: "FLASH".to_string(),
+            used: 960,
+            size: 1000,
+        }];
+        assert_eq!(measured(tmp.path(), regions.clone()), regions);
+        // A build that didn't relink reports the last measurement
+        assert_eq!(measured(tmp.path(), Vec::new()), regions);
+    }
+}
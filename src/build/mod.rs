@@ -1,3 +1,23 @@
+pub mod artifact_cache;
 pub mod artifacts;
+pub mod compare;
+pub mod devicetree;
+pub mod diagnostics;
+pub mod durations;
+pub mod filter;
+pub mod hints;
+pub mod history;
+pub mod incremental;
+pub mod kconfig;
+pub mod last_run;
+pub mod logs;
+pub mod map_file;
+pub mod memory;
 pub mod orchestrator;
+pub mod phases;
+pub mod provenance;
+pub mod remote_cache;
+pub mod reproducible;
+pub mod sbom;
+pub mod size_report;
 pub mod target;
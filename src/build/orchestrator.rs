@@ -1,18 +1,27 @@
 use anyhow::Result;
 
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use super::artifacts::collect_artifact;
+use super::diagnostics::{self, Diagnostic, PathMap, Severity};
+use super::durations::Durations;
+use super::logs::BuildLog;
+use super::memory::{self, MemoryRegion};
+use super::phases::{parse_build_progress, PhaseTimer, Phases};
 use super::target::BuildTarget;
+use crate::cache::CompilerCache;
+use crate::config::lfz_toml::ProxyConfig;
 use crate::config::project::Project;
-use crate::container::{ContainerCommand, Runtime, DEFAULT_IMAGE};
+use crate::config::west_yml::ProjectOverride;
+use crate::container::{ContainerCommand, ResourceLimits, Runtime, DEFAULT_IMAGE};
+use crate::events::{self, Event};
 use crate::output::{self, BuildProgress, BuildState};
-use crate::paths;
 use crate::workspace::BuildHashes;
 
 /// Result of a single build
@@ -23,6 +32,32 @@ pub struct BuildResult {
     pub error: Option<String>,
     pub error_output: Option<String>,
     pub artifact_path: Option<PathBuf>,
+    /// Whether the build was killed for exceeding its timeout
+    pub timed_out: bool,
+    /// Whether the build was skipped or stopped because another target failed
+    /// (`--fail-fast`)
+    pub cancelled: bool,
+    /// Failed attempts before this one (`--retries`)
+    pub retries: u32,
+    /// How long the build took, retries included
+    pub duration: Duration,
+    /// Time spent configuring, compiling and linking
+    pub phases: Phases,
+    /// Flash and RAM usage reported by the linker
+    pub memory: Vec<MemoryRegion>,
+    /// Warnings in the build output
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl BuildResult {
+    /// A failed build of a target, with nothing else to report
+    fn failed(target_name: String, error: impl Into<String>) -> Self {
+        Self {
+            target_name,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
 }
 
 /// Orchestrates building multiple targets
@@ -31,11 +66,29 @@ pub struct BuildOrchestrator {
     workspace: PathBuf,
     project: Project,
     output_dir: PathBuf,
-    quiet: bool,
-    verbose: bool,
+    /// How much build detail to stream (the number of `-v`s; 0 shows progress only)
+    verbosity: u8,
     pristine: bool,
+    /// Run build containers without network access (`--offline`)
+    offline: bool,
+    /// Default per-target build timeout (`--timeout`)
+    timeout: Option<Duration>,
+    /// West projects replaced by local directories (`--override-module`)
+    overrides: Vec<ProjectOverride>,
+    /// Proxy settings from lfz.toml (host proxy variables apply regardless)
+    proxy: ProxyConfig,
     /// Current build hashes to save after successful builds
     build_hashes: BuildHashes,
+    /// Stop the remaining targets once one fails (`--fail-fast`)
+    fail_fast: bool,
+    /// Rebuild failed targets (`--retries`)
+    retry: Retry,
+    /// CPU and memory caps for each build container
+    limits: ResourceLimits,
+    /// Compiler cache the build containers use
+    compiler_cache: CompilerCache,
+    /// Image the build containers run
+    image: String,
 }
 
 impl BuildOrchestrator {
@@ -45,9 +98,12 @@ impl BuildOrchestrator {
         workspace: PathBuf,
         project: Project,
         output_dir: PathBuf,
-        quiet: bool,
-        verbose: bool,
+        verbosity: u8,
         pristine: bool,
+        offline: bool,
+        timeout: Option<Duration>,
+        overrides: Vec<ProjectOverride>,
+        proxy: ProxyConfig,
         build_hashes: BuildHashes,
     ) -> Self {
         Self {
@@ -55,28 +111,179 @@ impl BuildOrchestrator {
             workspace,
             project,
             output_dir,
-            quiet,
-            verbose,
+            verbosity,
             pristine,
+            offline,
+            timeout,
+            overrides,
+            proxy,
             build_hashes,
+            fail_fast: false,
+            retry: Retry::default(),
+            limits: ResourceLimits::default(),
+            compiler_cache: CompilerCache::default(),
+            image: DEFAULT_IMAGE.to_string(),
         }
     }
 
+    /// Stop the remaining targets once one fails
+    pub fn fail_fast(mut self, enabled: bool) -> Self {
+        self.fail_fast = enabled;
+        self
+    }
+
+    /// Rebuild a failed target up to `retries` times, pristine from the
+    /// second attempt on if `pristine` is set
+    pub fn retries(mut self, retries: u32, pristine: bool) -> Self {
+        self.retry = Retry { retries, pristine };
+        self
+    }
+
+    /// Cap the CPU and memory of each build container
+    pub fn limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Cache compilations with this compiler cache (ccache by default)
+    pub fn compiler_cache(mut self, compiler_cache: CompilerCache) -> Self {
+        self.compiler_cache = compiler_cache;
+        self
+    }
+
+    /// Build in this image instead of the default one
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Host directories behind the container paths in a target's build output
+    pub fn path_map(&self, target: &BuildTarget) -> PathMap {
+        let mut paths = PathMap::default().mount("/workspace/config", &self.project.config_dir);
+        let extra_modules = target.extra_modules_with(&self.project.extra_modules());
+        for (i, module) in extra_modules.iter().enumerate() {
+            paths = paths.mount(&format!("/workspace/module_{}", i), module);
+        }
+        for project in &self.overrides {
+            paths = paths.mount(
+                &format!("/workspace/{}", project.workspace_path),
+                &project.host_path,
+            );
+        }
+        paths
+    }
+
+    /// Run a build system target (e.g. `rom_report`) in a target's build
+    /// directory, configuring and building the target first if needed
+    pub fn run_west_target(&self, target: &BuildTarget, west_target: &str) -> Result<()> {
+        let west_args = target.west_target_args("/workspace/config", west_target);
+        self.run_west(target, &west_args, west_target)
+    }
+
+    /// Run CMake for a target without building it, which generates its
+    /// devicetree and Kconfig
+    pub fn configure(&self, target: &BuildTarget) -> Result<()> {
+        let west_args = target.west_configure_args("/workspace/config");
+        self.run_west(target, &west_args, "configure")
+    }
+
+    /// Run `west` with a target's mounts and environment, showing its
+    /// output only if it fails
+    fn run_west(&self, target: &BuildTarget, west_args: &[String], step: &str) -> Result<()> {
+        let west_cmd = format!("west {}", west_args.join(" "));
+        let cache_dir = self.compiler_cache.dir()?;
+
+        let mut container_cmd = ContainerCommand::new(self.runtime, &self.image)
+            .name(container_name(&format!(
+                "{}-{}",
+                target.artifact_name, step
+            )))
+            .proxy(&self.proxy)
+            .limits(&self.limits)
+            .offline(self.offline)
+            .mount(&self.workspace, "/workspace", false)
+            .mount(&self.project.config_dir, "/workspace/config", true)
+            .compiler_cache(&self.compiler_cache, &cache_dir)
+            .workdir("/workspace")
+            .env(
+                "CMAKE_PREFIX_PATH",
+                "/workspace/zephyr/share/zephyr-package/cmake",
+            );
+        for (key, value) in &target.env {
+            container_cmd = container_cmd.env(key, value);
+        }
+        let extra_modules = target.extra_modules_with(&self.project.extra_modules());
+        for (i, module_path) in extra_modules.iter().enumerate() {
+            container_cmd =
+                container_cmd.mount(module_path, format!("/workspace/module_{}", i), true);
+        }
+        for project in &self.overrides {
+            let container_path = format!("/workspace/{}", project.workspace_path);
+            container_cmd = container_cmd.mount(&project.host_path, &container_path, true);
+        }
+
+        let script = if extra_modules.is_empty() {
+            west_cmd
+        } else {
+            let modules_arg = (0..extra_modules.len())
+                .map(|i| format!("/workspace/module_{}", i))
+                .collect::<Vec<_>>()
+                .join(";");
+            format!("{} -DZMK_EXTRA_MODULES=\"{}\"", west_cmd, modules_arg)
+        };
+        if self.verbosity > 0 {
+            output::command(&script);
+        }
+
+        let result = container_cmd.shell_command(&script).build().output()?;
+        if !result.status.success() {
+            let output = format!(
+                "{}{}",
+                String::from_utf8_lossy(&result.stdout),
+                String::from_utf8_lossy(&result.stderr)
+            );
+            output::build_error_output(&target.artifact_name, &output);
+            anyhow::bail!(
+                "{} failed for {} with exit code: {:?}",
+                step,
+                target.artifact_name,
+                result.status.code()
+            );
+        }
+        Ok(())
+    }
+
     /// Build targets sequentially
     pub fn build_sequential(&self, targets: &[BuildTarget]) -> Result<Vec<BuildResult>> {
         let mut results = Vec::new();
 
         for target in targets {
-            let result = if self.verbose {
-                self.build_target_verbose(target)
-            } else {
-                self.build_target(target)
-            };
+            if self.fail_fast && results.iter().any(|r: &BuildResult| !r.success) {
+                results.push(FailFast::cancelled(target));
+                continue;
+            }
+            let result = report_events(&target.artifact_name, || {
+                self.retry.run(
+                    self.pristine || target.pristine,
+                    || false,
+                    |attempt, pristine| {
+                        if attempt > 0 {
+                            output::warning(&self.retry.message(&target.artifact_name, attempt));
+                        }
+                        if self.verbosity > 0 {
+                            self.build_target_verbose(target, pristine)
+                        } else {
+                            self.build_target(target, pristine)
+                        }
+                    },
+                )
+            });
             results.push(result);
         }
 
         // Save hashes if all builds succeeded (enables incremental builds next time)
         self.save_hashes_if_all_succeeded(&results);
+        self.record_durations(targets, &results);
 
         Ok(results)
     }
@@ -88,27 +295,38 @@ impl BuildOrchestrator {
         max_jobs: usize,
     ) -> Result<Vec<BuildResult>> {
         // Use verbose parallel mode if verbose flag is set
-        if self.verbose {
+        if self.verbosity > 0 {
             return self.build_parallel_verbose(targets, max_jobs);
         }
 
+        // Plain output and CI logs get one line per finished target instead of progress bars
+        let show_progress = !output::plain();
+
         // Hide cursor during progress display
         let term = console::Term::stderr();
-        if !self.quiet {
+        if show_progress {
             let _ = term.hide_cursor();
         }
 
         // Initialize the progress display with all target names
-        let progress = if !self.quiet {
+        let progress = if show_progress {
             let target_names: Vec<String> =
                 targets.iter().map(|t| t.artifact_name.clone()).collect();
-            Some(Arc::new(BuildProgress::new(&target_names)))
+            let durations = Durations::load(&self.workspace);
+            let estimates = targets
+                .iter()
+                .map(|t| durations.estimate(&t.artifact_name, self.pristine || t.pristine))
+                .collect();
+            Some(Arc::new(
+                BuildProgress::new(&target_names).estimates(estimates),
+            ))
         } else {
             None
         };
 
         let results = Arc::new(Mutex::new(Vec::new()));
         let semaphore = Arc::new(Semaphore::new(max_jobs));
+        let fail_fast = self.fail_fast.then(|| FailFast::new(self.runtime, targets));
         let mut handles = Vec::new();
 
         for (index, target) in targets.iter().enumerate() {
@@ -117,26 +335,81 @@ impl BuildOrchestrator {
             let workspace = self.workspace.clone();
             let project_config_dir = self.project.config_dir.clone();
             let extra_modules = self.project.extra_modules();
+            let overrides = self.overrides.clone();
+            let proxy = self.proxy.clone();
+            let limits = self.limits;
+            let compiler_cache = self.compiler_cache.clone();
+            let image = self.image.clone();
             let output_dir = self.output_dir.clone();
-            let pristine = self.pristine;
+            let pristine = self.pristine || target.pristine;
+            let offline = self.offline;
+            let timeout = target.timeout.or(self.timeout);
             let results = Arc::clone(&results);
             let semaphore = Arc::clone(&semaphore);
             let progress = progress.clone();
+            let fail_fast = fail_fast.clone();
+            let retry = self.retry;
+            let report_finished = progress.is_none();
 
             let handle = thread::spawn(move || {
                 // Acquire semaphore permit (blocks if max_jobs already running)
-                let _permit = semaphore.acquire();
-
-                let result = Self::build_target_with_progress(
-                    &runtime,
-                    &workspace,
-                    &project_config_dir,
-                    &extra_modules,
-                    &output_dir,
-                    &target,
-                    pristine,
-                    progress.as_ref().map(|p| (p.as_ref(), index)),
-                );
+                let _permit = semaphore.acquire(index);
+
+                if fail_fast.as_ref().is_some_and(FailFast::is_cancelled) {
+                    if let Some(progress) = &progress {
+                        progress.cancel(index);
+                    }
+                    results.lock().unwrap().push(FailFast::cancelled(&target));
+                    return;
+                }
+
+                let result = report_events(&target.artifact_name, || {
+                    let stopped = || fail_fast.as_ref().is_some_and(FailFast::is_cancelled);
+                    let result = retry.run(pristine, stopped, |attempt, pristine| {
+                        if attempt > 0 {
+                            match &progress {
+                                Some(progress) => progress.retry(index),
+                                None => output::build_status(
+                                    &target.artifact_name,
+                                    BuildState::Failed,
+                                    &retry.status(attempt),
+                                ),
+                            }
+                        }
+                        Self::build_target_with_progress(
+                            &runtime,
+                            &workspace,
+                            &project_config_dir,
+                            &extra_modules,
+                            &overrides,
+                            &proxy,
+                            &limits,
+                            &compiler_cache,
+                            &image,
+                            &output_dir,
+                            &target,
+                            pristine,
+                            offline,
+                            timeout,
+                            progress.as_ref().map(|p| (p.as_ref(), index)),
+                        )
+                    });
+                    match &fail_fast {
+                        Some(fail_fast) => fail_fast.finish(result),
+                        None => result,
+                    }
+                });
+                if let (true, Some(progress)) = (result.cancelled, &progress) {
+                    progress.cancel(index);
+                }
+                if report_finished {
+                    let state = if result.success {
+                        BuildState::Success
+                    } else {
+                        BuildState::Failed
+                    };
+                    output::build_status(&result.target_name, state, "");
+                }
 
                 let mut results = results.lock().unwrap();
                 results.push(result);
@@ -158,7 +431,7 @@ impl BuildOrchestrator {
         }
 
         // Restore cursor
-        if !self.quiet {
+        if show_progress {
             let _ = term.show_cursor();
         }
 
@@ -169,6 +442,7 @@ impl BuildOrchestrator {
 
         // Save hashes if all builds succeeded (enables incremental builds next time)
         self.save_hashes_if_all_succeeded(&results);
+        self.record_durations(targets, &results);
 
         Ok(results)
     }
@@ -184,6 +458,22 @@ impl BuildOrchestrator {
         }
     }
 
+    /// Remember how long successful builds took, for estimates next time.
+    /// Retried builds would skew them, so they are left out.
+    fn record_durations(&self, targets: &[BuildTarget], results: &[BuildResult]) {
+        let mut durations = Durations::load(&self.workspace);
+        for result in results.iter().filter(|r| r.success && r.retries == 0) {
+            let pristine = self.pristine
+                || targets
+                    .iter()
+                    .any(|t| t.artifact_name == result.target_name && t.pristine);
+            durations.record(&result.target_name, pristine, result.duration);
+        }
+        if let Err(e) = durations.save(&self.workspace) {
+            output::warning(&format!("Failed to save build durations: {}", e));
+        }
+    }
+
     /// Build targets in parallel with verbose streaming output (colored prefixes)
     fn build_parallel_verbose(
         &self,
@@ -192,6 +482,7 @@ impl BuildOrchestrator {
     ) -> Result<Vec<BuildResult>> {
         let results = Arc::new(Mutex::new(Vec::new()));
         let semaphore = Arc::new(Semaphore::new(max_jobs));
+        let fail_fast = self.fail_fast.then(|| FailFast::new(self.runtime, targets));
         let mut handles = Vec::new();
 
         for (index, target) in targets.iter().enumerate() {
@@ -200,25 +491,60 @@ impl BuildOrchestrator {
             let workspace = self.workspace.clone();
             let project_config_dir = self.project.config_dir.clone();
             let extra_modules = self.project.extra_modules();
+            let overrides = self.overrides.clone();
+            let proxy = self.proxy.clone();
+            let limits = self.limits;
+            let compiler_cache = self.compiler_cache.clone();
+            let image = self.image.clone();
             let output_dir = self.output_dir.clone();
-            let pristine = self.pristine;
+            let pristine = self.pristine || target.pristine;
+            let offline = self.offline;
+            let timeout = target.timeout.or(self.timeout);
+            let verbosity = self.verbosity;
             let results = Arc::clone(&results);
             let semaphore = Arc::clone(&semaphore);
+            let fail_fast = fail_fast.clone();
+            let retry = self.retry;
 
             let handle = thread::spawn(move || {
                 // Acquire semaphore permit (blocks if max_jobs already running)
-                let _permit = semaphore.acquire();
-
-                let result = Self::build_target_verbose_parallel(
-                    &runtime,
-                    &workspace,
-                    &project_config_dir,
-                    &extra_modules,
-                    &output_dir,
-                    &target,
-                    index,
-                    pristine,
-                );
+                let _permit = semaphore.acquire(index);
+
+                if fail_fast.as_ref().is_some_and(FailFast::is_cancelled) {
+                    results.lock().unwrap().push(FailFast::cancelled(&target));
+                    return;
+                }
+
+                let result = report_events(&target.artifact_name, || {
+                    let stopped = || fail_fast.as_ref().is_some_and(FailFast::is_cancelled);
+                    let result = retry.run(pristine, stopped, |attempt, pristine| {
+                        if attempt > 0 {
+                            output::warning(&retry.message(&target.artifact_name, attempt));
+                        }
+                        Self::build_target_verbose_parallel(
+                            &runtime,
+                            &workspace,
+                            &project_config_dir,
+                            &extra_modules,
+                            &overrides,
+                            &proxy,
+                            &limits,
+                            &compiler_cache,
+                            &image,
+                            &output_dir,
+                            &target,
+                            index,
+                            verbosity,
+                            pristine,
+                            offline,
+                            timeout,
+                        )
+                    });
+                    match &fail_fast {
+                        Some(fail_fast) => fail_fast.finish(result),
+                        None => result,
+                    }
+                });
 
                 let mut results = results.lock().unwrap();
                 results.push(result);
@@ -237,33 +563,49 @@ impl BuildOrchestrator {
             .into_inner()
             .unwrap();
 
+        self.record_durations(targets, &results);
+
         Ok(results)
     }
 
     /// Build a single target
-    fn build_target(&self, target: &BuildTarget) -> BuildResult {
+    fn build_target(&self, target: &BuildTarget, pristine: bool) -> BuildResult {
         Self::build_target_inner(
             &self.runtime,
             &self.workspace,
             &self.project.config_dir,
             &self.project.extra_modules(),
+            &self.overrides,
+            &self.proxy,
+            &self.limits,
+            &self.compiler_cache,
+            &self.image,
             &self.output_dir,
             target,
-            self.quiet,
-            self.pristine,
+            pristine,
+            self.offline,
+            target.timeout.or(self.timeout),
         )
     }
 
     /// Build a single target with verbose streaming output
-    fn build_target_verbose(&self, target: &BuildTarget) -> BuildResult {
+    fn build_target_verbose(&self, target: &BuildTarget, pristine: bool) -> BuildResult {
         Self::build_target_verbose_inner(
             &self.runtime,
             &self.workspace,
             &self.project.config_dir,
             &self.project.extra_modules(),
+            &self.overrides,
+            &self.proxy,
+            &self.limits,
+            &self.compiler_cache,
+            &self.image,
             &self.output_dir,
             target,
-            self.pristine,
+            self.verbosity,
+            pristine,
+            self.offline,
+            target.timeout.or(self.timeout),
         )
     }
 
@@ -274,52 +616,72 @@ impl BuildOrchestrator {
         workspace: &Path,
         config_dir: &Path,
         extra_modules: &[PathBuf],
+        overrides: &[ProjectOverride],
+        proxy: &ProxyConfig,
+        limits: &ResourceLimits,
+        compiler_cache: &CompilerCache,
+        image: &str,
         output_dir: &Path,
         target: &BuildTarget,
-        quiet: bool,
         pristine: bool,
+        offline: bool,
+        timeout: Option<Duration>,
     ) -> BuildResult {
         let start = Instant::now();
         let target_name = target.artifact_name.clone();
+        let log = Arc::new(BuildLog::create(workspace, &target_name));
+        let phases = Arc::new(PhaseTimer::new());
 
         // Build the west build command
-        let west_args = target.west_build_args("/workspace/config", pristine);
+        let west_args = target.west_args("/workspace/config", pristine);
         let west_cmd = format!("west {}", west_args.join(" "));
 
-        // Get ccache dir
-        let ccache_dir = match paths::ccache_dir() {
+        // Get the compiler cache dir
+        let cache_dir = match compiler_cache.dir() {
             Ok(dir) => dir,
             Err(e) => {
-                if !quiet {
-                    output::build_status(&target_name, BuildState::Failed, "ccache error");
-                }
-                return BuildResult {
+                output::build_status(&target_name, BuildState::Failed, "compiler cache error");
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to get ccache dir: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to get compiler cache dir: {}", e),
+                );
             }
         };
 
         // Build container command
-        let mut container_cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+        let container_name = container_name(&target_name);
+        let mut container_cmd = ContainerCommand::new(*runtime, image)
+            .name(&container_name)
+            .proxy(proxy)
+            .limits(limits)
+            .offline(offline)
             .mount(workspace, "/workspace", false)
             .mount(config_dir, "/workspace/config", true)
-            .mount(&ccache_dir, "/root/.ccache", false)
+            .compiler_cache(compiler_cache, &cache_dir)
             .workdir("/workspace")
             .env(
                 "CMAKE_PREFIX_PATH",
                 "/workspace/zephyr/share/zephyr-package/cmake",
             );
 
-        // Mount extra Zephyr modules
+        // Per-target environment from the build config
+        for (key, value) in &target.env {
+            container_cmd = container_cmd.env(key, value);
+        }
+
+        // Mount extra Zephyr modules (project-wide plus this target's own)
+        let extra_modules = target.extra_modules_with(extra_modules);
         for (i, module_path) in extra_modules.iter().enumerate() {
             let container_path = format!("/workspace/module_{}", i);
             container_cmd = container_cmd.mount(module_path, &container_path, true);
         }
 
+        // Local directories replacing west projects
+        for project in overrides {
+            let container_path = format!("/workspace/{}", project.workspace_path);
+            container_cmd = container_cmd.mount(&project.host_path, &container_path, true);
+        }
+
         // Add ZMK_EXTRA_MODULES cmake arg if we have extra modules
         let module_paths: Vec<String> = (0..extra_modules.len())
             .map(|i| format!("/workspace/module_{}", i))
@@ -332,6 +694,7 @@ impl BuildOrchestrator {
             format!("{} -DZMK_EXTRA_MODULES=\"{}\"", west_cmd, modules_arg)
         };
 
+        log.line(&format!("$ {}", build_script));
         let mut cmd = container_cmd.shell_command(&build_script).build();
 
         // Capture output silently
@@ -342,16 +705,11 @@ impl BuildOrchestrator {
         let mut child = match cmd.spawn() {
             Ok(child) => child,
             Err(e) => {
-                if !quiet {
-                    output::build_status(&target_name, BuildState::Failed, "spawn error");
-                }
-                return BuildResult {
+                output::build_status(&target_name, BuildState::Failed, "spawn error");
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to spawn build process: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to spawn build process: {}", e),
+                );
             }
         };
 
@@ -359,47 +717,56 @@ impl BuildOrchestrator {
         let stdout = child.stdout.take().expect("Failed to capture stdout");
         let stderr = child.stderr.take().expect("Failed to capture stderr");
 
+        let stdout_log = Arc::clone(&log);
+        let stdout_phases = Arc::clone(&phases);
+        let target_name_stdout = target_name.clone();
         let stdout_handle = thread::spawn(move || {
             let reader = BufReader::new(stdout);
             let mut all_output = Vec::new();
             for line in reader.lines().map_while(Result::ok) {
+                stdout_log.line(&line);
+                stdout_phases.line(&line);
+                report_progress(&target_name_stdout, &line);
                 all_output.push(line);
             }
             all_output.join("\n")
         });
 
+        let stderr_log = Arc::clone(&log);
         let stderr_handle = thread::spawn(move || {
             let reader = BufReader::new(stderr);
             let mut error_output = String::new();
             for line in reader.lines().map_while(Result::ok) {
+                stderr_log.line(&line);
                 error_output.push_str(&line);
                 error_output.push('\n');
             }
             error_output
         });
 
-        // Wait for process to complete
-        let status = match child.wait() {
+        // Wait for process to complete (None if it timed out)
+        let status = match wait_with_timeout(&mut child, runtime, &container_name, timeout) {
             Ok(status) => status,
             Err(e) => {
-                if !quiet {
-                    output::build_status(&target_name, BuildState::Failed, "wait error");
-                }
-                return BuildResult {
+                output::build_status(&target_name, BuildState::Failed, "wait error");
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to wait for build: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to wait for build: {}", e),
+                );
             }
         };
 
         let stdout_output = stdout_handle.join().unwrap_or_default();
         let stderr_output = stderr_handle.join().unwrap_or_default();
+        let warnings = collect_warnings(&stdout_output, &stderr_output);
+        let memory = memory::measured(
+            &workspace.join(&target.build_dir),
+            memory::parse(&stdout_output),
+        );
         let duration = start.elapsed();
 
-        if !status.success() {
+        if status.is_none_or(|s| !s.success()) {
+            log.line(&failure_message(status, timeout));
             let mut combined_output = stdout_output;
             if !stderr_output.is_empty() {
                 if !combined_output.is_empty() {
@@ -408,56 +775,67 @@ impl BuildOrchestrator {
                 combined_output.push_str(&stderr_output);
             }
 
-            if !quiet {
-                output::build_status(&target_name, BuildState::Failed, "error");
-            }
+            let state = if status.is_none() {
+                "timed out"
+            } else {
+                "error"
+            };
+            output::build_status(&target_name, BuildState::Failed, state);
 
             return BuildResult {
-                target_name,
-                success: false,
-                error: Some(format!("Build failed with exit code: {:?}", status.code())),
                 error_output: if combined_output.is_empty() {
                     None
                 } else {
                     Some(combined_output)
                 },
-                artifact_path: None,
+                timed_out: status.is_none(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, failure_message(status, timeout))
+            };
+        }
+
+        // Firmware too close to full fails like a broken build
+        if let Some(error) = target.memory_limits.check(&memory) {
+            log.line(&error);
+            output::build_status(&target_name, BuildState::Failed, "over memory limit");
+            return BuildResult {
+                phases: phases.phases(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, error)
             };
         }
 
         // Collect artifact
         match collect_artifact(workspace, target, output_dir) {
             Ok(artifact_path) => {
-                if !quiet {
-                    let artifact_name = artifact_path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy();
-                    let time_str = output::format_duration(duration);
-                    output::build_status(
-                        &target_name,
-                        BuildState::Success,
-                        &format!("{} ({})", artifact_name, time_str),
-                    );
-                }
+                let artifact_name = artifact_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                let time_str = output::format_duration(duration);
+                output::build_status(
+                    &target_name,
+                    BuildState::Success,
+                    &format!("{} ({})", artifact_name, time_str),
+                );
                 BuildResult {
                     target_name,
                     success: true,
-                    error: None,
-                    error_output: None,
                     artifact_path: Some(artifact_path),
+                    phases: phases.phases(),
+                    memory,
+                    warnings,
+                    ..Default::default()
                 }
             }
             Err(e) => {
-                if !quiet {
-                    output::build_status(&target_name, BuildState::Failed, "artifact error");
-                }
+                output::build_status(&target_name, BuildState::Failed, "artifact error");
                 BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to collect artifact: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
+                    memory,
+                    warnings,
+                    ..BuildResult::failed(target_name, format!("Failed to collect artifact: {}", e))
                 }
             }
         }
@@ -470,58 +848,81 @@ impl BuildOrchestrator {
         workspace: &Path,
         config_dir: &Path,
         extra_modules: &[PathBuf],
+        overrides: &[ProjectOverride],
+        proxy: &ProxyConfig,
+        limits: &ResourceLimits,
+        compiler_cache: &CompilerCache,
+        image: &str,
         output_dir: &Path,
         target: &BuildTarget,
         pristine: bool,
+        offline: bool,
+        timeout: Option<Duration>,
         progress: Option<(&BuildProgress, usize)>,
     ) -> BuildResult {
         use std::sync::mpsc::{channel, TryRecvError};
 
         let start = Instant::now();
         let target_name = target.artifact_name.clone();
+        let log = Arc::new(BuildLog::create(workspace, &target_name));
+        let phases = Arc::new(PhaseTimer::new());
 
         if let Some((prog, idx)) = progress {
             prog.update(idx, BuildState::Starting, "configuring");
         }
 
         // Build the west build command
-        let west_args = target.west_build_args("/workspace/config", pristine);
+        let west_args = target.west_args("/workspace/config", pristine);
         let west_cmd = format!("west {}", west_args.join(" "));
 
-        // Get ccache dir
-        let ccache_dir = match paths::ccache_dir() {
+        // Get the compiler cache dir
+        let cache_dir = match compiler_cache.dir() {
             Ok(dir) => dir,
             Err(e) => {
                 if let Some((prog, idx)) = progress {
                     prog.finish(idx, false, None, None);
                 }
-                return BuildResult {
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to get ccache dir: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to get compiler cache dir: {}", e),
+                );
             }
         };
 
         // Build container command
-        let mut container_cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+        let container_name = container_name(&target_name);
+        let mut container_cmd = ContainerCommand::new(*runtime, image)
+            .name(&container_name)
+            .proxy(proxy)
+            .limits(limits)
+            .offline(offline)
             .mount(workspace, "/workspace", false)
             .mount(config_dir, "/workspace/config", true)
-            .mount(&ccache_dir, "/root/.ccache", false)
+            .compiler_cache(compiler_cache, &cache_dir)
             .workdir("/workspace")
             .env(
                 "CMAKE_PREFIX_PATH",
                 "/workspace/zephyr/share/zephyr-package/cmake",
             );
 
-        // Mount extra Zephyr modules
+        // Per-target environment from the build config
+        for (key, value) in &target.env {
+            container_cmd = container_cmd.env(key, value);
+        }
+
+        // Mount extra Zephyr modules (project-wide plus this target's own)
+        let extra_modules = target.extra_modules_with(extra_modules);
         for (i, module_path) in extra_modules.iter().enumerate() {
             let container_path = format!("/workspace/module_{}", i);
             container_cmd = container_cmd.mount(module_path, &container_path, true);
         }
 
+        // Local directories replacing west projects
+        for project in overrides {
+            let container_path = format!("/workspace/{}", project.workspace_path);
+            container_cmd = container_cmd.mount(&project.host_path, &container_path, true);
+        }
+
         // Add ZMK_EXTRA_MODULES cmake arg if we have extra modules
         let module_paths: Vec<String> = (0..extra_modules.len())
             .map(|i| format!("/workspace/module_{}", i))
@@ -534,6 +935,7 @@ impl BuildOrchestrator {
             format!("{} -DZMK_EXTRA_MODULES=\"{}\"", west_cmd, modules_arg)
         };
 
+        log.line(&format!("$ {}", build_script));
         let mut cmd = container_cmd.shell_command(&build_script).build();
 
         // Set up for streaming output
@@ -547,13 +949,10 @@ impl BuildOrchestrator {
                 if let Some((prog, idx)) = progress {
                     prog.finish(idx, false, None, None);
                 }
-                return BuildResult {
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to spawn build process: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to spawn build process: {}", e),
+                );
             }
         };
 
@@ -564,15 +963,25 @@ impl BuildOrchestrator {
         let stderr = child.stderr.take().expect("Failed to capture stderr");
 
         // Spawn thread to read stdout, parse progress, and capture output
+        let stdout_log = Arc::clone(&log);
+        let stdout_phases = Arc::clone(&phases);
+        let target_name_stdout = target_name.clone();
         let stdout_handle = thread::spawn(move || {
             let reader = BufReader::new(stdout);
             let mut all_output: Vec<String> = Vec::new();
 
             for line in reader.lines().map_while(Result::ok) {
+                stdout_log.line(&line);
+                stdout_phases.line(&line);
                 all_output.push(line.clone());
 
                 // Parse ninja progress like [123/456]
                 if let Some((current, total, _phase)) = parse_build_progress(&line) {
+                    events::emit(Event::Progress {
+                        target: &target_name_stdout,
+                        current,
+                        total,
+                    });
                     // Send progress update as [current/total]
                     let msg = format!("[{}/{}]", current, total);
                     let _ = progress_tx.send(msg); // Ignore send errors
@@ -583,10 +992,12 @@ impl BuildOrchestrator {
         });
 
         // Spawn thread to read stderr
+        let stderr_log = Arc::clone(&log);
         let stderr_handle = thread::spawn(move || {
             let reader = BufReader::new(stderr);
             let mut error_output = String::new();
             for line in reader.lines().map_while(Result::ok) {
+                stderr_log.line(&line);
                 error_output.push_str(&line);
                 error_output.push('\n');
             }
@@ -594,6 +1005,7 @@ impl BuildOrchestrator {
         });
 
         // Poll for progress updates while waiting for process to complete
+        let deadline = timeout.map(|t| start + t);
         let status = loop {
             // Process any pending progress updates
             if let Some((prog, idx)) = progress {
@@ -608,7 +1020,11 @@ impl BuildOrchestrator {
 
             // Check if process is done
             match child.try_wait() {
-                Ok(Some(status)) => break Ok(status),
+                Ok(Some(status)) => break Ok(Some(status)),
+                Ok(None) if deadline.is_some_and(|d| Instant::now() >= d) => {
+                    kill_build(&mut child, runtime, &container_name);
+                    break Ok(None);
+                }
                 Ok(None) => {
                     // Process still running, sleep briefly
                     thread::sleep(Duration::from_millis(50));
@@ -623,23 +1039,26 @@ impl BuildOrchestrator {
                 if let Some((prog, idx)) = progress {
                     prog.finish(idx, false, None, None);
                 }
-                return BuildResult {
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to wait for build: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to wait for build: {}", e),
+                );
             }
         };
 
         // Get output from threads
         let stdout_output = stdout_handle.join().unwrap_or_default();
         let stderr_output = stderr_handle.join().unwrap_or_default();
+        let warnings = collect_warnings(&stdout_output, &stderr_output);
+        let memory = memory::measured(
+            &workspace.join(&target.build_dir),
+            memory::parse(&stdout_output),
+        );
 
         let duration = start.elapsed();
 
-        if !status.success() {
+        if status.is_none_or(|s| !s.success()) {
+            log.line(&failure_message(status, timeout));
             if let Some((prog, idx)) = progress {
                 prog.finish(idx, false, None, Some(duration));
             }
@@ -654,15 +1073,29 @@ impl BuildOrchestrator {
             }
 
             return BuildResult {
-                target_name,
-                success: false,
-                error: Some(format!("Build failed with exit code: {:?}", status.code())),
                 error_output: if combined_output.is_empty() {
                     None
                 } else {
                     Some(combined_output)
                 },
-                artifact_path: None,
+                timed_out: status.is_none(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, failure_message(status, timeout))
+            };
+        }
+
+        // Firmware too close to full fails like a broken build
+        if let Some(error) = target.memory_limits.check(&memory) {
+            log.line(&error);
+            if let Some((prog, idx)) = progress {
+                prog.finish(idx, false, None, Some(duration));
+            }
+            return BuildResult {
+                phases: phases.phases(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, error)
             };
         }
 
@@ -682,9 +1115,11 @@ impl BuildOrchestrator {
                 BuildResult {
                     target_name,
                     success: true,
-                    error: None,
-                    error_output: None,
                     artifact_path: Some(artifact_path),
+                    phases: phases.phases(),
+                    memory,
+                    warnings,
+                    ..Default::default()
                 }
             }
             Err(e) => {
@@ -692,11 +1127,9 @@ impl BuildOrchestrator {
                     prog.finish(idx, false, None, Some(duration));
                 }
                 BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to collect artifact: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
+                    memory,
+                    warnings,
+                    ..BuildResult::failed(target_name, format!("Failed to collect artifact: {}", e))
                 }
             }
         }
@@ -709,56 +1142,79 @@ impl BuildOrchestrator {
         workspace: &Path,
         config_dir: &Path,
         extra_modules: &[PathBuf],
+        overrides: &[ProjectOverride],
+        proxy: &ProxyConfig,
+        limits: &ResourceLimits,
+        compiler_cache: &CompilerCache,
+        image: &str,
         output_dir: &Path,
         target: &BuildTarget,
         color_index: usize,
+        verbosity: u8,
         pristine: bool,
+        offline: bool,
+        timeout: Option<Duration>,
     ) -> BuildResult {
         let start = Instant::now();
         let target_name = target.artifact_name.clone();
+        let log = Arc::new(BuildLog::create(workspace, &target_name));
+        let phases = Arc::new(PhaseTimer::new());
 
         output::verbose_start(&target_name, color_index);
 
         // Build the west build command
-        let west_args = target.west_build_args("/workspace/config", pristine);
-        let west_cmd = format!("west {}", west_args.join(" "));
+        let west_cmd = west_command(target, pristine, verbosity);
 
-        // Get ccache dir
-        let ccache_dir = match paths::ccache_dir() {
+        // Get the compiler cache dir
+        let cache_dir = match compiler_cache.dir() {
             Ok(dir) => dir,
             Err(e) => {
                 output::verbose_line(
                     &target_name,
                     color_index,
-                    &format!("error: Failed to get ccache dir: {}", e),
+                    &format!("error: Failed to get compiler cache dir: {}", e),
                 );
-                return BuildResult {
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to get ccache dir: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to get compiler cache dir: {}", e),
+                );
             }
         };
 
         // Build container command
-        let mut container_cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+        let container_name = container_name(&target_name);
+        let mut container_cmd = ContainerCommand::new(*runtime, image)
+            .name(&container_name)
+            .proxy(proxy)
+            .limits(limits)
+            .offline(offline)
             .mount(workspace, "/workspace", false)
             .mount(config_dir, "/workspace/config", true)
-            .mount(&ccache_dir, "/root/.ccache", false)
+            .compiler_cache(compiler_cache, &cache_dir)
             .workdir("/workspace")
             .env(
                 "CMAKE_PREFIX_PATH",
                 "/workspace/zephyr/share/zephyr-package/cmake",
             );
 
-        // Mount extra Zephyr modules
+        // Per-target environment from the build config
+        for (key, value) in &target.env {
+            container_cmd = container_cmd.env(key, value);
+        }
+
+        // Mount extra Zephyr modules (project-wide plus this target's own)
+        let extra_modules = target.extra_modules_with(extra_modules);
         for (i, module_path) in extra_modules.iter().enumerate() {
             let container_path = format!("/workspace/module_{}", i);
             container_cmd = container_cmd.mount(module_path, &container_path, true);
         }
 
+        // Local directories replacing west projects
+        for project in overrides {
+            let container_path = format!("/workspace/{}", project.workspace_path);
+            container_cmd = container_cmd.mount(&project.host_path, &container_path, true);
+        }
+
         // Add ZMK_EXTRA_MODULES cmake arg if we have extra modules
         let module_paths: Vec<String> = (0..extra_modules.len())
             .map(|i| format!("/workspace/module_{}", i))
@@ -771,7 +1227,16 @@ impl BuildOrchestrator {
             format!("{} -DZMK_EXTRA_MODULES=\"{}\"", west_cmd, modules_arg)
         };
 
-        let mut cmd = container_cmd.shell_command(&build_script).build();
+        log.line(&format!("$ {}", build_script));
+        let container_cmd = container_cmd.shell_command(&build_script);
+        if verbosity >= 2 {
+            output::verbose_line(
+                &target_name,
+                color_index,
+                &format!("$ {}", container_cmd.as_string()),
+            );
+        }
+        let mut cmd = container_cmd.build();
 
         // Capture stdout/stderr for prefixing
         cmd.stdout(Stdio::piped());
@@ -786,13 +1251,10 @@ impl BuildOrchestrator {
                     color_index,
                     &format!("error: Failed to spawn: {}", e),
                 );
-                return BuildResult {
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to spawn build process: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to spawn build process: {}", e),
+                );
             }
         };
 
@@ -802,52 +1264,88 @@ impl BuildOrchestrator {
         let target_name_stdout = target_name.clone();
         let target_name_stderr = target_name.clone();
 
-        // Stream stdout with prefix
+        // Stream stdout with prefix (keeping it for the warnings summary)
+        let stdout_log = Arc::clone(&log);
+        let stdout_phases = Arc::clone(&phases);
         let stdout_handle = thread::spawn(move || {
             let reader = BufReader::new(stdout);
+            let mut all_output = Vec::new();
             for line in reader.lines().map_while(Result::ok) {
                 output::verbose_line(&target_name_stdout, color_index, &line);
+                stdout_log.line(&line);
+                stdout_phases.line(&line);
+                report_progress(&target_name_stdout, &line);
+                all_output.push(line);
             }
+            all_output.join("\n")
         });
 
         // Stream stderr with prefix
+        let stderr_log = Arc::clone(&log);
         let stderr_handle = thread::spawn(move || {
             let reader = BufReader::new(stderr);
+            let mut all_output = Vec::new();
             for line in reader.lines().map_while(Result::ok) {
                 output::verbose_line(&target_name_stderr, color_index, &line);
+                stderr_log.line(&line);
+                all_output.push(line);
             }
+            all_output.join("\n")
         });
 
-        // Wait for output threads
-        let _ = stdout_handle.join();
-        let _ = stderr_handle.join();
+        // Wait for process (None if it timed out), then for output threads
+        let status = wait_with_timeout(&mut child, runtime, &container_name, timeout);
+        let stdout_output = stdout_handle.join().unwrap_or_default();
+        let stderr_output = stderr_handle.join().unwrap_or_default();
+        let warnings = collect_warnings(&stdout_output, &stderr_output);
+        let memory = memory::measured(
+            &workspace.join(&target.build_dir),
+            memory::parse(&stdout_output),
+        );
 
-        // Wait for process
-        let status = match child.wait() {
+        let status = match status {
             Ok(status) => status,
             Err(e) => {
                 let duration = start.elapsed();
                 output::verbose_done(&target_name, color_index, false, None, Some(duration));
                 return BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to wait for build: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
+                    memory,
+                    warnings,
+                    ..BuildResult::failed(target_name, format!("Failed to wait for build: {}", e))
                 };
             }
         };
 
         let duration = start.elapsed();
 
-        if !status.success() {
+        if status.is_none_or(|s| !s.success()) {
+            log.line(&failure_message(status, timeout));
+            if status.is_none() {
+                output::verbose_line(
+                    &target_name,
+                    color_index,
+                    &format!("error: {}", failure_message(status, timeout)),
+                );
+            }
+            output::verbose_done(&target_name, color_index, false, None, Some(duration));
+            return BuildResult {
+                timed_out: status.is_none(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, failure_message(status, timeout))
+            };
+        }
+
+        // Firmware too close to full fails like a broken build
+        if let Some(error) = target.memory_limits.check(&memory) {
+            log.line(&error);
+            output::verbose_line(&target_name, color_index, &format!("error: {}", error));
             output::verbose_done(&target_name, color_index, false, None, Some(duration));
             return BuildResult {
-                target_name,
-                success: false,
-                error: Some(format!("Build failed with exit code: {:?}", status.code())),
-                error_output: None,
-                artifact_path: None,
+                phases: phases.phases(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, error)
             };
         }
 
@@ -864,9 +1362,11 @@ impl BuildOrchestrator {
                 BuildResult {
                     target_name,
                     success: true,
-                    error: None,
-                    error_output: None,
                     artifact_path: Some(artifact_path),
+                    phases: phases.phases(),
+                    memory,
+                    warnings,
+                    ..Default::default()
                 }
             }
             Err(e) => {
@@ -877,11 +1377,9 @@ impl BuildOrchestrator {
                 );
                 output::verbose_done(&target_name, color_index, false, None, Some(duration));
                 BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to collect artifact: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
+                    memory,
+                    warnings,
+                    ..BuildResult::failed(target_name, format!("Failed to collect artifact: {}", e))
                 }
             }
         }
@@ -894,55 +1392,78 @@ impl BuildOrchestrator {
         workspace: &Path,
         config_dir: &Path,
         extra_modules: &[PathBuf],
+        overrides: &[ProjectOverride],
+        proxy: &ProxyConfig,
+        limits: &ResourceLimits,
+        compiler_cache: &CompilerCache,
+        image: &str,
         output_dir: &Path,
         target: &BuildTarget,
+        verbosity: u8,
         pristine: bool,
+        offline: bool,
+        timeout: Option<Duration>,
     ) -> BuildResult {
         let start = Instant::now();
         let target_name = target.artifact_name.clone();
+        let log = Arc::new(BuildLog::create(workspace, &target_name));
+        let phases = Arc::new(PhaseTimer::new());
 
         // Print header for this target
         output::verbose_header(&target_name);
 
         // Build the west build command
-        let west_args = target.west_build_args("/workspace/config", pristine);
-        let west_cmd = format!("west {}", west_args.join(" "));
+        let west_cmd = west_command(target, pristine, verbosity);
 
         output::command(&west_cmd);
-        println!();
+        output::line("");
 
-        // Get ccache dir
-        let ccache_dir = match paths::ccache_dir() {
+        // Get the compiler cache dir
+        let cache_dir = match compiler_cache.dir() {
             Ok(dir) => dir,
             Err(e) => {
-                output::error(&format!("Failed to get ccache dir: {}", e));
-                return BuildResult {
+                output::error(&format!("Failed to get compiler cache dir: {}", e));
+                return BuildResult::failed(
                     target_name,
-                    success: false,
-                    error: Some(format!("Failed to get ccache dir: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                    format!("Failed to get compiler cache dir: {}", e),
+                );
             }
         };
 
         // Build container command
-        let mut container_cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+        let container_name = container_name(&target_name);
+        let mut container_cmd = ContainerCommand::new(*runtime, image)
+            .name(&container_name)
+            .proxy(proxy)
+            .limits(limits)
+            .offline(offline)
             .mount(workspace, "/workspace", false)
             .mount(config_dir, "/workspace/config", true)
-            .mount(&ccache_dir, "/root/.ccache", false)
+            .compiler_cache(compiler_cache, &cache_dir)
             .workdir("/workspace")
             .env(
                 "CMAKE_PREFIX_PATH",
                 "/workspace/zephyr/share/zephyr-package/cmake",
             );
 
-        // Mount extra Zephyr modules
+        // Per-target environment from the build config
+        for (key, value) in &target.env {
+            container_cmd = container_cmd.env(key, value);
+        }
+
+        // Mount extra Zephyr modules (project-wide plus this target's own)
+        let extra_modules = target.extra_modules_with(extra_modules);
         for (i, module_path) in extra_modules.iter().enumerate() {
             let container_path = format!("/workspace/module_{}", i);
             container_cmd = container_cmd.mount(module_path, &container_path, true);
         }
 
+        // Local directories replacing west projects
+        for project in overrides {
+            let container_path = format!("/workspace/{}", project.workspace_path);
+            container_cmd = container_cmd.mount(&project.host_path, &container_path, true);
+        }
+
         // Add ZMK_EXTRA_MODULES cmake arg if we have extra modules
         let module_paths: Vec<String> = (0..extra_modules.len())
             .map(|i| format!("/workspace/module_{}", i))
@@ -955,39 +1476,98 @@ impl BuildOrchestrator {
             format!("{} -DZMK_EXTRA_MODULES=\"{}\"", west_cmd, modules_arg)
         };
 
-        let mut cmd = container_cmd.shell_command(&build_script).build();
+        log.line(&format!("$ {}", build_script));
+        let container_cmd = container_cmd.shell_command(&build_script);
+        if verbosity >= 2 {
+            output::command(&container_cmd.as_string());
+            output::line("");
+        }
+        let mut cmd = container_cmd.build();
 
-        // Inherit stdout/stderr for real-time streaming
-        cmd.stdout(Stdio::inherit());
-        cmd.stderr(Stdio::inherit());
+        // Stream stdout/stderr in real time, keeping them for the log and warnings
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
 
-        // Run the build
-        let status = match cmd.status() {
+        // Run the build (None if it timed out)
+        let mut output_handles = None;
+        let status = match cmd.spawn().and_then(|mut child| {
+            let stdout = child.stdout.take().expect("Failed to capture stdout");
+            let stderr = child.stderr.take().expect("Failed to capture stderr");
+            let stdout_log = Arc::clone(&log);
+            let stdout_phases = Arc::clone(&phases);
+            let stderr_log = Arc::clone(&log);
+            let target_name_stdout = target_name.clone();
+            output_handles = Some((
+                thread::spawn(move || {
+                    let mut all_output = Vec::new();
+                    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                        output::line(&format!("{}{}", output::timestamp(), line));
+                        stdout_log.line(&line);
+                        stdout_phases.line(&line);
+                        report_progress(&target_name_stdout, &line);
+                        all_output.push(line);
+                    }
+                    all_output.join("\n")
+                }),
+                thread::spawn(move || {
+                    let mut all_output = Vec::new();
+                    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                        eprintln!("{}{}", output::timestamp(), line);
+                        stderr_log.line(&line);
+                        all_output.push(line);
+                    }
+                    all_output.join("\n")
+                }),
+            ));
+            wait_with_timeout(&mut child, runtime, &container_name, timeout)
+        }) {
             Ok(status) => status,
             Err(e) => {
                 output::error(&format!("Failed to run build: {}", e));
-                return BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to run build: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
-                };
+                return BuildResult::failed(target_name, format!("Failed to run build: {}", e));
             }
         };
 
-        println!();
+        let (warnings, memory) = output_handles
+            .map(|(stdout, stderr)| {
+                let stdout = stdout.join().unwrap_or_default();
+                let warnings = collect_warnings(&stdout, &stderr.join().unwrap_or_default());
+                let parsed = memory::parse(&stdout);
+                (
+                    warnings,
+                    memory::measured(&workspace.join(&target.build_dir), parsed),
+                )
+            })
+            .unwrap_or_default();
+
+        output::line("");
 
         let duration = start.elapsed();
 
-        if !status.success() {
+        if status.is_none_or(|s| !s.success()) {
+            log.line(&failure_message(status, timeout));
+            if status.is_none() {
+                output::error(&failure_message(status, timeout));
+            }
+            output::verbose_result(&target_name, false, None, Some(duration));
+            return BuildResult {
+                timed_out: status.is_none(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, failure_message(status, timeout))
+            };
+        }
+
+        // Firmware too close to full fails like a broken build
+        if let Some(error) = target.memory_limits.check(&memory) {
+            log.line(&error);
+            output::error(&error);
             output::verbose_result(&target_name, false, None, Some(duration));
             return BuildResult {
-                target_name,
-                success: false,
-                error: Some(format!("Build failed with exit code: {:?}", status.code())),
-                error_output: None,
-                artifact_path: None,
+                phases: phases.phases(),
+                memory,
+                warnings,
+                ..BuildResult::failed(target_name, error)
             };
         }
 
@@ -998,76 +1578,266 @@ impl BuildOrchestrator {
                 BuildResult {
                     target_name,
                     success: true,
-                    error: None,
-                    error_output: None,
                     artifact_path: Some(artifact_path),
+                    phases: phases.phases(),
+                    memory,
+                    warnings,
+                    ..Default::default()
                 }
             }
             Err(e) => {
                 output::error(&format!("Failed to collect artifact: {}", e));
                 BuildResult {
-                    target_name,
-                    success: false,
-                    error: Some(format!("Failed to collect artifact: {}", e)),
-                    error_output: None,
-                    artifact_path: None,
+                    memory,
+                    warnings,
+                    ..BuildResult::failed(target_name, format!("Failed to collect artifact: {}", e))
                 }
             }
         }
     }
 }
 
-/// Parse ninja-style build progress like "[123/456] Building..."
-/// Returns (current, total, optional_phase_name)
-fn parse_build_progress(line: &str) -> Option<(usize, usize, Option<String>)> {
-    let line = line.trim();
-
-    // Match [current/total] pattern
-    if line.starts_with('[') {
-        if let Some(bracket_end) = line.find(']') {
-            let progress = &line[1..bracket_end];
-            if let Some(slash_pos) = progress.find('/') {
-                let current: usize = progress[..slash_pos].parse().ok()?;
-                let total: usize = progress[slash_pos + 1..].parse().ok()?;
-
-                // Check for special phases
-                let rest = &line[bracket_end + 1..];
-                let phase = if rest.contains("Linking") {
-                    Some("linking".to_string())
-                } else if rest.contains("Generating") {
-                    Some("generating".to_string())
-                } else {
-                    None
-                };
+/// The `west build` command for a target, with west's and CMake's
+/// diagnostics at `-vvv`
+fn west_command(target: &BuildTarget, pristine: bool, verbosity: u8) -> String {
+    let west_args = target.west_args("/workspace/config", pristine).join(" ");
+    if verbosity >= 3 {
+        format!("west -v {} -DCMAKE_VERBOSE_MAKEFILE=ON", west_args)
+    } else {
+        format!("west {}", west_args)
+    }
+}
 
-                return Some((current, total, phase));
+/// Stops the remaining targets after the first failure (`--fail-fast`)
+#[derive(Clone)]
+struct FailFast {
+    runtime: Runtime,
+    /// Containers of all targets; the builds that are running get killed
+    containers: Arc<Vec<String>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FailFast {
+    fn new(runtime: Runtime, targets: &[BuildTarget]) -> Self {
+        Self {
+            runtime,
+            containers: Arc::new(
+                targets
+                    .iter()
+                    .map(|t| container_name(&t.artifact_name))
+                    .collect(),
+            ),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Record a finished target. The first failure stops all other builds;
+    /// builds failing after that were stopped by it.
+    fn finish(&self, mut result: BuildResult) -> BuildResult {
+        if result.success {
+            return result;
+        }
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            result.cancelled = true;
+            result.timed_out = false;
+            result.error = Some(CANCELLED.to_string());
+            result.error_output = None;
+        } else {
+            for container in self.containers.iter() {
+                self.runtime.kill_container(container);
+            }
+        }
+        result
+    }
+
+    /// Result for a target that was never started
+    fn cancelled(target: &BuildTarget) -> BuildResult {
+        BuildResult {
+            cancelled: true,
+            ..BuildResult::failed(target.artifact_name.clone(), CANCELLED.to_string())
+        }
+    }
+}
+
+const CANCELLED: &str = "Cancelled after another target failed (--fail-fast)";
+
+/// Rebuilding failed targets (`--retries`)
+#[derive(Debug, Clone, Copy, Default)]
+struct Retry {
+    /// Attempts after the first one
+    retries: u32,
+    /// Build pristine from the second attempt on (`--retry-pristine`)
+    pristine: bool,
+}
+
+impl Retry {
+    /// Build until the target succeeds, is cancelled, runs out of attempts or
+    /// `stopped` says the run is over. `build` gets the attempt (0 for the
+    /// first) and whether to build pristine.
+    fn run(
+        &self,
+        pristine: bool,
+        stopped: impl Fn() -> bool,
+        mut build: impl FnMut(u32, bool) -> BuildResult,
+    ) -> BuildResult {
+        let mut attempt = 0;
+        loop {
+            let mut result = build(attempt, pristine || (attempt > 0 && self.pristine));
+            result.retries = attempt;
+            if result.success || result.cancelled || attempt >= self.retries || stopped() {
+                return result;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Status of a target about to be retried, e.g. "retrying (1 of 2, pristine)"
+    fn status(&self, attempt: u32) -> String {
+        let pristine = if self.pristine { ", pristine" } else { "" };
+        format!("retrying ({} of {}{})", attempt, self.retries, pristine)
+    }
+
+    fn message(&self, target: &str, attempt: u32) -> String {
+        format!("{} failed, {}", target, self.status(attempt))
+    }
+}
+
+/// Run a target's build, reporting its start, artifact and result as events
+fn report_events(target: &str, build: impl FnOnce() -> BuildResult) -> BuildResult {
+    let start = Instant::now();
+    events::emit(Event::TargetStarted { target });
+    let mut result = build();
+    result.duration = start.elapsed();
+    if let Some(path) = &result.artifact_path {
+        events::emit(Event::ArtifactCollected { target, path });
+    }
+    events::emit(Event::TargetFinished {
+        target,
+        success: result.success,
+        timed_out: result.timed_out,
+        cancelled: result.cancelled,
+        retries: result.retries,
+        duration_ms: result.duration.as_millis(),
+        memory: &result.memory,
+    });
+    result
+}
+
+/// Report a line of build output as a progress event if it is a ninja step
+fn report_progress(target: &str, line: &str) {
+    if let Some((current, total, _)) = parse_build_progress(line) {
+        events::emit(Event::Progress {
+            target,
+            current,
+            total,
+        });
+    }
+}
+
+/// Warnings in a build's output
+fn collect_warnings(stdout: &str, stderr: &str) -> Vec<Diagnostic> {
+    diagnostics::parse(&format!("{}\n{}", stdout, stderr))
+        .into_iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .collect()
+}
+
+/// Container name for a target's build, unique per lfz process
+fn container_name(target_name: &str) -> String {
+    let sanitized: String = target_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
             }
+        })
+        .collect();
+    format!("lfz-{}-{}", sanitized, std::process::id())
+}
+
+/// Wait for a build process, killing it once `timeout` has elapsed
+///
+/// Returns `Ok(None)` if the build timed out.
+fn wait_with_timeout(
+    child: &mut Child,
+    runtime: &Runtime,
+    container_name: &str,
+    timeout: Option<Duration>,
+) -> io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill_build(child, runtime, container_name);
+            return Ok(None);
         }
+        thread::sleep(Duration::from_millis(100));
     }
+}
+
+/// Stop a build: killing only the runtime client can leave the container
+/// running, so the named container is killed through the runtime first
+fn kill_build(child: &mut Child, runtime: &Runtime, container_name: &str) {
+    runtime.kill_container(container_name);
+    let _ = child.kill();
+    let _ = child.wait();
+}
 
-    None
+/// Error message for a failed build (`status` is None when it timed out)
+fn failure_message(status: Option<ExitStatus>, timeout: Option<Duration>) -> String {
+    match status {
+        Some(status) => format!("Build failed with exit code: {:?}", status.code()),
+        None => format!(
+            "Build timed out after {}",
+            timeout.map(output::format_duration).unwrap_or_default()
+        ),
+    }
 }
 
-/// A simple counting semaphore for limiting concurrency
+/// A simple counting semaphore for limiting concurrency. Permits are handed
+/// out in turn order, so targets start in the order they were scheduled.
 struct Semaphore {
-    count: Mutex<usize>,
+    state: Mutex<SemaphoreState>,
     condvar: Condvar,
 }
 
+struct SemaphoreState {
+    /// Permits available
+    count: usize,
+    /// The turn that gets the next permit
+    next: usize,
+}
+
 impl Semaphore {
     fn new(count: usize) -> Self {
         Self {
-            count: Mutex::new(count),
+            state: Mutex::new(SemaphoreState { count, next: 0 }),
             condvar: Condvar::new(),
         }
     }
 
-    fn acquire(&self) -> SemaphorePermit<'_> {
-        let mut count = self.count.lock().unwrap();
-        while *count == 0 {
-            count = self.condvar.wait(count).unwrap();
+    /// Wait for a permit; every turn from 0 up must call this exactly once
+    fn acquire(&self, turn: usize) -> SemaphorePermit<'_> {
+        let mut state = self.state.lock().unwrap();
+        while state.count == 0 || state.next != turn {
+            state = self.condvar.wait(state).unwrap();
         }
-        *count -= 1;
+        state.count -= 1;
+        state.next += 1;
+        // The next turn may be able to go right away
+        self.condvar.notify_all();
         SemaphorePermit { semaphore: self }
     }
 }
@@ -1079,8 +1849,66 @@ struct SemaphorePermit<'a> {
 
 impl Drop for SemaphorePermit<'_> {
     fn drop(&mut self) {
-        let mut count = self.semaphore.count.lock().unwrap();
-        *count += 1;
-        self.semaphore.condvar.notify_one();
+        let mut state = self.semaphore.state.lock().unwrap();
+        state.count += 1;
+        self.semaphore.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry() {
+        let retry = Retry {
+            retries: 2,
+            pristine: true,
+        };
+        let mut attempts = Vec::new();
+        let result = retry.run(
+            false,
+            || false,
+            |attempt, pristine| {
+                attempts.push(pristine);
+                BuildResult {
+                    success: attempt == 1,
+                    ..Default::default()
+                }
+            },
+        );
+        assert!(result.success);
+        assert_eq!(result.retries, 1);
+        assert_eq!(attempts, [false, true]);
+
+        let result = retry.run(false, || false, |_, _| BuildResult::default());
+        assert_eq!(result.retries, 2);
+
+        // Another target failing with --fail-fast ends the retries
+        let result = retry.run(false, || true, |_, _| BuildResult::default());
+        assert_eq!(result.retries, 0);
+        assert_eq!(retry.status(1), "retrying (1 of 2, pristine)");
+    }
+
+    #[test]
+    fn test_semaphore_order() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let started = Arc::new(Mutex::new(Vec::new()));
+        // Spawned in reverse, but permits go out in turn order
+        let handles: Vec<_> = (0..4)
+            .rev()
+            .map(|turn| {
+                let semaphore = Arc::clone(&semaphore);
+                let started = Arc::clone(&started);
+                thread::spawn(move || {
+                    let _permit = semaphore.acquire(turn);
+                    started.lock().unwrap().push(turn);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*started.lock().unwrap(), [0, 1, 2, 3]);
     }
 }
@@ -0,0 +1,132 @@
+//! Where a build's time goes: CMake configure, compiling and linking.
+//!
+//! Piped ninja prints each `[n/total]` step as it finishes, so the time
+//! since the previous step is counted towards the phase of the step:
+//! "Linking" steps are linking and every other step compiling (generated
+//! sources included). Everything before the first step is configuring.
+
+use std::ops::AddAssign;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Time spent in each phase of a build
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Phases {
+    pub configure: Duration,
+    pub compile: Duration,
+    pub link: Duration,
+}
+
+impl Phases {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AddAssign for Phases {
+    fn add_assign(&mut self, other: Self) {
+        self.configure += other.configure;
+        self.compile += other.compile;
+        self.link += other.link;
+    }
+}
+
+/// Times the phases of a running build from its output lines
+#[derive(Debug)]
+pub struct PhaseTimer {
+    state: Mutex<(Phases, Instant, bool)>,
+}
+
+impl PhaseTimer {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    fn starting_at(start: Instant) -> Self {
+        Self {
+            state: Mutex::new((Phases::default(), start, false)),
+        }
+    }
+
+    /// Account for a line of build output
+    pub fn line(&self, line: &str) {
+        self.line_at(line, Instant::now());
+    }
+
+    fn line_at(&self, line: &str, now: Instant) {
+        let Some((_, _, phase)) = parse_build_progress(line) else {
+            return;
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let (phases, last, started) = &mut *state;
+        let elapsed = now.saturating_duration_since(*last);
+        if !*started {
+            phases.configure += elapsed;
+            *started = true;
+        } else if phase.as_deref() == Some("linking") {
+            phases.link += elapsed;
+        } else {
+            phases.compile += elapsed;
+        }
+        *last = now;
+    }
+
+    /// The phases so far
+    pub fn phases(&self) -> Phases {
+        self.state.lock().map(|state| state.0).unwrap_or_default()
+    }
+}
+
+/// Parse ninja-style build progress like "[123/456] Building..."
+/// Returns (current, total, optional_phase_name)
+pub(super) fn parse_build_progress(line: &str) -> Option<(usize, usize, Option<String>)> {
+    let line = line.trim();
+
+    // Match [current/total] pattern
+    if line.starts_with('[') {
+        if let Some(bracket_end) = line.find(']') {
+            let progress = &line[1..bracket_end];
+            if let Some(slash_pos) = progress.find('/') {
+                let current: usize = progress[..slash_pos].parse().ok()?;
+                let total: usize = progress[slash_pos + 1..].parse().ok()?;
+
+                // Check for special phases
+                let rest = &line[bracket_end + 1..];
+                let phase = if rest.contains("Linking") {
+                    Some("linking".to_string())
+                } else if rest.contains("Generating") {
+                    Some("generating".to_string())
+                } else {
+                    None
+                };
+
+                return Some((current, total, phase));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let timer = PhaseTimer::starting_at(start);
+        timer.line_at("-- Configuring done", at(5));
+        timer.line_at("[1/3] Building C object zephyr/main.c.obj", at(20));
+        timer.line_at("[2/3] Building C object zephyr/kscan.c.obj", at(50));
+        timer.line_at("[3/3] Linking C executable zephyr/zephyr.elf", at(60));
+
+        let phases = timer.phases();
+        assert_eq!(phases.configure, Duration::from_secs(20));
+        assert_eq!(phases.compile, Duration::from_secs(30));
+        assert_eq!(phases.link, Duration::from_secs(10));
+    }
+}
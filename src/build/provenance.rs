@@ -0,0 +1,175 @@
+//! Provenance stamping: where a firmware file came from.
+//!
+//! The config commit, build time and lfz version go into Zephyr's
+//! `BUILD_VERSION` (printed in the boot banner) and into a
+//! `<artifact>.build-info.json` written beside each artifact.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use super::target::BuildTarget;
+
+/// Build facts shared by every target of one build
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    /// Config repository commit, if it is a git checkout
+    pub commit: Option<String>,
+    /// Whether the config repository had uncommitted changes
+    pub dirty: bool,
+    pub built_at: SystemTime,
+    /// Image the firmware was built in
+    pub image: String,
+}
+
+/// Contents of `<artifact>.build-info.json`
+#[derive(Debug, Serialize)]
+struct BuildInfo<'a> {
+    artifact: &'a str,
+    sha256: String,
+    board: &'a str,
+    shield: Option<&'a str>,
+    config_commit: Option<&'a str>,
+    config_dirty: bool,
+    built_at: String,
+    build_version: String,
+    lfz_version: &'static str,
+    image: &'a str,
+}
+
+impl Provenance {
+    /// Inspect the config repository. `epoch` (seconds) replaces the
+    /// current time, e.g. `SOURCE_DATE_EPOCH` for reproducible builds.
+    pub fn collect(project_root: &Path, epoch: Option<&str>, image: &str) -> Self {
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(project_root)
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        };
+
+        let built_at = epoch
+            .and_then(|e| e.parse().ok())
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap_or_else(SystemTime::now);
+
+        Self {
+            commit: git(&["rev-parse", "HEAD"]),
+            dirty: git(&["status", "--porcelain"]).is_some_and(|s| !s.is_empty()),
+            built_at,
+            image: image.to_string(),
+        }
+    }
+
+    /// Version string baked into the firmware, e.g. `1a2b3c4-dirty-lfz0.3.0-20250101T120000Z`
+    pub fn build_version(&self) -> String {
+        let commit = self
+            .commit
+            .as_deref()
+            .map(|c| &c[..c.len().min(7)])
+            .unwrap_or("unknown");
+        let time: String = humantime::format_rfc3339_seconds(self.built_at)
+            .to_string()
+            .chars()
+            .filter(|c| *c != '-' && *c != ':')
+            .collect();
+        format!(
+            "{}{}-lfz{}-{}",
+            commit,
+            if self.dirty { "-dirty" } else { "" },
+            env!("CARGO_PKG_VERSION"),
+            time
+        )
+    }
+
+    /// Pass the build version to Zephyr (unless the target sets its own)
+    pub fn apply(&self, target: &mut BuildTarget) {
+        if !target
+            .cmake_args
+            .iter()
+            .any(|a| a.starts_with("-DBUILD_VERSION="))
+        {
+            target
+                .cmake_args
+                .push(format!("-DBUILD_VERSION={}", self.build_version()));
+        }
+    }
+
+    /// Write `<artifact>.build-info.json` beside an artifact
+    pub fn write(&self, artifact: &Path, target: &BuildTarget) -> Result<PathBuf> {
+        let contents =
+            fs::read(artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+        let info = BuildInfo {
+            artifact: &target.artifact_name,
+            sha256: format!("{:x}", Sha256::digest(&contents)),
+            board: &target.board,
+            shield: target.shield.as_deref(),
+            config_commit: self.commit.as_deref(),
+            config_dirty: self.dirty,
+            built_at: humantime::format_rfc3339_seconds(self.built_at).to_string(),
+            build_version: self.build_version(),
+            lfz_version: env!("CARGO_PKG_VERSION"),
+            image: &self.image,
+        };
+
+        let path = artifact.with_extension("build-info.json");
+        let json = serde_json::to_string_pretty(&info).context("Failed to serialize build info")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn provenance() -> Provenance {
+        Provenance {
+            commit: Some("1a2b3c4d5e6f".to_string()),
+            dirty: true,
+            built_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            image: "zmkfirmware/zmk-build-arm:stable".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_version() {
+        assert_eq!(
+            provenance().build_version(),
+            format!(
+                "1a2b3c4-dirty-lfz{}-20231114T221320Z",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_build_info() {
+        let dir = tempdir().unwrap();
+        let artifact = dir.path().join("corne_left-nice_nano_v2-zmk.uf2");
+        fs::write(&artifact, b"firmware").unwrap();
+        let target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+
+        let path = provenance().write(&artifact, &target).unwrap();
+        assert_eq!(
+            path.file_name().unwrap(),
+            "corne_left-nice_nano_v2-zmk.build-info.json"
+        );
+        let info: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(info["shield"], "corne_left");
+        assert_eq!(info["config_commit"], "1a2b3c4d5e6f");
+        assert_eq!(info["built_at"], "2023-11-14T22:13:20Z");
+        assert_eq!(info["sha256"].as_str().unwrap().len(), 64);
+    }
+}
@@ -0,0 +1,184 @@
+//! A remote store for the artifact cache, so that firmware built on one
+//! machine (or by CI) can be reused by everyone sharing the config repo.
+//!
+//! Entries are plain files under a base URL, fetched with GET and uploaded
+//! with PUT. An HTTP server accepting uploads, a WebDAV share or an S3
+//! bucket (writable through its bucket policy or a signing proxy) all work:
+//!
+//! ```text
+//! <remote>/<key>.uf2
+//! <remote>/<key>.config
+//! <remote>/<key>.json    uploaded last; an entry without it is incomplete
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::time::Duration;
+
+use super::artifact_cache::{ArtifactCache, ENTRY_FILE, FIRMWARE_FILE, KCONFIG_FILE};
+use crate::config::lfz_toml::CacheConfig;
+
+/// How long a single download or upload may take
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The entry files in the order they are uploaded, with their remote extension
+const FILES: [(&str, &str); 3] = [
+    (FIRMWARE_FILE, "uf2"),
+    (KCONFIG_FILE, "config"),
+    (ENTRY_FILE, "json"),
+];
+
+pub struct RemoteCache {
+    agent: ureq::Agent,
+    url: String,
+    token: Option<String>,
+    push: bool,
+}
+
+impl RemoteCache {
+    /// The remote store configured in lfz.toml, if any. `push` uploads
+    /// built firmware even if lfz.toml doesn't.
+    pub fn open(config: &CacheConfig, push: bool) -> Result<Option<Self>> {
+        let Some(url) = &config.remote else {
+            return Ok(None);
+        };
+        let token = match &config.token_env {
+            Some(var) => Some(
+                std::env::var(var)
+                    .with_context(|| format!("{} (the cache's token-env) is not set", var))?,
+            ),
+            None => None,
+        };
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(TRANSFER_TIMEOUT))
+            .build()
+            .into();
+
+        Ok(Some(Self {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            push: push || config.push,
+        }))
+    }
+
+    /// The remote's URL, for messages
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether built firmware is uploaded
+    pub fn pushes(&self) -> bool {
+        self.push
+    }
+
+    /// Download the entry under a key into the local cache. Returns whether
+    /// the remote has it.
+    pub fn pull(&self, key: &str, cache: &ArtifactCache) -> Result<bool> {
+        let mut files = Vec::new();
+        // The entry file comes last on upload, so it tells a complete entry
+        for (name, extension) in FILES.iter().rev() {
+            match self.get(&file_url(&self.url, key, extension))? {
+                Some(contents) => files.push((*name, contents)),
+                None if *name == KCONFIG_FILE => {}
+                None => return Ok(false),
+            }
+        }
+        cache.insert(key, &files)?;
+        Ok(true)
+    }
+
+    /// Upload the entry under a key from the local cache
+    pub fn push(&self, key: &str, cache: &ArtifactCache) -> Result<()> {
+        let files = cache
+            .entry_files(key)
+            .with_context(|| format!("No cached firmware under {}", key))?;
+        for (name, extension) in FILES {
+            let Some((_, path)) = files.iter().find(|(n, _)| *n == name) else {
+                continue;
+            };
+            let contents =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            self.put(&file_url(&self.url, key, extension), &contents)?;
+        }
+        Ok(())
+    }
+
+    /// The body of a URL, or `None` if it doesn't exist
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let mut request = self.agent.get(url);
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let mut response = request
+            .call()
+            .with_context(|| format!("Failed to reach {}", self.url))?;
+        match response.status().as_u16() {
+            200..=299 => {}
+            // S3 answers 403 for missing keys of buckets that can't be listed
+            403 | 404 => return Ok(None),
+            status => anyhow::bail!("{} responded with {}", url, status),
+        }
+        let contents = response
+            .body_mut()
+            .with_config()
+            .limit(u64::MAX)
+            .read_to_vec()
+            .with_context(|| format!("Failed to download {}", url))?;
+        Ok(Some(contents))
+    }
+
+    fn put(&self, url: &str, contents: &[u8]) -> Result<()> {
+        let mut request = self
+            .agent
+            .put(url)
+            .header("Content-Type", "application/octet-stream");
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = request
+            .send(contents)
+            .with_context(|| format!("Failed to reach {}", self.url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} responded with {}", url, response.status());
+        }
+        Ok(())
+    }
+}
+
+/// URL of one file of an entry
+fn file_url(base: &str, key: &str, extension: &str) -> String {
+    format!("{}/{}.{}", base, key, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open() {
+        let config = CacheConfig::default();
+        assert!(RemoteCache::open(&config, true).unwrap().is_none());
+
+        let config = CacheConfig {
+            remote: Some("https://cache.example.com/lfz/".to_string()),
+            token_env: None,
+            push: false,
+        };
+        let remote = RemoteCache::open(&config, false).unwrap().unwrap();
+        assert_eq!(remote.url(), "https://cache.example.com/lfz");
+        assert!(!remote.pushes());
+        assert_eq!(
+            file_url(remote.url(), "abc", "uf2"),
+            "https://cache.example.com/lfz/abc.uf2"
+        );
+        assert!(RemoteCache::open(&config, true).unwrap().unwrap().pushes());
+
+        let config = CacheConfig {
+            token_env: Some("LFZ_TEST_UNSET_CACHE_TOKEN".to_string()),
+            ..config
+        };
+        assert!(RemoteCache::open(&config, false).is_err());
+    }
+}
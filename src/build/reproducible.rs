@@ -0,0 +1,97 @@
+//! Reproducible builds: fixed timestamps and paths, checked by building twice.
+
+use std::path::Path;
+use std::process::Command;
+
+use super::target::BuildTarget;
+
+/// Compiler option keeping build paths out of the firmware (single-quoted
+/// for the container shell, so the Kconfig string keeps its double quotes)
+const PREFIX_MAP: &str = "'-DCONFIG_COMPILER_OPT=\"-ffile-prefix-map=/workspace=.\"'";
+
+/// Timestamp to build with: the last commit of the config repository,
+/// so rebuilding the same commit later yields the same firmware
+pub fn source_date_epoch(project_root: &Path) -> String {
+    Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(project_root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|epoch| !epoch.is_empty())
+        .unwrap_or_else(|| "0".to_string())
+}
+
+/// Add reproducibility settings to a target (its own values take precedence)
+pub fn apply(target: &mut BuildTarget, epoch: &str) {
+    target
+        .env
+        .entry("SOURCE_DATE_EPOCH".to_string())
+        .or_insert_with(|| epoch.to_string());
+    if !target
+        .cmake_args
+        .iter()
+        .any(|a| a.contains("CONFIG_COMPILER_OPT="))
+    {
+        target.cmake_args.push(PREFIX_MAP.to_string());
+    }
+}
+
+/// Describe how two builds of the same firmware differ, if they do
+pub fn compare(first: &[u8], second: &[u8]) -> Option<String> {
+    if first == second {
+        return None;
+    }
+
+    let differing = first.iter().zip(second).filter(|(a, b)| a != b).count();
+    let first_offset = first
+        .iter()
+        .zip(second)
+        .position(|(a, b)| a != b)
+        .unwrap_or(first.len().min(second.len()));
+
+    let mut message = format!(
+        "{} byte(s) differ, first at offset {:#x}",
+        differing, first_offset
+    );
+    if first.len() != second.len() {
+        message.push_str(&format!(
+            "; sizes {} vs {} bytes",
+            first.len(),
+            second.len()
+        ));
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_keeps_target_settings() {
+        let mut target = BuildTarget::from_args("nice_nano_v2".to_string(), None).unwrap();
+        target
+            .env
+            .insert("SOURCE_DATE_EPOCH".to_string(), "42".to_string());
+        apply(&mut target, "1700000000");
+        apply(&mut target, "1700000000");
+
+        assert_eq!(target.env["SOURCE_DATE_EPOCH"], "42");
+        assert_eq!(target.cmake_args, [PREFIX_MAP]);
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare(b"abcd", b"abcd"), None);
+        assert_eq!(
+            compare(b"abcd", b"abxy").as_deref(),
+            Some("2 byte(s) differ, first at offset 0x2")
+        );
+        assert_eq!(
+            compare(b"abc", b"abcd").as_deref(),
+            Some("0 byte(s) differ, first at offset 0x3; sizes 3 vs 4 bytes")
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Software bill of materials for built firmware.
+//!
+//! Lists every west project at the commit it was built from, plus the
+//! build image, in CycloneDX or SPDX JSON next to each artifact.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Supported SBOM formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SbomFormat {
+    #[value(name = "cyclonedx")]
+    CycloneDx,
+    Spdx,
+}
+
+impl SbomFormat {
+    /// File extension replacing the artifact's `.uf2`
+    fn extension(&self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "cdx.json",
+            SbomFormat::Spdx => "spdx.json",
+        }
+    }
+}
+
+/// A west project that went into the firmware
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub name: String,
+    /// Commit the project was checked out at
+    pub commit: String,
+    pub url: Option<String>,
+}
+
+/// Everything shared by the SBOMs of one build
+#[derive(Debug)]
+pub struct BillOfMaterials {
+    pub components: Vec<Component>,
+    pub image: String,
+    /// Content digest of the build image, if the runtime reports one
+    pub image_digest: Option<String>,
+}
+
+impl BillOfMaterials {
+    /// Write the SBOM for an artifact next to it, returning its path
+    pub fn write(&self, format: SbomFormat, artifact: &Path, firmware: &str) -> Result<PathBuf> {
+        let document = match format {
+            SbomFormat::CycloneDx => self.cyclonedx(firmware),
+            SbomFormat::Spdx => self.spdx(firmware),
+        };
+        let path = artifact.with_extension(format.extension());
+        let contents =
+            serde_json::to_string_pretty(&document).context("Failed to serialize SBOM")?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    fn cyclonedx(&self, firmware: &str) -> Value {
+        let mut components: Vec<Value> = self
+            .components
+            .iter()
+            .map(|c| {
+                let mut component = json!({
+                    "type": "library",
+                    "bom-ref": c.name,
+                    "name": c.name,
+                    "version": c.commit,
+                });
+                if let Some(url) = &c.url {
+                    component["externalReferences"] = json!([{ "type": "vcs", "url": url }]);
+                }
+                component
+            })
+            .collect();
+        components.push(json!({
+            "type": "container",
+            "bom-ref": "build-image",
+            "name": self.image,
+            "version": self.image_digest.as_deref().unwrap_or("unknown"),
+        }));
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": timestamp(),
+                "tools": { "components": [{
+                    "type": "application",
+                    "name": "lfz",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]},
+                "component": { "type": "firmware", "bom-ref": firmware, "name": firmware },
+            },
+            "components": components,
+        })
+    }
+
+    fn spdx(&self, firmware: &str) -> Value {
+        let firmware_id = spdx_id(firmware);
+        let mut packages = vec![json!({
+            "name": firmware,
+            "SPDXID": firmware_id,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "FIRMWARE",
+        })];
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": firmware_id,
+        })];
+
+        for c in &self.components {
+            packages.push(json!({
+                "name": c.name,
+                "SPDXID": spdx_id(&c.name),
+                "versionInfo": c.commit,
+                "downloadLocation": c.url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+            }));
+            relationships.push(json!({
+                "spdxElementId": firmware_id,
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": spdx_id(&c.name),
+            }));
+        }
+        packages.push(json!({
+            "name": self.image,
+            "SPDXID": "SPDXRef-build-image",
+            "versionInfo": self.image_digest.as_deref().unwrap_or("NOASSERTION"),
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "CONTAINER",
+        }));
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-build-image",
+            "relationshipType": "BUILD_TOOL_OF",
+            "relatedSpdxElement": firmware_id,
+        }));
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": firmware,
+            "documentNamespace": format!(
+                "https://spdx.org/spdxdocs/lfz/{}-{}",
+                firmware,
+                unix_time()
+            ),
+            "creationInfo": {
+                "created": timestamp(),
+                "creators": [format!("Tool: lfz-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+/// SPDX identifiers only allow letters, digits, `.` and `-`
+fn spdx_id(name: &str) -> String {
+    let id: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-{}", id)
+}
+
+fn timestamp() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn bom() -> BillOfMaterials {
+        BillOfMaterials {
+            components: vec![Component {
+                name: "zmk".to_string(),
+                commit: "0123456789abcdef0123456789abcdef01234567".to_string(),
+                url: Some("https://github.com/zmkfirmware/zmk".to_string()),
+            }],
+            image: "zmkfirmware/zmk-build-arm:stable".to_string(),
+            image_digest: Some("sha256:abc".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_cyclonedx() {
+        let dir = tempdir().unwrap();
+        let artifact = dir.path().join("corne_left-nice_nano_v2-zmk.uf2");
+        let path = bom()
+            .write(
+                SbomFormat::CycloneDx,
+                &artifact,
+                "corne_left-nice_nano_v2-zmk",
+            )
+            .unwrap();
+        assert_eq!(
+            path.file_name().unwrap(),
+            "corne_left-nice_nano_v2-zmk.cdx.json"
+        );
+
+        let doc: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["components"][0]["name"], "zmk");
+        assert_eq!(
+            doc["components"][0]["externalReferences"][0]["url"],
+            "https://github.com/zmkfirmware/zmk"
+        );
+        assert_eq!(doc["components"][1]["version"], "sha256:abc");
+    }
+
+    #[test]
+    fn test_spdx() {
+        let doc = bom().spdx("corne_left-nice_nano_v2-zmk");
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["packages"][1]["SPDXID"], "SPDXRef-zmk");
+        assert_eq!(
+            doc["packages"][0]["SPDXID"],
+            "SPDXRef-corne-left-nice-nano-v2-zmk"
+        );
+        assert_eq!(doc["relationships"].as_array().unwrap().len(), 3);
+    }
+}
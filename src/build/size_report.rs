@@ -0,0 +1,109 @@
+//! Zephyr's size reports (`rom_report`/`ram_report`), which break a
+//! firmware's flash or RAM usage down by source path. Besides printing
+//! them, the build system writes them as `rom.json`/`ram.json` into the
+//! build directory, which is what lfz reads.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use super::target::BuildTarget;
+
+/// Which memory a size report covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportKind {
+    Rom,
+    Ram,
+}
+
+impl ReportKind {
+    /// Build system target producing the report
+    pub fn west_target(&self) -> &'static str {
+        match self {
+            ReportKind::Rom => "rom_report",
+            ReportKind::Ram => "ram_report",
+        }
+    }
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            ReportKind::Rom => "rom.json",
+            ReportKind::Ram => "ram.json",
+        }
+    }
+}
+
+/// A path, file or symbol and the bytes it takes up, including its children
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SizeNode {
+    pub name: String,
+    pub size: u64,
+    #[serde(default)]
+    pub children: Vec<SizeNode>,
+}
+
+#[derive(Deserialize)]
+struct SizeReport {
+    symbols: SizeNode,
+}
+
+/// Load the report written by the last report run of a target (for
+/// sysbuild, the zmk image's)
+pub fn load(workspace: &Path, target: &BuildTarget, kind: ReportKind) -> Result<SizeNode> {
+    let candidates = [
+        workspace.join(&target.build_dir).join(kind.file_name()),
+        workspace
+            .join(&target.build_dir)
+            .join("zmk")
+            .join(kind.file_name()),
+    ];
+    let path = candidates
+        .iter()
+        .find(|path| path.is_file())
+        .with_context(|| {
+            format!(
+                "No {} in {}'s build directory",
+                kind.file_name(),
+                target.artifact_name
+            )
+        })?;
+    parse(&fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn parse(json: &str) -> Result<SizeNode> {
+    Ok(serde_json::from_str::<SizeReport>(json)?.symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{
+            "symbols": {
+                "identifier": "root",
+                "name": "Root",
+                "size": 300,
+                "children": [
+                    {
+                        "identifier": "WORKSPACE",
+                        "name": "WORKSPACE",
+                        "size": 300,
+                        "children": [
+                            {"identifier": "main.c", "name": "main.c", "size": 300}
+                        ]
+                    }
+                ]
+            },
+            "total_size": 300
+        }"#;
+        let root = parse(json).unwrap();
+        assert_eq!(root.size, 300);
+        assert_eq!(root.children[0].children[0].name, "main.c");
+        assert!(root.children[0].children[0].children.is_empty());
+    }
+}
@@ -1,9 +1,14 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::config::build_yaml::BuildInclude;
+use super::filter::matches_any_glob;
+use super::memory::MemoryLimits;
+use crate::config::build_config::BuildInclude;
 
 /// A resolved build target ready for building
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BuildTarget {
     /// Board identifier (e.g., "nice_nano_v2", "seeeduino_xiao_ble")
     pub board: String,
@@ -25,6 +30,36 @@ pub struct BuildTarget {
 
     /// Optional group for filtering (e.g., "central", "peripheral")
     pub group: Option<String>,
+
+    /// Free-form tags for filtering with `--tags` expressions
+    pub tags: Vec<String>,
+
+    /// Extra environment variables for this target's build container
+    pub env: BTreeMap<String, String>,
+
+    /// Zephyr modules mounted for this target only
+    pub extra_modules: Vec<PathBuf>,
+
+    /// Per-target build timeout (overrides `--timeout`)
+    pub timeout: Option<Duration>,
+
+    /// Targets with a higher priority start building first
+    pub priority: i32,
+
+    /// Parallel compile jobs within this target's build (`--build-jobs`;
+    /// ninja's default otherwise)
+    pub build_jobs: Option<usize>,
+
+    /// Flash and RAM usage past which the build fails
+    pub memory_limits: MemoryLimits,
+
+    /// Build from scratch even if the run is incremental, because inputs
+    /// of this target changed since its build directory was configured
+    pub pristine: bool,
+
+    /// Only keymaps and `.conf` files changed since this target's build
+    /// directory was built: rebuild it in place, without the pristine checks
+    pub rebuild_only: bool,
 }
 
 impl BuildTarget {
@@ -41,9 +76,35 @@ impl BuildTarget {
             artifact_name,
             build_dir,
             group: None,
+            tags: Vec::new(),
+            env: BTreeMap::new(),
+            extra_modules: Vec::new(),
+            timeout: None,
+            priority: 0,
+            build_jobs: None,
+            memory_limits: MemoryLimits::default(),
+            pristine: false,
+            rebuild_only: false,
         })
     }
 
+    /// Create a target from a `board:shield` (or just `board`) CLI pair
+    pub fn from_pair(pair: &str) -> Result<Self> {
+        let (board, shield) = match pair.split_once(':') {
+            Some((board, shield)) => (board.trim(), Some(shield.trim())),
+            None => (pair.trim(), None),
+        };
+
+        if board.is_empty() || shield.is_some_and(|s| s.is_empty()) {
+            anyhow::bail!(
+                "Invalid pair '{}': expected 'board:shield' or 'board'",
+                pair
+            );
+        }
+
+        Self::from_args(board.to_string(), shield.map(String::from))
+    }
+
     /// Create a target from a build.yaml include entry
     pub fn from_include(include: &BuildInclude) -> Result<Self> {
         let artifact_name = include.artifact_name.clone().unwrap_or_else(|| {
@@ -71,9 +132,69 @@ impl BuildTarget {
             artifact_name,
             build_dir,
             group: include.group.clone(),
+            tags: include.tags.clone(),
+            env: include.env.clone(),
+            extra_modules: include.extra_modules.clone(),
+            timeout: include.timeout,
+            priority: include.priority.unwrap_or(0),
+            build_jobs: None,
+            memory_limits: MemoryLimits {
+                max_flash: include.max_flash,
+                max_ram: include.max_ram,
+            },
+            pristine: false,
+            rebuild_only: false,
         })
     }
 
+    /// The one target matching `glob`, or the only target there is
+    pub fn select(targets: Vec<BuildTarget>, glob: Option<&str>) -> Result<BuildTarget> {
+        let matching: Vec<_> = match glob {
+            Some(glob) => targets
+                .into_iter()
+                .filter(|t| matches_any_glob(&[glob.to_string()], &t.artifact_name))
+                .collect(),
+            None => targets,
+        };
+
+        match matching.len() {
+            1 => Ok(matching.into_iter().next().unwrap()),
+            0 => anyhow::bail!("No targets match. Run 'lfz list' to see available targets."),
+            _ => anyhow::bail!(
+                "Pick a single target with --target: {}",
+                matching
+                    .iter()
+                    .map(|t| t.artifact_name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Sort targets into the order they should start building: by priority,
+    /// then by the position of their group in `group_order`, then as listed
+    pub fn schedule(targets: &mut [BuildTarget], group_order: &[String]) {
+        targets.sort_by_key(|t| {
+            let group = t
+                .group
+                .as_ref()
+                .and_then(|g| group_order.iter().position(|o| o == g))
+                .unwrap_or(group_order.len());
+            (std::cmp::Reverse(t.priority), group)
+        });
+    }
+
+    /// Project-wide modules followed by this target's own extra modules
+    pub fn extra_modules_with(&self, project_modules: &[PathBuf]) -> Vec<PathBuf> {
+        let mut modules = project_modules.to_vec();
+        for module in &self.extra_modules {
+            if !modules.contains(module) {
+                modules.push(module.clone());
+            }
+        }
+        modules
+    }
+
     /// Sanitize a board identifier for use in filesystem paths.
     /// Replaces `//` (sysbuild domain qualifier) with `_` to avoid nested directories.
     /// e.g. "xiao_ble//zmk" -> "xiao_ble_zmk"
@@ -84,7 +205,7 @@ impl BuildTarget {
     /// Generate artifact name from board and shield.
     /// Matches the ZMK GitHub Actions naming scheme:
     ///   ${artifact_name:-${shield:+$shield-}${board//\//_}-zmk}
-    fn generate_artifact_name(board: &str, shield: Option<&str>) -> String {
+    pub(crate) fn generate_artifact_name(board: &str, shield: Option<&str>) -> String {
         let sanitized = Self::sanitize_board(board);
         match shield {
             Some(s) => format!("{}-{}-zmk", s, sanitized),
@@ -118,6 +239,11 @@ impl BuildTarget {
             }
         }
 
+        // Passed through to ninja
+        if let Some(jobs) = self.build_jobs {
+            args.push(format!("-o=-j{}", jobs));
+        }
+
         // Add -- separator for CMake args
         args.push("--".to_string());
 
@@ -135,6 +261,55 @@ impl BuildTarget {
         args
     }
 
+    /// `west build` arguments for building the target: a plain rebuild of
+    /// its build directory if it is `rebuild_only` (and not built pristine),
+    /// a full build otherwise
+    pub fn west_args(&self, config_path: &str, pristine: bool) -> Vec<String> {
+        if self.rebuild_only && !pristine {
+            self.west_rebuild_args()
+        } else {
+            self.west_build_args(config_path, pristine)
+        }
+    }
+
+    /// `west build` arguments rebuilding an existing build directory. Without
+    /// a board, west takes it from the CMake cache; ninja reruns CMake itself
+    /// since keymaps and `.conf` files are configure dependencies. The
+    /// trailing `--` keeps CMake arguments appended by the build script
+    /// (`-DZMK_EXTRA_MODULES=...`) from being read as the source directory.
+    pub fn west_rebuild_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "build".to_string(),
+            "-d".to_string(),
+            self.build_dir.clone(),
+        ];
+        if let Some(jobs) = self.build_jobs {
+            args.push(format!("-o=-j{}", jobs));
+        }
+        args.push("--".to_string());
+        args
+    }
+
+    /// `west build` arguments running a build system target (e.g.
+    /// `rom_report`) instead of the default one
+    pub fn west_target_args(&self, config_path: &str, west_target: &str) -> Vec<String> {
+        let mut args = self.west_build_args(config_path, false);
+        let separator = args.iter().position(|a| a == "--").unwrap_or(args.len());
+        args.splice(
+            separator..separator,
+            ["-t".to_string(), west_target.to_string()],
+        );
+        args
+    }
+
+    /// `west build` arguments running only CMake, without building
+    pub fn west_configure_args(&self, config_path: &str) -> Vec<String> {
+        let mut args = self.west_build_args(config_path, false);
+        let separator = args.iter().position(|a| a == "--").unwrap_or(args.len());
+        args.insert(separator, "--cmake-only".to_string());
+        args
+    }
+
     /// Get candidate paths for the output firmware file (relative to workspace root).
     /// Returns paths in priority order:
     ///   1. {build_dir}/zephyr/zmk.uf2  - standard or merged sysbuild output
@@ -211,6 +386,28 @@ mod tests {
         assert_eq!(target.artifact_name, "xiao_ble_zmk-zmk");
     }
 
+    #[test]
+    fn test_from_pair_with_shield() {
+        let target = BuildTarget::from_pair("nice_nano_v2:corne_left").unwrap();
+        assert_eq!(target.board, "nice_nano_v2");
+        assert_eq!(target.shield, Some("corne_left".to_string()));
+        assert_eq!(target.artifact_name, "corne_left-nice_nano_v2-zmk");
+    }
+
+    #[test]
+    fn test_from_pair_hwmv2_board_without_shield() {
+        let target = BuildTarget::from_pair("xiao_ble//zmk").unwrap();
+        assert_eq!(target.board, "xiao_ble//zmk");
+        assert_eq!(target.shield, None);
+    }
+
+    #[test]
+    fn test_from_pair_invalid() {
+        assert!(BuildTarget::from_pair("").is_err());
+        assert!(BuildTarget::from_pair(":corne_left").is_err());
+        assert!(BuildTarget::from_pair("nice_nano_v2:").is_err());
+    }
+
     #[test]
     fn test_from_include_custom_artifact_name_preserved() {
         let include = BuildInclude {
@@ -220,6 +417,13 @@ mod tests {
             snippet: None,
             artifact_name: Some("my_custom_name".to_string()),
             group: None,
+            tags: Vec::new(),
+            env: BTreeMap::new(),
+            extra_modules: Vec::new(),
+            timeout: None,
+            priority: None,
+            max_flash: None,
+            max_ram: None,
         };
 
         let target = BuildTarget::from_include(&include).unwrap();
@@ -269,6 +473,61 @@ mod tests {
         assert!(args.contains(&"-p".to_string()));
     }
 
+    #[test]
+    fn test_west_build_args_build_jobs() {
+        let mut target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+        target.build_jobs = Some(4);
+
+        let args = target.west_build_args("/workspace/config", false);
+
+        let jobs = args.iter().position(|a| a == "-o=-j4").unwrap();
+        assert!(jobs < args.iter().position(|a| a == "--").unwrap());
+    }
+
+    #[test]
+    fn test_west_args_rebuild_only() {
+        let mut target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+        target.rebuild_only = true;
+        target.build_jobs = Some(4);
+
+        assert_eq!(
+            target.west_args("/workspace/config", false),
+            [
+                "build",
+                "-d",
+                "build/corne_left-nice_nano_v2-zmk",
+                "-o=-j4",
+                "--"
+            ]
+        );
+        // Retrying pristine builds from scratch
+        assert_eq!(
+            target.west_args("/workspace/config", true),
+            target.west_build_args("/workspace/config", true)
+        );
+    }
+
+    #[test]
+    fn test_west_target_args() {
+        let target =
+            BuildTarget::from_args("nice_nano_v2".to_string(), Some("corne_left".to_string()))
+                .unwrap();
+
+        let args = target.west_target_args("/workspace/config", "rom_report");
+
+        let separator = args.iter().position(|a| a == "--").unwrap();
+        assert_eq!(args[separator - 2..separator], ["-t", "rom_report"]);
+        assert!(args.contains(&"-DSHIELD=corne_left".to_string()));
+
+        let args = target.west_configure_args("/workspace/config");
+        let separator = args.iter().position(|a| a == "--").unwrap();
+        assert_eq!(args[separator - 1], "--cmake-only");
+    }
+
     #[test]
     fn test_west_build_args_with_snippet() {
         let include = BuildInclude {
@@ -278,6 +537,13 @@ mod tests {
             snippet: Some("studio-rpc-usb-uart zmk-usb-logging".to_string()),
             artifact_name: None,
             group: None,
+            tags: Vec::new(),
+            env: BTreeMap::new(),
+            extra_modules: Vec::new(),
+            timeout: None,
+            priority: None,
+            max_flash: None,
+            max_ram: None,
         };
 
         let target = BuildTarget::from_include(&include).unwrap();
@@ -313,6 +579,13 @@ mod tests {
             snippet: None,
             artifact_name: None,
             group: None,
+            tags: Vec::new(),
+            env: BTreeMap::new(),
+            extra_modules: Vec::new(),
+            timeout: None,
+            priority: None,
+            max_flash: None,
+            max_ram: None,
         };
 
         let target = BuildTarget::from_include(&include).unwrap();
@@ -323,6 +596,27 @@ mod tests {
             .contains(&"-DCONFIG_ZMK_SPLIT=y".to_string()));
     }
 
+    #[test]
+    fn test_schedule() {
+        let target = |shield: &str, group: Option<&str>, priority: i32| BuildTarget {
+            group: group.map(str::to_string),
+            priority,
+            ..BuildTarget::from_args("nice_nano_v2".to_string(), Some(shield.to_string())).unwrap()
+        };
+        let mut targets = vec![
+            target("reset", None, 0),
+            target("right", Some("peripheral"), 0),
+            target("left", Some("central"), 0),
+            target("dongle", Some("dongle"), 5),
+        ];
+        BuildTarget::schedule(&mut targets, &["central".to_string()]);
+        let order: Vec<_> = targets
+            .iter()
+            .map(|t| t.shield.as_deref().unwrap())
+            .collect();
+        assert_eq!(order, ["dongle", "left", "reset", "right"]);
+    }
+
     #[test]
     fn test_firmware_path_candidates() {
         let target =
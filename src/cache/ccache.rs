@@ -0,0 +1,339 @@
+//! ccache statistics of the shared cache, to tell how much it saves.
+//!
+//! `ccache --print-stats` (run in the build image) reports them as
+//! `name<TAB>value` lines. Between two points of a build the counters are
+//! read straight from the cache's `stats` files instead, which hold one
+//! counter per line in ccache's fixed order; that needs no container.
+//!
+//! The `[ccache]` settings of lfz.toml go into the cache's `ccache.conf`,
+//! which ccache reads in every build container.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::ops::Sub;
+use std::path::{Path, PathBuf};
+
+use crate::config::lfz_toml::CcacheConfig;
+use crate::container::{ContainerCommand, Runtime, DEFAULT_IMAGE};
+
+/// ccache's configuration file in the cache directory
+const CONFIG_FILE: &str = "ccache.conf";
+
+/// First line of a configuration lfz wrote, which it may replace
+const CONFIG_HEADER: &str = "# Written by lfz from [ccache] in lfz.toml";
+
+/// Names the image a cache directory is for (see `paths::image_ccache_dir`)
+const IMAGE_FILE: &str = ".lfz_image";
+
+/// Line of each counter in a `stats` file
+const DIRECT_HIT: usize = 22;
+const PREPROCESSED_HIT: usize = 8;
+const MISS: usize = 4;
+const FILES: usize = 11;
+const SIZE_KIB: usize = 12;
+const CLEANUPS: usize = 29;
+
+/// Counters of the cache, or how they changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CcacheStats {
+    /// Compilations served from the cache
+    pub hits: u64,
+    /// Compilations that ran and were added to the cache
+    pub misses: u64,
+    pub files: u64,
+    pub size_kib: u64,
+    /// Times ccache evicted files to stay under its size limit
+    pub cleanups: u64,
+}
+
+impl CcacheStats {
+    /// Percentage of cacheable compilations that were hits
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 * 100.0 / total as f64)
+    }
+
+    /// Parse the output of `ccache --print-stats`
+    pub fn parse(output: &str) -> Self {
+        let mut stats = Self::default();
+        for line in output.lines() {
+            let Some((name, value)) = line.split_once('\t') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<u64>() else {
+                continue;
+            };
+            match name {
+                "direct_cache_hit" | "preprocessed_cache_hit" => stats.hits += value,
+                "cache_miss" => stats.misses += value,
+                "files_in_cache" => stats.files += value,
+                "cache_size_kibibyte" => stats.size_kib += value,
+                "cleanups_performed" => stats.cleanups += value,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Sum the `stats` files of a cache directory (ccache keeps them in its
+    /// first and, since 4.x, second level of subdirectories)
+    pub fn read(dir: &Path) -> Self {
+        let mut stats = Self::default();
+        add_stats_files(dir, 2, &mut stats);
+        stats
+    }
+
+    fn add_counters(&mut self, contents: &str) {
+        let counters: Vec<u64> = contents
+            .lines()
+            .map(|line| line.trim().parse().unwrap_or(0))
+            .collect();
+        let counter = |index: usize| counters.get(index).copied().unwrap_or(0);
+        self.hits += counter(DIRECT_HIT) + counter(PREPROCESSED_HIT);
+        self.misses += counter(MISS);
+        self.files += counter(FILES);
+        self.size_kib += counter(SIZE_KIB);
+        self.cleanups += counter(CLEANUPS);
+    }
+}
+
+impl Sub for CcacheStats {
+    type Output = Self;
+
+    /// How the counters changed since `earlier` (zero where the cache was
+    /// cleared in between)
+    fn sub(self, earlier: Self) -> Self {
+        Self {
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+            files: self.files.saturating_sub(earlier.files),
+            size_kib: self.size_kib.saturating_sub(earlier.size_kib),
+            cleanups: self.cleanups.saturating_sub(earlier.cleanups),
+        }
+    }
+}
+
+/// Note which image the cache in `dir` is for
+pub fn record_image(dir: &Path, image: &str) -> Result<()> {
+    let path = dir.join(IMAGE_FILE);
+    if fs::read_to_string(&path).is_ok_and(|recorded| recorded == image) {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).context("Failed to create ccache directory")?;
+    fs::write(&path, image).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The per-image caches in the ccache directory `root`, with the image
+/// each is for
+pub fn image_caches(root: &Path) -> Vec<(PathBuf, String)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut caches: Vec<(PathBuf, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let image = fs::read_to_string(entry.path().join(IMAGE_FILE)).ok()?;
+            Some((entry.path(), image.trim().to_string()))
+        })
+        .collect();
+    caches.sort();
+    caches
+}
+
+/// Apply lfz.toml's settings to the cache in `dir`. Without any, the
+/// configuration stays as it is: the cache is shared, and another
+/// project's settings shouldn't come and go with each build.
+pub fn configure(dir: &Path, config: &CcacheConfig) -> Result<()> {
+    let path = dir.join(CONFIG_FILE);
+    let existing = fs::read_to_string(&path).ok();
+    if let Some(contents) = render(config)? {
+        if existing.as_deref() != Some(contents.as_str()) {
+            fs::create_dir_all(dir).context("Failed to create ccache directory")?;
+            fs::write(&path, contents)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// The `ccache.conf` for lfz.toml's settings, if there are any
+fn render(config: &CcacheConfig) -> Result<Option<String>> {
+    let mut lines = Vec::new();
+    if let Some(max_size) = config.max_size()? {
+        // Without a suffix ccache reads gigabytes
+        lines.push(format!("max_size = {}Ki", max_size / 1024));
+    }
+    if let Some(compression) = config.compression {
+        lines.push(format!("compression = {}", compression));
+    }
+    if let Some(level) = config.compression_level {
+        lines.push(format!("compression_level = {}", level));
+    }
+    if lines.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!("{}\n{}\n", CONFIG_HEADER, lines.join("\n"))))
+}
+
+/// Evict files from the cache in `dir` until it fits its size limit.
+/// ccache does this as it compiles, but only once a subdirectory it writes
+/// to overflows, so a lowered limit would otherwise take long to apply.
+pub fn trim(runtime: &Runtime, dir: &Path) -> Result<()> {
+    let status = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+        .mount(dir, "/root/.ccache", false)
+        .env("CCACHE_DIR", "/root/.ccache")
+        .command(vec!["ccache".to_string(), "--cleanup".to_string()])
+        .build()
+        .stdout(std::process::Stdio::null())
+        .status()
+        .context("Failed to run ccache in the container")?;
+    if !status.success() {
+        anyhow::bail!("ccache --cleanup failed");
+    }
+    Ok(())
+}
+
+fn add_stats_files(dir: &Path, depth: usize, stats: &mut CcacheStats) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                add_stats_files(&path, depth - 1, stats);
+            }
+        } else if entry.file_name() == "stats" {
+            if let Ok(contents) = fs::read_to_string(&path) {
+                stats.add_counters(&contents);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn stats_file(counters: &[(usize, u64)]) -> String {
+        let mut lines = vec![0u64; 32];
+        for &(index, value) in counters {
+            lines[index] = value;
+        }
+        lines.iter().map(|v| format!("{}\n", v)).collect::<String>()
+    }
+
+    #[test]
+    fn test_parse() {
+        let output = "cache_miss\t30\n\
+                      cache_size_kibibyte\t20480\n\
+                      cleanups_performed\t2\n\
+                      direct_cache_hit\t100\n\
+                      files_in_cache\t450\n\
+                      preprocessed_cache_hit\t20\n\
+                      stats_updated_timestamp\t1700000000\n";
+        let stats = CcacheStats::parse(output);
+        assert_eq!(
+            stats,
+            CcacheStats {
+                hits: 120,
+                misses: 30,
+                files: 450,
+                size_kib: 20480,
+                cleanups: 2,
+            }
+        );
+        assert_eq!(stats.hit_rate(), Some(80.0));
+        assert_eq!(CcacheStats::default().hit_rate(), None);
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a")).unwrap();
+        fs::create_dir_all(dir.path().join("b/3")).unwrap();
+        fs::write(
+            dir.path().join("a/stats"),
+            stats_file(&[(DIRECT_HIT, 5), (MISS, 2), (FILES, 7)]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b/3/stats"),
+            stats_file(&[(PREPROCESSED_HIT, 1), (SIZE_KIB, 64), (CLEANUPS, 1)]),
+        )
+        .unwrap();
+        // Older ccache versions have fewer counters
+        fs::write(dir.path().join("b/stats"), "0\n0\n0\n0\n3\n").unwrap();
+
+        let before = CcacheStats::read(dir.path());
+        assert_eq!(
+            before,
+            CcacheStats {
+                hits: 6,
+                misses: 5,
+                files: 7,
+                size_kib: 64,
+                cleanups: 1,
+            }
+        );
+        fs::write(
+            dir.path().join("a/stats"),
+            stats_file(&[(DIRECT_HIT, 9), (MISS, 2), (FILES, 7)]),
+        )
+        .unwrap();
+        let delta = CcacheStats::read(dir.path()) - before;
+        assert_eq!((delta.hits, delta.misses), (4, 0));
+        assert_eq!(
+            CcacheStats::read(&dir.path().join("missing")),
+            CcacheStats::default()
+        );
+    }
+
+    #[test]
+    fn test_image_caches() {
+        let root = tempdir().unwrap();
+        // The cache of lfz versions before per-image caches
+        fs::create_dir_all(root.path().join("a")).unwrap();
+        fs::write(root.path().join("a/stats"), "0\n").unwrap();
+        let stable = root.path().join("0a1b2c3d4e5f");
+        record_image(&stable, "zmk-build-arm@sha256:0a1b").unwrap();
+        record_image(&stable, "zmk-build-arm@sha256:0a1b").unwrap();
+        assert_eq!(
+            image_caches(root.path()),
+            [(stable, "zmk-build-arm@sha256:0a1b".to_string())]
+        );
+        assert!(image_caches(&root.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_configure() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        let config = CcacheConfig {
+            max_size: Some("2g".to_string()),
+            compression: Some(true),
+            compression_level: None,
+        };
+        configure(dir.path(), &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!(
+                "{}\nmax_size = 2097152Ki\ncompression = true\n",
+                CONFIG_HEADER
+            )
+        );
+
+        // Settings removed from lfz.toml
+        // A project without settings leaves them to the others
+        let written = fs::read_to_string(&path).unwrap();
+        configure(dir.path(), &CcacheConfig::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+
+        let invalid = CcacheConfig {
+            max_size: Some("lots".to_string()),
+            ..Default::default()
+        };
+        assert!(configure(dir.path(), &invalid).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! The compiler cache build containers share, so that rebuilding a target
+//! (or building another one from the same sources) skips unchanged
+//! compilations.
+//!
+//! With ccache (the default) Zephyr finds `ccache` in the image and
+//! launches the compiler through it. sccache is set as CMake's compiler
+//! launcher through the environment instead, with ccache disabled so the
+//! two don't cache the same compilations.
+
+pub mod ccache;
+pub mod remote;
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::lfz_toml::{CacheBackend, CompilerCacheConfig};
+use crate::container::{Runtime, DEFAULT_IMAGE};
+use crate::paths;
+use remote::Remote;
+
+/// Where the cache directory is mounted in the containers
+const CCACHE_MOUNT: &str = "/root/.ccache";
+const SCCACHE_MOUNT: &str = "/root/.cache/sccache";
+
+/// Where an sccache binary from the host is mounted
+const SCCACHE_BINARY_MOUNT: &str = "/usr/local/bin/sccache";
+
+/// What ccache's directory is keyed by: the build image's digest, or its
+/// tag if the runtime can't tell
+pub fn image_key(runtime: &Runtime, image: &str) -> String {
+    runtime
+        .image_digest(image)
+        .unwrap_or_else(|| image.to_string())
+}
+
+/// The compiler cache of a project's builds
+#[derive(Debug, Clone, Default)]
+pub struct CompilerCache {
+    backend: CacheBackend,
+    sccache_binary: Option<PathBuf>,
+    remote: Option<Remote>,
+    /// Digest of the build image, which ccache's directory is keyed by
+    image: Option<String>,
+}
+
+impl CompilerCache {
+    pub fn from_config(config: &CompilerCacheConfig) -> Result<Self> {
+        let sccache_binary = config.sccache_binary();
+        if let Some(binary) = sccache_binary.as_ref().filter(|binary| !binary.is_file()) {
+            anyhow::bail!(
+                "compiler-cache.sccache-binary in lfz.toml: {} doesn't exist",
+                binary.display()
+            );
+        }
+        Ok(Self {
+            backend: config.backend,
+            sccache_binary,
+            remote: None,
+            image: None,
+        })
+    }
+
+    /// Key the cache by this image digest (the image's tag by default)
+    pub fn image(mut self, digest: impl Into<String>) -> Self {
+        self.image = Some(digest.into());
+        self
+    }
+
+    /// Share compilations through a remote store as well
+    pub fn remote(mut self, remote: Remote) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    pub fn backend(&self) -> CacheBackend {
+        self.backend
+    }
+
+    /// The host directory holding the cache
+    pub fn dir(&self) -> Result<PathBuf> {
+        match self.backend {
+            CacheBackend::Ccache => {
+                paths::image_ccache_dir(self.image.as_deref().unwrap_or(DEFAULT_IMAGE))
+            }
+            CacheBackend::Sccache => paths::sccache_dir(),
+        }
+    }
+
+    /// Mounts (host path, container path, read-only) a build container
+    /// needs, given the cache directory
+    pub fn mounts(&self, dir: &Path) -> Vec<(PathBuf, &'static str, bool)> {
+        match self.backend {
+            CacheBackend::Ccache => vec![(dir.to_path_buf(), CCACHE_MOUNT, false)],
+            CacheBackend::Sccache => {
+                let mut mounts = vec![(dir.to_path_buf(), SCCACHE_MOUNT, false)];
+                if let Some(binary) = &self.sccache_binary {
+                    mounts.push((binary.clone(), SCCACHE_BINARY_MOUNT, true));
+                }
+                mounts
+            }
+        }
+    }
+
+    /// Environment a build container needs that holds credentials
+    pub fn secret_env(&self) -> Vec<(String, String)> {
+        self.remote.as_ref().map(Remote::env).unwrap_or_default()
+    }
+
+    /// Environment a build container needs
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        match self.backend {
+            CacheBackend::Ccache => Vec::new(),
+            CacheBackend::Sccache => vec![
+                ("SCCACHE_DIR", SCCACHE_MOUNT.to_string()),
+                ("CMAKE_C_COMPILER_LAUNCHER", "sccache".to_string()),
+                ("CMAKE_CXX_COMPILER_LAUNCHER", "sccache".to_string()),
+                ("CCACHE_DISABLE", "1".to_string()),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sccache() {
+        let ccache = CompilerCache::default();
+        let dir = Path::new("/cache/ccache");
+        assert_eq!(
+            ccache.mounts(dir),
+            [(dir.to_path_buf(), "/root/.ccache", false)]
+        );
+        assert!(ccache.env().is_empty());
+
+        let tmp = tempdir().unwrap();
+        let binary = tmp.path().join("sccache");
+        let mut config = CompilerCacheConfig {
+            backend: CacheBackend::Sccache,
+            sccache_binary: Some(binary.clone()),
+            ..Default::default()
+        };
+        assert!(CompilerCache::from_config(&config).is_err());
+        fs::write(&binary, "").unwrap();
+        let sccache = CompilerCache::from_config(&config).unwrap();
+        let dir = Path::new("/cache/sccache");
+        assert_eq!(
+            sccache.mounts(dir),
+            [
+                (dir.to_path_buf(), "/root/.cache/sccache", false),
+                (binary, "/usr/local/bin/sccache", true)
+            ]
+        );
+        config.sccache_binary = None;
+        let sccache = CompilerCache::from_config(&config).unwrap();
+        assert_eq!(sccache.mounts(dir).len(), 1);
+        let env = sccache.env();
+        assert!(env.contains(&("CMAKE_C_COMPILER_LAUNCHER", "sccache".to_string())));
+        assert!(env.contains(&("CCACHE_DISABLE", "1".to_string())));
+    }
+}
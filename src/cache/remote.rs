@@ -0,0 +1,228 @@
+//! A remote store the compiler cache shares compilations through, so a
+//! team (or one person's desktop and laptop) compiles each file once.
+//!
+//! ccache (4.4 and later) talks to HTTP and Redis servers; sccache to
+//! WebDAV-style HTTP servers, Redis and S3 buckets. Either is configured
+//! through environment variables, which carry credentials and so are kept
+//! off the container command line.
+
+use anyhow::{Context, Result};
+
+use crate::config::lfz_toml::{CacheBackend, CompilerCacheConfig};
+
+/// Host variables sccache's S3 storage reads, passed through when set
+const S3_VARS: [&str; 5] = [
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "SCCACHE_REGION",
+    "SCCACHE_ENDPOINT",
+];
+
+/// A remote store for the compiler cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    backend: CacheBackend,
+    url: String,
+    token: Option<String>,
+}
+
+impl Remote {
+    /// The remote lfz.toml configures, if any
+    pub fn from_config(config: &CompilerCacheConfig) -> Result<Option<Self>> {
+        let Some(url) = &config.remote else {
+            return Ok(None);
+        };
+        let token = config
+            .token_env
+            .as_ref()
+            .map(|var| {
+                std::env::var(var)
+                    .with_context(|| format!("{} (the compiler cache's token-env) is not set", var))
+            })
+            .transpose()?;
+        let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+        let supported: &[&str] = match config.backend {
+            // ccache's HTTP storage has no TLS, and its Redis one no rediss
+            CacheBackend::Ccache => &["http", "redis"],
+            CacheBackend::Sccache => &["http", "https", "redis", "rediss", "s3"],
+        };
+        if !scheme.is_some_and(|scheme| supported.contains(&scheme)) {
+            anyhow::bail!(
+                "{} can't use '{}' as a remote (expected a {} URL)",
+                config.backend.as_str(),
+                url,
+                supported
+                    .iter()
+                    .map(|s| format!("{}://", s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(Some(Self {
+            backend: config.backend,
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        }))
+    }
+
+    /// The URL without credentials, to show
+    pub fn display_url(&self) -> String {
+        match self.url.split_once("://") {
+            Some((scheme, rest)) => match rest.split_once('@') {
+                Some((_, host)) => format!("{}://***@{}", scheme, host),
+                None => self.url.clone(),
+            },
+            None => self.url.clone(),
+        }
+    }
+
+    /// Environment pointing the build's compiler cache at the remote
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        let mut set = |key: &str, value: String| env.push((key.to_string(), value));
+        let scheme = self.url.split_once("://").map_or("", |(scheme, _)| scheme);
+        match self.backend {
+            CacheBackend::Ccache => {
+                let mut storage = self.url.clone();
+                if let Some(token) = &self.token {
+                    storage.push_str(&format!("|bearer-token={}", token));
+                }
+                // ccache 4.4 to 4.7 called it secondary storage
+                set("CCACHE_REMOTE_STORAGE", storage.clone());
+                set("CCACHE_SECONDARY_STORAGE", storage);
+            }
+            CacheBackend::Sccache => match scheme {
+                "s3" => {
+                    let path = &self.url["s3://".len()..];
+                    let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+                    set("SCCACHE_BUCKET", bucket.to_string());
+                    if !prefix.is_empty() {
+                        set("SCCACHE_S3_KEY_PREFIX", prefix.to_string());
+                    }
+                    for var in S3_VARS {
+                        if let Ok(value) = std::env::var(var) {
+                            set(var, value);
+                        }
+                    }
+                }
+                "redis" | "rediss" => set("SCCACHE_REDIS", self.url.clone()),
+                _ => {
+                    set("SCCACHE_WEBDAV_ENDPOINT", self.url.clone());
+                    if let Some(token) = &self.token {
+                        set("SCCACHE_WEBDAV_TOKEN", token.clone());
+                    }
+                }
+            },
+        }
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(backend: CacheBackend, remote: &str) -> CompilerCacheConfig {
+        CompilerCacheConfig {
+            backend,
+            remote: Some(remote.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn env(remote: &Remote) -> Vec<(String, String)> {
+        // The host's AWS variables depend on the machine
+        remote
+            .env()
+            .into_iter()
+            .filter(|(key, _)| !S3_VARS.contains(&key.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(
+            Remote::from_config(&CompilerCacheConfig::default()).unwrap(),
+            None
+        );
+        let remote =
+            Remote::from_config(&config(CacheBackend::Ccache, "http://cache:8080/ccache/"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            env(&remote),
+            [
+                (
+                    "CCACHE_REMOTE_STORAGE".to_string(),
+                    "http://cache:8080/ccache".to_string()
+                ),
+                (
+                    "CCACHE_SECONDARY_STORAGE".to_string(),
+                    "http://cache:8080/ccache".to_string()
+                )
+            ]
+        );
+        assert!(Remote::from_config(&config(CacheBackend::Ccache, "s3://bucket")).is_err());
+        assert!(
+            Remote::from_config(&config(CacheBackend::Ccache, "https://cache/ccache")).is_err()
+        );
+        assert!(Remote::from_config(&config(CacheBackend::Ccache, "rediss://cache")).is_err());
+        assert!(Remote::from_config(&config(CacheBackend::Sccache, "cache:8080")).is_err());
+
+        let remote = Remote::from_config(&config(CacheBackend::Sccache, "s3://builds/zmk"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            env(&remote),
+            [
+                ("SCCACHE_BUCKET".to_string(), "builds".to_string()),
+                ("SCCACHE_S3_KEY_PREFIX".to_string(), "zmk".to_string())
+            ]
+        );
+        let remote = Remote::from_config(&config(
+            CacheBackend::Sccache,
+            "redis://:secret@desktop:6379",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(remote.display_url(), "redis://***@desktop:6379");
+        assert_eq!(
+            env(&remote),
+            [(
+                "SCCACHE_REDIS".to_string(),
+                "redis://:secret@desktop:6379".to_string()
+            )]
+        );
+
+        let unset = CompilerCacheConfig {
+            token_env: Some("LFZ_TEST_UNSET_CCACHE_TOKEN".to_string()),
+            ..config(CacheBackend::Ccache, "http://cache/ccache")
+        };
+        assert!(Remote::from_config(&unset).is_err());
+    }
+
+    #[test]
+    fn test_token() {
+        let remote = Remote {
+            backend: CacheBackend::Ccache,
+            url: "http://cache/ccache".to_string(),
+            token: Some("t0k".to_string()),
+        };
+        assert_eq!(env(&remote)[0].1, "http://cache/ccache|bearer-token=t0k");
+        let remote = Remote {
+            backend: CacheBackend::Sccache,
+            ..remote
+        };
+        assert_eq!(
+            env(&remote),
+            [
+                (
+                    "SCCACHE_WEBDAV_ENDPOINT".to_string(),
+                    "http://cache/ccache".to_string()
+                ),
+                ("SCCACHE_WEBDAV_TOKEN".to_string(), "t0k".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::build::filter::matches_any_glob;
+use crate::build::orchestrator::BuildOrchestrator;
+use crate::build::target::BuildTarget;
+use crate::cache::{self, CompilerCache};
+use crate::config::build_config::BuildConfig;
+use crate::config::lfz_toml::LfzConfig;
+use crate::config::project::Project;
+use crate::config::upstream::short;
+use crate::config::west_yml::WestManifest;
+use crate::container::{project_image, Runtime};
+use crate::output;
+use crate::paths;
+use crate::workspace::{BuildHashes, WorkspaceManager};
+
+/// How a bisect step turned out
+enum Verdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+/// Run the bisect command - find the ZMK commit that broke a target
+pub fn run(
+    good: String,
+    bad: String,
+    board: Option<String>,
+    shield: Option<String>,
+    target: Option<String>,
+    test: bool,
+    output_path: String,
+) -> Result<()> {
+    let project = Project::detect()?;
+    let target = select_target(&project, board, shield, target)?;
+    output::status("Target", &target.artifact_name);
+
+    let runtime = Runtime::detect()?;
+    output::status("Runtime", runtime.name());
+    runtime.ensure_running()?;
+
+    // Steps share one scratch workspace, moving zmk between commits
+    let workspace_manager = WorkspaceManager::new()?.scratch(Some("bisect".to_string()));
+    let _guard = workspace_manager.lock(&project)?;
+    let workspace = workspace_manager.get_or_create(&project)?;
+    output::status("Workspace", &paths::anonymize_path(&workspace));
+
+    let mut commits = workspace_manager.zmk_commits(&workspace, &project, &good, &bad)?;
+    if commits.is_empty() {
+        anyhow::bail!("No commits between {} and {}", good, bad);
+    }
+
+    let overrides = WestManifest::load(&project.config_dir.join("west.yml"))?
+        .local_projects(&project.config_dir)
+        .iter()
+        .map(|local| local.as_override())
+        .collect();
+    let hashes = BuildHashes::calculate(
+        &project.root,
+        &project.build_file,
+        &project.config_dir.join("west.yml"),
+    )?;
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let image = project_image::build_image(&project, &runtime)?;
+    let compiler_cache = CompilerCache::from_config(&lfz_config.compiler_cache)?
+        .image(cache::image_key(&runtime, &image));
+    let orchestrator = BuildOrchestrator::new(
+        runtime,
+        workspace.clone(),
+        project.clone(),
+        PathBuf::from(&output_path),
+        0,     // verbosity
+        true,  // pristine: every step builds different ZMK sources
+        false, // offline
+        None,
+        overrides,
+        lfz_config.proxy,
+        hashes,
+    )
+    .compiler_cache(compiler_cache)
+    .image(image);
+
+    // The first bad commit is in commits[lo..=hi]; the last one is known bad
+    let (mut lo, mut hi) = (0, commits.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let (sha, subject) = commits[mid].clone();
+        output::header(&format!(
+            "Testing {} {} ({} commits left, ~{} steps)",
+            short(&sha),
+            subject,
+            hi - lo + 1,
+            steps(hi - lo + 1)
+        ));
+
+        workspace_manager.checkout_zmk(&workspace, &project, &sha)?;
+        let result = orchestrator
+            .build_sequential(std::slice::from_ref(&target))?
+            .remove(0);
+
+        let verdict = match (result.success, test) {
+            (true, false) => Verdict::Good,
+            (false, false) => Verdict::Bad,
+            // A commit that doesn't build can't be tested on the keyboard
+            (false, true) => Verdict::Skip,
+            (true, true) => {
+                if let Some(artifact) = &result.artifact_path {
+                    output::info(&format!("Flash {}", paths::anonymize_path(artifact)));
+                }
+                match output::select("Does this firmware work?", &["good", "bad", "skip"])? {
+                    0 => Verdict::Good,
+                    1 => Verdict::Bad,
+                    _ => Verdict::Skip,
+                }
+            }
+        };
+
+        match verdict {
+            Verdict::Good => lo = mid + 1,
+            Verdict::Bad => hi = mid,
+            Verdict::Skip => {
+                output::warning(&format!("Skipping {}", short(&sha)));
+                commits.remove(mid);
+                hi -= 1;
+            }
+        }
+    }
+
+    let (sha, subject) = &commits[lo];
+    println!();
+    output::success(&format!("First bad commit: {} {}", short(sha), subject));
+    output::info(&format!(
+        "https://github.com/zmkfirmware/zmk/commit/{}",
+        sha
+    ));
+
+    Ok(())
+}
+
+/// Rough number of remaining bisect steps for a range of commits
+fn steps(commits: usize) -> usize {
+    (usize::BITS - commits.leading_zeros()) as usize - 1
+}
+
+/// The single target to bisect: from --board/--shield, a build.yaml
+/// artifact name, or the only target in build.yaml
+fn select_target(
+    project: &Project,
+    board: Option<String>,
+    shield: Option<String>,
+    target: Option<String>,
+) -> Result<BuildTarget> {
+    if let Some(board) = board {
+        return BuildTarget::from_args(board, shield);
+    }
+
+    let targets = BuildConfig::load(&project.build_file)?.expand_targets()?;
+    let matching: Vec<_> = match &target {
+        Some(glob) => targets
+            .into_iter()
+            .filter(|t| matches_any_glob(std::slice::from_ref(glob), &t.artifact_name))
+            .collect(),
+        None => targets,
+    };
+
+    match matching.len() {
+        1 => Ok(matching.into_iter().next().unwrap()),
+        0 => anyhow::bail!("No targets match. Run 'lfz list' to see available targets."),
+        _ => anyhow::bail!(
+            "Bisect builds a single target; pick one with --target: {}",
+            matching
+                .iter()
+                .map(|t| t.artifact_name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(steps(1), 0);
+        assert_eq!(steps(2), 1);
+        assert_eq!(steps(100), 6);
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+
+use crate::config::project::Project;
+use crate::output;
+use crate::workspace::{board_roots, discover_boards, WorkspaceManager};
+
+/// Run the boards command - list boards available in the cached workspace
+pub fn run(filter: Option<String>) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace_manager = WorkspaceManager::new()?;
+
+    let Some(workspace) = workspace_manager.find_workspace(&project)? else {
+        anyhow::bail!(
+            "No cached workspace found for this project. \
+             Run 'lfz build' or 'lfz update' first."
+        );
+    };
+
+    let roots = board_roots(&workspace, &project.extra_modules());
+    let boards: Vec<_> = discover_boards(&roots)?
+        .into_iter()
+        .filter(|b| {
+            filter
+                .as_ref()
+                .is_none_or(|f| b.name.to_lowercase().contains(&f.to_lowercase()))
+        })
+        .collect();
+
+    let header = match filter {
+        Some(ref f) => format!("Boards matching '{}' ({})", f, boards.len()),
+        None => format!("Boards ({})", boards.len()),
+    };
+    output::header(&header);
+
+    if boards.is_empty() {
+        output::error("No boards found");
+        return Ok(());
+    }
+
+    let width = boards.iter().map(|b| b.name.len()).max().unwrap_or(0);
+    for board in &boards {
+        println!(
+            "  {} {} {}",
+            console::style(format!("{:<width$}", board.name)).cyan(),
+            console::style(format!("{:<8}", board.arch.as_deref().unwrap_or("?"))).dim(),
+            console::style(format!("[{}]", board.source)).yellow()
+        );
+    }
+
+    Ok(())
+}
@@ -1,30 +1,91 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::build::orchestrator::BuildOrchestrator;
+use super::size::format_size;
+use crate::build::artifact_cache::{ArtifactCache, CacheInputs};
+use crate::build::diagnostics::{self, Diagnostic, PathMap, Severity};
+use crate::build::filter::{matches_any_glob, GroupFilter, TagExpr};
+use crate::build::hints;
+use crate::build::history;
+use crate::build::incremental::{IncrementalInputs, TargetHashes};
+use crate::build::kconfig;
+use crate::build::last_run::LastRun;
+use crate::build::orchestrator::{BuildOrchestrator, BuildResult};
+use crate::build::phases::Phases;
+use crate::build::provenance::Provenance;
+use crate::build::remote_cache::RemoteCache;
+use crate::build::reproducible;
+use crate::build::sbom::{BillOfMaterials, Component, SbomFormat};
 use crate::build::target::BuildTarget;
-use crate::config::build_yaml::BuildConfig;
+use crate::cache::ccache::{self, CcacheStats};
+use crate::cache::remote::Remote;
+use crate::cache::{self, CompilerCache};
+use crate::config::build_config::BuildConfig;
+use crate::config::lfz_toml::{CacheBackend, CacheConfig, LfzConfig};
 use crate::config::project::Project;
-use crate::config::west_yml;
-use crate::container::Runtime;
+use crate::config::west_yml::{self, ProjectOverride, WestManifest};
+use crate::container::project_image;
+use crate::container::resources::{self, ResourceLimits};
+use crate::container::{remote, shares};
+use crate::container::{Runtime, DEFAULT_IMAGE};
+use crate::events::{self, Event};
+use crate::notify;
 use crate::output;
 use crate::paths;
-use crate::workspace::{is_incremental_safe, BuildHashes, WorkspaceManager};
+use crate::workspace::{
+    rebuild_only_changes, BuildHashes, WestLock, WorkspaceManager, WorkspaceMetadata,
+};
 use crate::BuildMode;
 
+/// Lines of build output shown from the first error of a failed target
+const ERROR_CONTEXT_LINES: usize = 20;
+
 #[allow(clippy::too_many_arguments)]
 pub fn run(
     board: Option<String>,
     shield: Option<String>,
+    pairs: Vec<String>,
     output_path: String,
     jobs: Option<usize>,
     quiet: bool,
-    verbose: bool,
+    verbosity: u8,
     build_mode: BuildMode,
-    group: String,
+    group: Option<String>,
+    tags: Option<String>,
+    target_globs: Vec<String>,
+    only_failed: bool,
+    pick: bool,
+    timeout: Option<Duration>,
+    build_jobs: Option<usize>,
+    cpu_limit: Option<f64>,
+    memory_limit: Option<u64>,
+    override_modules: Vec<String>,
+    offline: bool,
+    no_update: bool,
+    locked: bool,
+    zmk_pr: Option<u32>,
+    reproducible: bool,
+    sbom: Option<SbomFormat>,
+    stamp: bool,
+    warnings_as_errors: bool,
+    log_json: Option<PathBuf>,
+    notify: bool,
+    fail_fast: Option<bool>,
+    retries: u32,
+    retry_pristine: bool,
+    no_cache: bool,
+    changed_only: bool,
+    push_cache: bool,
+    wait: bool,
 ) -> Result<()> {
+    output::set_quiet(quiet);
+    if let Some(path) = &log_json {
+        events::open(path)?;
+    }
+
     // 1. Detect project structure
     let project = Project::detect()?;
     let project_display = west_yml::format_project_display(&project.config_dir)
@@ -33,49 +94,194 @@ pub fn run(
 
     // 2. Detect container runtime and ensure it's running
     let runtime = Runtime::detect()?;
-    output::status("Runtime", runtime.name());
+    match remote::host(&runtime) {
+        Some(host) => output::status(
+            "Runtime",
+            &format!("{} (remote daemon at {})", runtime.name(), host),
+        ),
+        None => output::status("Runtime", runtime.name()),
+    }
     runtime.ensure_running()?;
+    shares::check(&runtime, &project.root)?;
+    // Projects with an lfz.Dockerfile build in their own image; west still
+    // runs in the default one
+    if offline {
+        output::status("Network", "offline");
+        let mut images = vec![DEFAULT_IMAGE.to_string()];
+        images.push(project_image::image_name(&project)?);
+        images.dedup();
+        for image in &images {
+            if !runtime.image_exists(image)? {
+                anyhow::bail!(
+                    "Image {} is not available locally, and --offline prevents fetching it.\n\
+                     Run 'lfz build' once with network access first.",
+                    image
+                );
+            }
+        }
+    }
+    let image = project_image::build_image(&project, &runtime)?;
 
     // 3. Get or create workspace
-    let workspace_manager = WorkspaceManager::new()?;
+    // Reproducible builds need pinned module commits
+    let workspace_manager = WorkspaceManager::new()?
+        .offline(offline)
+        .no_update(no_update)
+        .locked(locked || reproducible)
+        .scratch(zmk_pr.map(|n| format!("zmk-pr-{}", n)))
+        .wait(wait);
+    let _guard = workspace_manager.lock(&project)?;
     let workspace = workspace_manager.get_or_create(&project)?;
     output::status("Workspace", &paths::anonymize_path(&workspace));
+    if let Some(number) = zmk_pr {
+        workspace_manager.checkout_pull_request(&workspace, &project, number)?;
+        output::status("ZMK", &format!("pull request #{}", number));
+    }
+
+    // Local projects from west.yml are built from their working tree, and
+    // --override-module replaces (or adds to) those for this build
+    let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+    if offline {
+        let missing: Vec<&str> = manifest
+            .projects
+            .iter()
+            .filter(|p| !workspace.join(p.path()).exists())
+            .map(|p| p.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Workspace is missing {} (west.yml changed since the last update?).\n\
+                 Run 'lfz build' with network access to fetch them.",
+                missing.join(", ")
+            );
+        }
+    }
+    let mut overrides: Vec<ProjectOverride> = manifest
+        .local_projects(&project.config_dir)
+        .iter()
+        .map(|local| local.as_override())
+        .collect();
+    if !override_modules.is_empty() {
+        let projects = manifest.resolved_projects(&workspace);
+        for spec in &override_modules {
+            let explicit = ProjectOverride::resolve(spec, &projects)?;
+            overrides.retain(|o| o.name != explicit.name);
+            overrides.push(explicit);
+        }
+    }
+    for project in &overrides {
+        output::status(
+            "Override",
+            &format!(
+                "{} -> {}",
+                project.name,
+                paths::anonymize_path(&project.host_path)
+            ),
+        );
+    }
 
     // 4. Calculate current config hashes and determine pristine mode
     let west_yml_path = project.config_dir.join("west.yml");
     let current_hashes =
-        BuildHashes::calculate(&project.root, &project.build_yaml, &west_yml_path)?;
+        BuildHashes::calculate(&project.root, &project.build_file, &west_yml_path)?;
 
+    // Auto mode decides per target, once the targets are known
     let (pristine, mode_reason) = match build_mode {
-        BuildMode::Incremental => (false, "incremental (forced)"),
-        BuildMode::Pristine => (true, "pristine (forced)"),
-        BuildMode::Auto => {
-            if is_incremental_safe(&workspace, &current_hashes) {
-                (false, "incremental (configs unchanged)")
-            } else {
-                (true, "pristine (configs changed or first build)")
-            }
-        }
+        _ if reproducible => (true, Some("pristine (reproducible)")),
+        BuildMode::Incremental => (false, Some("incremental (forced)")),
+        BuildMode::Pristine => (true, Some("pristine (forced)")),
+        BuildMode::Auto => (false, None),
     };
-    output::status("Build mode", mode_reason);
+    if let Some(reason) = mode_reason {
+        output::status("Build mode", reason);
+    }
 
     // 5. Determine build targets
-    let is_full_build = board.is_none() && group == "all";
-    let targets = if let Some(board) = board {
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let fail_fast = fail_fast.unwrap_or(lfz_config.fail_fast);
+    if let Some(max_size) = lfz_config.workspaces.max_size()? {
+        if let Err(e) = super::clean::evict(max_size, Some(&workspace), false) {
+            output::warning(&format!("Failed to evict workspaces: {}", e));
+        }
+    }
+    let image_digest = cache::image_key(&runtime, &image);
+    if let Err(e) = WorkspaceMetadata::record_image(&workspace, &image_digest) {
+        output::warning(&format!("Failed to record the build image: {}", e));
+    }
+    let mut compiler_cache =
+        CompilerCache::from_config(&lfz_config.compiler_cache)?.image(&image_digest);
+    let uses_ccache = compiler_cache.backend() == CacheBackend::Ccache;
+    if !uses_ccache {
+        output::status("Compiler cache", compiler_cache.backend().as_str());
+    }
+    // Offline build containers couldn't reach it
+    match Remote::from_config(&lfz_config.compiler_cache) {
+        Ok(Some(remote)) if !offline => {
+            output::status("Remote compiler cache", &remote.display_url());
+            compiler_cache = compiler_cache.remote(remote);
+        }
+        Ok(_) => {}
+        Err(e) => output::warning(&format!("Not using the remote compiler cache: {}", e)),
+    }
+    let ccache_dir = paths::image_ccache_dir(&image_digest)?;
+    let ccache_cap = lfz_config.ccache.max_size()?.filter(|_| uses_ccache);
+    if uses_ccache {
+        let configured = ccache::record_image(&ccache_dir, &image_digest)
+            .and_then(|_| ccache::configure(&ccache_dir, &lfz_config.ccache));
+        if let Err(e) = configured {
+            output::warning(&format!("Failed to configure ccache: {}", e));
+        }
+    }
+    let notify_config = lfz_config.notify.clone();
+    let group = lfz_config.group_filter(group);
+    let group_filter = GroupFilter::parse_with_aliases(&group, &lfz_config.groups)?;
+    let tag_expr = tags.as_deref().map(TagExpr::parse).transpose()?;
+    let mut is_full_build = board.is_none()
+        && pairs.is_empty()
+        && group_filter.is_all()
+        && tag_expr.is_none()
+        && target_globs.is_empty();
+    let mut targets = if let Some(board) = board {
         // Single target from CLI args (ignore group filter)
         vec![BuildTarget::from_args(board, shield)?]
+    } else if !pairs.is_empty() {
+        // Ad-hoc targets from --pair (ignore group filter)
+        let mut targets: Vec<BuildTarget> = Vec::new();
+        for pair in &pairs {
+            let target = BuildTarget::from_pair(pair)?;
+            // Repeated pairs would share a build directory
+            if !targets
+                .iter()
+                .any(|t| t.artifact_name == target.artifact_name)
+            {
+                targets.push(target);
+            }
+        }
+        targets
     } else {
-        // Parse build.yaml (path already detected by Project)
-        let build_config = BuildConfig::load(&project.build_yaml)?;
+        // Parse build config (path already detected by Project)
+        let build_config = BuildConfig::load(&project.build_file)?;
         let all_targets = build_config.expand_targets()?;
 
         // Filter by group if specified (and not "all")
-        if group == "all" {
+        let grouped = if group_filter.is_all() {
             all_targets
         } else {
+            let available = build_config.available_groups();
+            if let Some(unknown) = group_filter
+                .groups()
+                .find(|g| !available.iter().any(|a| a == g))
+            {
+                anyhow::bail!(
+                    "Unknown group '{}'. Available groups: {}",
+                    unknown,
+                    available.join(", ")
+                );
+            }
+
             let filtered: Vec<_> = all_targets
                 .into_iter()
-                .filter(|t| t.group.as_deref() == Some(group.as_str()))
+                .filter(|t| group_filter.matches(t.group.as_deref()))
                 .collect();
 
             if filtered.is_empty() {
@@ -86,13 +292,286 @@ pub fn run(
                 );
             }
             filtered
+        };
+
+        // Filter by tag expression if specified
+        let tagged = if let Some(ref expr) = tag_expr {
+            let filtered: Vec<_> = grouped
+                .into_iter()
+                .filter(|t| expr.matches_target(t))
+                .collect();
+
+            if filtered.is_empty() {
+                anyhow::bail!(
+                    "No targets match tags '{}'. Available tags: {}",
+                    tags.as_deref().unwrap_or_default(),
+                    build_config.available_tags().join(", ")
+                );
+            }
+            filtered
+        } else {
+            grouped
+        };
+
+        // Filter by artifact name globs if specified
+        if target_globs.is_empty() {
+            tagged
+        } else {
+            let filtered: Vec<_> = tagged
+                .into_iter()
+                .filter(|t| matches_any_glob(&target_globs, &t.artifact_name))
+                .collect();
+
+            if filtered.is_empty() {
+                anyhow::bail!(
+                    "No targets match '{}'. Run 'lfz list' to see available targets.",
+                    target_globs.join("', '")
+                );
+            }
+            filtered
+        }
+    };
+
+    // Rebuild only what didn't build last time
+    if only_failed {
+        let last_run = LastRun::load(&workspace)?
+            .context("No previous build in this workspace. Run 'lfz build' first.")?;
+        let unfinished: Vec<&str> = last_run.unfinished().collect();
+        if unfinished.is_empty() {
+            output::success("Every target built in the last run; nothing to retry.");
+            return Ok(());
+        }
+        targets.retain(|t| unfinished.contains(&t.artifact_name.as_str()));
+        if targets.is_empty() {
+            anyhow::bail!(
+                "None of the targets that failed last time are selected: {}",
+                unfinished.join(", ")
+            );
+        }
+        is_full_build = false;
+        output::status(
+            "Retrying",
+            &format!("{} target(s) that failed last time", targets.len()),
+        );
+    }
+
+    // Let the user narrow down the matrix interactively
+    if pick && targets.len() > 1 {
+        let names: Vec<String> = targets.iter().map(|t| t.artifact_name.clone()).collect();
+        let chosen = output::pick("Targets to build", &names)?;
+        if chosen.is_empty() {
+            anyhow::bail!("No targets selected");
+        }
+        if chosen.len() < targets.len() {
+            is_full_build = false;
+        }
+        targets = chosen.into_iter().map(|i| targets[i].clone()).collect();
+    }
+
+    BuildTarget::schedule(&mut targets, &lfz_config.build_order);
+
+    // lfz.toml's memory limits apply where build.yaml sets none
+    for target in &mut targets {
+        target.memory_limits = target.memory_limits.or(lfz_config.memory);
+    }
+
+    // Per-target extra modules must exist to be mounted
+    for target in &mut targets {
+        for module in &mut target.extra_modules {
+            *module = module.canonicalize().with_context(|| {
+                format!(
+                    "Extra module '{}' for {} not found",
+                    module.display(),
+                    target.artifact_name
+                )
+            })?;
+        }
+    }
+
+    // Targets whose modules, build config or board and shield files changed
+    // since their build directory was configured build pristine
+    let incremental = IncrementalInputs::collect(&project.root, &current_hashes, &targets)?
+        .sources(&WestLock::capture(&workspace, &manifest), &image_digest);
+    let target_hashes: HashMap<String, TargetHashes> = targets
+        .iter()
+        .map(|t| (t.artifact_name.clone(), incremental.hashes(t)))
+        .collect();
+
+    // Targets last built from the same inputs keep the firmware they built
+    let output_dir = PathBuf::from(&output_path);
+    let mut up_to_date = Vec::new();
+    // Verifying reproducibility needs every target built twice
+    if changed_only && reproducible {
+        output::status("Changed only", "off for a reproducible build");
+    } else if changed_only {
+        targets.retain(|target| {
+            let unchanged = target_hashes[&target.artifact_name].up_to_date(&workspace, target)
+                && output_dir
+                    .join(format!("{}.uf2", target.artifact_name))
+                    .is_file();
+            if unchanged {
+                output::status("Up to date", &target.artifact_name);
+                up_to_date.push(target.artifact_name.clone());
+            }
+            !unchanged
+        });
+        if !up_to_date.is_empty() {
+            is_full_build = false;
+        }
+    }
+
+    if mode_reason.is_none() && !targets.is_empty() {
+        // Build directories that are up to date apart from edited keymaps and
+        // .conf files only need ninja (stamping needs CMake to see the new
+        // version)
+        let changed_files = rebuild_only_changes(&workspace, &current_hashes).filter(|_| !stamp);
+        let mut reasons = Vec::new();
+        for target in &mut targets {
+            let reason = target_hashes[&target.artifact_name].pristine_reason(
+                &workspace,
+                target,
+                &current_hashes,
+            );
+            if reason.is_none()
+                && changed_files.is_some()
+                && TargetHashes::load(&workspace, target).is_some()
+            {
+                target.rebuild_only = true;
+                continue;
+            }
+            target.pristine = reason.is_some();
+            reasons.extend(reason.map(|r| (target.artifact_name.clone(), r)));
+        }
+        let rebuilt = targets.iter().filter(|t| t.rebuild_only).count();
+        if let Some(changed) = changed_files.filter(|_| rebuilt > 0) {
+            output::status(
+                "Build mode",
+                &format!("rebuild only ({} changed)", changed.join(", ")),
+            );
+        }
+        if reasons.is_empty() {
+            if rebuilt == 0 {
+                output::status("Build mode", "incremental (configs unchanged)");
+            }
+        } else if reasons.len() == targets.len() {
+            output::status("Build mode", "pristine (configs changed or first build)");
+        } else {
+            output::status(
+                "Build mode",
+                &format!(
+                    "incremental, pristine for {} of {} target(s)",
+                    reasons.len(),
+                    targets.len()
+                ),
+            );
+            for (target, reason) in &reasons {
+                output::status("Pristine", &format!("{} ({})", target, reason));
+            }
+        }
+    }
+    let all_pristine = pristine || (!targets.is_empty() && targets.iter().all(|t| t.pristine));
+
+    let epoch = reproducible.then(|| reproducible::source_date_epoch(&project.root));
+    if let Some(epoch) = &epoch {
+        output::status("Reproducible", &format!("SOURCE_DATE_EPOCH={}", epoch));
+        for target in &mut targets {
+            reproducible::apply(target, epoch);
+        }
+    }
+
+    let provenance = stamp.then(|| Provenance::collect(&project.root, epoch.as_deref(), &image));
+    if let Some(provenance) = &provenance {
+        output::status("Stamp", &provenance.build_version());
+        for target in &mut targets {
+            provenance.apply(target);
         }
+    }
+
+    // 6. Clean stale artifacts from output directory
+    clean_output_dir(&output_dir, &targets, is_full_build);
+
+    // 7. Reuse firmware built before from the same inputs
+    let cache = if no_cache {
+        None
+    } else if provenance.is_some() || warnings_as_errors || reproducible || !overrides.is_empty() {
+        // Stamped firmware is unique to its build, warnings need a fresh
+        // build to be seen, reproducibility is checked against a second
+        // build, and local modules aren't tracked
+        output::status("Cache", "off for this build");
+        None
+    } else {
+        open_cache(
+            &runtime,
+            &image,
+            &workspace,
+            &project,
+            &manifest,
+            &current_hashes,
+            &targets,
+        )
     };
+    // --offline doesn't reach for it either
+    let remote = cache
+        .as_ref()
+        .filter(|_| !offline)
+        .and_then(|_| open_remote(&lfz_config.cache, push_cache));
+    let mut cache_keys: HashMap<String, String> = HashMap::new();
+    let mut cached: Vec<BuildResult> = Vec::new();
+    if let Some((cache, inputs)) = &cache {
+        for target in &targets {
+            let Some(key) = inputs.key(target) else {
+                continue;
+            };
+            // Firmware the local cache lacks may have been built elsewhere
+            let mut pulled = false;
+            if let Some(remote) = remote.as_ref().filter(|_| !cache.contains(&key)) {
+                match remote.pull(&key, cache) {
+                    Ok(found) => pulled = found,
+                    Err(e) => output::warning(&format!(
+                        "Failed to pull {} from the remote cache: {}",
+                        target.artifact_name, e
+                    )),
+                }
+            }
+            match cache.restore(&key, target, &output_dir) {
+                Ok(Some(result)) => {
+                    let status = if pulled { "Pulled" } else { "Cached" };
+                    output::status(status, &target.artifact_name);
+                    cached.push(result);
+                }
+                Ok(None) => {}
+                Err(e) => output::warning(&format!(
+                    "Failed to reuse the cached firmware of {}: {}",
+                    target.artifact_name, e
+                )),
+            }
+            cache_keys.insert(target.artifact_name.clone(), key);
+        }
+    }
+    targets.retain(|t| !cached.iter().any(|r| r.target_name == t.artifact_name));
 
-    // Determine parallelism: -j1 = sequential, -jN = N parallel, default = all parallel
-    let num_jobs = jobs.unwrap_or(targets.len()).max(1);
+    // Determine parallelism: -j1 = sequential, -jN = N parallel, default = as
+    // many as the host's CPUs and memory allow
+    let num_jobs = jobs
+        .unwrap_or_else(|| resources::default_jobs(targets.len()))
+        .max(1);
 
-    if verbose {
+    let verbose = verbosity > 0;
+    let running = if verbose {
+        1
+    } else {
+        num_jobs.min(targets.len())
+    };
+    // Split the CPUs between the builds running side by side
+    let build_jobs = build_jobs.or_else(|| resources::build_jobs_share(running));
+    for target in &mut targets {
+        target.build_jobs = build_jobs;
+    }
+    if targets.is_empty() && up_to_date.is_empty() {
+        output::header("Every target is in the artifact cache; nothing to build");
+    } else if targets.is_empty() {
+        output::header("Every target is up to date or cached; nothing to build");
+    } else if verbose {
         output::header(&format!(
             "Building {} target(s) with verbose output",
             targets.len()
@@ -107,64 +586,706 @@ pub fn run(
         output::header(&format!("Building {} target(s)", targets.len()));
     }
 
-    // 6. Clean stale artifacts from output directory
-    let output_dir = PathBuf::from(&output_path);
-    clean_output_dir(&output_dir, &targets, is_full_build);
+    let limits = ResourceLimits::for_jobs(cpu_limit, memory_limit, running);
+    if !limits.is_empty() {
+        output::status("Limits", &format!("{} per build", limits.describe()));
+    }
 
-    // 7. Run builds
+    // 8. Run builds
+    let project_root = project.root.clone();
     let orchestrator = BuildOrchestrator::new(
         runtime,
         workspace.clone(),
         project,
         output_dir,
-        quiet,
-        verbose,
+        verbosity,
         pristine,
-        current_hashes,
-    );
+        offline,
+        timeout,
+        overrides,
+        lfz_config.proxy,
+        current_hashes.clone(),
+    )
+    .fail_fast(fail_fast)
+    .retries(retries, retry_pristine)
+    .limits(limits)
+    .compiler_cache(compiler_cache)
+    .image(&image);
+
+    events::emit(Event::BuildStarted {
+        targets: cached
+            .iter()
+            .map(|r| r.target_name.as_str())
+            .chain(targets.iter().map(|t| t.artifact_name.as_str()))
+            .collect(),
+        jobs: if verbose { 1 } else { num_jobs },
+    });
+    for result in &cached {
+        report_cached(result);
+    }
 
     let build_start = Instant::now();
+    let ccache_before = CcacheStats::read(&ccache_dir);
     // Always use parallel build path (with progress bars) unless verbose mode
     // Verbose mode streams full output, so needs sequential handling
-    let results = if verbose {
+    let built = if targets.is_empty() {
+        Vec::new()
+    } else if verbose {
         orchestrator.build_sequential(&targets)?
     } else {
         orchestrator.build_parallel(&targets, num_jobs)?
     };
     let total_time = build_start.elapsed();
 
-    // 6. Report results
+    let mut pushed = 0;
+    if let Some((cache, _)) = &cache {
+        for result in built.iter().filter(|r| r.success) {
+            let (Some(key), Some(artifact), Some(target)) = (
+                cache_keys.get(&result.target_name),
+                &result.artifact_path,
+                targets
+                    .iter()
+                    .find(|t| t.artifact_name == result.target_name),
+            ) else {
+                continue;
+            };
+            let kconfig = kconfig::find(&workspace, target);
+            if let Err(e) = cache.store(key, target, artifact, kconfig.as_deref(), &result.memory) {
+                output::warning(&format!(
+                    "Failed to cache the firmware of {}: {}",
+                    result.target_name, e
+                ));
+                continue;
+            }
+            if let Some(remote) = remote.as_ref().filter(|r| r.pushes()) {
+                match remote.push(key, cache) {
+                    Ok(()) => pushed += 1,
+                    Err(e) => output::warning(&format!(
+                        "Failed to push {} to the remote cache: {}",
+                        result.target_name, e
+                    )),
+                }
+            }
+        }
+        cache.prune();
+    }
+    for result in built.iter().filter(|r| r.success) {
+        let Some(target) = targets
+            .iter()
+            .find(|t| t.artifact_name == result.target_name)
+        else {
+            continue;
+        };
+        if let Err(e) = target_hashes[&result.target_name].save(&workspace, target) {
+            output::warning(&format!(
+                "Failed to save the build hashes of {}: {}",
+                result.target_name, e
+            ));
+        }
+    }
+    let from_cache = cached.len();
+    let mut results = cached;
+    results.extend(built);
+
+    // 9. Report results
     let succeeded: Vec<_> = results.iter().filter(|r| r.success).collect();
-    let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|r| !r.success && !r.cancelled)
+        .collect();
+    let cancelled = results.iter().filter(|r| r.cancelled).count();
+    if let Err(e) = LastRun::from_results(&results).save(&workspace) {
+        output::warning(&format!("Failed to record the build results: {}", e));
+    }
+    let run = history::Run::new(
+        &results,
+        all_pristine,
+        total_time,
+        SystemTime::now(),
+        Some(current_hashes),
+    );
+    if let Err(e) = history::history_file(&workspace).and_then(|path| history::append(&path, &run))
+    {
+        output::warning(&format!("Failed to record the build history: {}", e));
+    }
+    if let Err(e) = history::record_output(Path::new(&output_path), &run) {
+        output::warning(&format!(
+            "Failed to record the build in the output directory: {}",
+            e
+        ));
+    }
+    // Restored firmware came with its Kconfig
+    for target in targets
+        .iter()
+        .filter(|t| succeeded.iter().any(|r| r.target_name == t.artifact_name))
+    {
+        if let Err(e) = kconfig::collect(&workspace, target, Path::new(&output_path)) {
+            output::warning(&format!(
+                "Failed to record the Kconfig of {}: {}",
+                target.artifact_name, e
+            ));
+        }
+    }
 
-    output::summary(succeeded.len(), failed.len(), Some(total_time));
+    output::summary(
+        succeeded.len(),
+        failed.len(),
+        cancelled,
+        up_to_date.len(),
+        Some(total_time),
+    );
+    if from_cache > 0 {
+        output::info(&format!(
+            "{} target(s) reused from the artifact cache (--no-cache rebuilds them)",
+            from_cache
+        ));
+    }
+    if let Some(remote) = remote.as_ref().filter(|_| pushed > 0) {
+        output::info(&format!("{} target(s) pushed to {}", pushed, remote.url()));
+    }
+    for result in results.iter().filter(|r| r.retries > 0) {
+        let outcome = if result.success {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        output::warning(&format!(
+            "{} {} after {} {}",
+            result.target_name,
+            outcome,
+            result.retries,
+            if result.retries == 1 {
+                "retry"
+            } else {
+                "retries"
+            }
+        ));
+    }
+    report_phases(&succeeded);
+    let ccache_after = CcacheStats::read(&ccache_dir);
+    report_ccache(ccache_after - ccache_before);
+    if ccache_cap.is_some_and(|cap| ccache_after.size_kib * 1024 > cap) {
+        let spinner = output::spinner("Trimming ccache to ccache.max-size");
+        let trimmed = ccache::trim(&runtime, &ccache_dir);
+        spinner.finish_and_clear();
+        if let Err(e) = trimmed {
+            output::warning(&format!("Failed to trim ccache: {}", e));
+        }
+    }
+    report_memory(&results);
+    for result in &failed {
+        report_error(result, &targets, &orchestrator);
+    }
+    events::emit(Event::BuildFinished {
+        succeeded: succeeded.len(),
+        failed: failed.len(),
+        duration_ms: total_time.as_millis(),
+    });
+    if notify || notify_config.desktop {
+        notify::desktop(succeeded.len(), failed.len(), total_time);
+    }
+    notify::hooks(&notify_config, &notify::Summary::new(&results, total_time));
+    let warning_count = print_warnings(&results, &targets, &orchestrator, &project_root);
 
     if !failed.is_empty() {
         output::header("Failed builds");
         for result in &failed {
+            let paths = targets
+                .iter()
+                .find(|t| t.artifact_name == result.target_name)
+                .map(|t| orchestrator.path_map(t))
+                .unwrap_or_default();
+
+            if output::ci() {
+                annotate_failure(result, &paths, &project_root, &workspace);
+                continue;
+            }
+
+            // Lead with the error that broke the build, in terms of the user's files
+            let error_output = result.error_output.as_deref().unwrap_or_default();
+            let diagnostics = diagnostics::parse(error_output);
+            let Some(first) = diagnostics::first_error(&diagnostics) else {
+                output::error(&format!(
+                    "{}: {}",
+                    result.target_name,
+                    result.error.as_deref().unwrap_or("unknown error")
+                ));
+                if !error_output.is_empty() {
+                    output::line("");
+                    output::build_error_output(&result.target_name, error_output);
+                }
+                continue;
+            };
+
             output::error(&format!(
                 "{}: {}",
                 result.target_name,
-                result.error.as_deref().unwrap_or("unknown error")
+                first.summary(&paths, &project_root)
             ));
-
-            // Show the build error output if available
-            if let Some(error_output) = &result.error_output {
-                // Print a separator and the error output
-                println!();
-                output::build_error_output(&result.target_name, error_output);
+            for hint in diagnostic_hints(first, &workspace) {
+                output::hint(&hint);
+            }
+            // Kconfig warnings are the cause of failures but easy to miss in the output
+            for warning in kconfig_warnings(&diagnostics) {
+                output::warning(&warning.summary(&paths, &project_root));
+                for hint in diagnostic_hints(warning, &workspace) {
+                    output::hint(&hint);
+                }
             }
+            let context = diagnostics::context(error_output, first);
+            output::line("");
+            output::build_error_output(
+                &result.target_name,
+                &context[..context.len().min(ERROR_CONTEXT_LINES)].join("\n"),
+            );
+            if context.len() > ERROR_CONTEXT_LINES {
+                output::info(&format!(
+                    "{} more line(s) omitted; run 'lfz logs {}' for the full output",
+                    context.len() - ERROR_CONTEXT_LINES,
+                    result.target_name
+                ));
+            }
+        }
+        let timed_out = failed.iter().filter(|r| r.timed_out).count();
+        let mut details = Vec::new();
+        if timed_out > 0 {
+            details.push(format!("{} timed out", timed_out));
+        }
+        if cancelled > 0 {
+            details.push(format!("{} other(s) cancelled", cancelled));
+        }
+        let names: Vec<&str> = failed.iter().map(|r| r.target_name.as_str()).collect();
+        if details.is_empty() {
+            anyhow::bail!("{} build(s) failed: {}", failed.len(), names.join(", "));
         }
-        anyhow::bail!("{} build(s) failed", failed.len());
+        anyhow::bail!(
+            "{} build(s) failed ({}): {}",
+            failed.len(),
+            details.join(", "),
+            names.join(", ")
+        );
+    }
+
+    if warnings_as_errors && warning_count > 0 {
+        anyhow::bail!(
+            "{} warning(s) treated as errors (--warnings-as-errors)",
+            warning_count
+        );
+    }
+
+    if reproducible {
+        verify_reproducible(&orchestrator, &targets, &results, num_jobs, verbose)?;
     }
 
+    let bom = sbom.map(|_| bill_of_materials(&runtime, &image, &workspace, &manifest));
+
     output::header(&format!("Firmware written to {}", output_path));
     for result in &succeeded {
         if let Some(artifact) = &result.artifact_path {
             output::list_item(&artifact.display().to_string());
+            if let (Some(format), Some(bom)) = (sbom, &bom) {
+                let path = bom.write(format, artifact, &result.target_name)?;
+                output::list_item(&path.display().to_string());
+            }
+            let target = targets
+                .iter()
+                .find(|t| t.artifact_name == result.target_name);
+            if let (Some(provenance), Some(target)) = (&provenance, target) {
+                let path = provenance.write(artifact, target)?;
+                output::list_item(&path.display().to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The artifact cache and the inputs keying this build's targets, unless
+/// they can't be determined
+fn open_cache(
+    runtime: &Runtime,
+    image: &str,
+    workspace: &Path,
+    project: &Project,
+    manifest: &WestManifest,
+    hashes: &BuildHashes,
+    targets: &[BuildTarget],
+) -> Option<(ArtifactCache, CacheInputs)> {
+    // Without a digest, a changed image would go unnoticed
+    let digest = runtime.image_digest(image)?;
+    let lock = WestLock::capture(workspace, manifest);
+    let cache = ArtifactCache::open().and_then(|cache| {
+        let inputs = CacheInputs::collect(&project.config_dir, hashes, &lock, digest, targets)?;
+        Ok((cache, inputs))
+    });
+    match cache {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            output::warning(&format!("Not using the artifact cache: {}", e));
+            None
+        }
+    }
+}
+
+/// The remote store of the artifact cache; without it (or if it can't be
+/// used) only the local cache is
+fn open_remote(config: &CacheConfig, push: bool) -> Option<RemoteCache> {
+    match RemoteCache::open(config, push) {
+        Ok(remote) => {
+            if let Some(remote) = &remote {
+                let mode = if remote.pushes() {
+                    "pull and push"
+                } else {
+                    "pull"
+                };
+                output::status("Remote cache", &format!("{} ({})", remote.url(), mode));
+            }
+            remote
+        }
+        Err(e) => {
+            output::warning(&format!("Not using the remote cache: {}", e));
+            None
+        }
+    }
+}
+
+/// Report a target restored from the cache like a finished build
+fn report_cached(result: &BuildResult) {
+    let target = &result.target_name;
+    if let Some(path) = &result.artifact_path {
+        events::emit(Event::ArtifactCollected { target, path });
+    }
+    events::emit(Event::TargetFinished {
+        target,
+        success: true,
+        timed_out: false,
+        cancelled: false,
+        retries: 0,
+        duration_ms: 0,
+        memory: &result.memory,
+    });
+}
+
+/// Targets that printed a warning
+type WarnedTargets<'a> = Vec<&'a str>;
+
+/// Print the warnings of all targets, de-duplicated and grouped by file,
+/// returning how many distinct warnings there were
+fn print_warnings(
+    results: &[BuildResult],
+    targets: &[BuildTarget],
+    orchestrator: &BuildOrchestrator,
+    project_root: &Path,
+) -> usize {
+    let mut grouped: BTreeMap<String, BTreeMap<(Option<u32>, String), WarnedTargets>> =
+        BTreeMap::new();
+    for result in results {
+        let paths = targets
+            .iter()
+            .find(|t| t.artifact_name == result.target_name)
+            .map(|t| orchestrator.path_map(t))
+            .unwrap_or_default();
+        for warning in &result.warnings {
+            let file = warning
+                .display_file(&paths, project_root)
+                .unwrap_or_default();
+            grouped
+                .entry(file)
+                .or_default()
+                .entry((warning.line, warning.message.clone()))
+                .or_default()
+                .push(&result.target_name);
+        }
+    }
+
+    let count = grouped.values().map(BTreeMap::len).sum();
+    if count == 0 {
+        return 0;
+    }
+
+    output::header(&format!("Warnings ({})", count));
+    for (file, warnings) in &grouped {
+        let in_repo = !file.is_empty() && project_root.join(file).is_file();
+        output::line(&format!(
+            "  {}",
+            console::style(if file.is_empty() { "(build)" } else { file }).bold()
+        ));
+        for ((line, message), warned) in warnings {
+            let location = line.map(|l| format!("{}: ", l)).unwrap_or_default();
+            let which = if warned.len() == results.len() && results.len() > 1 {
+                "all targets".to_string()
+            } else {
+                warned.join(", ")
+            };
+            output::line(&format!(
+                "    {}{} {}",
+                location,
+                console::style(message).yellow(),
+                console::style(format!("({})", which)).dim()
+            ));
+            if output::ci() {
+                let location = in_repo.then(|| output::CiLocation {
+                    file,
+                    line: *line,
+                    column: None,
+                });
+                output::ci_annotation(false, &which, location, message);
+            }
+        }
+    }
+
+    count
+}
+
+/// Report a failed build as GitHub Actions annotations plus a log group
+/// holding its output
+fn annotate_failure(result: &BuildResult, paths: &PathMap, project_root: &Path, workspace: &Path) {
+    let diagnostics = result
+        .error_output
+        .as_deref()
+        .map(diagnostics::parse)
+        .unwrap_or_default();
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+
+    if errors.is_empty() {
+        output::ci_annotation(
+            true,
+            &result.target_name,
+            None,
+            result.error.as_deref().unwrap_or("unknown error"),
+        );
+    }
+    for diagnostic in errors.into_iter().chain(kconfig_warnings(&diagnostics)) {
+        let hints: String = diagnostic_hints(diagnostic, workspace)
+            .iter()
+            .map(|hint| format!("\nhint: {}", hint))
+            .collect();
+        // Only files in the repository can be annotated
+        let file = diagnostic
+            .host_file(paths)
+            .and_then(|host| Some(host.strip_prefix(project_root).ok()?.display().to_string()));
+        let (location, message) = match &file {
+            Some(file) => (
+                Some(output::CiLocation {
+                    file,
+                    line: diagnostic.line,
+                    column: diagnostic.column,
+                }),
+                diagnostic.message.clone(),
+            ),
+            None => (None, diagnostic.summary(paths, project_root)),
+        };
+        output::ci_annotation(
+            diagnostic.severity == Severity::Error,
+            &result.target_name,
+            location,
+            &format!("{}{}", message, hints),
+        );
+    }
+
+    if let Some(error_output) = &result.error_output {
+        output::ci_group(
+            &format!("{} build output", result.target_name),
+            error_output,
+        );
+    }
+}
+
+/// Show where the successful builds spent their time, e.g. to check that
+/// ccache is helping the compile phase
+fn report_phases(results: &[&BuildResult]) {
+    let mut phases = Phases::default();
+    for result in results {
+        phases += result.phases;
+    }
+    if phases.is_empty() {
+        return;
+    }
+    output::info(&format!(
+        "Time spent configuring {}, compiling {}, linking {} (all targets)",
+        output::format_duration(phases.configure),
+        output::format_duration(phases.compile),
+        output::format_duration(phases.link)
+    ));
+}
+
+/// Show how many of this run's compilations ccache served, to tell
+/// whether a pristine build (or a cleared cache) cost time
+fn report_ccache(delta: CcacheStats) {
+    let Some(rate) = delta.hit_rate() else {
+        return;
+    };
+    let evictions = if delta.cleanups > 0 {
+        format!(", {} cleanup(s) evicted files", delta.cleanups)
+    } else {
+        String::new()
+    };
+    output::info(&format!(
+        "ccache: {} hits, {} misses ({:.0}% hit rate{})",
+        delta.hits, delta.misses, rate, evictions
+    ));
+}
+
+/// Show each target's flash and RAM usage, as the linker reported it
+fn report_memory(results: &[BuildResult]) {
+    let results: Vec<_> = results.iter().filter(|r| !r.memory.is_empty()).collect();
+    if results.is_empty() {
+        return;
+    }
+    output::header("Memory usage");
+    let width = results
+        .iter()
+        .map(|r| r.target_name.len())
+        .max()
+        .unwrap_or(0);
+    for result in results {
+        let regions: Vec<String> = result
+            .memory
+            .iter()
+            .map(|region| {
+                let percent = format!("{:.1}%", region.percent());
+                let percent = if region.percent() >= 90.0 {
+                    console::style(percent).yellow()
+                } else {
+                    console::style(percent).dim()
+                };
+                format!(
+                    "{} {} / {} {}",
+                    region.name,
+                    format_size(region.used),
+                    format_size(region.size),
+                    percent
+                )
+            })
+            .collect();
+        output::line(&format!(
+            "  {:width$}  {}",
+            console::style(&result.target_name).cyan(),
+            regions.join("   "),
+            width = width
+        ));
+    }
+}
+
+/// Report why a target failed as an event, at its first error if one was found
+fn report_error(result: &BuildResult, targets: &[BuildTarget], orchestrator: &BuildOrchestrator) {
+    let diagnostics = result
+        .error_output
+        .as_deref()
+        .map(diagnostics::parse)
+        .unwrap_or_default();
+    let first = diagnostics::first_error(&diagnostics);
+    let paths = targets
+        .iter()
+        .find(|t| t.artifact_name == result.target_name)
+        .map(|t| orchestrator.path_map(t))
+        .unwrap_or_default();
+    events::emit(Event::Error {
+        target: &result.target_name,
+        message: first
+            .map(|d| d.message.as_str())
+            .or(result.error.as_deref())
+            .unwrap_or("unknown error"),
+        file: first.and_then(|d| d.host_file(&paths)),
+        line: first.and_then(|d| d.line),
+    });
+}
+
+/// Kconfig warnings among a build's diagnostics
+fn kconfig_warnings(diagnostics: &[Diagnostic]) -> impl Iterator<Item = &Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning && d.is_kconfig())
+}
+
+/// Hints for a diagnostic, including a likely intended Kconfig symbol
+fn diagnostic_hints(diagnostic: &Diagnostic, workspace: &Path) -> Vec<String> {
+    let mut hints: Vec<String> = hints::hint(diagnostic).into_iter().collect();
+    if let Some(similar) = hints::undefined_symbol(diagnostic)
+        .and_then(|symbol| hints::similar_symbol(workspace, symbol))
+    {
+        hints.push(format!("did you mean CONFIG_{}?", similar));
+    }
+    hints
+}
+
+/// West projects as checked out in the workspace, and the build image
+fn bill_of_materials(
+    runtime: &Runtime,
+    image: &str,
+    workspace: &Path,
+    manifest: &WestManifest,
+) -> BillOfMaterials {
+    let components = WestLock::capture(workspace, manifest)
+        .projects
+        .into_iter()
+        .map(|(name, locked)| Component {
+            url: manifest
+                .projects
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| manifest.fetch_url(p)),
+            name,
+            commit: locked.revision,
+        })
+        .collect();
+    BillOfMaterials {
+        components,
+        image: image.to_string(),
+        image_digest: runtime.image_digest(image),
+    }
+}
+
+/// Build the targets a second time and compare the firmware byte for byte
+fn verify_reproducible(
+    orchestrator: &BuildOrchestrator,
+    targets: &[BuildTarget],
+    first: &[BuildResult],
+    num_jobs: usize,
+    verbose: bool,
+) -> Result<()> {
+    let mut first_builds = HashMap::new();
+    for result in first {
+        if let Some(artifact) = &result.artifact_path {
+            let bytes = fs::read(artifact)
+                .with_context(|| format!("Failed to read {}", artifact.display()))?;
+            first_builds.insert(result.target_name.clone(), bytes);
         }
     }
 
+    output::header("Rebuilding to verify reproducibility");
+    let second = if verbose {
+        orchestrator.build_sequential(targets)?
+    } else {
+        orchestrator.build_parallel(targets, num_jobs)?
+    };
+
+    let mut mismatches = 0;
+    for result in &second {
+        let (Some(artifact), Some(expected)) =
+            (&result.artifact_path, first_builds.get(&result.target_name))
+        else {
+            mismatches += 1;
+            output::error(&format!("{}: second build failed", result.target_name));
+            continue;
+        };
+        let bytes =
+            fs::read(artifact).with_context(|| format!("Failed to read {}", artifact.display()))?;
+        match reproducible::compare(expected, &bytes) {
+            Some(diff) => {
+                mismatches += 1;
+                output::error(&format!("{}: {}", result.target_name, diff));
+            }
+            None => output::success(&format!("{}: identical", result.target_name)),
+        }
+    }
+
+    if mismatches > 0 {
+        anyhow::bail!("{} target(s) did not build reproducibly", mismatches);
+    }
     Ok(())
 }
 
@@ -186,6 +1307,7 @@ fn clean_output_dir(output_dir: &PathBuf, targets: &[BuildTarget], full_build: b
                 }
             }
         }
+        let _ = fs::remove_dir_all(output_dir.join(kconfig::OUTPUT_DIR));
     } else {
         // Remove only artifacts for targets being built
         for target in targets {
@@ -193,6 +1315,7 @@ fn clean_output_dir(output_dir: &PathBuf, targets: &[BuildTarget], full_build: b
             if artifact.exists() {
                 let _ = fs::remove_file(&artifact);
             }
+            let _ = fs::remove_file(kconfig::output_path(output_dir, &target.artifact_name));
         }
     }
 }
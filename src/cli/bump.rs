@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use std::fs;
+
+use crate::config::project::Project;
+use crate::config::upstream::{is_commit, is_release, short, RemoteRefs};
+use crate::config::west_edit;
+use crate::config::west_yml::WestManifest;
+use crate::output;
+
+/// Run the bump command - rewrite west.yml revisions to newer upstream refs
+pub fn run(names: Vec<String>, to: Option<String>, dry_run: bool) -> Result<()> {
+    let project = Project::detect()?;
+    let west_yml_path = project.config_dir.join("west.yml");
+    let mut content = fs::read_to_string(&west_yml_path)
+        .with_context(|| format!("Failed to read {}", west_yml_path.display()))?;
+    let manifest = WestManifest::parse(&content)
+        .with_context(|| format!("Failed to parse {}", west_yml_path.display()))?;
+
+    if let Some(unknown) = names
+        .iter()
+        .find(|n| !manifest.projects.iter().any(|p| &&p.name == n))
+    {
+        anyhow::bail!("Project '{}' not found in west.yml", unknown);
+    }
+
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .filter(|p| names.is_empty() || names.contains(&p.name))
+        .filter(|p| !manifest.is_local(p))
+        .filter_map(|p| Some((p, manifest.fetch_url(p)?)))
+        .collect();
+
+    let spinner = output::spinner(&format!("Checking {} project(s)...", projects.len()));
+    let urls: Vec<&str> = projects.iter().map(|(_, url)| url.as_str()).collect();
+    let refs = RemoteRefs::fetch_all(&urls);
+    spinner.finish_and_clear();
+
+    let mut bumped = 0;
+    for ((project, _), refs) in projects.iter().zip(refs) {
+        let current = manifest.revision(project);
+        let refs = refs.with_context(|| format!("Failed to check {}", project.name))?;
+
+        let target = match &to {
+            Some(to) => {
+                if !is_commit(to) && !refs.tags.contains_key(to) && !refs.branches.contains_key(to)
+                {
+                    anyhow::bail!("'{}' is not a tag or branch of {}", to, project.name);
+                }
+                to.clone()
+            }
+            // Branches already follow upstream
+            None if refs.branches.contains_key(current) && !refs.tags.contains_key(current) => {
+                output::info(&format!("{} tracks {}, skipping", project.name, current));
+                continue;
+            }
+            // Only ever move a release forward
+            None if refs.tags.contains_key(current) && is_release(current) => {
+                match refs.newer_tag(current) {
+                    Some(tag) => tag.to_string(),
+                    None => continue,
+                }
+            }
+            None => match refs.latest_tag() {
+                Some(tag) if refs.tags[tag].starts_with(current) => continue,
+                // A commit or branch may well be ahead of the latest release
+                Some(tag) => {
+                    output::warning(&format!(
+                        "{}: can't tell whether {} is newer than {}; pass --to {} to move to it",
+                        project.name, tag, current, tag
+                    ));
+                    continue;
+                }
+                None => {
+                    let branch = refs.default_branch.as_deref().unwrap_or("master");
+                    match refs.branches.get(branch) {
+                        Some(head) if head.starts_with(current) => continue,
+                        Some(head) => head.clone(),
+                        None => {
+                            output::warning(&format!(
+                                "{}: no tags or default branch",
+                                project.name
+                            ));
+                            continue;
+                        }
+                    }
+                }
+            },
+        };
+
+        if target == current {
+            continue;
+        }
+        let display = if is_commit(&target) {
+            short(&target)
+        } else {
+            &target
+        };
+        output::status(&project.name, &format!("{} -> {}", current, display));
+        content = west_edit::set_revision(&content, &project.name, &target)?;
+        bumped += 1;
+    }
+
+    if bumped == 0 {
+        output::success("All projects are up to date");
+        return Ok(());
+    }
+    if dry_run {
+        output::info(&format!("Would bump {} project(s) (dry run)", bumped));
+        return Ok(());
+    }
+
+    fs::write(&west_yml_path, content)
+        .with_context(|| format!("Failed to write {}", west_yml_path.display()))?;
+    output::success(&format!("Bumped {} project(s) in west.yml", bumped));
+    output::info("The workspace will be updated on the next build");
+
+    Ok(())
+}
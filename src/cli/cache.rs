@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+
+use super::size::format_size;
+use crate::cache::{self, ccache::CcacheStats};
+use crate::config::lfz_toml::{CacheBackend, LfzConfig};
+use crate::config::project::Project;
+use crate::container::project_image::ProjectImage;
+use crate::container::{ContainerCommand, Runtime, DEFAULT_IMAGE};
+use crate::output;
+use crate::paths;
+
+/// Show how effective the shared ccache is, as ccache itself counts it
+pub fn stats() -> Result<()> {
+    // Outside a project there's no lfz.toml choosing sccache, nor an
+    // lfz.Dockerfile choosing the image
+    let (backend, image) = match Project::detect() {
+        Ok(project) => (
+            LfzConfig::load(&project.root)?.compiler_cache.backend,
+            ProjectImage::detect(&project.root)?.map(|image| image.tag().to_string()),
+        ),
+        Err(_) => (CacheBackend::Ccache, None),
+    };
+    if backend == CacheBackend::Sccache {
+        let dir = paths::sccache_dir()?;
+        output::status("Sccache", &paths::anonymize_path(&dir));
+        println!();
+        println!("  Size:        {:>10}", format_size(paths::dir_size(&dir)));
+        output::info(
+            "sccache counts hits per server, and each build container runs its own; \
+             it keeps no statistics between builds",
+        );
+        return Ok(());
+    }
+
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    runtime.ensure_image(DEFAULT_IMAGE)?;
+    // The cache of the current image; `lfz size` lists the others
+    let image = image.as_deref().unwrap_or(DEFAULT_IMAGE);
+    let ccache_dir = paths::image_ccache_dir(&cache::image_key(&runtime, image))?;
+    output::status("Ccache", &paths::anonymize_path(&ccache_dir));
+    std::fs::create_dir_all(&ccache_dir).context("Failed to create ccache directory")?;
+    let result = ContainerCommand::new(runtime, DEFAULT_IMAGE)
+        .mount(&ccache_dir, "/root/.ccache", false)
+        .env("CCACHE_DIR", "/root/.ccache")
+        .shell_command("ccache --print-stats && ccache --get-config max_size")
+        .build()
+        .output()
+        .context("Failed to run ccache in the container")?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "ccache --print-stats failed:\n{}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    let output = String::from_utf8_lossy(&result.stdout);
+    let stats = CcacheStats::parse(&output);
+    // The last line is the size limit
+    let max_size = output.lines().last().filter(|line| !line.contains('\t'));
+
+    println!();
+    match stats.hit_rate() {
+        Some(rate) => println!(
+            "  Hit rate:    {:>9.1}%  ({} hits, {} misses)",
+            rate, stats.hits, stats.misses
+        ),
+        None => println!("  Hit rate:           -   (nothing compiled yet)"),
+    }
+    println!(
+        "  Size:        {:>10}  ({} files{})",
+        format_size(stats.size_kib * 1024),
+        stats.files,
+        max_size
+            .map(|max| format!(", limit {}", max.trim()))
+            .unwrap_or_default()
+    );
+    println!("  Evictions:   {:>10}  (cleanups)", stats.cleanups);
+    Ok(())
+}
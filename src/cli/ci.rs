@@ -0,0 +1,322 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::build::filter::{GroupFilter, TagExpr};
+use crate::build::target::BuildTarget;
+use crate::config::build_config::BuildConfig;
+use crate::config::lfz_toml::LfzConfig;
+use crate::config::project::Project;
+use crate::container::DEFAULT_IMAGE;
+use crate::output;
+use crate::paths;
+
+/// Default location of the generated workflow
+pub const WORKFLOW_PATH: &str = ".github/workflows/build.yml";
+
+/// GitHub Actions' own default job timeout is 6 hours; lfz builds don't need that long
+const DEFAULT_TIMEOUT_MINUTES: u64 = 60;
+
+/// Run `ci generate` - write a GitHub Actions workflow for the build.yaml matrix
+pub fn generate(
+    group: Option<String>,
+    tags: Option<String>,
+    output_path: Option<String>,
+    stdout: bool,
+    check: bool,
+    force: bool,
+) -> Result<()> {
+    let project = Project::detect()?;
+    let targets = select_targets(&project, group.as_deref(), tags.as_deref())?;
+    let workflow = workflow(&project, &targets);
+
+    if stdout {
+        print!("{}", workflow);
+        return Ok(());
+    }
+
+    let path = output_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| project.root.join(WORKFLOW_PATH));
+    let display = paths::anonymize_path(&path);
+
+    if check {
+        let current = fs::read_to_string(&path).unwrap_or_default();
+        if current != workflow {
+            anyhow::bail!(
+                "{} is out of date with build.yaml. Run 'lfz ci generate --force'.",
+                display
+            );
+        }
+        output::success(&format!("{} matches build.yaml", display));
+        return Ok(());
+    }
+
+    if path.exists() && !force {
+        let current = fs::read_to_string(&path).unwrap_or_default();
+        if current == workflow {
+            output::success(&format!("{} is up to date", display));
+            return Ok(());
+        }
+        anyhow::bail!("{} already exists. Use --force to overwrite it.", display);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, &workflow).with_context(|| format!("Failed to write {}", path.display()))?;
+    output::success(&format!("Wrote {} ({} target(s))", display, targets.len()));
+    output::info("Commit it and push to build firmware on GitHub Actions");
+
+    Ok(())
+}
+
+/// build.yaml targets, filtered like a build (but without lfz.toml's
+/// `default-group`, which is a local convenience)
+fn select_targets(
+    project: &Project,
+    group: Option<&str>,
+    tags: Option<&str>,
+) -> Result<Vec<BuildTarget>> {
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let group_filter = GroupFilter::parse_with_aliases(group.unwrap_or("all"), &lfz_config.groups)?;
+    let tag_expr = tags.map(TagExpr::parse).transpose()?;
+
+    let targets: Vec<_> = BuildConfig::load(&project.build_file)?
+        .expand_targets()?
+        .into_iter()
+        .filter(|t| group_filter.matches(t.group.as_deref()))
+        .filter(|t| tag_expr.as_ref().is_none_or(|e| e.matches_target(t)))
+        .collect();
+
+    if targets.is_empty() {
+        anyhow::bail!("No targets match. Run 'lfz list' to see available targets.");
+    }
+    Ok(targets)
+}
+
+/// The workflow file for a set of targets
+fn workflow(project: &Project, targets: &[BuildTarget]) -> String {
+    let config_dir =
+        relative(&project.root, &project.config_dir).unwrap_or_else(|| "config".to_string());
+
+    let mut matrix = String::new();
+    for target in targets {
+        let timeout = target
+            .timeout
+            .map(|t| t.as_secs().div_ceil(60).max(1))
+            .unwrap_or(DEFAULT_TIMEOUT_MINUTES);
+        matrix.push_str(&format!(
+            "          - artifact-name: {}\n",
+            quote(&target.artifact_name)
+        ));
+        if let Some(group) = &target.group {
+            matrix.push_str(&format!("            group: {}\n", quote(group)));
+        }
+        matrix.push_str(&format!("            timeout: {}\n", timeout));
+        matrix.push_str(&format!(
+            "            build: {}\n",
+            quote(&build_command(project, target, &config_dir))
+        ));
+    }
+
+    format!(
+        r#"# Generated by `lfz ci generate` from {build_file}.
+# Run it again after changing the build targets (`lfz ci generate --check` verifies).
+name: Build ZMK firmware
+
+on:
+  push:
+  pull_request:
+  workflow_dispatch:
+
+jobs:
+  build:
+    name: ${{{{ matrix.artifact-name }}}}
+    runs-on: ubuntu-latest
+    container:
+      image: {image}
+    timeout-minutes: ${{{{ matrix.timeout }}}}
+    strategy:
+      fail-fast: false
+      matrix:
+        include:
+{matrix}    steps:
+      - uses: actions/checkout@v4
+
+      - name: Cache west modules
+        uses: actions/cache@v4
+        with:
+          path: |
+            modules/
+            tools/
+            zephyr/
+            bootloader/
+            zmk/
+          key: west-${{{{ hashFiles('{config_dir}/west.yml') }}}}
+          restore-keys: west-
+
+      - name: West init
+        run: west init -l "{config_dir}"
+
+      - name: West update
+        run: west update --fetch-opt=--filter=tree:0
+
+      - name: West zephyr-export
+        run: west zephyr-export
+
+      - name: Build
+        run: ${{{{ matrix.build }}}}
+
+      - name: Collect firmware
+        run: |
+          mkdir -p firmware
+          for uf2 in "build/${{{{ matrix.artifact-name }}}}/zephyr/zmk.uf2" "build/${{{{ matrix.artifact-name }}}}/zmk/zephyr/zmk.uf2"; do
+            if [ -f "$uf2" ]; then cp "$uf2" "firmware/${{{{ matrix.artifact-name }}}}.uf2"; break; fi
+          done
+
+      - uses: actions/upload-artifact@v4
+        with:
+          name: ${{{{ matrix.artifact-name }}}}
+          path: firmware/*.uf2
+          if-no-files-found: error
+"#,
+        build_file = project
+            .build_file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        image = DEFAULT_IMAGE,
+        matrix = matrix,
+        config_dir = config_dir,
+    )
+}
+
+/// The `west build` command for a target, as lfz runs it in its container
+fn build_command(project: &Project, target: &BuildTarget, config_dir: &str) -> String {
+    let config_path = format!("$GITHUB_WORKSPACE/{}", config_dir);
+    let mut command = String::new();
+    if !target.env.is_empty() {
+        command.push_str("env ");
+        for (key, value) in &target.env {
+            command.push_str(&format!("{}={} ", key, shell_quote(value)));
+        }
+    }
+    command.push_str("west ");
+    command.push_str(&target.west_build_args(&config_path, true).join(" "));
+
+    // Modules are only reachable on the runner if they're part of the repository
+    let modules: Vec<String> = target
+        .extra_modules_with(&project.extra_modules())
+        .iter()
+        .filter_map(|module| match relative(&project.root, module) {
+            Some(path) if path.is_empty() => Some("$GITHUB_WORKSPACE".to_string()),
+            Some(path) => Some(format!("$GITHUB_WORKSPACE/{}", path)),
+            None => {
+                output::warning(&format!(
+                    "{}: extra module {} is outside the repository and is left out of CI; add it to west.yml instead",
+                    target.artifact_name,
+                    paths::anonymize_path(module)
+                ));
+                None
+            }
+        })
+        .collect();
+    if !modules.is_empty() {
+        command.push_str(&format!(" -DZMK_EXTRA_MODULES=\"{}\"", modules.join(";")));
+    }
+
+    command
+}
+
+/// `path` relative to `root`, with `/` separators, if it is inside it
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let relative = path.strip_prefix(&root).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// A YAML scalar that is always read as a string (JSON strings are valid YAML)
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn project(build_yaml: &str) -> (tempfile::TempDir, Project) {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("config")).unwrap();
+        fs::write(
+            dir.path().join("config/west.yml"),
+            "manifest:\n  projects: []",
+        )
+        .unwrap();
+        fs::write(dir.path().join("build.yaml"), build_yaml).unwrap();
+        let project = Project::detect_from(dir.path()).unwrap();
+        (dir, project)
+    }
+
+    #[test]
+    fn test_workflow_matrix() {
+        let (_dir, project) = project(
+            "include:\n  - board: nice_nano_v2\n    shield: corne_left\n    group: central\n    timeout: 90s\n    env:\n      FOO: it's\n  - board: nice_nano_v2\n    shield: corne_right\n",
+        );
+        let targets = select_targets(&project, None, None).unwrap();
+        let workflow = workflow(&project, &targets);
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&workflow).unwrap();
+        let include = yaml["jobs"]["build"]["strategy"]["matrix"]["include"]
+            .as_sequence()
+            .unwrap();
+        assert_eq!(include.len(), 2);
+        assert_eq!(include[0]["artifact-name"], "corne_left-nice_nano_v2-zmk");
+        assert_eq!(include[0]["group"], "central");
+        assert_eq!(include[0]["timeout"], 2);
+        assert_eq!(include[1]["timeout"], DEFAULT_TIMEOUT_MINUTES);
+        assert_eq!(
+            include[0]["build"],
+            "env FOO='it'\\''s' west build -s zmk/app -d build/corne_left-nice_nano_v2-zmk \
+             -b nice_nano_v2 -p -- -DZMK_CONFIG=$GITHUB_WORKSPACE/config -DSHIELD=corne_left"
+        );
+        assert!(workflow.contains("name: ${{ matrix.artifact-name }}"));
+        assert!(workflow.contains("west init -l \"config\""));
+    }
+
+    #[test]
+    fn test_select_targets_by_group() {
+        let (_dir, project) = project(
+            "include:\n  - board: a\n    group: central\n  - board: b\n    group: peripheral\n",
+        );
+        let targets = select_targets(&project, Some("peripheral"), None).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].board, "b");
+        assert!(select_targets(&project, None, Some("nope")).is_err());
+    }
+
+    #[test]
+    fn test_repo_module_path() {
+        let (dir, project) = project("include:\n  - board: a\n");
+        fs::create_dir_all(dir.path().join("zephyr")).unwrap();
+        fs::write(dir.path().join("zephyr/module.yml"), "").unwrap();
+        let project = Project::detect_from(&project.root).unwrap();
+
+        let target = BuildTarget::from_args("a".to_string(), None).unwrap();
+        assert!(build_command(&project, &target, "config")
+            .ends_with(" -DZMK_EXTRA_MODULES=\"$GITHUB_WORKSPACE\""));
+    }
+}
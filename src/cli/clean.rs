@@ -1,11 +1,20 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 
+use crate::build::artifact_cache::ArtifactCache;
+use crate::build::logs;
+use crate::config::lfz_toml::LfzConfig;
 use crate::config::project::Project;
 use crate::output;
 use crate::paths;
-use crate::workspace::WorkspaceManager;
+use crate::workspace::eviction::{self, WorkspaceUsage};
+use crate::workspace::guard;
+use crate::workspace::{WorkspaceManager, WorkspaceMetadata};
+
+use super::purge::purge_workspaces;
+use super::size::format_size;
 
 /// Recursively remove a directory, fixing permissions as needed.
 /// Some files (like git objects) may be read-only.
@@ -49,31 +58,276 @@ fn fix_permissions(path: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn run(all: bool) -> Result<()> {
-    if all {
-        // Remove all cached workspaces
-        let workspaces_dir = paths::workspaces_dir()?;
-        if workspaces_dir.exists() {
-            let spinner = output::spinner(&format!(
-                "Removing all cached workspaces: {}",
-                paths::anonymize_path(&workspaces_dir)
+/// Remove the least recently used workspaces until all of them take at
+/// most `max_size`, keeping the one at `keep` and those other lfz runs are
+/// using. With `dry_run`, only report
+/// what would be removed. Returns how many workspaces were (or would be)
+/// removed and the space they took.
+pub fn evict(max_size: u64, keep: Option<&Path>, dry_run: bool) -> Result<(usize, u64)> {
+    let workspaces = eviction::scan(&paths::workspaces_dir()?)?;
+    let keep: Vec<&Path> = workspaces
+        .iter()
+        .map(|w| w.path.as_path())
+        .filter(|path| keep == Some(*path) || guard::in_use(path))
+        .collect();
+    let evicted = eviction::plan(&workspaces, max_size, &keep);
+    if evicted.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let total: u64 = workspaces.iter().map(|w| w.size).sum();
+    output::header(&format!(
+        "Workspaces take {}, over the {} quota",
+        format_size(total),
+        format_size(max_size)
+    ));
+    let freed = remove_workspaces(&evicted, dry_run)?;
+    let verb = if dry_run { "Would evict" } else { "Evicted" };
+    output::info(&format!(
+        "{} {} workspace(s), freeing {}",
+        verb,
+        evicted.len(),
+        format_size(freed)
+    ));
+    Ok((evicted.len(), freed))
+}
+
+/// List workspaces and remove them (unless `dry_run`). Returns the space
+/// they took.
+pub fn remove_workspaces(workspaces: &[&WorkspaceUsage], dry_run: bool) -> Result<u64> {
+    let mut freed = 0;
+    for workspace in workspaces {
+        let last_used = humantime::format_rfc3339_seconds(workspace.last_used).to_string();
+        output::list_item(&format!(
+            "{} ({}, last used {})",
+            paths::anonymize_path(&workspace.path),
+            format_size(workspace.size),
+            &last_used[..10]
+        ));
+        if !dry_run {
+            remove_dir_all(&workspace.path)?;
+            guard::remove_lock(&workspace.path)?;
+        }
+        freed += workspace.size;
+    }
+    Ok(freed)
+}
+
+/// Remove the workspaces not used within `max_age`, and with `logs` and
+/// `artifacts` the build logs and cached firmware as old
+fn remove_older_than(max_age: Duration, logs: bool, artifacts: bool, dry_run: bool) -> Result<()> {
+    let cutoff = SystemTime::now()
+        .checked_sub(max_age)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let age = humantime::format_duration(max_age);
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+
+    let (removed, freed) = remove_unused(max_age, None, dry_run)?;
+    if removed == 0 {
+        output::info(&format!("No workspace unused for {}", age));
+    } else {
+        output::info(&format!(
+            "{} {} workspace(s), freeing {}",
+            verb,
+            removed,
+            format_size(freed)
+        ));
+    }
+
+    if logs {
+        let (removed, freed) = remove_logs_before(cutoff, dry_run)?;
+        output::info(&format!(
+            "{} {} build log(s), freeing {}",
+            verb,
+            removed,
+            format_size(freed)
+        ));
+    }
+
+    if artifacts {
+        let (removed, freed) = remove_artifacts_before(cutoff, dry_run)?;
+        output::info(&format!(
+            "{} {} cached firmware file(s), freeing {}",
+            verb,
+            removed,
+            format_size(freed)
+        ));
+    }
+    Ok(())
+}
+
+/// List and remove (unless `dry_run`) the workspaces not used within
+/// `max_age`, except `keep` and those a build is using. Returns how many
+/// there were and the space they took.
+pub fn remove_unused(
+    max_age: Duration,
+    keep: Option<&Path>,
+    dry_run: bool,
+) -> Result<(usize, u64)> {
+    let workspaces = eviction::scan(&paths::workspaces_dir()?)?;
+    let mut unused = eviction::unused_for(&workspaces, max_age, SystemTime::now());
+    unused.retain(|w| keep != Some(w.path.as_path()) && !guard::in_use(&w.path));
+    if unused.is_empty() {
+        return Ok((0, 0));
+    }
+    output::header(&format!(
+        "Workspaces unused for {}",
+        humantime::format_duration(max_age)
+    ));
+    let freed = remove_workspaces(&unused, dry_run)?;
+    Ok((unused.len(), freed))
+}
+
+/// Remove (unless `dry_run`) the build logs written before `cutoff`.
+/// Returns how many there were and the space they took.
+pub fn remove_logs_before(cutoff: SystemTime, dry_run: bool) -> Result<(usize, u64)> {
+    let old = logs::written_before(&paths::logs_dir()?, cutoff);
+    let mut freed = 0;
+    for log in &old {
+        freed += fs::metadata(log).map(|m| m.len()).unwrap_or(0);
+        if !dry_run {
+            fs::remove_file(log).with_context(|| format!("Failed to remove {}", log.display()))?;
+        }
+    }
+    Ok((old.len(), freed))
+}
+
+/// Remove (unless `dry_run`) the cached firmware not used since `cutoff`.
+/// Returns how many entries there were and the space they took.
+pub fn remove_artifacts_before(cutoff: SystemTime, dry_run: bool) -> Result<(usize, u64)> {
+    let old = ArtifactCache::open()?.used_before(cutoff);
+    let mut freed = 0;
+    for entry in &old {
+        freed += paths::dir_size(entry);
+        if !dry_run {
+            remove_dir_all(entry)?;
+        }
+    }
+    Ok((old.len(), freed))
+}
+
+/// The workspace a spec names: its id (or a prefix of it), or the
+/// repository, branch or `repo:branch` it was recorded for
+fn resolve<'a>(workspaces: &'a [WorkspaceUsage], spec: &str) -> Result<&'a WorkspaceUsage> {
+    let id = |w: &WorkspaceUsage| {
+        w.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    let mut matches: Vec<&WorkspaceUsage> = workspaces
+        .iter()
+        .filter(|w| id(w).starts_with(spec))
+        .collect();
+    if matches.is_empty() {
+        matches = workspaces
+            .iter()
+            .filter(|w| WorkspaceMetadata::load(&w.path).is_some_and(|m| m.matches(spec)))
+            .collect();
+    }
+
+    match matches.as_slice() {
+        [] => anyhow::bail!("No workspace matches '{}' (see lfz workspaces)", spec),
+        [workspace] => Ok(workspace),
+        _ => {
+            let candidates: Vec<String> = matches
+                .iter()
+                .map(|w| match WorkspaceMetadata::load(&w.path) {
+                    Some(metadata) => format!("  {} ({})", id(w), metadata.name()),
+                    None => format!("  {}", id(w)),
+                })
+                .collect();
+            anyhow::bail!(
+                "'{}' matches {} workspaces; use one of their ids:\n{}",
+                spec,
+                matches.len(),
+                candidates.join("\n")
+            )
+        }
+    }
+}
+
+/// Remove the workspace a spec names (see [`resolve`])
+fn remove_named(spec: &str, dry_run: bool) -> Result<()> {
+    let workspaces = eviction::scan(&paths::workspaces_dir()?)?;
+    let workspace = resolve(&workspaces, spec)?;
+    if guard::in_use(&workspace.path) {
+        anyhow::bail!(
+            "Another lfz build is running in the workspace of '{}'",
+            spec
+        );
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    let name = WorkspaceMetadata::load(&workspace.path)
+        .map(|m| m.name())
+        .unwrap_or_else(|| spec.to_string());
+    output::header(&format!("Workspace of {}", name));
+    let freed = remove_workspaces(&[workspace], dry_run)?;
+    output::info(&format!(
+        "{} 1 workspace, freeing {}",
+        verb,
+        format_size(freed)
+    ));
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    all: bool,
+    workspace: Option<String>,
+    evict_lru: bool,
+    max_size: Option<u64>,
+    older_than: Option<Duration>,
+    logs: bool,
+    artifacts: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if let Some(spec) = workspace {
+        remove_named(&spec, dry_run)?;
+    } else if let Some(max_age) = older_than {
+        remove_older_than(max_age, logs, artifacts, dry_run)?;
+    } else if evict_lru {
+        // The workspace of the project at hand is in use, and its lfz.toml may set the quota
+        let project = Project::detect().ok();
+        let max_size = match (max_size, &project) {
+            (Some(size), _) => Some(size),
+            (None, Some(project)) => LfzConfig::load(&project.root)?.workspaces.max_size()?,
+            (None, None) => None,
+        };
+        let Some(max_size) = max_size else {
+            anyhow::bail!(
+                "No quota to evict down to: pass --max-size or set workspaces.max-size in lfz.toml"
+            );
+        };
+        let keep = match &project {
+            Some(project) => WorkspaceManager::new()?.find_workspace(project)?,
+            None => None,
+        };
+        if evict(max_size, keep.as_deref(), dry_run)?.0 == 0 {
+            output::info(&format!(
+                "Workspaces fit in the {} quota; nothing to evict",
+                format_size(max_size)
             ));
-            remove_dir_all(&workspaces_dir)?;
-            spinner.finish_with_message("All cached workspaces removed.");
-        } else {
-            output::info("No cached workspaces found.");
         }
+    } else if all {
+        // Remove all cached workspaces other runs aren't using
+        purge_workspaces()?;
     } else {
         // Remove workspace for current project
         let project = Project::detect()?;
         let workspace_manager = WorkspaceManager::new()?;
 
         if let Some(workspace) = workspace_manager.find_workspace(&project)? {
+            if guard::in_use(&workspace) {
+                anyhow::bail!("Another lfz build is running in this project's workspace");
+            }
             let spinner = output::spinner(&format!(
                 "Removing workspace: {}",
                 paths::anonymize_path(&workspace)
             ));
             remove_dir_all(&workspace)?;
+            guard::remove_lock(&workspace)?;
             spinner.finish_with_message("Workspace removed.");
         } else {
             output::info("No cached workspace found for this project.");
@@ -82,3 +336,75 @@ pub fn run(all: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn workspace(dir: &Path, id: &str, repo: &str, branch: &str) -> WorkspaceUsage {
+        let path = dir.join(id);
+        fs::create_dir(&path).unwrap();
+        let metadata = WorkspaceMetadata {
+            repo: repo.to_string(),
+            branch: branch.to_string(),
+            config_dir: PathBuf::from("/config"),
+            scratch: None,
+            created: 0,
+            image: None,
+            key: None,
+        };
+        fs::write(
+            path.join(".lfz_workspace.json"),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+        WorkspaceUsage {
+            path,
+            size: 0,
+            last_used: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = [
+            workspace(
+                dir.path(),
+                "a1b2",
+                "git@github.com:me/corne-config.git",
+                "main",
+            ),
+            workspace(
+                dir.path(),
+                "a1c3",
+                "git@github.com:me/corne-config.git",
+                "dev",
+            ),
+            workspace(
+                dir.path(),
+                "d4e5",
+                "https://github.com/me/lily58.git",
+                "main",
+            ),
+        ];
+        let id = |spec| {
+            resolve(&workspaces, spec)
+                .map(|w| w.path.file_name().unwrap().to_string_lossy().to_string())
+        };
+
+        assert_eq!(id("a1b2").unwrap(), "a1b2");
+        assert_eq!(id("d4").unwrap(), "d4e5");
+        assert_eq!(id("corne-config:main").unwrap(), "a1b2");
+        assert_eq!(id("dev").unwrap(), "a1c3");
+        assert_eq!(id("lily58").unwrap(), "d4e5");
+
+        let ambiguous = id("corne-config").unwrap_err().to_string();
+        assert!(ambiguous.contains("matches 2 workspaces"));
+        assert!(ambiguous.contains("a1c3 (corne-config:dev)"));
+        assert!(id("a1").is_err());
+        assert!(id("main").is_err());
+        assert!(id("sofle").is_err());
+    }
+}
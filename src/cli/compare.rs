@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::path::Path;
+
+use super::size::format_size;
+use crate::build::compare::{Change, Comparison};
+use crate::build::history::{self, Run};
+use crate::config::project::Project;
+use crate::output;
+use crate::workspace::WorkspaceManager;
+
+/// Compare two builds target by target. Each is an output directory or a
+/// history entry: `@0` for the latest run, `@1` for the one before and so on.
+pub fn run(before: String, after: String, json: bool) -> Result<()> {
+    let comparison = Comparison::new(&load(&before)?, &load(&after)?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+
+    output::header(&format!("{} → {}", before, after));
+    match &comparison.changed_config {
+        Some(changed) if changed.is_empty() => output::info("Config unchanged"),
+        Some(changed) => output::info(&format!("Config changed: {}", changed.join(", "))),
+        None => {}
+    }
+    if comparison.targets.is_empty() {
+        output::info("No firmware to compare.");
+    }
+
+    for target in &comparison.targets {
+        println!("\n  {}", style(&target.target).cyan());
+        println!("    {:9} {}", "firmware", format_change(target.size));
+        for region in &target.memory {
+            let percent = region
+                .used
+                .after
+                .filter(|_| region.size > 0)
+                .map(|used| {
+                    format!(
+                        " ({:.1}% of {})",
+                        used as f64 * 100.0 / region.size as f64,
+                        format_size(region.size)
+                    )
+                })
+                .unwrap_or_default();
+            println!(
+                "    {:9} {}{}",
+                region.name,
+                format_change(region.used),
+                style(percent).dim()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A run from the history (`@N`) or an output directory
+fn load(source: &str) -> Result<Run> {
+    let Some(back) = source.strip_prefix('@') else {
+        return history::load_output(Path::new(source));
+    };
+    let back: usize = back
+        .parse()
+        .with_context(|| format!("Invalid history entry '{}' (expected e.g. @1)", source))?;
+
+    let project = Project::detect()?;
+    let workspace = WorkspaceManager::new()?.workspace_path(&project)?;
+    let runs = history::load(&history::history_file(&workspace)?);
+    runs.len()
+        .checked_sub(back + 1)
+        .map(|index| runs[index].clone())
+        .with_context(|| {
+            format!(
+                "No run {} in the history ({} recorded). Run 'lfz history' to see them.",
+                source,
+                runs.len()
+            )
+        })
+}
+
+/// e.g. "350.00 KB → 352.00 KB  +2.00 KB"
+fn format_change(change: Change) -> String {
+    let value = |v: Option<u64>| v.map(format_size).unwrap_or_else(|| "-".to_string());
+    let delta = match change.delta() {
+        Some(0) => style(format_delta(0)).dim(),
+        Some(delta) if delta > 0 => style(format_delta(delta)).yellow(),
+        Some(delta) => style(format_delta(delta)).green(),
+        None => style(String::new()),
+    };
+    format!(
+        "{:>10} → {:>10}  {}",
+        value(change.before),
+        value(change.after),
+        delta
+    )
+}
+
+/// e.g. "+2.00 KB", "-12 B" or "unchanged"
+fn format_delta(delta: i64) -> String {
+    match delta {
+        0 => "unchanged".to_string(),
+        d if d > 0 => format!("+{}", format_size(d as u64)),
+        d => format!("-{}", format_size(d.unsigned_abs())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(1024), "+1.00 KB");
+        assert_eq!(format_delta(-12), "-12 B");
+        assert_eq!(format_delta(0), "unchanged");
+    }
+}
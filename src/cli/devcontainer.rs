@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{self, CompilerCache};
+use crate::config::lfz_toml::LfzConfig;
+use crate::config::project::Project;
+use crate::config::west_yml::{self, WestManifest};
+use crate::container::project_image::{ProjectImage, DOCKERFILE};
+use crate::container::{Runtime, DEFAULT_IMAGE};
+use crate::output;
+use crate::paths;
+use crate::workspace::WorkspaceManager;
+
+/// Where the build container gets its image
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ImageSource {
+    Image(String),
+    /// The project's lfz.Dockerfile, with the paths as devcontainer.json
+    /// writes them
+    Dockerfile {
+        dockerfile: String,
+        context: String,
+    },
+}
+
+/// The build environment lfz runs west in, as a dev container
+#[derive(Debug)]
+struct Devcontainer {
+    name: String,
+    image: ImageSource,
+    workspace: PathBuf,
+    /// (host path, container path, read-only)
+    mounts: Vec<(PathBuf, String, bool)>,
+    env: Vec<(String, String)>,
+}
+
+impl Devcontainer {
+    fn to_json(&self) -> Value {
+        let mut config = Map::new();
+        config.insert("name".to_string(), json!(self.name));
+        match &self.image {
+            ImageSource::Image(image) => {
+                config.insert("image".to_string(), json!(image));
+            }
+            ImageSource::Dockerfile {
+                dockerfile,
+                context,
+            } => {
+                config.insert(
+                    "build".to_string(),
+                    json!({ "dockerfile": dockerfile, "context": context }),
+                );
+            }
+        }
+        config.insert(
+            "workspaceMount".to_string(),
+            json!(bind(&self.workspace, "/workspace", false)),
+        );
+        config.insert("workspaceFolder".to_string(), json!("/workspace"));
+        config.insert(
+            "mounts".to_string(),
+            json!(self
+                .mounts
+                .iter()
+                .map(|(host, container, readonly)| bind(host, container, *readonly))
+                .collect::<Vec<_>>()),
+        );
+        config.insert(
+            "containerEnv".to_string(),
+            Value::Object(
+                self.env
+                    .iter()
+                    .map(|(key, value)| (key.clone(), json!(value)))
+                    .collect(),
+            ),
+        );
+        // The images run builds as root
+        config.insert("remoteUser".to_string(), json!("root"));
+        Value::Object(config)
+    }
+}
+
+/// A bind mount as devcontainer.json (and `docker run --mount`) takes it
+fn bind(host: &Path, container: &str, readonly: bool) -> String {
+    format!(
+        "source={},target={},type=bind{}",
+        host.display(),
+        container,
+        if readonly { ",readonly" } else { "" }
+    )
+}
+
+/// Write a devcontainer.json opening the project's workspace in its build
+/// image, with the mounts and environment lfz builds with
+pub fn export(output_path: Option<PathBuf>, force: bool) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace = WorkspaceManager::new()?
+        .find_workspace(&project)?
+        .context("No workspace for this project yet. Run 'lfz prefetch' (or 'lfz build') first.")?;
+    let output_path =
+        output_path.unwrap_or_else(|| project.root.join(".devcontainer/devcontainer.json"));
+    if output_path.exists() && !force {
+        anyhow::bail!(
+            "{} exists. Pass --force to replace it.",
+            output_path.display()
+        );
+    }
+
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let project_image = ProjectImage::detect(&project.root)?;
+    let tag = project_image
+        .as_ref()
+        .map_or(DEFAULT_IMAGE, ProjectImage::tag);
+    let image = match &project_image {
+        None => ImageSource::Image(DEFAULT_IMAGE.to_string()),
+        Some(_) => {
+            // Relative to devcontainer.json when it sits in the project
+            let dir = output_path.parent().unwrap_or(Path::new("."));
+            let root = match dir.strip_prefix(&project.root) {
+                Ok(relative) => relative
+                    .components()
+                    .map(|_| "..")
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                Err(_) => project.root.display().to_string(),
+            };
+            let root = if root.is_empty() {
+                ".".to_string()
+            } else {
+                root
+            };
+            ImageSource::Dockerfile {
+                dockerfile: format!("{}/{}", root, DOCKERFILE),
+                context: root,
+            }
+        }
+    };
+
+    let image_key = match Runtime::detect() {
+        Ok(runtime) => cache::image_key(&runtime, tag),
+        Err(_) => tag.to_string(),
+    };
+    let compiler_cache = CompilerCache::from_config(&lfz_config.compiler_cache)?.image(&image_key);
+    let cache_dir = compiler_cache.dir()?;
+    fs::create_dir_all(&cache_dir).context("Failed to create the compiler cache directory")?;
+
+    // Editable, unlike in builds: this is for hacking on the config
+    let mut mounts = vec![(
+        project.config_dir.clone(),
+        "/workspace/config".to_string(),
+        false,
+    )];
+    for (host, container, readonly) in compiler_cache.mounts(&cache_dir) {
+        mounts.push((host, container.to_string(), readonly));
+    }
+    let extra_modules = project.extra_modules();
+    for (i, module) in extra_modules.iter().enumerate() {
+        mounts.push((module.clone(), format!("/workspace/module_{}", i), false));
+    }
+    let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+    for local in manifest.local_projects(&project.config_dir) {
+        let local = local.as_override();
+        mounts.push((
+            local.host_path,
+            format!("/workspace/{}", local.workspace_path),
+            false,
+        ));
+    }
+
+    let mut env = vec![(
+        "CMAKE_PREFIX_PATH".to_string(),
+        "/workspace/zephyr/share/zephyr-package/cmake".to_string(),
+    )];
+    env.extend(
+        compiler_cache
+            .env()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value)),
+    );
+    if !extra_modules.is_empty() {
+        let modules = (0..extra_modules.len())
+            .map(|i| format!("/workspace/module_{}", i))
+            .collect::<Vec<_>>()
+            .join(";");
+        env.push(("ZMK_EXTRA_MODULES".to_string(), modules));
+    }
+
+    let name = west_yml::format_project_display(&project.config_dir)
+        .unwrap_or_else(|_| paths::anonymize_path(&project.root));
+    let devcontainer = Devcontainer {
+        name: format!("lfz: {}", name),
+        image,
+        workspace,
+        mounts,
+        env,
+    };
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(
+        &output_path,
+        serde_json::to_string_pretty(&devcontainer.to_json())? + "\n",
+    )
+    .with_context(|| format!("Failed to write {}", output_path.display()))?;
+
+    output::success(&format!("Wrote {}", output_path.display()));
+    output::info(
+        "It holds this machine's paths. Inside, build with e.g. \
+         'west build -s zmk/app -b <board> -- -DZMK_CONFIG=/workspace/config'",
+    );
+    if devcontainer
+        .env
+        .iter()
+        .any(|(key, _)| key == "ZMK_EXTRA_MODULES")
+    {
+        output::info(
+            "and add -DZMK_EXTRA_MODULES=\"$ZMK_EXTRA_MODULES\" for the project's modules",
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let devcontainer = Devcontainer {
+            name: "lfz: corne-config".to_string(),
+            image: ImageSource::Image(DEFAULT_IMAGE.to_string()),
+            workspace: PathBuf::from("/cache/workspaces/0a1b"),
+            mounts: vec![
+                (
+                    PathBuf::from("/src/corne-config/config"),
+                    "/workspace/config".to_string(),
+                    false,
+                ),
+                (
+                    PathBuf::from("/usr/bin/sccache"),
+                    "/usr/local/bin/sccache".to_string(),
+                    true,
+                ),
+            ],
+            env: vec![("CMAKE_PREFIX_PATH".to_string(), "/cmake".to_string())],
+        };
+        let config = devcontainer.to_json();
+        assert_eq!(config["image"], DEFAULT_IMAGE);
+        assert_eq!(
+            config["workspaceMount"],
+            "source=/cache/workspaces/0a1b,target=/workspace,type=bind"
+        );
+        assert_eq!(
+            config["mounts"][1],
+            "source=/usr/bin/sccache,target=/usr/local/bin/sccache,type=bind,readonly"
+        );
+        assert_eq!(config["containerEnv"]["CMAKE_PREFIX_PATH"], "/cmake");
+
+        let devcontainer = Devcontainer {
+            image: ImageSource::Dockerfile {
+                dockerfile: "../lfz.Dockerfile".to_string(),
+                context: "..".to_string(),
+            },
+            ..devcontainer
+        };
+        let config = devcontainer.to_json();
+        assert!(config.get("image").is_none());
+        assert_eq!(config["build"]["dockerfile"], "../lfz.Dockerfile");
+    }
+}
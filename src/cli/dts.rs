@@ -0,0 +1,52 @@
+use anyhow::Result;
+use console::style;
+
+use crate::build::devicetree;
+use crate::output;
+
+/// Generate a target's devicetree and print it, or only the nodes matching
+/// `node` (a path, name or label)
+pub fn run(target: Option<String>, node: Option<String>) -> Result<()> {
+    let (target, workspace, orchestrator, _guard) = super::report::prepare(target)?;
+
+    output::status("Running", "CMake");
+    orchestrator.configure(&target)?;
+    let dts = devicetree::load(&workspace, &target)?;
+
+    let text = match &node {
+        Some(query) => {
+            let nodes = devicetree::select(&dts, query);
+            if nodes.is_empty() {
+                anyhow::bail!("No node matches '{}' in {}", query, target.artifact_name);
+            }
+            nodes.join("\n")
+        }
+        None => dts,
+    };
+    println!();
+    for line in text.lines() {
+        print_line(line);
+    }
+    Ok(())
+}
+
+/// Print a devicetree line, with node headers highlighted and comments dimmed
+fn print_line(line: &str) {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if trimmed.starts_with("/*") || trimmed.starts_with("//") {
+        println!("{}{}", indent, style(trimmed).dim());
+    } else if let Some(header) = trimmed.strip_suffix('{') {
+        match header.rsplit_once(": ") {
+            Some((labels, name)) => println!(
+                "{}{}: {}{{",
+                indent,
+                style(labels).cyan(),
+                style(name).bold()
+            ),
+            None => println!("{}{}{{", indent, style(header).bold()),
+        }
+    } else {
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::time::{Duration, SystemTime};
+
+use super::clean::{evict, remove_artifacts_before, remove_logs_before, remove_unused};
+use super::size::{format_size, short_image};
+use crate::cache::ccache;
+use crate::config::lfz_toml::{CcacheConfig, LfzConfig};
+use crate::config::project::Project;
+use crate::container::Runtime;
+use crate::output;
+use crate::paths::{self, dir_size};
+use crate::workspace::WorkspaceManager;
+
+/// Apply lfz.toml's retention policies: the `[gc]` ages, the workspace
+/// quota and the ccache's size cap. With `dry_run`, only report.
+pub fn run(dry_run: bool) -> Result<()> {
+    // The project at hand supplies the policies, and its workspace stays
+    let project = Project::detect().ok();
+    let config = match &project {
+        Some(project) => LfzConfig::load(&project.root)?,
+        None => LfzConfig::default(),
+    };
+    let keep = match &project {
+        Some(project) => WorkspaceManager::new()?.find_workspace(project)?,
+        None => None,
+    };
+    let workspaces_max_size = config.workspaces.max_size()?;
+    let ccache_max_size = config.ccache.max_size()?;
+    if !config.gc.is_configured() && workspaces_max_size.is_none() && ccache_max_size.is_none() {
+        anyhow::bail!(
+            "No retention policy to apply: set [gc] ages, workspaces.max-size or ccache.max-size in lfz.toml"
+        );
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    let mut reclaimed = 0;
+
+    if let Some(max_age) = config.gc.workspaces_max_age()? {
+        let (removed, freed) = remove_unused(max_age, keep.as_deref(), dry_run)?;
+        if removed > 0 {
+            output::info(&format!(
+                "{} {} workspace(s), freeing {}",
+                verb,
+                removed,
+                format_size(freed)
+            ));
+        }
+        reclaimed += freed;
+    }
+    if let Some(max_size) = workspaces_max_size {
+        reclaimed += evict(max_size, keep.as_deref(), dry_run)?.1;
+    }
+    if let Some(cap) = ccache_max_size {
+        reclaimed += trim_ccaches(cap, &config.ccache, dry_run)?;
+    }
+    if let Some(max_age) = config.gc.logs_max_age()? {
+        let (removed, freed) = remove_logs_before(cutoff(max_age), dry_run)?;
+        if removed > 0 {
+            output::info(&format!(
+                "{} {} build log(s) older than {}, freeing {}",
+                verb,
+                removed,
+                humantime::format_duration(max_age),
+                format_size(freed)
+            ));
+        }
+        reclaimed += freed;
+    }
+    if let Some(max_age) = config.gc.artifacts_max_age()? {
+        let (removed, freed) = remove_artifacts_before(cutoff(max_age), dry_run)?;
+        if removed > 0 {
+            output::info(&format!(
+                "{} {} cached firmware file(s) unused for {}, freeing {}",
+                verb,
+                removed,
+                humantime::format_duration(max_age),
+                format_size(freed)
+            ));
+        }
+        reclaimed += freed;
+    }
+
+    match (reclaimed, dry_run) {
+        (0, _) => output::info("Everything is within the retention policies"),
+        (_, true) => output::info(&format!("Would reclaim {}", format_size(reclaimed))),
+        (_, false) => output::success(&format!("Reclaimed {}", format_size(reclaimed))),
+    }
+    Ok(())
+}
+
+fn cutoff(max_age: Duration) -> SystemTime {
+    SystemTime::now()
+        .checked_sub(max_age)
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Trim each image's ccache that is over `cap`. Returns the space freed
+/// (with `dry_run`, the space over the cap).
+fn trim_ccaches(cap: u64, config: &CcacheConfig, dry_run: bool) -> Result<u64> {
+    let over: Vec<_> = ccache::image_caches(&paths::ccache_dir()?)
+        .into_iter()
+        .map(|(dir, image)| {
+            let size = dir_size(&dir);
+            (dir, image, size)
+        })
+        .filter(|(_, _, size)| *size > cap)
+        .collect();
+    if over.is_empty() {
+        return Ok(0);
+    }
+
+    output::header(&format!("Ccaches over the {} cap", format_size(cap)));
+    if dry_run {
+        let mut excess = 0;
+        for (_, image, size) in &over {
+            output::list_item(&format!("{} ({})", short_image(image), format_size(*size)));
+            excess += size - cap;
+        }
+        output::info(&format!("Would trim about {}", format_size(excess)));
+        return Ok(excess);
+    }
+
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let mut freed = 0;
+    for (dir, image, size) in &over {
+        let spinner = output::spinner(&format!("Trimming {}", short_image(image)));
+        let trimmed = ccache::configure(dir, config).and_then(|_| ccache::trim(&runtime, dir));
+        spinner.finish_and_clear();
+        if let Err(e) = trimmed {
+            output::warning(&format!("Failed to trim {}: {}", short_image(image), e));
+            continue;
+        }
+        let after = dir_size(dir);
+        output::list_item(&format!(
+            "{}: {} -> {}",
+            short_image(image),
+            format_size(*size),
+            format_size(after)
+        ));
+        freed += size.saturating_sub(after);
+    }
+    Ok(freed)
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use console::style;
+use std::time::{Duration, SystemTime};
+
+use super::size::format_size;
+use crate::build::history::{self, Run};
+use crate::config::project::Project;
+use crate::output;
+use crate::workspace::WorkspaceManager;
+
+/// Show recent runs and per-target statistics, or the recent builds of one target
+pub fn run(target: Option<String>, count: usize) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace = WorkspaceManager::new()?.workspace_path(&project)?;
+    let runs = history::load(&history::history_file(&workspace)?);
+    if runs.is_empty() {
+        output::info("No builds recorded for this project yet.");
+        return Ok(());
+    }
+
+    match target {
+        Some(target) => show_target(&runs, &target, count),
+        None => {
+            show_runs(&runs, count);
+            show_stats(&runs);
+            Ok(())
+        }
+    }
+}
+
+fn show_runs(runs: &[Run], count: usize) {
+    output::header(&format!("Last {} run(s)", count.min(runs.len())));
+    for run in runs.iter().rev().take(count) {
+        let failed = run.failed();
+        let result = if failed == 0 {
+            style(format!("{} ok", run.targets.len())).green()
+        } else {
+            style(format!(
+                "{} ok, {} failed",
+                run.targets.len() - failed,
+                failed
+            ))
+            .red()
+        };
+        println!(
+            "  {}  {:11}  {:>9}  {}",
+            style(format_time(run.time)).dim(),
+            mode(run.pristine),
+            output::format_duration(Duration::from_millis(run.duration_ms)),
+            result
+        );
+    }
+}
+
+fn show_stats(runs: &[Run]) {
+    let stats = history::stats(runs);
+    output::header("Targets");
+    let width = stats.iter().map(|s| s.target.len()).max().unwrap_or(0);
+    for stats in &stats {
+        let average = stats
+            .average
+            .map(output::format_duration)
+            .unwrap_or_else(|| "-".to_string());
+        let size = stats.size.map(format_size).unwrap_or_default();
+        let status = match stats.failure_streak {
+            0 => style("passing".to_string()).green(),
+            n => style(format!("failing ({} in a row)", n)).red(),
+        };
+        println!(
+            "  {:width$}  {:>3} run(s), {:>3} failed  avg {:>9}  {:>10}  {}",
+            style(&stats.target).cyan(),
+            stats.runs,
+            stats.failures,
+            average,
+            size,
+            status,
+            width = width
+        );
+    }
+}
+
+fn show_target(runs: &[Run], target: &str, count: usize) -> Result<()> {
+    let builds: Vec<_> = runs
+        .iter()
+        .flat_map(|run| {
+            run.targets
+                .iter()
+                .filter(|t| t.target == target)
+                .map(move |t| (run, t))
+        })
+        .collect();
+    if builds.is_empty() {
+        anyhow::bail!(
+            "No builds of '{}' recorded. Run 'lfz history' to see all targets.",
+            target
+        );
+    }
+
+    output::header(&format!(
+        "Last {} build(s) of {}",
+        count.min(builds.len()),
+        target
+    ));
+    for (run, build) in builds.iter().rev().take(count) {
+        let result = if build.success {
+            style("ok".to_string()).green()
+        } else {
+            style("failed".to_string()).red()
+        };
+        let retries = match build.retries {
+            0 => String::new(),
+            1 => "  (1 retry)".to_string(),
+            n => format!("  ({} retries)", n),
+        };
+        println!(
+            "  {}  {:11}  {:>9}  {:>10}  {}{}",
+            style(format_time(run.time)).dim(),
+            mode(run.pristine),
+            output::format_duration(Duration::from_millis(build.duration_ms)),
+            build.size.map(format_size).unwrap_or_default(),
+            result,
+            retries
+        );
+    }
+    Ok(())
+}
+
+fn mode(pristine: bool) -> &'static str {
+    if pristine {
+        "pristine"
+    } else {
+        "incremental"
+    }
+}
+
+/// "2025-01-01 12:00" (UTC)
+fn format_time(secs: u64) -> String {
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    humantime::format_rfc3339_seconds(time).to_string()[..16].replace('T', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(1_700_000_000), "2023-11-14 22:13");
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use console::style;
+
+use super::size::format_size;
+use crate::config::project::Project;
+use crate::container::project_image::{ProjectImage, DOCKERFILE, PROJECT_IMAGES};
+use crate::container::{Image, Runtime, BUILD_IMAGES, DEFAULT_IMAGE};
+use crate::output;
+use crate::paths;
+use crate::workspace::{eviction, WorkspaceMetadata};
+
+/// The local build images (ZMK's and those built from lfz.Dockerfiles),
+/// each with the workspaces last built with it
+pub(super) fn build_images(runtime: &Runtime) -> Result<Vec<(Image, Vec<String>)>> {
+    let mut images = runtime.images(BUILD_IMAGES)?;
+    images.extend(runtime.images(PROJECT_IMAGES)?);
+    images.sort_by_key(|image| std::cmp::Reverse(image.size));
+    let workspaces: Vec<WorkspaceMetadata> = eviction::scan(&paths::workspaces_dir()?)?
+        .iter()
+        .filter_map(|workspace| WorkspaceMetadata::load(&workspace.path))
+        .collect();
+    Ok(images
+        .into_iter()
+        .map(|image| {
+            let users = workspaces
+                .iter()
+                .filter(|m| m.image.as_deref().is_some_and(|i| image.matches(i)))
+                .map(WorkspaceMetadata::name)
+                .collect();
+            (image, users)
+        })
+        .collect())
+}
+
+/// Whether `prune` keeps an image: builds use it (the default image, or
+/// the project at hand's), or a workspace was last built with it
+fn is_kept(image: &Image, users: &[String], current: Option<&str>) -> bool {
+    image.matches(DEFAULT_IMAGE)
+        || current.is_some_and(|tag| image.matches(tag))
+        || !users.is_empty()
+}
+
+/// The tag of the image the project at hand builds in, if it has an
+/// lfz.Dockerfile
+fn current_project_image() -> Result<Option<String>> {
+    match Project::detect() {
+        Ok(project) => {
+            Ok(ProjectImage::detect(&project.root)?.map(|image| image.tag().to_string()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// List the build images, biggest first, with their size and the
+/// workspaces that reference them
+pub fn list() -> Result<()> {
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let images = build_images(&runtime)?;
+    if images.is_empty() {
+        output::info("No build images found; the next build pulls one");
+        return Ok(());
+    }
+
+    let width = images
+        .iter()
+        .map(|(image, _)| image.name().len())
+        .chain(["IMAGE".len()])
+        .max()
+        .unwrap_or(0);
+    println!();
+    println!(
+        "  {}",
+        style(format!("{:width$}  {:>10}  WORKSPACES", "IMAGE", "SIZE")).dim()
+    );
+    for (image, users) in &images {
+        let users = if users.is_empty() {
+            "-".to_string()
+        } else {
+            users.join(", ")
+        };
+        println!(
+            "  {:width$}  {:>10}  {}",
+            image.name(),
+            format_size(image.size),
+            users
+        );
+    }
+    println!();
+
+    let total: u64 = images.iter().map(|(image, _)| image.size).sum();
+    let current = current_project_image()?;
+    let unreferenced = images
+        .iter()
+        .filter(|(image, users)| !is_kept(image, users, current.as_deref()))
+        .count();
+    output::info(&format!(
+        "{} image(s), {}",
+        images.len(),
+        format_size(total)
+    ));
+    if unreferenced > 0 {
+        output::info(&format!(
+            "{} no workspace references; `lfz image prune` removes them",
+            unreferenced
+        ));
+    }
+    Ok(())
+}
+
+/// Pull the build image again, to pick up a newer one under its tag
+pub fn pull() -> Result<()> {
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let before = runtime.image_digest(DEFAULT_IMAGE);
+    runtime.pull_image(DEFAULT_IMAGE)?;
+    let after = runtime.image_digest(DEFAULT_IMAGE);
+    if before.is_some() && before == after {
+        output::success(&format!("{} is up to date", DEFAULT_IMAGE));
+    } else {
+        output::success(&format!("Pulled {}", DEFAULT_IMAGE));
+        if before.is_some() {
+            output::info(
+                "The previous image stays while workspaces reference it; \
+                 `lfz image prune` removes it once none does",
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Build the project's image from its lfz.Dockerfile, even if this version
+/// of the Dockerfile was built (to pick up changes to the files it copies,
+/// or a base image `lfz image pull` updated)
+pub fn build() -> Result<()> {
+    let project = Project::detect()?;
+    let image = ProjectImage::detect(&project.root)?.with_context(|| {
+        format!(
+            "No {} in {}; builds use {}",
+            DOCKERFILE,
+            paths::anonymize_path(&project.root),
+            DEFAULT_IMAGE
+        )
+    })?;
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    image.build(&runtime)?;
+    output::success(&format!("Built {}", image.tag()));
+    Ok(())
+}
+
+/// Remove the build images no workspace references, other than the one
+/// builds use. With `dry_run`, only list them.
+pub fn prune(dry_run: bool) -> Result<()> {
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let current = current_project_image()?;
+    let unreferenced: Vec<Image> = build_images(&runtime)?
+        .into_iter()
+        .filter(|(image, users)| !is_kept(image, users, current.as_deref()))
+        .map(|(image, _)| image)
+        .collect();
+    if unreferenced.is_empty() {
+        output::info("Every build image is in use");
+        return Ok(());
+    }
+
+    output::header("Unreferenced build images");
+    let mut freed = 0;
+    let mut removed = 0;
+    for image in &unreferenced {
+        output::list_item(&format!("{} ({})", image.name(), format_size(image.size)));
+        if dry_run {
+            continue;
+        }
+        match runtime.remove_image(&image.id) {
+            Ok(()) => {
+                freed += image.size;
+                removed += 1;
+            }
+            Err(e) => output::warning(&e.to_string()),
+        }
+    }
+    if dry_run {
+        let size: u64 = unreferenced.iter().map(|image| image.size).sum();
+        output::info(&format!(
+            "Would remove {} image(s), freeing {}",
+            unreferenced.len(),
+            format_size(size)
+        ));
+    } else {
+        output::success(&format!(
+            "Removed {} image(s), freeing {}",
+            removed,
+            format_size(freed)
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+use console::style;
+use std::path::Path;
+
+use crate::build::filter::matches_any_glob;
+use crate::build::kconfig::{self, Difference, NOT_SET};
+use crate::output;
+
+/// Configure a target and print its resolved Kconfig, or only the symbols
+/// matching `grep`
+pub fn run(target: Option<String>, grep: Option<String>) -> Result<()> {
+    let (target, workspace, orchestrator, _guard) = super::report::prepare(target)?;
+
+    output::status("Running", "CMake");
+    orchestrator.configure(&target)?;
+    let config = kconfig::parse(&kconfig::load(&workspace, &target)?);
+
+    let symbols: Vec<_> = config
+        .iter()
+        .filter(|(name, _)| grep.as_ref().is_none_or(|g| kconfig::matches(name, g)))
+        .collect();
+    if symbols.is_empty() {
+        output::info(&format!(
+            "No symbols match '{}'",
+            grep.as_deref().unwrap_or_default()
+        ));
+        return Ok(());
+    }
+
+    println!();
+    for (name, value) in symbols {
+        if value == NOT_SET {
+            println!("{}", style(format!("# {} is not set", name)).dim());
+        } else {
+            println!("{}={}", name, value);
+        }
+    }
+    Ok(())
+}
+
+/// Show the symbols whose values differ between two targets, or between
+/// two builds (output directories), target by target
+pub fn diff(a: String, b: String, target: Option<String>) -> Result<()> {
+    if Path::new(&a).is_dir() && Path::new(&b).is_dir() {
+        return diff_outputs(&a, &b, target);
+    }
+    if target.is_some() {
+        anyhow::bail!("--target only applies when comparing two output directories");
+    }
+
+    let (targets, workspace, orchestrator, _guard) =
+        super::report::prepare_all(&[Some(a), Some(b)])?;
+    let mut configs = Vec::new();
+    for target in &targets {
+        output::status("Running", &format!("CMake for {}", target.artifact_name));
+        orchestrator.configure(target)?;
+        configs.push(kconfig::parse(&kconfig::load(&workspace, target)?));
+    }
+
+    print_differences(
+        &targets[0].artifact_name,
+        &targets[1].artifact_name,
+        &kconfig::diff(&configs[0], &configs[1]),
+    );
+    Ok(())
+}
+
+fn diff_outputs(a: &str, b: &str, target: Option<String>) -> Result<()> {
+    let before = kconfig::load_output(Path::new(a))?;
+    let after = kconfig::load_output(Path::new(b))?;
+    let targets: Vec<&String> = before
+        .keys()
+        .filter(|name| after.contains_key(*name))
+        .filter(|name| {
+            target
+                .as_ref()
+                .is_none_or(|glob| matches_any_glob(std::slice::from_ref(glob), name))
+        })
+        .collect();
+    if targets.is_empty() {
+        anyhow::bail!("No target has a recorded Kconfig in both {} and {}", a, b);
+    }
+
+    for name in targets {
+        output::header(name);
+        print_differences(
+            a,
+            b,
+            &kconfig::diff(
+                &kconfig::parse(&before[name]),
+                &kconfig::parse(&after[name]),
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Print differences as a table with a column per side
+fn print_differences(a: &str, b: &str, differences: &[Difference]) {
+    if differences.is_empty() {
+        output::info("No differences");
+        return;
+    }
+
+    let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    let name_width = differences.iter().map(|d| d.name.len()).max().unwrap_or(0);
+    let value_width = differences
+        .iter()
+        .map(|d| value(&d.before).len())
+        .chain([a.len()])
+        .max()
+        .unwrap_or(0);
+
+    println!();
+    println!(
+        "{}",
+        style(format!(
+            "{:name_width$}  {:value_width$}  {}",
+            "",
+            a,
+            b,
+            name_width = name_width,
+            value_width = value_width
+        ))
+        .dim()
+    );
+    for difference in differences {
+        println!(
+            "{:name_width$}  {:value_width$}  {}",
+            difference.name,
+            value(&difference.before),
+            value(&difference.after),
+            name_width = name_width,
+            value_width = value_width
+        );
+    }
+}
@@ -1,26 +1,40 @@
 use anyhow::Result;
 
-use crate::config::build_yaml::BuildConfig;
+use crate::build::filter::{GroupFilter, TagExpr};
+use crate::config::build_config::BuildConfig;
+use crate::config::lfz_toml::LfzConfig;
 use crate::config::project::Project;
 use crate::output;
 
 /// Run the list command - show available build targets and groups
-pub fn run(group: Option<String>) -> Result<()> {
+pub fn run(group: Option<String>, tags: Option<String>) -> Result<()> {
     let project = Project::detect()?;
-    let build_config = BuildConfig::load(&project.build_yaml)?;
+    let build_config = BuildConfig::load(&project.build_file)?;
     let targets = build_config.expand_targets()?;
     let groups = build_config.available_groups();
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let group_filter = group
+        .as_deref()
+        .map(|g| GroupFilter::parse_with_aliases(g, &lfz_config.groups))
+        .transpose()?;
+    let tag_expr = tags.as_deref().map(TagExpr::parse).transpose()?;
 
     // Filter by group if specified
-    let filtered_targets: Vec<_> = if let Some(ref g) = group {
+    let filtered_targets: Vec<_> = if let Some(ref f) = group_filter {
         targets
             .into_iter()
-            .filter(|t| t.group.as_deref() == Some(g.as_str()))
+            .filter(|t| f.matches(t.group.as_deref()))
             .collect()
     } else {
         targets
     };
 
+    // Filter by tag expression if specified
+    let filtered_targets: Vec<_> = filtered_targets
+        .into_iter()
+        .filter(|t| tag_expr.as_ref().is_none_or(|e| e.matches_target(t)))
+        .collect();
+
     // Show groups if any exist
     if !groups.is_empty() {
         output::header("Groups");
@@ -29,8 +43,27 @@ pub fn run(group: Option<String>) -> Result<()> {
         }
     }
 
+    // Show group aliases from lfz.toml
+    if !lfz_config.groups.is_empty() {
+        output::header("Group aliases");
+        for (alias, members) in &lfz_config.groups {
+            output::list_item(&format!("{} = {}", alias, members.join(", ")));
+        }
+    }
+
+    // Show tags if any exist
+    let all_tags = build_config.available_tags();
+    if !all_tags.is_empty() {
+        output::header("Tags");
+        for t in &all_tags {
+            output::list_item(t);
+        }
+    }
+
     // Show targets
-    let header = if let Some(ref g) = group {
+    let header = if let Some(ref t) = tags {
+        format!("Targets matching tags '{}'", t)
+    } else if let Some(ref g) = group {
         format!("Targets in group '{}'", g)
     } else {
         format!("Targets ({})", filtered_targets.len())
@@ -38,13 +71,15 @@ pub fn run(group: Option<String>) -> Result<()> {
     output::header(&header);
 
     if filtered_targets.is_empty() {
-        if let Some(g) = group {
+        if let Some(t) = tags {
+            output::error(&format!("No targets match tags '{}'", t));
+        } else if let Some(g) = group {
             output::error(&format!("No targets found in group '{}'", g));
             if !groups.is_empty() {
                 output::info(&format!("Available groups: {}", groups.join(", ")));
             }
         } else {
-            output::error("No targets found in build.yaml");
+            output::error("No targets found in build config");
         }
         return Ok(());
     }
@@ -56,6 +91,12 @@ pub fn run(group: Option<String>) -> Result<()> {
             .map(|g| format!(" [{}]", g))
             .unwrap_or_default();
 
+        let tags_suffix = if target.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" #{}", target.tags.join(" #"))
+        };
+
         let details = format!(
             "board: {}{}",
             target.board,
@@ -67,10 +108,11 @@ pub fn run(group: Option<String>) -> Result<()> {
         );
 
         println!(
-            "  {} {}{}",
+            "  {} {}{}{}",
             console::style(&target.artifact_name).cyan(),
             console::style(details).dim(),
-            console::style(group_suffix).yellow()
+            console::style(group_suffix).yellow(),
+            console::style(tags_suffix).green()
         );
     }
 
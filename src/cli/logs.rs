@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::build::last_run::LastRun;
+use crate::build::logs;
+use crate::config::project::Project;
+use crate::output;
+use crate::paths;
+use crate::workspace::WorkspaceManager;
+
+/// How often `--follow` checks the log for new output
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Show the most recent build log of a target (or of the last run's first
+/// failure, or of any target)
+pub fn run(target: Option<String>, follow: bool, lines: Option<usize>, list: bool) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace = WorkspaceManager::new()?.workspace_path(&project)?;
+    let dir = logs::log_dir(&workspace)?;
+
+    if list {
+        let logs = logs::list(&dir, None);
+        if logs.is_empty() {
+            output::info("No build logs for this project yet.");
+        }
+        for log in logs.iter().rev() {
+            output::list_item(&paths::anonymize_path(log));
+        }
+        return Ok(());
+    }
+
+    // Without a target, show the first failure of the last run
+    let target = target.or_else(|| {
+        LastRun::load(&workspace)
+            .ok()
+            .flatten()
+            .and_then(|run| run.failed.into_iter().next())
+    });
+    let log = latest(&dir, target.as_deref())?;
+    output::status("Log", &paths::anonymize_path(&log));
+    println!();
+
+    let contents =
+        fs::read_to_string(&log).with_context(|| format!("Failed to read {}", log.display()))?;
+    print!("{}", tail(&contents, lines));
+
+    if follow {
+        follow_file(&log, contents.len() as u64)?;
+    }
+
+    Ok(())
+}
+
+/// The most recent log of the target, which may be given by part of its name
+fn latest(dir: &Path, target: Option<&str>) -> Result<PathBuf> {
+    let Some(target) = target else {
+        return logs::list(dir, None)
+            .pop()
+            .context("No build logs for this project yet. Run 'lfz build' first.");
+    };
+
+    if let Some(log) = logs::list(dir, Some(target)).pop() {
+        return Ok(log);
+    }
+
+    let matches: Vec<PathBuf> = logs::list(dir, None)
+        .into_iter()
+        .filter(|log| target_name(log).contains(target))
+        .collect();
+    let targets: BTreeSet<String> = matches.iter().map(|log| target_name(log)).collect();
+    match targets.len() {
+        0 => anyhow::bail!(
+            "No build logs for '{}'. Run 'lfz logs --list' to see available logs.",
+            target
+        ),
+        1 => Ok(matches.into_iter().last().expect("matches is not empty")),
+        _ => anyhow::bail!(
+            "'{}' matches several targets: {}",
+            target,
+            targets.into_iter().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+fn target_name(log: &Path) -> String {
+    let name = log.file_name().unwrap_or_default().to_string_lossy();
+    logs::target_of(&name).unwrap_or_default().to_string()
+}
+
+/// The last `lines` lines of a log, or all of it
+fn tail(contents: &str, lines: Option<usize>) -> &str {
+    let Some(lines) = lines else {
+        return contents;
+    };
+    let start = contents
+        .trim_end_matches('\n')
+        .rmatch_indices('\n')
+        .nth(lines.saturating_sub(1))
+        .filter(|_| lines > 0)
+        .map(|(i, _)| i + 1);
+    match start {
+        Some(start) => &contents[start..],
+        None if lines == 0 => "",
+        None => contents,
+    }
+}
+
+/// Print output appended to a log until interrupted
+fn follow_file(log: &Path, mut position: u64) -> Result<()> {
+    let mut file = File::open(log).with_context(|| format!("Failed to open {}", log.display()))?;
+    let mut stdout = std::io::stdout();
+    loop {
+        let len = file.metadata()?.len();
+        if len > position {
+            file.seek(SeekFrom::Start(position))?;
+            let mut new = Vec::new();
+            Read::by_ref(&mut file)
+                .take(len - position)
+                .read_to_end(&mut new)?;
+            stdout.write_all(&new)?;
+            stdout.flush()?;
+            position = len;
+        }
+        thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tail() {
+        let contents = "a\nb\nc\n";
+        assert_eq!(tail(contents, None), contents);
+        assert_eq!(tail(contents, Some(2)), "b\nc\n");
+        assert_eq!(tail(contents, Some(5)), contents);
+        assert_eq!(tail(contents, Some(0)), "");
+    }
+
+    #[test]
+    fn test_latest_by_partial_name() {
+        let dir = tempdir().unwrap();
+        for name in [
+            "corne_left-nice_nano_v2-zmk-20250101T120000Z.log",
+            "corne_left-nice_nano_v2-zmk-20250102T120000Z.log",
+            "corne_right-nice_nano_v2-zmk-20250101T120000Z.log",
+        ] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        let log = latest(dir.path(), Some("corne_left")).unwrap();
+        assert!(log.ends_with("corne_left-nice_nano_v2-zmk-20250102T120000Z.log"));
+        let log = latest(dir.path(), Some("corne_right-nice_nano_v2-zmk")).unwrap();
+        assert!(log.ends_with("corne_right-nice_nano_v2-zmk-20250101T120000Z.log"));
+        assert!(latest(dir.path(), Some("corne")).is_err());
+        assert!(latest(dir.path(), Some("sofle")).is_err());
+    }
+}
@@ -1,6 +1,29 @@
+pub mod bisect;
+pub mod boards;
 pub mod build;
+pub mod bump;
+pub mod cache;
+pub mod ci;
 pub mod clean;
+pub mod compare;
+pub mod devcontainer;
+pub mod dts;
+pub mod gc;
+pub mod history;
+pub mod image;
+pub mod kconfig;
 pub mod list;
+pub mod logs;
+pub mod outdated;
+pub mod prefetch;
 pub mod purge;
+pub mod release;
+pub mod report;
+pub mod shields;
 pub mod size;
+pub mod symbols;
+pub mod unshallow;
 pub mod update;
+pub mod validate;
+pub mod workspace_archive;
+pub mod workspaces;
@@ -0,0 +1,76 @@
+use anyhow::Result;
+
+use crate::config::project::Project;
+use crate::config::upstream::{Freshness, RemoteRefs};
+use crate::config::west_yml::WestManifest;
+use crate::output;
+use crate::workspace::{WestLock, WorkspaceManager};
+
+/// Run the outdated command - compare west.yml revisions with upstream
+pub fn run() -> Result<()> {
+    let project = Project::detect()?;
+    let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+
+    // Branch-tracking projects are compared with what the workspace checked out
+    let checkouts = match WorkspaceManager::new()?.find_workspace(&project)? {
+        Some(workspace) => WestLock::capture(&workspace, &manifest),
+        None => WestLock::default(),
+    };
+
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .filter(|p| !manifest.is_local(p))
+        .filter_map(|p| Some((p, manifest.fetch_url(p)?)))
+        .collect();
+
+    let spinner = output::spinner(&format!("Checking {} project(s)...", projects.len()));
+    let urls: Vec<&str> = projects.iter().map(|(_, url)| url.as_str()).collect();
+    let refs = RemoteRefs::fetch_all(&urls);
+    spinner.finish_and_clear();
+
+    output::header("West projects");
+    let width = projects
+        .iter()
+        .map(|(p, _)| p.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut behind = 0;
+    for ((project, _), refs) in projects.iter().zip(refs) {
+        let revision = manifest.revision(project);
+        let checkout = checkouts
+            .projects
+            .get(&project.name)
+            .map(|p| p.revision.as_str());
+
+        let status = match refs {
+            Ok(refs) => match refs.freshness(revision, checkout) {
+                Freshness::UpToDate => console::style("up to date".to_string()).green(),
+                Freshness::Behind(latest) => {
+                    behind += 1;
+                    console::style(format!("behind, latest {}", latest)).yellow()
+                }
+                Freshness::Tracking(branch) => console::style(format!("tracks {}", branch)).dim(),
+                Freshness::Unknown => console::style("not found upstream".to_string()).red(),
+            },
+            Err(e) => console::style(format!("{:#}", e)).red(),
+        };
+
+        println!(
+            "  {:width$}  {}  {}",
+            console::style(&project.name).cyan(),
+            console::style(revision).dim(),
+            status,
+            width = width
+        );
+    }
+
+    println!();
+    if behind == 0 {
+        output::success("All projects are up to date");
+    } else {
+        output::warning(&format!("{} project(s) are behind upstream", behind));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+
+use crate::config::project::Project;
+use crate::config::west_yml;
+use crate::container::{project_image, shares, Runtime, DEFAULT_IMAGE};
+use crate::output;
+use crate::paths;
+use crate::workspace::WorkspaceManager;
+
+/// Pull the build image and initialize or update the workspace, so that a
+/// later build only compiles (and can run `--offline`)
+pub fn run(locked: bool, wait: bool) -> Result<()> {
+    let project = Project::detect()?;
+    let project_display = west_yml::format_project_display(&project.config_dir)
+        .unwrap_or_else(|_| paths::anonymize_path(&project.root));
+    output::status("Project", &project_display);
+
+    let runtime = Runtime::detect()?;
+    output::status("Runtime", runtime.name());
+    runtime.ensure_running()?;
+    shares::check(&runtime, &project.root)?;
+
+    // Pull even if the image exists, to get the latest of its tag
+    runtime.pull_image(DEFAULT_IMAGE)?;
+    // Projects with an lfz.Dockerfile build in their own image
+    project_image::build_image(&project, &runtime)?;
+
+    let workspace_manager = WorkspaceManager::new()?.locked(locked).wait(wait);
+    let _guard = workspace_manager.lock(&project)?;
+    let workspace = workspace_manager.get_or_create(&project)?;
+    output::status("Workspace", &paths::anonymize_path(&workspace));
+
+    output::success("Image and workspace are ready; builds need no network ('lfz --offline')");
+    Ok(())
+}
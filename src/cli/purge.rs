@@ -1,10 +1,54 @@
 use anyhow::Result;
+use std::path::PathBuf;
 
-use crate::cli::clean::remove_dir_all;
+use super::clean::{remove_dir_all, remove_workspaces};
+use super::image::build_images;
+use super::size::format_size;
+use crate::container::project_image::PROJECT_IMAGES;
+use crate::container::{Image, Runtime, DEFAULT_IMAGE};
 use crate::output;
-use crate::paths;
+use crate::paths::{self, dir_size};
+use crate::workspace::{eviction, guard};
 
-pub fn run() -> Result<()> {
+/// What `lfz purge` removes; nothing selected means every cache
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Selection {
+    pub ccache: bool,
+    pub workspaces: bool,
+    pub logs: bool,
+    pub images: bool,
+}
+
+impl Selection {
+    fn is_empty(&self) -> bool {
+        !(self.ccache || self.workspaces || self.logs || self.images)
+    }
+}
+
+pub fn run(selection: Selection) -> Result<()> {
+    if selection.is_empty() {
+        return purge_all();
+    }
+
+    if selection.workspaces {
+        purge_workspaces()?;
+    }
+    if selection.ccache {
+        purge_dir(
+            "Compiler caches",
+            &[paths::ccache_dir()?, paths::sccache_dir()?],
+        )?;
+    }
+    if selection.logs {
+        purge_dir("Build logs", &[paths::logs_dir()?])?;
+    }
+    if selection.images {
+        purge_images()?;
+    }
+    Ok(())
+}
+
+fn purge_all() -> Result<()> {
     let cache_dir = paths::cache_dir()?;
 
     if cache_dir.exists() {
@@ -17,3 +61,82 @@ pub fn run() -> Result<()> {
 
     Ok(())
 }
+
+/// Remove every workspace no lfz run is using
+pub(super) fn purge_workspaces() -> Result<()> {
+    let workspaces = eviction::scan(&paths::workspaces_dir()?)?;
+    let (busy, idle): (Vec<_>, Vec<_>) = workspaces.iter().partition(|w| guard::in_use(&w.path));
+    if idle.is_empty() && busy.is_empty() {
+        output::info("No cached workspaces found.");
+        return Ok(());
+    }
+    output::header("Workspaces");
+    let freed = remove_workspaces(&idle, false)?;
+    output::success(&format!(
+        "Removed {} workspace(s), freeing {}",
+        idle.len(),
+        format_size(freed)
+    ));
+    for workspace in busy {
+        output::warning(&format!(
+            "Kept {}: another lfz build is running in it",
+            paths::anonymize_path(&workspace.path)
+        ));
+    }
+    Ok(())
+}
+
+fn purge_dir(what: &str, dirs: &[PathBuf]) -> Result<()> {
+    let size: u64 = dirs.iter().map(|dir| dir_size(dir)).sum();
+    if !dirs.iter().any(|dir| dir.exists()) {
+        output::info(&format!("{}: nothing to remove", what));
+        return Ok(());
+    }
+    for dir in dirs {
+        remove_dir_all(dir)?;
+    }
+    output::success(&format!("{} removed, freeing {}", what, format_size(size)));
+    Ok(())
+}
+
+/// Remove the build images lfz uses: the default image, the project
+/// images it built, and the ones workspaces were last built with. Images
+/// the user pulled themselves stay, as do images a container still uses.
+fn purge_images() -> Result<()> {
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let project_prefix = PROJECT_IMAGES.trim_end_matches('*');
+    let images: Vec<Image> = build_images(&runtime)?
+        .into_iter()
+        .filter(|(image, users)| {
+            image.matches(DEFAULT_IMAGE)
+                || image.tags.iter().any(|tag| tag.starts_with(project_prefix))
+                || !users.is_empty()
+        })
+        .map(|(image, _)| image)
+        .collect();
+    if images.is_empty() {
+        output::info("Images: no lfz build images found");
+        return Ok(());
+    }
+
+    output::header("Images");
+    let mut freed = 0;
+    let mut removed = 0;
+    for image in &images {
+        match runtime.untag_image(image) {
+            Ok(()) => {
+                output::list_item(&format!("{} ({})", image.name(), format_size(image.size)));
+                freed += image.size;
+                removed += 1;
+            }
+            Err(e) => output::warning(&e.to_string()),
+        }
+    }
+    output::success(&format!(
+        "Removed {} image(s), freeing {}; the next build pulls them again",
+        removed,
+        format_size(freed)
+    ));
+    Ok(())
+}
@@ -0,0 +1,502 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::project::Project;
+use crate::config::west_yml::WestManifest;
+use crate::output;
+use crate::paths;
+use crate::workspace::{WestLock, WorkspaceManager};
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// Releases per page when listing them (the API maximum)
+const RELEASES_PER_PAGE: usize = 100;
+
+/// Files written next to firmware by `--sbom` and `--stamp`, released alongside it
+const SIDECAR_SUFFIXES: &[&str] = &[".build-info.json", ".cdx.json", ".spdx.json"];
+
+/// Run the release command - publish built firmware as a GitHub release
+pub fn run(
+    tag: String,
+    output_dir: String,
+    repo: Option<String>,
+    notes: Option<String>,
+    draft: bool,
+    prerelease: bool,
+) -> Result<()> {
+    let project = Project::detect()?;
+    let token = std::env::var("GITHUB_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .context("GITHUB_TOKEN is not set. Create a token with 'contents: write' access to the repository.")?;
+
+    let repo = match repo {
+        Some(repo) => repo,
+        None => {
+            let url = git(&project.root, &["remote", "get-url", "origin"])
+                .context("No 'origin' remote found; pass --repo owner/name")?;
+            github_repo(&url)
+                .with_context(|| format!("'{}' is not a GitHub repository; pass --repo", url))?
+        }
+    };
+
+    let output_dir = PathBuf::from(output_dir);
+    let files = release_files(&output_dir)?;
+    if !files.iter().any(|f| has_extension(f, "uf2")) {
+        anyhow::bail!(
+            "No firmware found in {}. Run 'lfz' to build first.",
+            output_dir.display()
+        );
+    }
+
+    output::status("Repository", &repo);
+    output::status("Tag", &tag);
+
+    // Checksums and manifest describe the firmware, not the sidecar files
+    let mut firmware = Vec::new();
+    for path in files.iter().filter(|f| has_extension(f, "uf2")) {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        firmware.push((file_name(path), contents));
+    }
+    let commit = git(&project.root, &["rev-parse", "HEAD"]);
+    let sums = checksums(&firmware);
+    let manifest = release_manifest(
+        &tag,
+        commit.as_deref(),
+        &firmware,
+        &module_revisions(&project),
+    );
+
+    let github = GitHub::new(&repo, token);
+    let release = match github.release_by_tag(&tag)? {
+        Some(release) => {
+            output::info("Uploading to existing release");
+            release
+        }
+        None => {
+            output::info("Creating release");
+            github.create_release(&tag, commit.as_deref(), notes.as_deref(), draft, prerelease)?
+        }
+    };
+
+    let mut assets: Vec<(String, Vec<u8>)> = firmware;
+    for path in files.iter().filter(|f| !has_extension(f, "uf2")) {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        assets.push((file_name(path), contents));
+    }
+    assets.push(("SHA256SUMS".to_string(), sums.into_bytes()));
+    assets.push((
+        "manifest.json".to_string(),
+        serde_json::to_vec_pretty(&manifest).context("Failed to serialize manifest")?,
+    ));
+
+    for (name, contents) in &assets {
+        let spinner = output::spinner(&format!("Uploading {}...", name));
+        let result = github.upload_asset(&release, name, contents);
+        spinner.finish_and_clear();
+        result.with_context(|| format!("Failed to upload {}", name))?;
+        output::list_item(name);
+    }
+
+    println!();
+    output::success(&format!("Released {} asset(s)", assets.len()));
+    if let Some(url) = release["html_url"].as_str() {
+        output::info(url);
+    }
+
+    Ok(())
+}
+
+/// Firmware and sidecar files in the output directory (recursively), sorted
+fn release_files(output_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !output_dir.is_dir() {
+        anyhow::bail!(
+            "Output directory {} not found. Run 'lfz' to build first.",
+            paths::anonymize_path(output_dir)
+        );
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![output_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .flatten()
+        {
+            let path = entry.path();
+            let name = file_name(&path);
+            if path.is_dir() {
+                dirs.push(path);
+            } else if has_extension(&path, "uf2")
+                || SIDECAR_SUFFIXES.iter().any(|s| name.ends_with(s))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Module commits the firmware was built from: the committed west.lock,
+/// else the checkouts in the cached workspace
+fn module_revisions(project: &Project) -> WestLock {
+    if let Ok(Some(lock)) = WestLock::load(&project.config_dir.join("west.lock")) {
+        return lock;
+    }
+    let workspace = WorkspaceManager::new()
+        .and_then(|manager| manager.find_workspace(project))
+        .ok()
+        .flatten();
+    match (
+        workspace,
+        WestManifest::load(&project.config_dir.join("west.yml")),
+    ) {
+        (Some(workspace), Ok(manifest)) => WestLock::capture(&workspace, &manifest),
+        _ => WestLock::default(),
+    }
+}
+
+/// `SHA256SUMS` contents in the format `sha256sum -c` reads
+fn checksums(firmware: &[(String, Vec<u8>)]) -> String {
+    firmware
+        .iter()
+        .map(|(name, contents)| format!("{:x}  {}\n", Sha256::digest(contents), name))
+        .collect()
+}
+
+/// `manifest.json`: what the release contains and what it was built from
+fn release_manifest(
+    tag: &str,
+    commit: Option<&str>,
+    firmware: &[(String, Vec<u8>)],
+    modules: &WestLock,
+) -> Value {
+    let artifacts: Vec<Value> = firmware
+        .iter()
+        .map(|(name, contents)| {
+            json!({
+                "name": name,
+                "size": contents.len(),
+                "sha256": format!("{:x}", Sha256::digest(contents)),
+            })
+        })
+        .collect();
+    let modules: serde_json::Map<String, Value> = modules
+        .projects
+        .iter()
+        .map(|(name, locked)| (name.clone(), json!(locked.revision)))
+        .collect();
+
+    json!({
+        "tag": tag,
+        "config_commit": commit,
+        "lfz_version": env!("CARGO_PKG_VERSION"),
+        "artifacts": artifacts,
+        "modules": modules,
+    })
+}
+
+/// `owner/name` of a GitHub remote URL (HTTPS or SSH)
+fn github_repo(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("http://github.com/"))
+        .or_else(|| url.strip_prefix("git@github.com:"))
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    let (owner, name) = path.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+    Some(format!("{}/{}", owner, name))
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|e| e == extension)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Minimal client for the GitHub releases API
+struct GitHub {
+    agent: ureq::Agent,
+    repo: String,
+    token: String,
+}
+
+impl GitHub {
+    fn new(repo: &str, token: String) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Self {
+            agent,
+            repo: repo.to_string(),
+            token,
+        }
+    }
+
+    /// The release for a tag, if there is one. Releases are listed rather
+    /// than looked up by tag, which doesn't find drafts.
+    fn release_by_tag(&self, tag: &str) -> Result<Option<Value>> {
+        for page in 1.. {
+            let url = format!(
+                "{}/repos/{}/releases?per_page={}&page={}",
+                GITHUB_API, self.repo, RELEASES_PER_PAGE, page
+            );
+            let response = self.authorize(self.agent.get(&url)).call();
+            let (status, releases) = read(response)?;
+            ensure_success(status, &releases)?;
+            if let Some(release) = find_release(&releases, tag) {
+                return Ok(Some(release));
+            }
+            if releases.as_array().map_or(0, Vec::len) < RELEASES_PER_PAGE {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    fn create_release(
+        &self,
+        tag: &str,
+        commit: Option<&str>,
+        notes: Option<&str>,
+        draft: bool,
+        prerelease: bool,
+    ) -> Result<Value> {
+        let mut body = json!({
+            "tag_name": tag,
+            "name": tag,
+            "draft": draft,
+            "prerelease": prerelease,
+        });
+        // A new tag points at the commit that was built (it must be pushed)
+        if let Some(commit) = commit {
+            body["target_commitish"] = json!(commit);
+        }
+        match notes {
+            Some(notes) => body["body"] = json!(notes),
+            None => body["generate_release_notes"] = json!(true),
+        }
+
+        let url = format!("{}/repos/{}/releases", GITHUB_API, self.repo);
+        let response = self
+            .authorize(self.agent.post(&url))
+            .header("Content-Type", "application/json")
+            .send(body.to_string());
+        let (status, release) = read(response)?;
+        ensure_success(status, &release)?;
+        Ok(release)
+    }
+
+    /// Upload an asset, replacing one of the same name
+    fn upload_asset(&self, release: &Value, name: &str, contents: &[u8]) -> Result<()> {
+        let existing = release["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|asset| asset["name"] == name)
+            .filter_map(|asset| asset["id"].as_u64());
+        for id in existing {
+            let url = format!("{}/repos/{}/releases/assets/{}", GITHUB_API, self.repo, id);
+            let (status, body) = read(self.authorize(self.agent.delete(&url)).call())?;
+            ensure_success(status, &body)?;
+        }
+
+        let upload_url = release["upload_url"]
+            .as_str()
+            .context("GitHub returned a release without an upload URL")?;
+        let url = asset_upload_url(upload_url, name);
+        let content_type = if name.ends_with(".json") {
+            "application/json"
+        } else if name.ends_with(".uf2") {
+            "application/octet-stream"
+        } else {
+            "text/plain"
+        };
+        let response = self
+            .authorize(self.agent.post(&url))
+            .header("Content-Type", content_type)
+            .send(contents);
+        let (status, body) = read(response)?;
+        ensure_success(status, &body)
+    }
+
+    fn authorize<B>(&self, request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+        request
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .header("User-Agent", format!("lfz/{}", env!("CARGO_PKG_VERSION")))
+    }
+}
+
+/// Status and JSON body of an API response
+fn read(response: Result<ureq::http::Response<ureq::Body>, ureq::Error>) -> Result<(u16, Value)> {
+    let mut response = response.context("Failed to reach GitHub")?;
+    let status = response.status().as_u16();
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .context("Failed to read GitHub response")?;
+    Ok((status, serde_json::from_str(&body).unwrap_or(Value::Null)))
+}
+
+fn ensure_success(status: u16, body: &Value) -> Result<()> {
+    if (200..300).contains(&status) {
+        return Ok(());
+    }
+    let message = body["message"].as_str().unwrap_or("request failed");
+    let hint = match status {
+        401 => " (check GITHUB_TOKEN)",
+        403 | 404 => " (does GITHUB_TOKEN have 'contents: write' access to the repository?)",
+        _ => "",
+    };
+    let details: Vec<&str> = body["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e["message"].as_str().or(e["code"].as_str()))
+        .collect();
+    if details.is_empty() {
+        anyhow::bail!("GitHub API error {}: {}{}", status, message, hint)
+    } else {
+        anyhow::bail!(
+            "GitHub API error {}: {} ({}){}",
+            status,
+            message,
+            details.join(", "),
+            hint
+        )
+    }
+}
+
+/// The release with a tag in a page of releases
+fn find_release(releases: &Value, tag: &str) -> Option<Value> {
+    releases
+        .as_array()?
+        .iter()
+        .find(|release| release["tag_name"].as_str() == Some(tag))
+        .cloned()
+}
+
+/// Expand the `upload_url` template GitHub returns (`.../assets{?name,label}`)
+fn asset_upload_url(template: &str, name: &str) -> String {
+    let base = template.split('{').next().unwrap_or(template);
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("{}?name={}", base, encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_github_repo() {
+        for url in [
+            "https://github.com/user/zmk-config.git",
+            "https://github.com/user/zmk-config",
+            "git@github.com:user/zmk-config.git",
+            "ssh://git@github.com/user/zmk-config",
+        ] {
+            assert_eq!(
+                github_repo(url).as_deref(),
+                Some("user/zmk-config"),
+                "{}",
+                url
+            );
+        }
+        assert_eq!(github_repo("https://gitlab.com/user/zmk-config"), None);
+        assert_eq!(github_repo("https://github.com/user"), None);
+    }
+
+    #[test]
+    fn test_checksums() {
+        let firmware = vec![("a.uf2".to_string(), b"firmware".to_vec())];
+        let sums = checksums(&firmware);
+        assert!(sums.ends_with("  a.uf2\n"));
+        assert_eq!(sums.split("  ").next().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_find_release() {
+        let releases = json!([
+            {"tag_name": "v2", "draft": true, "id": 2},
+            {"tag_name": "v1", "draft": false, "id": 1}
+        ]);
+        assert_eq!(find_release(&releases, "v2").unwrap()["id"], 2);
+        assert_eq!(find_release(&releases, "v3"), None);
+        assert_eq!(
+            find_release(&json!({"message": "Bad credentials"}), "v1"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_asset_upload_url() {
+        assert_eq!(
+            asset_upload_url(
+                "https://uploads.github.com/repos/u/r/releases/1/assets{?name,label}",
+                "corne left.uf2"
+            ),
+            "https://uploads.github.com/repos/u/r/releases/1/assets?name=corne%20left.uf2"
+        );
+    }
+
+    #[test]
+    fn test_release_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        for file in [
+            "a.uf2",
+            "a.build-info.json",
+            "a.cdx.json",
+            "nested/b.uf2",
+            "notes.txt",
+        ] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let names: Vec<String> = release_files(dir.path())
+            .unwrap()
+            .iter()
+            .map(|p| file_name(p))
+            .collect();
+        assert_eq!(names, ["a.build-info.json", "a.cdx.json", "a.uf2", "b.uf2"]);
+    }
+}
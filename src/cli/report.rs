@@ -0,0 +1,209 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::path::PathBuf;
+
+use super::size::format_size;
+use crate::build::orchestrator::BuildOrchestrator;
+use crate::build::size_report::{self, ReportKind, SizeNode};
+use crate::build::target::BuildTarget;
+use crate::cache::{self, CompilerCache};
+use crate::config::build_config::BuildConfig;
+use crate::config::lfz_toml::LfzConfig;
+use crate::config::project::Project;
+use crate::config::west_yml::WestManifest;
+use crate::container::{project_image, Runtime};
+use crate::output;
+use crate::paths;
+use crate::workspace::guard::WorkspaceGuard;
+use crate::workspace::{BuildHashes, WorkspaceManager};
+
+/// Order of the entries under each path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportSort {
+    /// Largest first
+    Size,
+    /// Alphabetical
+    Name,
+}
+
+/// Show what a target's flash (or RAM) is used by, as a tree of paths
+pub fn run(target: Option<String>, kind: ReportKind, depth: usize, sort: ReportSort) -> Result<()> {
+    let (target, workspace, orchestrator, _guard) = prepare(target)?;
+
+    output::status("Running", kind.west_target());
+    orchestrator.run_west_target(&target, kind.west_target())?;
+
+    let root = size_report::load(&workspace, &target, kind)?;
+    println!();
+    for line in render(&root, depth, sort) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Set up for running build system steps on the single build.yaml target
+/// matching `target` (a name or glob): the target, the workspace and an
+/// orchestrator that reuses the target's last build, plus this run's hold
+/// on the workspace: keep it until the orchestrator is done
+pub fn prepare(
+    target: Option<String>,
+) -> Result<(BuildTarget, PathBuf, BuildOrchestrator, WorkspaceGuard)> {
+    let (mut targets, workspace, orchestrator, guard) = prepare_all(&[target])?;
+    Ok((targets.remove(0), workspace, orchestrator, guard))
+}
+
+/// Like [`prepare`], for one target per entry of `globs`
+pub fn prepare_all(
+    globs: &[Option<String>],
+) -> Result<(Vec<BuildTarget>, PathBuf, BuildOrchestrator, WorkspaceGuard)> {
+    let project = Project::detect()?;
+    let all_targets = BuildConfig::load(&project.build_file)?.expand_targets()?;
+    let targets = globs
+        .iter()
+        .map(|glob| BuildTarget::select(all_targets.clone(), glob.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+    for target in &targets {
+        output::status("Target", &target.artifact_name);
+    }
+
+    let runtime = Runtime::detect()?;
+    runtime.ensure_running()?;
+    let workspace_manager = WorkspaceManager::new()?;
+    let guard = workspace_manager.lock(&project)?;
+    let workspace = workspace_manager.get_or_create(&project)?;
+    output::status("Workspace", &paths::anonymize_path(&workspace));
+
+    let overrides = WestManifest::load(&project.config_dir.join("west.yml"))?
+        .local_projects(&project.config_dir)
+        .iter()
+        .map(|local| local.as_override())
+        .collect();
+    let hashes = BuildHashes::calculate(
+        &project.root,
+        &project.build_file,
+        &project.config_dir.join("west.yml"),
+    )?;
+    let lfz_config = LfzConfig::load(&project.root)?;
+    let image = project_image::build_image(&project, &runtime)?;
+    let compiler_cache = CompilerCache::from_config(&lfz_config.compiler_cache)?
+        .image(cache::image_key(&runtime, &image));
+    let orchestrator = BuildOrchestrator::new(
+        runtime,
+        workspace.clone(),
+        project,
+        PathBuf::new(),
+        0,     // verbosity
+        false, // pristine: reuse the target's last build
+        false, // offline
+        None,
+        overrides,
+        lfz_config.proxy,
+        hashes,
+    )
+    .compiler_cache(compiler_cache)
+    .image(image);
+    Ok((targets, workspace, orchestrator, guard))
+}
+
+/// The report as tree lines down to `depth` levels below the root, with
+/// sizes and shares of the total in aligned columns. Empty entries are left out.
+fn render(root: &SizeNode, depth: usize, sort: ReportSort) -> Vec<String> {
+    let mut rows = vec![(root.name.clone(), root.size)];
+    add_children(root, "", depth, sort, &mut rows);
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.into_iter()
+        .map(|(name, size)| {
+            let percent = if root.size == 0 {
+                0.0
+            } else {
+                size as f64 * 100.0 / root.size as f64
+            };
+            format!(
+                "{:width$}  {:>10}  {:>5.1}%",
+                name,
+                format_size(size),
+                percent,
+                width = width
+            )
+        })
+        .collect()
+}
+
+fn add_children(
+    node: &SizeNode,
+    indent: &str,
+    depth: usize,
+    sort: ReportSort,
+    rows: &mut Vec<(String, u64)>,
+) {
+    if depth == 0 {
+        return;
+    }
+    let mut children: Vec<&SizeNode> = node.children.iter().filter(|c| c.size > 0).collect();
+    match sort {
+        ReportSort::Size => children.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name))),
+        ReportSort::Name => children.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+
+    for (i, child) in children.iter().enumerate() {
+        let last = i == children.len() - 1;
+        let (branch, continuation) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        rows.push((format!("{}{}{}", indent, branch, child.name), child.size));
+        add_children(
+            child,
+            &format!("{}{}", indent, continuation),
+            depth - 1,
+            sort,
+            rows,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<SizeNode>) -> SizeNode {
+        SizeNode {
+            name: name.to_string(),
+            size,
+            children,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let root = node(
+            "Root",
+            2048,
+            vec![
+                node("app", 512, vec![node("main.c", 512, vec![])]),
+                node("zephyr", 1536, vec![node("kernel", 1536, vec![])]),
+                node("(hidden)", 0, vec![]),
+            ],
+        );
+
+        assert_eq!(
+            render(&root, 1, ReportSort::Size),
+            [
+                "Root           2.00 KB  100.0%",
+                "├── zephyr     1.50 KB   75.0%",
+                "└── app          512 B   25.0%",
+            ]
+        );
+        let lines = render(&root, 2, ReportSort::Name);
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("├── app"));
+        assert!(lines[2].starts_with("│   └── main.c"));
+        assert!(lines[4].starts_with("    └── kernel"));
+    }
+}
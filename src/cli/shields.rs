@@ -0,0 +1,51 @@
+use anyhow::Result;
+
+use crate::config::project::Project;
+use crate::output;
+use crate::workspace::{board_roots, discover_shields, WorkspaceManager};
+
+/// Run the shields command - list shields available in the cached workspace
+pub fn run(filter: Option<String>) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace_manager = WorkspaceManager::new()?;
+
+    let Some(workspace) = workspace_manager.find_workspace(&project)? else {
+        anyhow::bail!(
+            "No cached workspace found for this project. \
+             Run 'lfz build' or 'lfz update' first."
+        );
+    };
+
+    let roots = board_roots(&workspace, &project.extra_modules());
+    let shields: Vec<_> = discover_shields(&roots)?
+        .into_iter()
+        .filter(|s| {
+            filter
+                .as_ref()
+                .is_none_or(|f| s.name.to_lowercase().contains(&f.to_lowercase()))
+        })
+        .collect();
+
+    let header = match filter {
+        Some(ref f) => format!("Shields matching '{}' ({})", f, shields.len()),
+        None => format!("Shields ({})", shields.len()),
+    };
+    output::header(&header);
+
+    if shields.is_empty() {
+        output::error("No shields found");
+        return Ok(());
+    }
+
+    let width = shields.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for shield in &shields {
+        println!(
+            "  {} {} {}",
+            console::style(format!("{:<width$}", shield.name)).cyan(),
+            console::style(format!("({})", shield.family)).dim(),
+            console::style(format!("[{}]", shield.source)).yellow()
+        );
+    }
+
+    Ok(())
+}
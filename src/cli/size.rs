@@ -1,32 +1,15 @@
 use anyhow::Result;
-use std::fs;
-use std::path::Path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
+use crate::cache::ccache;
 use crate::output;
-use crate::paths;
-
-/// Calculate directory size recursively
-fn dir_size(path: &Path) -> u64 {
-    if !path.exists() {
-        return 0;
-    }
-
-    let mut size = 0;
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                size += dir_size(&path);
-            } else if let Ok(meta) = entry.metadata() {
-                size += meta.len();
-            }
-        }
-    }
-    size
-}
+use crate::paths::{self, dir_size};
+use crate::workspace::eviction;
+use crate::workspace::WorkspaceMetadata;
 
 /// Format bytes as human-readable string
-fn format_size(bytes: u64) -> String {
+pub fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -42,40 +25,174 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
-/// Count items in directory
-fn count_items(path: &Path) -> usize {
-    if !path.exists() {
-        return 0;
+/// An image digest with the hash cut short
+pub(super) fn short_image(image: &str) -> &str {
+    match image.find("sha256:") {
+        Some(start) => &image[..(start + "sha256:".len() + 12).min(image.len())],
+        None => image,
     }
-    fs::read_dir(path).map(|e| e.count()).unwrap_or(0)
 }
 
-pub fn run() -> Result<()> {
-    let cache_dir = paths::cache_dir()?;
-    let workspaces_dir = paths::workspaces_dir()?;
-    let ccache_dir = paths::ccache_dir()?;
+/// Disk used by lfz, as `lfz size --json` prints it
+#[derive(Debug, Serialize)]
+struct SizeReport {
+    cache_dir: PathBuf,
+    workspaces: Vec<WorkspaceSize>,
+    /// One cache per build image
+    ccache: Vec<CcacheSize>,
+    sccache: u64,
+    artifacts: u64,
+    logs: u64,
+    history: u64,
+    /// Everything in the cache directory
+    total: u64,
+    /// The firmware output directory, outside the cache
+    output: Option<OutputSize>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkspaceSize {
+    id: String,
+    /// `repo:branch`, if recorded
+    name: Option<String>,
+    path: PathBuf,
+    size: u64,
+    last_used: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CcacheSize {
+    /// Digest (or tag) of the image, none for the cache of lfz versions
+    /// before per-image caches
+    image: Option<String>,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputSize {
+    path: PathBuf,
+    size: u64,
+}
 
-    output::status("Cache", &paths::anonymize_path(&cache_dir));
+/// Show the disk space of lfz's caches (and of the firmware output
+/// directory `output`), biggest workspaces first
+pub fn run(output: String, json: bool) -> Result<()> {
+    let report = measure(Path::new(&output))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    output::status("Cache", &paths::anonymize_path(&report.cache_dir));
     println!();
 
-    // Workspaces
-    let workspaces_size = dir_size(&workspaces_dir);
-    let workspaces_count = count_items(&workspaces_dir);
+    let workspaces_size: u64 = report.workspaces.iter().map(|w| w.size).sum();
     println!(
         "  Workspaces:  {:>10}  ({} workspace{})",
         format_size(workspaces_size),
-        workspaces_count,
-        if workspaces_count == 1 { "" } else { "s" }
+        report.workspaces.len(),
+        if report.workspaces.len() == 1 {
+            ""
+        } else {
+            "s"
+        }
     );
+    for workspace in &report.workspaces {
+        println!(
+            "    {:>10}  {}  {}  (last used {})",
+            format_size(workspace.size),
+            workspace.id,
+            workspace.name.as_deref().unwrap_or("-"),
+            &workspace.last_used[..10]
+        );
+    }
 
-    // Ccache
-    let ccache_size = dir_size(&ccache_dir);
+    let ccache_size: u64 = report.ccache.iter().map(|c| c.size).sum();
     println!("  Ccache:      {:>10}", format_size(ccache_size));
-
-    // Total
-    let total_size = workspaces_size + ccache_size;
+    if report.ccache.iter().any(|c| c.image.is_some()) {
+        for cache in &report.ccache {
+            match &cache.image {
+                Some(image) => {
+                    println!(
+                        "    {:>10}  {}",
+                        format_size(cache.size),
+                        short_image(image)
+                    )
+                }
+                None => println!(
+                    "    {:>10}  (from lfz versions before per-image caches)",
+                    format_size(cache.size)
+                ),
+            }
+        }
+    }
+    if report.sccache > 0 {
+        println!("  Sccache:     {:>10}", format_size(report.sccache));
+    }
+    println!("  Artifacts:   {:>10}", format_size(report.artifacts));
+    println!("  Logs:        {:>10}", format_size(report.logs));
+    println!("  History:     {:>10}", format_size(report.history));
     println!("  ─────────────────────");
-    println!("  Total:       {:>10}", format_size(total_size));
+    println!("  Total:       {:>10}", format_size(report.total));
 
+    if let Some(output) = &report.output {
+        println!();
+        println!(
+            "  Output:      {:>10}  ({}, not part of the cache)",
+            format_size(output.size),
+            output.path.display()
+        );
+    }
     Ok(())
 }
+
+fn measure(output: &Path) -> Result<SizeReport> {
+    let mut workspaces: Vec<WorkspaceSize> = eviction::scan(&paths::workspaces_dir()?)?
+        .into_iter()
+        .map(|usage| WorkspaceSize {
+            id: usage
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            name: WorkspaceMetadata::load(&usage.path).map(|m| m.name()),
+            size: usage.size,
+            last_used: humantime::format_rfc3339_seconds(usage.last_used).to_string(),
+            path: usage.path,
+        })
+        .collect();
+    workspaces.sort_by_key(|w| std::cmp::Reverse(w.size));
+
+    let ccache_dir = paths::ccache_dir()?;
+    let mut ccache: Vec<CcacheSize> = ccache::image_caches(&ccache_dir)
+        .into_iter()
+        .map(|(dir, image)| CcacheSize {
+            image: Some(image),
+            size: dir_size(&dir),
+        })
+        .collect();
+    let older = dir_size(&ccache_dir).saturating_sub(ccache.iter().map(|c| c.size).sum());
+    if older > 0 {
+        ccache.push(CcacheSize {
+            image: None,
+            size: older,
+        });
+    }
+
+    let cache_dir = paths::cache_dir()?;
+    Ok(SizeReport {
+        total: dir_size(&cache_dir),
+        workspaces,
+        ccache,
+        sccache: dir_size(&paths::sccache_dir()?),
+        artifacts: dir_size(&paths::artifacts_dir()?),
+        logs: dir_size(&paths::logs_dir()?),
+        history: dir_size(&paths::history_dir()?),
+        output: output.is_dir().then(|| OutputSize {
+            path: output.to_path_buf(),
+            size: dir_size(output),
+        }),
+        cache_dir,
+    })
+}
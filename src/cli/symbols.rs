@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::fs;
+use std::path::PathBuf;
+
+use super::size::format_size;
+use crate::build::map_file::{self, MapFile};
+use crate::build::target::BuildTarget;
+use crate::config::build_config::BuildConfig;
+use crate::config::project::Project;
+use crate::output;
+use crate::paths;
+use crate::workspace::WorkspaceManager;
+
+/// List the largest symbols (or sections) in a target's flash or RAM, from
+/// the linker map of its last build
+pub fn run(
+    target: Option<String>,
+    map: Option<PathBuf>,
+    ram: bool,
+    sections: bool,
+    count: usize,
+) -> Result<()> {
+    let path = match map {
+        Some(path) => path,
+        None => find_map(target)?,
+    };
+    output::status("Map", &paths::anonymize_path(&path));
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let map = map_file::parse(&contents);
+
+    let region_name = if ram { "RAM" } else { "FLASH" };
+    let region = map
+        .regions
+        .iter()
+        .find(|r| r.name == region_name)
+        .with_context(|| format!("No {} region in {}", region_name, path.display()))?;
+
+    let rows = if sections {
+        section_rows(&map, region_name)
+    } else {
+        symbol_rows(&map, region_name)
+    };
+    let used: u64 = rows.iter().map(|(size, _, _)| size).sum();
+    output::header(&format!(
+        "Largest {} in {} ({} of {} used)",
+        if sections { "sections" } else { "symbols" },
+        region_name,
+        format_size(used),
+        format_size(region.length)
+    ));
+    for (size, name, detail) in rows.into_iter().take(count) {
+        println!(
+            "  {:>10}  {:>5.1}%  {} {}",
+            format_size(size),
+            size as f64 * 100.0 / used.max(1) as f64,
+            name,
+            style(detail).dim()
+        );
+    }
+    Ok(())
+}
+
+/// (size, name, detail) of the symbols in a region, largest first
+fn symbol_rows(map: &MapFile, region: &str) -> Vec<(u64, String, String)> {
+    let mut rows: Vec<_> = map
+        .symbols
+        .iter()
+        .filter(|s| map.region_of(s.address).is_some_and(|r| r.name == region))
+        .map(|s| (s.size, s.name.clone(), s.object.clone()))
+        .collect();
+    rows.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    rows
+}
+
+/// (size, name, detail) of the output sections in a region, largest first
+fn section_rows(map: &MapFile, region: &str) -> Vec<(u64, String, String)> {
+    let mut rows: Vec<_> = map
+        .sections
+        .iter()
+        .filter(|s| s.size > 0 && map.region_of(s.address).is_some_and(|r| r.name == region))
+        .map(|s| (s.size, s.name.clone(), format!("at {:#x}", s.address)))
+        .collect();
+    rows.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    rows
+}
+
+/// The map file of the build.yaml target matching `target` (a name or
+/// glob), or of the only target there is
+fn find_map(target: Option<String>) -> Result<PathBuf> {
+    let project = Project::detect()?;
+    let targets = BuildConfig::load(&project.build_file)?.expand_targets()?;
+    let target = BuildTarget::select(targets, target.as_deref())?;
+
+    let workspace = WorkspaceManager::new()?.workspace_path(&project)?;
+    map_file::find(&workspace, &target).with_context(|| {
+        format!(
+            "No linker map for {}. Run 'lfz --target {}' to build it first.",
+            target.artifact_name, target.artifact_name
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::map_file::{Region, Section, Symbol};
+
+    #[test]
+    fn test_rows() {
+        let symbol = |name: &str, address, size| Symbol {
+            name: name.to_string(),
+            section: "text".to_string(),
+            address,
+            size,
+            object: String::new(),
+        };
+        let map = MapFile {
+            regions: vec![
+                Region {
+                    name: "FLASH".to_string(),
+                    origin: 0,
+                    length: 0x1000,
+                },
+                Region {
+                    name: "RAM".to_string(),
+                    origin: 0x2000,
+                    length: 0x1000,
+                },
+            ],
+            sections: vec![Section {
+                name: "text".to_string(),
+                address: 0,
+                size: 0x30,
+            }],
+            symbols: vec![
+                symbol("small", 0, 0x10),
+                symbol("large", 0x10, 0x20),
+                symbol("buffer", 0x2000, 0x100),
+            ],
+        };
+
+        let names: Vec<_> = symbol_rows(&map, "FLASH")
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect();
+        assert_eq!(names, ["large", "small"]);
+        assert_eq!(symbol_rows(&map, "RAM")[0].1, "buffer");
+        assert_eq!(section_rows(&map, "FLASH")[0].0, 0x30);
+        assert!(section_rows(&map, "RAM").is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+use anyhow::Result;
+
+use crate::config::project::Project;
+use crate::container::Runtime;
+use crate::output;
+use crate::workspace::WorkspaceManager;
+
+/// Turn shallow clones in the project's workspace (`projects`, or all of
+/// them) into full ones
+pub fn run(projects: Vec<String>) -> Result<()> {
+    let project = Project::detect()?;
+    output::status("Project", &project.root.display().to_string());
+
+    let runtime = Runtime::detect()?;
+    output::status("Runtime", runtime.name());
+    runtime.ensure_running()?;
+
+    let workspace_manager = WorkspaceManager::new()?;
+    let _guard = workspace_manager.lock(&project)?;
+    workspace_manager.unshallow(&project, &runtime, &projects)
+}
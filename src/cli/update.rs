@@ -1,11 +1,13 @@
 use anyhow::Result;
 
 use crate::config::project::Project;
-use crate::container::Runtime;
+use crate::container::{shares, Runtime};
 use crate::output;
 use crate::workspace::WorkspaceManager;
 
-pub fn run() -> Result<()> {
+/// Reinitialize the project's workspace, or with `projects` fetch only
+/// those again
+pub fn run(projects: Vec<String>) -> Result<()> {
     // 1. Detect project structure
     let project = Project::detect()?;
     output::status("Project", &project.root.display().to_string());
@@ -14,12 +16,18 @@ pub fn run() -> Result<()> {
     let runtime = Runtime::detect()?;
     output::status("Runtime", runtime.name());
     runtime.ensure_running()?;
+    shares::check(&runtime, &project.root)?;
 
     // 3. Get workspace manager
     let workspace_manager = WorkspaceManager::new()?;
 
-    // 4. Force refresh the workspace
-    let workspace = workspace_manager.refresh(&project, &runtime)?;
+    // 4. Force refresh the workspace, or just some of its projects
+    let _guard = workspace_manager.lock(&project)?;
+    let workspace = if projects.is_empty() {
+        workspace_manager.refresh(&project, &runtime)?
+    } else {
+        workspace_manager.refresh_projects(&project, &runtime, &projects)?
+    };
     output::status("Workspace", &workspace.display().to_string());
 
     Ok(())
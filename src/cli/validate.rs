@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::fs;
+use std::path::Path;
+
+use crate::config::build_config::BuildConfigFormat;
+use crate::config::lfz_toml::{LfzConfig, LFZ_TOML};
+use crate::config::project::Project;
+use crate::config::validate::{validate_build_config, validate_west_yml, Diagnostic, Severity};
+use crate::output;
+
+/// Run the validate command - check build config, west.yml and lfz.toml
+pub fn run() -> Result<()> {
+    let project = Project::detect()?;
+    let mut errors = 0;
+    let mut warnings = 0;
+
+    let mut report = |path: &Path, diagnostics: Vec<Diagnostic>| {
+        let display = path.strip_prefix(&project.root).unwrap_or(path);
+        for diagnostic in &diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => {
+                    errors += 1;
+                    style("error:").red().bold()
+                }
+                Severity::Warning => {
+                    warnings += 1;
+                    style("warning:").yellow().bold()
+                }
+            };
+            let location = match diagnostic.location {
+                Some((line, column)) => format!("{}:{}:{}:", display.display(), line, column),
+                None => format!("{}:", display.display()),
+            };
+            eprintln!(
+                "{} {} {}",
+                style(location).bold(),
+                severity,
+                diagnostic.message
+            );
+        }
+    };
+
+    let content = read(&project.build_file)?;
+    let format = BuildConfigFormat::from_path(&project.build_file);
+    report(&project.build_file, validate_build_config(format, &content));
+
+    let west_yml = project.config_dir.join("west.yml");
+    report(&west_yml, validate_west_yml(&read(&west_yml)?));
+
+    if let Err(e) = LfzConfig::load(&project.root) {
+        report(
+            &project.root.join(LFZ_TOML),
+            vec![Diagnostic {
+                severity: Severity::Error,
+                location: None,
+                message: format!("{:#}", e),
+            }],
+        );
+    }
+
+    if errors > 0 {
+        anyhow::bail!(
+            "Validation failed with {} error(s) and {} warning(s)",
+            errors,
+            warnings
+        );
+    }
+
+    if warnings > 0 {
+        output::success(&format!("Configuration is valid ({} warning(s))", warnings));
+    } else {
+        output::success("Configuration is valid");
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
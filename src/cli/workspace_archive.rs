@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::clean::remove_dir_all;
+use super::size::format_size;
+use crate::config::project::Project;
+use crate::config::west_yml;
+use crate::container::{project_image, Runtime, DEFAULT_IMAGE};
+use crate::output;
+use crate::paths;
+use crate::workspace::archive::{self, ArchiveManifest, IMAGE_FILE, MANIFEST_FILE};
+use crate::workspace::guard::WorkspaceGuard;
+use crate::workspace::{WorkspaceManager, WorkspaceMetadata};
+
+/// Pack the project's workspace (and with `image` the build image) into a
+/// tarball
+pub fn export(output_path: Option<PathBuf>, image: bool) -> Result<()> {
+    let project = Project::detect()?;
+    let workspace_manager = WorkspaceManager::new()?;
+    let workspace = workspace_manager
+        .find_workspace(&project)?
+        .context("No workspace for this project yet. Run 'lfz prefetch' (or 'lfz build') first.")?;
+    // Nothing may change the workspace while it's packed
+    let _guard = workspace_manager.lock(&project)?;
+
+    let metadata = WorkspaceMetadata::load(&workspace);
+    let output_path = output_path.unwrap_or_else(|| {
+        let name = metadata
+            .as_ref()
+            .map(|m| m.name().replace([':', '/'], "-"))
+            .unwrap_or_else(|| "workspace".to_string());
+        PathBuf::from(format!("lfz-{}.tar.gz", name))
+    });
+
+    // The project's own image if it has an lfz.Dockerfile; west runs in
+    // the default one either way
+    let build_image = project_image::image_name(&project)?;
+    let runtime = Runtime::detect().ok();
+    let manifest = ArchiveManifest {
+        id: workspace
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        metadata,
+        image: build_image.clone(),
+        image_digest: runtime.and_then(|runtime| runtime.image_digest(&build_image)),
+        includes_image: image,
+    };
+
+    let staging = paths::cache_dir()?.join(format!("export-{}", std::process::id()));
+    fs::create_dir_all(&staging).context("Failed to create staging directory")?;
+    let result = (|| -> Result<()> {
+        fs::write(
+            staging.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        if image {
+            let runtime = runtime.context("Exporting the image needs Docker or Podman")?;
+            runtime.ensure_running()?;
+            runtime.ensure_image(DEFAULT_IMAGE)?;
+            project_image::build_image(&project, &runtime)?;
+            let mut images = vec![DEFAULT_IMAGE];
+            if build_image != DEFAULT_IMAGE {
+                images.push(&build_image);
+            }
+            let spinner = output::spinner(&format!("Saving image {}", images.join(", ")));
+            runtime.save_images(&images, &staging.join(IMAGE_FILE))?;
+            spinner.finish_and_clear();
+        }
+        let spinner = output::spinner(&format!(
+            "Packing workspace {}",
+            paths::anonymize_path(&workspace)
+        ));
+        archive::pack(&workspace, &staging, &output_path)?;
+        spinner.finish_and_clear();
+        Ok(())
+    })();
+    let _ = remove_dir_all(&staging);
+    result?;
+
+    let size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+    output::success(&format!(
+        "Exported to {} ({})",
+        output_path.display(),
+        format_size(size)
+    ));
+    if !image {
+        output::info(&format!(
+            "The image isn't included; machines without {} need --image",
+            build_image
+        ));
+    }
+    Ok(())
+}
+
+/// Restore a workspace exported with `export`, as the workspace of the
+/// project at hand if there is one. `force` replaces an existing workspace.
+pub fn import(archive_path: PathBuf, force: bool) -> Result<()> {
+    let staging = paths::cache_dir()?.join(format!("import-{}", std::process::id()));
+    fs::create_dir_all(&staging).context("Failed to create staging directory")?;
+    let result = import_from(&archive_path, &staging, force);
+    let _ = remove_dir_all(&staging);
+    result
+}
+
+fn import_from(archive_path: &Path, staging: &Path, force: bool) -> Result<()> {
+    let spinner = output::spinner(&format!("Extracting {}", archive_path.display()));
+    let manifest = archive::unpack(archive_path, staging)?;
+    spinner.finish_and_clear();
+    if let Some(metadata) = &manifest.metadata {
+        output::status("Project", &metadata.name());
+    }
+
+    // The project's own key may differ from the exporting machine's
+    // (another remote URL, say)
+    let destination = match Project::detect() {
+        Ok(project) => {
+            let (repo, _) = west_yml::get_git_info(&project.config_dir)?;
+            match &manifest.metadata {
+                Some(metadata) if metadata.repo != repo && !force => anyhow::bail!(
+                    "The archive holds the workspace of {}, not of this project ({}).\n\
+                     Pass --force to import it as this project's workspace anyway.",
+                    metadata.repo,
+                    repo
+                ),
+                _ => {}
+            }
+            WorkspaceManager::new()?.workspace_path(&project)?
+        }
+        Err(_) => paths::workspaces_dir()?.join(&manifest.id),
+    };
+    let _guard = WorkspaceGuard::acquire(&destination, false)?;
+    if destination.exists() {
+        if !force {
+            anyhow::bail!(
+                "A workspace already exists at {}. Pass --force to replace it.",
+                paths::anonymize_path(&destination)
+            );
+        }
+        remove_dir_all(&destination)?;
+    }
+    fs::rename(staging.join(&manifest.id), &destination).with_context(|| {
+        format!(
+            "Failed to move the workspace to {}",
+            paths::anonymize_path(&destination)
+        )
+    })?;
+    output::status("Workspace", &paths::anonymize_path(&destination));
+
+    let runtime = Runtime::detect().ok();
+    if manifest.includes_image {
+        let runtime = runtime.context("Importing the image needs Docker or Podman")?;
+        runtime.ensure_running()?;
+        let spinner = output::spinner(&format!("Loading image {}", manifest.image));
+        runtime.load_image(&staging.join(IMAGE_FILE))?;
+        spinner.finish_and_clear();
+        output::status("Image", &manifest.image);
+    } else if !runtime.is_some_and(|runtime| runtime.image_exists(&manifest.image).unwrap_or(false))
+    {
+        output::warning(&format!(
+            "{} isn't available here; pull it or export the workspace with --image",
+            manifest.image
+        ));
+    }
+
+    output::success("Workspace imported");
+    Ok(())
+}
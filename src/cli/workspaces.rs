@@ -0,0 +1,120 @@
+use anyhow::Result;
+use console::style;
+use std::time::SystemTime;
+
+use super::size::format_size;
+use crate::config::project::Project;
+use crate::output;
+use crate::paths;
+use crate::workspace::eviction;
+use crate::workspace::{WorkspaceManager, WorkspaceMetadata};
+
+/// List the cached workspaces, most recently used first, with the project
+/// and branch each belongs to
+pub fn run() -> Result<()> {
+    let mut workspaces = eviction::scan(&paths::workspaces_dir()?)?;
+    if workspaces.is_empty() {
+        output::info("No cached workspaces found.");
+        return Ok(());
+    }
+    workspaces.sort_by_key(|w| std::cmp::Reverse(w.last_used));
+
+    // The workspace of the project at hand, if any
+    let current = Project::detect()
+        .ok()
+        .and_then(|project| WorkspaceManager::new().ok()?.workspace_path(&project).ok());
+
+    let rows: Vec<[String; 6]> = workspaces
+        .iter()
+        .map(|workspace| {
+            let id = workspace
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let metadata = WorkspaceMetadata::load(&workspace.path);
+            let project = match &metadata {
+                Some(m) => match &m.scratch {
+                    Some(scratch) => format!("{} ({})", m.name(), scratch),
+                    None => m.name(),
+                },
+                None => "-".to_string(),
+            };
+            let config = metadata
+                .as_ref()
+                .map(|m| paths::anonymize_path(&m.config_dir))
+                .unwrap_or_else(|| "-".to_string());
+            let created = metadata
+                .as_ref()
+                .map(|m| format_date(m.created()))
+                .unwrap_or_else(|| "-".to_string());
+            [
+                id,
+                project,
+                config,
+                created,
+                format_date(workspace.last_used),
+                format_size(workspace.size),
+            ]
+        })
+        .collect();
+
+    let header = ["ID", "PROJECT", "CONFIG", "CREATED", "LAST USED", "SIZE"];
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let format_row = |row: &[String]| {
+        format!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}  {:w4$}  {:>w5$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+            w5 = widths[5]
+        )
+    };
+
+    println!();
+    let header = header.map(String::from);
+    println!("  {}", style(format_row(&header)).dim());
+    for (workspace, row) in workspaces.iter().zip(&rows) {
+        let line = format_row(row);
+        if current.as_ref() == Some(&workspace.path) {
+            println!("{} {}", style("*").green(), style(line).green());
+        } else {
+            println!("  {}", line);
+        }
+    }
+
+    let total: u64 = workspaces.iter().map(|w| w.size).sum();
+    println!();
+    output::info(&format!(
+        "{} workspace(s), {} in {}",
+        workspaces.len(),
+        format_size(total),
+        paths::anonymize_path(&paths::workspaces_dir()?)
+    ));
+    if rows.iter().any(|row| row[1] == "-") {
+        output::info("Workspaces without a project were last used before lfz recorded it");
+    }
+    Ok(())
+}
+
+/// `2025-01-31`
+fn format_date(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()[..10].to_string()
+}
@@ -0,0 +1,881 @@
+//! Build config parsing and matrix expansion.
+//!
+//! The build config is normally ZMK's `build.yaml`, but `build.toml` and
+//! `build.json` with the same schema are accepted too. Besides the plain
+//! ZMK schema, string values may reference environment
+//! variables as `${VAR}` or `${VAR:-default}` (`$${` is a literal `${`),
+//! and YAML anchors/aliases plus `<<` merge keys can share fragments
+//! between entries. `cmake-args` and `snippet` also accept lists, which
+//! are joined with spaces so anchored lists can be combined:
+//!
+//! ```yaml
+//! common: &split [-DCONFIG_ZMK_SPLIT=y, -DCONFIG_ZMK_SLEEP=y]
+//! include:
+//!   - board: nice_nano_v2
+//!     shield: corne_left
+//!     cmake-args: [*split, "-DCONFIG_ZMK_KEYBOARD_NAME=\"${KB_NAME:-Corne}\""]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::build::target::BuildTarget;
+
+/// Accepted build config file names, in detection order
+pub const BUILD_CONFIG_FILES: &[&str] = &["build.yaml", "build.yml", "build.toml", "build.json"];
+
+/// Serialization format of a build config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl BuildConfigFormat {
+    /// Determine the format from a file extension (defaults to YAML)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => BuildConfigFormat::Toml,
+            Some("json") => BuildConfigFormat::Json,
+            _ => BuildConfigFormat::Yaml,
+        }
+    }
+}
+
+/// Represents a build config file that defines build targets
+#[derive(Debug, Deserialize)]
+pub struct BuildConfig {
+    /// Top-level list of boards to build for all shields
+    #[serde(default)]
+    pub board: Vec<String>,
+
+    /// Top-level list of shields to build for all boards
+    #[serde(default)]
+    pub shield: Vec<String>,
+
+    /// Specific board+shield combinations with additional options
+    #[serde(default)]
+    pub include: Vec<BuildInclude>,
+}
+
+/// A specific build configuration from the include array
+#[derive(Debug, Deserialize, Clone)]
+pub struct BuildInclude {
+    pub board: String,
+
+    #[serde(default)]
+    pub shield: Option<String>,
+
+    #[serde(rename = "cmake-args", default, deserialize_with = "string_or_list")]
+    pub cmake_args: Option<String>,
+
+    #[serde(default, deserialize_with = "string_or_list")]
+    pub snippet: Option<String>,
+
+    #[serde(rename = "artifact-name")]
+    pub artifact_name: Option<String>,
+
+    /// Optional group for filtering (e.g., "central", "peripheral")
+    #[serde(default)]
+    pub group: Option<String>,
+
+    /// Free-form tags for filtering with `--tags` expressions
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Environment variables set in the build container for this target only
+    #[serde(default, deserialize_with = "scalar_map")]
+    pub env: BTreeMap<String, String>,
+
+    /// Zephyr modules added to `ZMK_EXTRA_MODULES` for this target only
+    /// (relative paths are resolved against the build config's directory)
+    #[serde(rename = "extra-modules", default)]
+    pub extra_modules: Vec<PathBuf>,
+
+    /// Maximum build time for this target (e.g. "20m" or seconds)
+    #[serde(default, deserialize_with = "optional_duration")]
+    pub timeout: Option<Duration>,
+
+    /// Build order: higher priorities start first (default 0)
+    #[serde(default)]
+    pub priority: Option<i32>,
+
+    /// Highest percentage of flash the firmware may use
+    #[serde(rename = "max-flash", default)]
+    pub max_flash: Option<u8>,
+
+    /// Highest percentage of RAM the firmware may use
+    #[serde(rename = "max-ram", default)]
+    pub max_ram: Option<u8>,
+}
+
+impl BuildConfig {
+    /// Load a build config (build.yaml, build.toml or build.json) from a path
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read build config at {}", path.display()))?;
+
+        let mut config = Self::parse(BuildConfigFormat::from_path(path), &content, |name| {
+            std::env::var(name).ok()
+        })
+        .with_context(|| format!("Failed to parse build config at {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        for include in &mut config.include {
+            for module in &mut include.extra_modules {
+                *module = base.join(&module);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Parse build config contents, resolving merge keys and `${VAR}` references
+    ///
+    /// All formats go through the same YAML value tree so interpolation
+    /// and list-valued args behave identically.
+    pub fn parse(
+        format: BuildConfigFormat,
+        content: &str,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut value: Value = match format {
+            BuildConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            BuildConfigFormat::Toml => {
+                serde_yaml::to_value(toml::from_str::<toml::Value>(content)?)?
+            }
+            BuildConfigFormat::Json => {
+                serde_yaml::to_value(serde_json::from_str::<serde_json::Value>(content)?)?
+            }
+        };
+        value.apply_merge()?;
+        interpolate_value(&mut value, &env)?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// Expand the build config into a list of concrete build targets
+    ///
+    /// Follows GitHub Actions matrix semantics, like the ZMK user workflow:
+    /// the `board` × `shield` cartesian product forms the base combinations,
+    /// and each `include` entry either augments every combination whose
+    /// original board/shield it doesn't contradict, or (if it matches none)
+    /// becomes a new combination of its own.
+    pub fn expand_targets(&self) -> Result<Vec<BuildTarget>> {
+        // Base combinations, remembering which keys came from the matrix
+        let mut combos: Vec<MatrixCombo> = Vec::new();
+        if !self.board.is_empty() {
+            for board in &self.board {
+                if self.shield.is_empty() {
+                    combos.push(MatrixCombo::new(board.clone(), None));
+                } else {
+                    for shield in &self.shield {
+                        combos.push(MatrixCombo::new(board.clone(), Some(shield.clone())));
+                    }
+                }
+            }
+        }
+        let base_len = combos.len();
+
+        for include in &self.include {
+            let mut augmented = false;
+            for combo in combos[..base_len].iter_mut() {
+                if combo.accepts(include) {
+                    combo.augment(include);
+                    augmented = true;
+                }
+            }
+            if !augmented {
+                combos.push(MatrixCombo {
+                    original_shield: None,
+                    entry: include.clone(),
+                });
+            }
+        }
+
+        let mut targets: Vec<BuildTarget> = Vec::new();
+        for combo in &combos {
+            let target = BuildTarget::from_include(&combo.entry)?;
+            match targets
+                .iter()
+                .find(|t| t.artifact_name == target.artifact_name)
+            {
+                // Identical entries collapse into one build
+                Some(existing) if *existing == target => {}
+                Some(_) => anyhow::bail!(
+                    "Multiple different targets produce artifact '{}'. \
+                     Set a unique artifact-name for each.",
+                    target.artifact_name
+                ),
+                None => targets.push(target),
+            }
+        }
+
+        if targets.is_empty() {
+            anyhow::bail!("No build targets found in build config");
+        }
+
+        Ok(targets)
+    }
+
+    /// Get list of unique tags defined in the config
+    pub fn available_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .include
+            .iter()
+            .flat_map(|inc| inc.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Get list of unique groups defined in the config
+    pub fn available_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .include
+            .iter()
+            .filter_map(|inc| inc.group.clone())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+}
+
+/// Accept either a string or a (possibly nested) list of strings, joined with spaces
+fn string_or_list<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    fn flatten(value: &Value, parts: &mut Vec<String>) -> bool {
+        match value {
+            Value::String(s) => parts.push(s.clone()),
+            Value::Number(n) => parts.push(n.to_string()),
+            Value::Bool(b) => parts.push(b.to_string()),
+            Value::Sequence(seq) => return seq.iter().all(|v| flatten(v, parts)),
+            _ => return false,
+        }
+        true
+    }
+
+    let value = Option::<Value>::deserialize(deserializer)?;
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => {
+            let mut parts = Vec::new();
+            if !flatten(&value, &mut parts) {
+                return Err(serde::de::Error::custom(
+                    "expected a string or a list of strings",
+                ));
+            }
+            Ok(Some(parts.join(" ")))
+        }
+    }
+}
+
+/// Parse a duration given as plain seconds or in humantime form ("90s", "1h 30m")
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    humantime::parse_duration(input).with_context(|| {
+        format!(
+            "Invalid duration '{}' (expected e.g. \"90s\", \"20m\" or seconds)",
+            input
+        )
+    })
+}
+
+/// Accept a duration as a number of seconds or a humantime string
+fn optional_duration<'de, D>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => match n.as_u64() {
+            Some(seconds) => Ok(Some(Duration::from_secs(seconds))),
+            None => Err(serde::de::Error::custom(
+                "timeout must be a whole number of seconds",
+            )),
+        },
+        Some(Value::String(s)) => parse_duration(&s)
+            .map(Some)
+            .map_err(|e| serde::de::Error::custom(e.to_string())),
+        Some(_) => Err(serde::de::Error::custom(
+            "timeout must be a duration like \"20m\" or a number of seconds",
+        )),
+    }
+}
+
+/// Accept a mapping of scalars, stringifying numbers and booleans
+fn scalar_map<'de, D>(deserializer: D) -> std::result::Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let map = Option::<BTreeMap<String, Value>>::deserialize(deserializer)?.unwrap_or_default();
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                Value::Null => String::new(),
+                _ => {
+                    return Err(serde::de::Error::custom(format!(
+                        "env value for '{}' must be a string, number or boolean",
+                        key
+                    )))
+                }
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Replace `${VAR}` references in every string of a YAML document
+fn interpolate_value(value: &mut Value, env: &impl Fn(&str) -> Option<String>) -> Result<()> {
+    match value {
+        Value::String(s) => *s = interpolate(s, env)?,
+        Value::Sequence(seq) => {
+            for v in seq {
+                interpolate_value(v, env)?;
+            }
+        }
+        Value::Mapping(map) => {
+            for (_, v) in map.iter_mut() {
+                interpolate_value(v, env)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` in a string; `$${` yields a literal `${`
+fn interpolate(input: &str, env: &impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos..];
+
+        if let Some(stripped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            rest = stripped;
+        } else if let Some(stripped) = after.strip_prefix("${") {
+            let end = stripped
+                .find('}')
+                .with_context(|| format!("Unterminated '${{' in '{}'", input))?;
+            let expr = &stripped[..end];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+
+            match env(name).or_else(|| default.map(String::from)) {
+                Some(v) => result.push_str(&v),
+                None => anyhow::bail!("Environment variable '{}' is not set", name),
+            }
+            rest = &stripped[end + 1..];
+        } else {
+            result.push('$');
+            rest = &after[1..];
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// One combination of the build matrix
+struct MatrixCombo {
+    /// Shield from the matrix itself (include entries can't change it)
+    original_shield: Option<String>,
+    /// Accumulated values for this combination
+    entry: BuildInclude,
+}
+
+impl MatrixCombo {
+    fn new(board: String, shield: Option<String>) -> Self {
+        Self {
+            original_shield: shield.clone(),
+            entry: BuildInclude {
+                board,
+                shield,
+                cmake_args: None,
+                snippet: None,
+                artifact_name: None,
+                group: None,
+                tags: Vec::new(),
+                env: BTreeMap::new(),
+                extra_modules: Vec::new(),
+                timeout: None,
+                priority: None,
+                max_flash: None,
+                max_ram: None,
+            },
+        }
+    }
+
+    /// An include applies if it doesn't overwrite an original matrix value
+    fn accepts(&self, include: &BuildInclude) -> bool {
+        let shield_ok = match (&self.original_shield, &include.shield) {
+            (Some(original), Some(shield)) => original == shield,
+            _ => true,
+        };
+        self.entry.board == include.board && shield_ok
+    }
+
+    /// Add (or overwrite previously added) values from an include entry
+    fn augment(&mut self, include: &BuildInclude) {
+        if include.shield.is_some() {
+            self.entry.shield = include.shield.clone();
+        }
+        if include.cmake_args.is_some() {
+            self.entry.cmake_args = include.cmake_args.clone();
+        }
+        if include.snippet.is_some() {
+            self.entry.snippet = include.snippet.clone();
+        }
+        if include.artifact_name.is_some() {
+            self.entry.artifact_name = include.artifact_name.clone();
+        }
+        if include.group.is_some() {
+            self.entry.group = include.group.clone();
+        }
+        if !include.tags.is_empty() {
+            self.entry.tags = include.tags.clone();
+        }
+        if !include.env.is_empty() {
+            self.entry.env = include.env.clone();
+        }
+        if !include.extra_modules.is_empty() {
+            self.entry.extra_modules = include.extra_modules.clone();
+        }
+        if include.timeout.is_some() {
+            self.entry.timeout = include.timeout;
+        }
+        if include.priority.is_some() {
+            self.entry.priority = include.priority;
+        }
+        if include.max_flash.is_some() {
+            self.entry.max_flash = include.max_flash;
+        }
+        if include.max_ram.is_some() {
+            self.entry.max_ram = include.max_ram;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple_build_yaml() {
+        let yaml = r#"
+board:
+  - nice_nano_v2
+shield:
+  - corne_left
+  - corne_right
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.board, vec!["nice_nano_v2"]);
+        assert_eq!(config.shield, vec!["corne_left", "corne_right"]);
+    }
+
+    #[test]
+    fn test_parse_include_build_yaml() {
+        let yaml = r#"
+include:
+  - board: seeeduino_xiao_ble
+    shield: cygnus_left
+    cmake-args: -DCONFIG_ZMK_SPLIT=y
+  - board: seeeduino_xiao_ble
+    shield: cygnus_right
+    artifact-name: cygnus_right_custom
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.include.len(), 2);
+        assert_eq!(config.include[0].board, "seeeduino_xiao_ble");
+        assert_eq!(config.include[0].shield, Some("cygnus_left".to_string()));
+        assert_eq!(
+            config.include[0].cmake_args,
+            Some("-DCONFIG_ZMK_SPLIT=y".to_string())
+        );
+        assert_eq!(
+            config.include[1].artifact_name,
+            Some("cygnus_right_custom".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expand_cartesian_product() {
+        let yaml = r#"
+board:
+  - nice_nano_v2
+shield:
+  - corne_left
+  - corne_right
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].artifact_name, "corne_left-nice_nano_v2-zmk");
+        assert_eq!(targets[1].artifact_name, "corne_right-nice_nano_v2-zmk");
+    }
+
+    #[test]
+    fn test_expand_include_augments_matrix() {
+        let yaml = r#"
+board:
+  - nice_nano_v2
+shield:
+  - corne_left
+  - corne_right
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    cmake-args: -DCONFIG_ZMK_SPLIT_ROLE_CENTRAL=y
+    group: central
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].artifact_name, "corne_left-nice_nano_v2-zmk");
+        assert_eq!(targets[0].group, Some("central".to_string()));
+        assert_eq!(
+            targets[0].cmake_args,
+            vec!["-DCONFIG_ZMK_SPLIT_ROLE_CENTRAL=y"]
+        );
+        assert_eq!(targets[1].group, None);
+    }
+
+    #[test]
+    fn test_expand_include_board_only_augments_all_shields() {
+        let yaml = r#"
+board:
+  - nice_nano_v2
+shield:
+  - corne_left
+  - corne_right
+include:
+  - board: nice_nano_v2
+    snippet: zmk-usb-logging
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(targets
+            .iter()
+            .all(|t| t.snippet.as_deref() == Some("zmk-usb-logging")));
+    }
+
+    #[test]
+    fn test_expand_include_adds_new_combination() {
+        let yaml = r#"
+board:
+  - nice_nano_v2
+shield:
+  - corne_left
+  - corne_right
+include:
+  - board: nice_nano_v2
+    shield: settings_reset
+  - board: seeeduino_xiao_ble
+    shield: corne_dongle
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        let names: Vec<_> = config
+            .expand_targets()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.artifact_name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "corne_left-nice_nano_v2-zmk",
+                "corne_right-nice_nano_v2-zmk",
+                "settings_reset-nice_nano_v2-zmk",
+                "corne_dongle-seeeduino_xiao_ble-zmk",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_deduplicates_identical_includes() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+  - board: nice_nano_v2
+    shield: corne_left
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.expand_targets().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_expand_rejects_conflicting_artifact_names() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+  - board: nice_nano_v2
+    shield: corne_left
+    cmake-args: -DCONFIG_ZMK_SLEEP=y
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.expand_targets().is_err());
+    }
+
+    fn test_env(name: &str) -> Option<String> {
+        match name {
+            "KB_NAME" => Some("Corne".to_string()),
+            "SHIELD" => Some("corne_left".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(interpolate("${KB_NAME}-v1", &test_env).unwrap(), "Corne-v1");
+        assert_eq!(interpolate("${MISSING:-x}", &test_env).unwrap(), "x");
+        assert_eq!(interpolate("$${KEEP} $5", &test_env).unwrap(), "${KEEP} $5");
+        assert!(interpolate("${MISSING}", &test_env).is_err());
+        assert!(interpolate("${KB_NAME", &test_env).is_err());
+    }
+
+    #[test]
+    fn test_parse_env_interpolation() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: ${SHIELD}
+    cmake-args: -DCONFIG_ZMK_KEYBOARD_NAME="${KB_NAME}"
+"#;
+        let config = BuildConfig::parse(BuildConfigFormat::Yaml, yaml, test_env).unwrap();
+        assert_eq!(config.include[0].shield, Some("corne_left".to_string()));
+        assert_eq!(
+            config.include[0].cmake_args,
+            Some("-DCONFIG_ZMK_KEYBOARD_NAME=\"Corne\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_anchors_merge_and_lists() {
+        let yaml = r#"
+split: &split [-DCONFIG_ZMK_SPLIT=y, -DCONFIG_ZMK_SLEEP=y]
+defaults: &defaults
+  board: nice_nano_v2
+  snippet: zmk-usb-logging
+include:
+  - <<: *defaults
+    shield: corne_left
+    cmake-args: [*split, -DCONFIG_ZMK_SPLIT_ROLE_CENTRAL=y]
+  - <<: *defaults
+    shield: corne_right
+    cmake-args: *split
+"#;
+        let config = BuildConfig::parse(BuildConfigFormat::Yaml, yaml, test_env).unwrap();
+        assert_eq!(config.include.len(), 2);
+        assert_eq!(config.include[0].board, "nice_nano_v2");
+        assert_eq!(
+            config.include[0].snippet,
+            Some("zmk-usb-logging".to_string())
+        );
+        assert_eq!(
+            config.include[0].cmake_args,
+            Some(
+                "-DCONFIG_ZMK_SPLIT=y -DCONFIG_ZMK_SLEEP=y -DCONFIG_ZMK_SPLIT_ROLE_CENTRAL=y"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            config.include[1].cmake_args,
+            Some("-DCONFIG_ZMK_SPLIT=y -DCONFIG_ZMK_SLEEP=y".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let toml = r#"
+board = ["nice_nano_v2"]
+
+[[include]]
+board = "seeeduino_xiao_ble"
+shield = "cygnus_left"
+cmake-args = ["-DCONFIG_ZMK_SPLIT=y", "-DCONFIG_ZMK_SLEEP=y"]
+group = "central"
+"#;
+        let config = BuildConfig::parse(BuildConfigFormat::Toml, toml, test_env).unwrap();
+        assert_eq!(config.board, vec!["nice_nano_v2"]);
+        assert_eq!(config.include[0].shield, Some("cygnus_left".to_string()));
+        assert_eq!(
+            config.include[0].cmake_args,
+            Some("-DCONFIG_ZMK_SPLIT=y -DCONFIG_ZMK_SLEEP=y".to_string())
+        );
+        assert_eq!(config.include[0].group, Some("central".to_string()));
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{
+  "include": [
+    { "board": "nice_nano_v2", "shield": "${SHIELD}", "artifact-name": "left" }
+  ]
+}"#;
+        let config = BuildConfig::parse(BuildConfigFormat::Json, json, test_env).unwrap();
+        assert_eq!(config.include[0].shield, Some("corne_left".to_string()));
+        assert_eq!(config.include[0].artifact_name, Some("left".to_string()));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            BuildConfigFormat::from_path(Path::new("build.yml")),
+            BuildConfigFormat::Yaml
+        );
+        assert_eq!(
+            BuildConfigFormat::from_path(Path::new("build.toml")),
+            BuildConfigFormat::Toml
+        );
+        assert_eq!(
+            BuildConfigFormat::from_path(Path::new("build.json")),
+            BuildConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_parse_include_with_group() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    group: central
+  - board: nice_nano_v2
+    shield: corne_right
+    group: peripheral
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.include.len(), 2);
+        assert_eq!(config.include[0].group, Some("central".to_string()));
+        assert_eq!(config.include[1].group, Some("peripheral".to_string()));
+
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets[0].group, Some("central".to_string()));
+        assert_eq!(targets[1].group, Some("peripheral".to_string()));
+    }
+
+    #[test]
+    fn test_available_groups() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    group: central
+  - board: nice_nano_v2
+    shield: corne_right
+    group: peripheral
+  - board: nice_nano_v2
+    shield: corne_dongle
+    group: central
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        let groups = config.available_groups();
+        assert_eq!(groups, vec!["central", "peripheral"]);
+    }
+
+    #[test]
+    fn test_parse_include_with_tags() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    tags: [corne, left]
+  - board: nice_nano_v2
+    shield: sofle_left
+    tags: [sofle, left]
+"#;
+        let config: BuildConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.available_tags(), vec!["corne", "left", "sofle"]);
+
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets[0].tags, vec!["corne", "left"]);
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let yaml = r#"
+board: [nice_nano_v2]
+shield: [corne_left, corne_right]
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    env:
+      ZEPHYR_TOOLCHAIN_VARIANT: llvm
+      MY_DEBUG: 1
+"#;
+        let config = BuildConfig::parse(BuildConfigFormat::Yaml, yaml, |_| None).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets[0].env["ZEPHYR_TOOLCHAIN_VARIANT"], "llvm");
+        assert_eq!(targets[0].env["MY_DEBUG"], "1");
+        assert!(targets[1].env.is_empty());
+
+        let yaml = "include:\n  - board: x\n    env:\n      FOO: [a]\n";
+        assert!(BuildConfig::parse(BuildConfigFormat::Yaml, yaml, |_| None).is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_extra_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build.yaml");
+        fs::write(
+            &path,
+            "include:\n  - board: nice_nano_v2\n    extra-modules: [../my-driver, /abs/module]\n",
+        )
+        .unwrap();
+
+        let targets = BuildConfig::load(&path).unwrap().expand_targets().unwrap();
+        assert_eq!(
+            targets[0].extra_modules,
+            vec![
+                dir.path().join("../my-driver"),
+                PathBuf::from("/abs/module")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let yaml = "include:\n  - board: a\n    timeout: 20m\n  - board: b\n    timeout: 90\n  - board: c\n";
+        let config = BuildConfig::parse(BuildConfigFormat::Yaml, yaml, |_| None).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets[0].timeout, Some(Duration::from_secs(20 * 60)));
+        assert_eq!(targets[1].timeout, Some(Duration::from_secs(90)));
+        assert_eq!(targets[2].timeout, None);
+
+        assert!(parse_duration("1h 30m").is_ok());
+        assert!(parse_duration("soon").is_err());
+    }
+
+    #[test]
+    fn test_parse_memory_limits() {
+        let yaml = "board: [a]\ninclude:\n  - board: a\n    max-flash: 90\n    max-ram: 75\n";
+        let config = BuildConfig::parse(BuildConfigFormat::Yaml, yaml, |_| None).unwrap();
+        let targets = config.expand_targets().unwrap();
+        assert_eq!(targets[0].memory_limits.max_flash, Some(90));
+        assert_eq!(targets[0].memory_limits.max_ram, Some(75));
+    }
+}
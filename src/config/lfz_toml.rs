@@ -0,0 +1,656 @@
+//! Optional per-project lfz settings, read from `lfz.toml` in the project root.
+//!
+//! ```toml
+//! default-group = "daily"
+//! build-order = ["central"]
+//! fail-fast = true
+//! runtime = "docker"
+//!
+//! [groups]
+//! daily = ["central", "peripheral"]
+//!
+//! [ssh]
+//! forward-agent = true
+//!
+//! [git]
+//! token-env = "GITHUB_TOKEN"
+//!
+//! [proxy]
+//! https = "http://proxy.corp.example:3128"
+//!
+//! [memory]
+//! max-flash = 95
+//!
+//! [notify]
+//! desktop = true
+//! webhook = "https://hooks.slack.com/services/..."
+//!
+//! [cache]
+//! remote = "https://cache.example.com/lfz"
+//! token-env = "LFZ_CACHE_TOKEN"
+//!
+//! [compiler-cache]
+//! backend = "sccache"
+//! remote = "s3://team-builds/sccache"
+//!
+//! [ccache]
+//! max-size = "10g"
+//! compression-level = 5
+//!
+//! [workspaces]
+//! max-size = "20g"
+//! key = "manifest"
+//! update-jobs = 8
+//! pinned = true
+//!
+//! [gc]
+//! workspaces-max-age = "30d"
+//! logs-max-age = "14d"
+//! artifacts-max-age = "60d"
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::build::memory::MemoryLimits;
+use crate::config::build_config::parse_duration;
+use crate::container::resources::parse_memory;
+use crate::container::Runtime;
+
+/// File name of the project settings file
+pub const LFZ_TOML: &str = "lfz.toml";
+
+/// Project-level lfz settings
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LfzConfig {
+    /// Group filter used when `--group` isn't passed
+    #[serde(default)]
+    pub default_group: Option<String>,
+
+    /// Named group aliases (e.g., `daily = ["central", "peripheral"]`)
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+
+    /// Groups whose targets start building first, in this order
+    #[serde(default)]
+    pub build_order: Vec<String>,
+
+    /// Stop at the first failed target unless `--keep-going` is passed
+    #[serde(default)]
+    pub fail_fast: bool,
+
+    /// Container runtime to use instead of detecting one
+    #[serde(default)]
+    pub runtime: Option<Runtime>,
+
+    /// Command of the `oci` runtime: another Docker-compatible CLI
+    #[serde(default)]
+    pub runtime_command: Option<String>,
+
+    /// SSH access for private module repositories
+    #[serde(default)]
+    pub ssh: SshConfig,
+
+    /// HTTPS access for private module repositories
+    #[serde(default)]
+    pub git: GitCredentials,
+
+    /// Proxy settings for containers
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    /// What to notify when a build run finishes
+    #[serde(default)]
+    pub notify: NotifyConfig,
+
+    /// Flash and RAM usage limits for targets that don't set their own
+    #[serde(default)]
+    pub memory: MemoryLimits,
+
+    /// Remote store shared by the artifact cache
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// Which compiler cache builds use, and its remote store
+    #[serde(default)]
+    pub compiler_cache: CompilerCacheConfig,
+
+    /// Size cap and compression of the ccache
+    #[serde(default)]
+    pub ccache: CcacheConfig,
+
+    /// Disk quota for the cached workspaces
+    #[serde(default)]
+    pub workspaces: WorkspacesConfig,
+
+    /// How long `lfz gc` keeps unused workspaces, logs and cached firmware
+    #[serde(default)]
+    pub gc: GcConfig,
+}
+
+/// How west containers authenticate to SSH remotes
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SshConfig {
+    /// Forward the host's SSH agent into west init/update containers
+    #[serde(default)]
+    pub forward_agent: bool,
+
+    /// Private key mounted read-only into west init/update containers
+    /// (`~/` is expanded)
+    pub key: Option<PathBuf>,
+}
+
+/// How west containers authenticate to HTTPS remotes
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GitCredentials {
+    /// Environment variable holding an access token (instead of `LFZ_GIT_TOKEN`)
+    pub token_env: Option<String>,
+
+    /// Host the token is offered to (default: github.com)
+    pub token_host: Option<String>,
+
+    /// Mount the host's `~/.netrc` read-only
+    #[serde(default)]
+    pub netrc: bool,
+
+    /// Mount the host's `~/.git-credentials` read-only and use it as a credential store
+    #[serde(default)]
+    pub git_credentials: bool,
+}
+
+/// Proxies passed into every container, taking precedence over the
+/// host's `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProxyConfig {
+    pub http: Option<String>,
+    pub https: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+/// Notifications when a build run finishes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Show a desktop notification (as `--notify` does)
+    #[serde(default)]
+    pub desktop: bool,
+
+    /// URL the run's JSON summary is POSTed to (Slack-compatible: the
+    /// summary has a `text` field)
+    pub webhook: Option<String>,
+
+    /// Shell command run with the JSON summary on stdin
+    pub command: Option<String>,
+}
+
+/// A remote store for the artifact cache
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Base URL firmware is fetched from (GET) and uploaded to (PUT)
+    pub remote: Option<String>,
+
+    /// Environment variable holding a token sent as `Authorization: Bearer`
+    pub token_env: Option<String>,
+
+    /// Upload firmware built by every run (as `--push-cache` does)
+    #[serde(default)]
+    pub push: bool,
+}
+
+/// Which compiler cache the build containers use
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CompilerCacheConfig {
+    #[serde(default)]
+    pub backend: CacheBackend,
+
+    /// A Linux sccache binary to mount into the build containers, for
+    /// images that don't have one
+    pub sccache_binary: Option<PathBuf>,
+
+    /// Store shared with other machines: an HTTP, Redis or (sccache only)
+    /// S3 URL
+    pub remote: Option<String>,
+
+    /// Environment variable holding a token for an HTTP remote
+    pub token_env: Option<String>,
+}
+
+/// A compiler cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheBackend {
+    /// ccache, which Zephyr picks up on its own
+    #[default]
+    Ccache,
+    /// sccache, run as CMake's compiler launcher
+    Sccache,
+}
+
+impl CacheBackend {
+    /// Name as written in lfz.toml
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ccache => "ccache",
+            Self::Sccache => "sccache",
+        }
+    }
+}
+
+/// Settings lfz writes into the shared ccache's configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CcacheConfig {
+    /// Size past which ccache evicts the files used longest ago (e.g. "10g")
+    pub max_size: Option<String>,
+
+    /// Compress cached files
+    pub compression: Option<bool>,
+
+    /// Compression level (zstd's -5 to 19 on ccache 4, 1 to 9 on ccache 3)
+    pub compression_level: Option<i8>,
+}
+
+/// How the cached workspaces are keyed and how much disk they may take
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WorkspacesConfig {
+    /// Size past which the least recently used workspaces are removed
+    /// (e.g. "20g")
+    pub max_size: Option<String>,
+
+    /// Which builds share a workspace
+    #[serde(default)]
+    pub key: WorkspaceKey,
+
+    /// Projects west fetches at once when initializing or updating a
+    /// workspace (default: one at a time)
+    pub update_jobs: Option<u16>,
+
+    /// Never update the workspace on its own; west.yml changes only warn
+    /// until `lfz update`
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Retention ages `lfz gc` applies, next to the workspace quota and the
+/// ccache's size cap
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GcConfig {
+    /// Remove workspaces not used within this long (e.g. "30d")
+    pub workspaces_max_age: Option<String>,
+
+    /// Remove build logs older than this
+    pub logs_max_age: Option<String>,
+
+    /// Remove cached firmware not used within this long
+    pub artifacts_max_age: Option<String>,
+}
+
+/// What a workspace is keyed by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkspaceKey {
+    /// The contents of west.yml: every config with the same modules shares
+    /// one, and editing west.yml starts a new one
+    Manifest,
+    /// Config repository and branch
+    #[default]
+    Branch,
+    /// Config repository: all its branches share one
+    Repo,
+}
+
+impl WorkspaceKey {
+    pub const ALL: [Self; 3] = [Self::Manifest, Self::Branch, Self::Repo];
+
+    /// Name as written in lfz.toml
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Manifest => "manifest",
+            Self::Branch => "branch",
+            Self::Repo => "repo",
+        }
+    }
+}
+
+impl WorkspacesConfig {
+    /// The quota in bytes, if one is set
+    pub fn max_size(&self) -> Result<Option<u64>> {
+        self.max_size
+            .as_deref()
+            .map(|size| parse_memory(size).context("Invalid workspaces.max-size in lfz.toml"))
+            .transpose()
+    }
+}
+
+impl CompilerCacheConfig {
+    /// The sccache binary path with a leading `~/` expanded
+    pub fn sccache_binary(&self) -> Option<PathBuf> {
+        self.sccache_binary.as_deref().map(expand_home)
+    }
+}
+
+impl CcacheConfig {
+    /// The cap in bytes, if one is set
+    pub fn max_size(&self) -> Result<Option<u64>> {
+        self.max_size
+            .as_deref()
+            .map(|size| parse_memory(size).context("Invalid ccache.max-size in lfz.toml"))
+            .transpose()
+    }
+}
+
+impl GcConfig {
+    pub fn workspaces_max_age(&self) -> Result<Option<Duration>> {
+        parse_age(&self.workspaces_max_age, "gc.workspaces-max-age")
+    }
+
+    pub fn logs_max_age(&self) -> Result<Option<Duration>> {
+        parse_age(&self.logs_max_age, "gc.logs-max-age")
+    }
+
+    pub fn artifacts_max_age(&self) -> Result<Option<Duration>> {
+        parse_age(&self.artifacts_max_age, "gc.artifacts-max-age")
+    }
+
+    /// Whether any age is set
+    pub fn is_configured(&self) -> bool {
+        self.workspaces_max_age.is_some()
+            || self.logs_max_age.is_some()
+            || self.artifacts_max_age.is_some()
+    }
+}
+
+fn parse_age(age: &Option<String>, key: &str) -> Result<Option<Duration>> {
+    age.as_deref()
+        .map(|age| parse_duration(age).with_context(|| format!("Invalid {} in lfz.toml", key)))
+        .transpose()
+}
+
+impl GitCredentials {
+    /// Host the token is offered to
+    pub fn token_host(&self) -> &str {
+        self.token_host.as_deref().unwrap_or("github.com")
+    }
+}
+
+impl SshConfig {
+    /// Whether any SSH authentication is configured
+    pub fn is_configured(&self) -> bool {
+        self.forward_agent || self.key.is_some()
+    }
+
+    /// The key path with a leading `~/` expanded
+    pub fn key_path(&self) -> Option<PathBuf> {
+        self.key.as_deref().map(expand_home)
+    }
+}
+
+/// A path with a leading `~/` expanded
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl LfzConfig {
+    /// Load `lfz.toml` from a project root, or defaults if it doesn't exist
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(LFZ_TOML);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Resolve the effective group filter from the CLI value and the default
+    pub fn group_filter(&self, cli_group: Option<String>) -> String {
+        cli_group
+            .or_else(|| self.default_group.clone())
+            .unwrap_or_else(|| "all".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_missing_is_default() {
+        let dir = tempdir().unwrap();
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert!(config.default_group.is_none());
+        assert!(config.groups.is_empty());
+    }
+
+    #[test]
+    fn test_load_groups_and_default() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "default-group = \"daily\"\n\n[groups]\ndaily = [\"central\", \"peripheral\"]\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.default_group, Some("daily".to_string()));
+        assert_eq!(config.groups["daily"], vec!["central", "peripheral"]);
+        assert_eq!(config.group_filter(None), "daily");
+        assert_eq!(config.group_filter(Some("reset".to_string())), "reset");
+    }
+
+    #[test]
+    fn test_load_fail_fast() {
+        let dir = tempdir().unwrap();
+        assert!(!LfzConfig::load(dir.path()).unwrap().fail_fast);
+        fs::write(dir.path().join(LFZ_TOML), "fail-fast = true\n").unwrap();
+        assert!(LfzConfig::load(dir.path()).unwrap().fail_fast);
+    }
+
+    #[test]
+    fn test_load_ssh() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[ssh]\nforward-agent = true\nkey = \"~/.ssh/id_ed25519\"\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert!(config.ssh.forward_agent);
+        assert!(config.ssh.is_configured());
+        let key = config.ssh.key_path().unwrap();
+        assert!(key.ends_with(".ssh/id_ed25519"));
+        assert!(!key.starts_with("~"));
+    }
+
+    #[test]
+    fn test_load_git_credentials() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[git]\ntoken-env = \"GITHUB_TOKEN\"\nnetrc = true\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.git.token_env.as_deref(), Some("GITHUB_TOKEN"));
+        assert_eq!(config.git.token_host(), "github.com");
+        assert!(config.git.netrc);
+        assert!(!config.git.git_credentials);
+    }
+
+    #[test]
+    fn test_load_proxy() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[proxy]\nhttps = \"http://proxy:3128\"\nno-proxy = \"localhost\"\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.proxy.http, None);
+        assert_eq!(config.proxy.https.as_deref(), Some("http://proxy:3128"));
+        assert_eq!(config.proxy.no_proxy.as_deref(), Some("localhost"));
+    }
+
+    #[test]
+    fn test_load_cache() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[cache]\nremote = \"https://cache.example.com/lfz\"\ntoken-env = \"CACHE_TOKEN\"\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(
+            config.cache.remote.as_deref(),
+            Some("https://cache.example.com/lfz")
+        );
+        assert_eq!(config.cache.token_env.as_deref(), Some("CACHE_TOKEN"));
+        assert!(!config.cache.push);
+    }
+
+    #[test]
+    fn test_load_compiler_cache() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[compiler-cache]\nbackend = \"sccache\"\nsccache-binary = \"~/bin/sccache\"\n\
+             remote = \"redis://desktop:6379\"\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.compiler_cache.backend, CacheBackend::Sccache);
+        let binary = config.compiler_cache.sccache_binary().unwrap();
+        assert!(binary.ends_with("bin/sccache"));
+        assert!(!binary.starts_with("~"));
+        assert_eq!(
+            config.compiler_cache.remote.as_deref(),
+            Some("redis://desktop:6379")
+        );
+        assert_eq!(
+            LfzConfig::default().compiler_cache.backend,
+            CacheBackend::Ccache
+        );
+    }
+
+    #[test]
+    fn test_load_ccache() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[ccache]\nmax-size = \"10g\"\ncompression = false\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.ccache.max_size().unwrap(), Some(10 << 30));
+        assert_eq!(config.ccache.compression, Some(false));
+        assert_eq!(config.ccache.compression_level, None);
+        assert_eq!(LfzConfig::default().ccache.max_size().unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_workspaces() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[workspaces]\nmax-size = \"20g\"\nkey = \"repo\"\nupdate-jobs = 8\npinned = true\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.workspaces.max_size().unwrap(), Some(20 << 30));
+        assert_eq!(config.workspaces.key, WorkspaceKey::Repo);
+        assert_eq!(config.workspaces.update_jobs, Some(8));
+        assert!(config.workspaces.pinned);
+        let default = LfzConfig::default();
+        assert_eq!(default.workspaces.max_size().unwrap(), None);
+        assert_eq!(default.workspaces.key, WorkspaceKey::Branch);
+
+        let invalid = WorkspacesConfig {
+            max_size: Some("lots".to_string()),
+            key: WorkspaceKey::Branch,
+            update_jobs: None,
+            pinned: false,
+        };
+        assert!(invalid.max_size().is_err());
+    }
+
+    #[test]
+    fn test_load_runtime() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(LFZ_TOML), "runtime = \"docker\"\n").unwrap();
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.runtime, Some(Runtime::Docker));
+
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "runtime = \"oci\"\nruntime-command = \"finch\"\n",
+        )
+        .unwrap();
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert_eq!(config.runtime, Some(Runtime::Oci));
+        assert_eq!(config.runtime_command.as_deref(), Some("finch"));
+
+        fs::write(dir.path().join(LFZ_TOML), "runtime = \"lxc\"\n").unwrap();
+        assert!(LfzConfig::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_gc() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(LFZ_TOML),
+            "[gc]\nworkspaces-max-age = \"30d\"\nlogs-max-age = \"2w\"\n",
+        )
+        .unwrap();
+
+        let config = LfzConfig::load(dir.path()).unwrap();
+        assert!(config.gc.is_configured());
+        assert_eq!(
+            config.gc.workspaces_max_age().unwrap(),
+            Some(Duration::from_secs(30 * 86400))
+        );
+        assert_eq!(
+            config.gc.logs_max_age().unwrap(),
+            Some(Duration::from_secs(14 * 86400))
+        );
+        assert_eq!(config.gc.artifacts_max_age().unwrap(), None);
+        assert!(!LfzConfig::default().gc.is_configured());
+
+        let invalid = GcConfig {
+            logs_max_age: Some("forever".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.logs_max_age().is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(LFZ_TOML), "defualt-group = \"daily\"\n").unwrap();
+        assert!(LfzConfig::load(dir.path()).is_err());
+    }
+}
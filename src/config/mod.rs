@@ -1,3 +1,7 @@
-pub mod build_yaml;
+pub mod build_config;
+pub mod lfz_toml;
 pub mod project;
+pub mod upstream;
+pub mod validate;
+pub mod west_edit;
 pub mod west_yml;
@@ -2,8 +2,10 @@ use anyhow::{Context, Result};
 use std::env;
 use std::path::{Path, PathBuf};
 
+use super::build_config::BUILD_CONFIG_FILES;
+
 /// Represents a detected ZMK keyboard project
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Project {
     /// Root directory of the project (where lfz is invoked)
     pub root: PathBuf,
@@ -11,8 +13,9 @@ pub struct Project {
     /// Path to the config directory (contains west.yml, keymaps, etc.)
     pub config_dir: PathBuf,
 
-    /// Path to build.yaml or build.yml (in root directory)
-    pub build_yaml: PathBuf,
+    /// Path to the build config: build.yaml, build.yml, build.toml or build.json
+    /// (in root directory)
+    pub build_file: PathBuf,
 
     /// Whether the project root is a valid Zephyr module (has zephyr/module.yml)
     pub is_zephyr_module: bool,
@@ -38,17 +41,16 @@ impl Project {
             );
         }
 
-        // Check for build.yaml or build.yml in root directory
-        let build_yaml = root.join("build.yaml");
-        let build_yml = root.join("build.yml");
-        let build_yaml = if build_yaml.is_file() {
-            build_yaml
-        } else if build_yml.is_file() {
-            build_yml
-        } else {
+        // Check for build.yaml, build.yml, build.toml or build.json in root directory
+        let Some(build_file) = BUILD_CONFIG_FILES
+            .iter()
+            .map(|name| root.join(name))
+            .find(|path| path.is_file())
+        else {
             anyhow::bail!(
-                "No 'build.yaml' or 'build.yml' found in {}. \
+                "No build config ({}) found in {}. \
                  This file is required to define build targets.",
+                BUILD_CONFIG_FILES.join(", "),
                 root.display()
             );
         };
@@ -69,7 +71,7 @@ impl Project {
         Ok(Self {
             root: root.to_path_buf(),
             config_dir,
-            build_yaml,
+            build_file,
             is_zephyr_module,
         })
     }
@@ -106,9 +108,9 @@ mod tests {
         fs::write(root.join("build.yaml"), "board: [nice_nano_v2]").unwrap();
         fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
 
-        let project = Project::detect_from(&root.to_path_buf()).unwrap();
+        let project = Project::detect_from(root).unwrap();
         assert_eq!(project.config_dir, config_dir);
-        assert_eq!(project.build_yaml, root.join("build.yaml"));
+        assert_eq!(project.build_file, root.join("build.yaml"));
         assert!(!project.is_zephyr_module);
     }
 
@@ -122,8 +124,30 @@ mod tests {
         fs::write(root.join("build.yml"), "board: [nice_nano_v2]").unwrap();
         fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
 
-        let project = Project::detect_from(&root.to_path_buf()).unwrap();
-        assert_eq!(project.build_yaml, root.join("build.yml"));
+        let project = Project::detect_from(root).unwrap();
+        assert_eq!(project.build_file, root.join("build.yml"));
+    }
+
+    #[test]
+    fn test_detect_with_build_toml() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let config_dir = root.join("config");
+        fs::create_dir(&config_dir).unwrap();
+        fs::write(root.join("build.toml"), "board = [\"nice_nano_v2\"]").unwrap();
+        fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
+
+        let project = Project::detect_from(root).unwrap();
+        assert_eq!(project.build_file, root.join("build.toml"));
+    }
+
+    #[test]
+    fn test_detect_without_build_config() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("config")).unwrap();
+
+        let err = Project::detect_from(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("build.yaml, build.yml, build.toml, build.json"));
     }
 
     #[test]
@@ -139,7 +163,7 @@ mod tests {
         fs::write(root.join("build.yaml"), "board: [nice_nano_v2]").unwrap();
         fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
 
-        let project = Project::detect_from(&root.to_path_buf()).unwrap();
+        let project = Project::detect_from(root).unwrap();
         // boards/ alone is not a Zephyr module - need zephyr/module.yml
         assert!(!project.is_zephyr_module);
         assert!(project.extra_modules().is_empty());
@@ -157,7 +181,7 @@ mod tests {
         fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
         fs::write(zephyr_dir.join("module.yml"), "build:\n  cmake: zephyr").unwrap();
 
-        let project = Project::detect_from(&root.to_path_buf()).unwrap();
+        let project = Project::detect_from(root).unwrap();
         assert!(project.is_zephyr_module);
         assert_eq!(project.extra_modules(), vec![root.to_path_buf()]);
     }
@@ -0,0 +1,236 @@
+//! Refs published by a project's remote, read with `git ls-remote` on the host.
+
+use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::thread;
+
+/// Branches and tags of a remote repository
+#[derive(Debug, Default)]
+pub struct RemoteRefs {
+    /// Branch `HEAD` points to
+    pub default_branch: Option<String>,
+    /// Branch name -> commit
+    pub branches: BTreeMap<String, String>,
+    /// Tag name -> commit (annotated tags are peeled)
+    pub tags: BTreeMap<String, String>,
+}
+
+/// How a pinned revision compares to the remote
+#[derive(Debug, PartialEq, Eq)]
+pub enum Freshness {
+    UpToDate,
+    /// A newer tag or commit exists (described by the string)
+    Behind(String),
+    /// Follows a branch, with no checkout to compare against
+    Tracking(String),
+    /// The revision isn't a ref or commit the remote advertises
+    Unknown,
+}
+
+impl RemoteRefs {
+    /// List the refs of a remote repository
+    pub fn fetch(url: &str) -> Result<Self> {
+        let output = Command::new("git")
+            .args(["ls-remote", "--symref", url])
+            // Fail instead of waiting for a password
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .context("Failed to run git ls-remote")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git ls-remote {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// List the refs of several remotes concurrently, in order
+    pub fn fetch_all(urls: &[&str]) -> Vec<Result<Self>> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = urls
+                .iter()
+                .map(|url| scope.spawn(move || Self::fetch(url)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("ls-remote thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Parse `git ls-remote --symref` output
+    pub fn parse(output: &str) -> Self {
+        let mut refs = Self::default();
+        for line in output.lines() {
+            let Some((value, name)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Some(target) = value.strip_prefix("ref: ") {
+                if name == "HEAD" {
+                    refs.default_branch = target.strip_prefix("refs/heads/").map(String::from);
+                }
+            } else if let Some(branch) = name.strip_prefix("refs/heads/") {
+                refs.branches.insert(branch.to_string(), value.to_string());
+            } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+                // `tag^{}` lines carry the commit an annotated tag points to
+                let tag = tag.strip_suffix("^{}").unwrap_or(tag);
+                refs.tags.insert(tag.to_string(), value.to_string());
+            }
+        }
+        refs
+    }
+
+    /// The highest version tag (e.g. `v3.5.0`), ignoring other tags
+    pub fn latest_tag(&self) -> Option<&str> {
+        self.tags
+            .keys()
+            .filter(|tag| version_key(tag).is_some())
+            .max_by(|a, b| compare_versions(a, b))
+            .map(String::as_str)
+    }
+
+    /// The highest version tag, if `revision` is an older version tag.
+    /// Nothing for commits, branches and other tags: they can't be ordered
+    /// against a release.
+    pub fn newer_tag(&self, revision: &str) -> Option<&str> {
+        version_key(revision)?;
+        self.latest_tag()
+            .filter(|latest| compare_versions(latest, revision) == Ordering::Greater)
+    }
+
+    /// Compare a west `revision` with the remote
+    ///
+    /// `checkout` is the commit currently checked out in the workspace, used
+    /// to tell whether a branch-tracking project has moved on since.
+    pub fn freshness(&self, revision: &str, checkout: Option<&str>) -> Freshness {
+        if self.tags.contains_key(revision) {
+            return match self.latest_tag() {
+                Some(latest) if compare_versions(latest, revision) == Ordering::Greater => {
+                    Freshness::Behind(latest.to_string())
+                }
+                _ => Freshness::UpToDate,
+            };
+        }
+
+        if let Some(head) = self.branches.get(revision) {
+            return match checkout {
+                Some(commit) if commit != head => {
+                    Freshness::Behind(format!("{} @ {}", revision, short(head)))
+                }
+                Some(_) => Freshness::UpToDate,
+                None => Freshness::Tracking(revision.to_string()),
+            };
+        }
+
+        if is_commit(revision) {
+            let branch = self.default_branch.as_deref().unwrap_or("master");
+            return match self.branches.get(branch) {
+                Some(head) if head.starts_with(revision) => Freshness::UpToDate,
+                Some(head) => match self.latest_tag() {
+                    Some(tag) if !self.tags[tag].starts_with(revision) => {
+                        Freshness::Behind(format!("{} or {} @ {}", tag, branch, short(head)))
+                    }
+                    Some(_) => Freshness::UpToDate,
+                    None => Freshness::Behind(format!("{} @ {}", branch, short(head))),
+                },
+                None => Freshness::Unknown,
+            };
+        }
+
+        Freshness::Unknown
+    }
+}
+
+/// Whether a revision looks like a (possibly abbreviated) commit SHA
+pub fn is_commit(revision: &str) -> bool {
+    (7..=40).contains(&revision.len()) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether a tag names a release (`v1.2.3`)
+pub fn is_release(tag: &str) -> bool {
+    version_key(tag).is_some()
+}
+
+/// Abbreviate a commit SHA for display
+pub fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// Numeric components of a version tag (`v1.2.3` -> `[1, 2, 3]`)
+fn version_key(tag: &str) -> Option<Vec<u64>> {
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    version_key(a).cmp(&version_key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LS_REMOTE: &str = "\
+ref: refs/heads/main\tHEAD
+1111111111111111111111111111111111111111\tHEAD
+1111111111111111111111111111111111111111\trefs/heads/main
+2222222222222222222222222222222222222222\trefs/heads/v3.5-branch
+3333333333333333333333333333333333333333\trefs/tags/v3.5.0
+4444444444444444444444444444444444444444\trefs/tags/v3.10.0
+5555555555555555555555555555555555555555\trefs/tags/v3.10.0^{}
+6666666666666666666666666666666666666666\trefs/tags/nightly
+";
+
+    #[test]
+    fn test_parse() {
+        let refs = RemoteRefs::parse(LS_REMOTE);
+        assert_eq!(refs.default_branch.as_deref(), Some("main"));
+        assert_eq!(refs.branches.len(), 2);
+        assert_eq!(refs.tags["v3.10.0"], "5".repeat(40));
+        assert_eq!(refs.latest_tag(), Some("v3.10.0"));
+    }
+
+    #[test]
+    fn test_newer_tag() {
+        let refs = RemoteRefs::parse(LS_REMOTE);
+        assert_eq!(refs.newer_tag("v3.5.0"), Some("v3.10.0"));
+        assert_eq!(refs.newer_tag("v3.10.0"), None);
+        assert_eq!(refs.newer_tag("v4.0.0"), None);
+        assert_eq!(refs.newer_tag("nightly"), None);
+        assert_eq!(refs.newer_tag(&"2".repeat(40)), None);
+    }
+
+    #[test]
+    fn test_freshness() {
+        let refs = RemoteRefs::parse(LS_REMOTE);
+        let main = "1".repeat(40);
+
+        assert_eq!(
+            refs.freshness("v3.5.0", None),
+            Freshness::Behind("v3.10.0".to_string())
+        );
+        assert_eq!(refs.freshness("v3.10.0", None), Freshness::UpToDate);
+
+        assert_eq!(
+            refs.freshness("main", None),
+            Freshness::Tracking("main".to_string())
+        );
+        assert_eq!(refs.freshness("main", Some(&main)), Freshness::UpToDate);
+        assert_eq!(
+            refs.freshness("main", Some("abc")),
+            Freshness::Behind("main @ 1111111".to_string())
+        );
+
+        assert_eq!(refs.freshness("1111111", None), Freshness::UpToDate);
+        assert_eq!(refs.freshness("5555555", None), Freshness::UpToDate);
+        assert_eq!(
+            refs.freshness("abcdef0", None),
+            Freshness::Behind("v3.10.0 or main @ 1111111".to_string())
+        );
+        assert_eq!(refs.freshness("no-such-ref", None), Freshness::Unknown);
+    }
+}
@@ -0,0 +1,1067 @@
+//! Schema validation for the build config and west.yml.
+//!
+//! serde only reports the first problem and drops source positions, so
+//! validation walks a position-annotated tree built from the YAML event
+//! stream instead and collects every problem it finds. JSON build configs
+//! go through the same YAML parser; TOML parse errors are located from
+//! their byte span, but TOML values themselves carry no positions.
+
+use std::collections::HashMap;
+use std::fmt;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use super::build_config::{parse_duration, BuildConfig, BuildConfigFormat};
+use crate::build::target::BuildTarget;
+
+/// Top-level keys of a build config
+const BUILD_KEYS: &[&str] = &["board", "shield", "include"];
+
+/// Keys of a build config `include` entry
+const INCLUDE_KEYS: &[&str] = &[
+    "board",
+    "shield",
+    "cmake-args",
+    "snippet",
+    "artifact-name",
+    "group",
+    "tags",
+    "env",
+    "extra-modules",
+    "timeout",
+    "priority",
+    "max-flash",
+    "max-ram",
+];
+
+/// Keys of the `manifest` mapping in west.yml
+const MANIFEST_KEYS: &[&str] = &[
+    "version",
+    "remotes",
+    "defaults",
+    "projects",
+    "self",
+    "group-filter",
+];
+
+const REMOTE_KEYS: &[&str] = &["name", "url-base"];
+const DEFAULTS_KEYS: &[&str] = &["remote", "revision"];
+const SELF_KEYS: &[&str] = &["path", "west-commands", "import"];
+const PROJECT_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "url",
+    "remote",
+    "repo-path",
+    "revision",
+    "path",
+    "clone-depth",
+    "west-commands",
+    "import",
+    "groups",
+    "submodules",
+    "userdata",
+];
+
+/// How serious a validation finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single validation finding
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// 1-based (line, column), when the format preserves positions
+    pub location: Option<(usize, usize)>,
+
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(location: Option<(usize, usize)>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            location,
+            message: message.into(),
+        }
+    }
+
+    fn warning(location: Option<(usize, usize)>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            location,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.location {
+            Some((line, column)) => {
+                write!(f, "{}:{}: {}: {}", line, column, severity, self.message)
+            }
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Validate build config contents against the build config schema
+///
+/// Besides the schema itself, this reports artifact names claimed by more
+/// than one include entry, and - once the structure is sound - runs the
+/// real parser and matrix expansion so interpolation errors surface too.
+pub fn validate_build_config(format: BuildConfigFormat, content: &str) -> Vec<Diagnostic> {
+    let root = match format {
+        BuildConfigFormat::Yaml | BuildConfigFormat::Json => match parse_yaml(content) {
+            Ok(root) => root,
+            Err(diagnostic) => return vec![diagnostic],
+        },
+        BuildConfigFormat::Toml => match toml::from_str::<toml::Value>(content) {
+            Ok(value) => Node::from_toml(&value),
+            Err(e) => {
+                let location = e.span().map(|span| location_of(content, span.start));
+                return vec![Diagnostic::error(location, e.message())];
+            }
+        },
+    };
+
+    let mut checker = Checker::default();
+    checker.build_config(&root);
+
+    if !checker.has_errors() {
+        let expanded = BuildConfig::parse(format, content, |name| std::env::var(name).ok())
+            .and_then(|config| config.expand_targets());
+        if let Err(e) = expanded {
+            checker.error(None, format!("{:#}", e));
+        }
+    }
+
+    checker.diagnostics
+}
+
+/// Validate west.yml contents against the west manifest schema
+pub fn validate_west_yml(content: &str) -> Vec<Diagnostic> {
+    let root = match parse_yaml(content) {
+        Ok(root) => root,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+
+    let mut checker = Checker::default();
+    checker.west_yml(&root);
+    checker.diagnostics
+}
+
+/// Convert a byte offset into a 1-based (line, column)
+fn location_of(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+/// A parsed value with the position it starts at
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    location: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Null,
+    Scalar(String),
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+}
+
+impl Node {
+    fn from_toml(value: &toml::Value) -> Self {
+        let kind = match value {
+            toml::Value::Array(items) => NodeKind::Seq(items.iter().map(Node::from_toml).collect()),
+            toml::Value::Table(table) => NodeKind::Map(
+                table
+                    .iter()
+                    .map(|(k, v)| (Node::scalar(k), Node::from_toml(v)))
+                    .collect(),
+            ),
+            toml::Value::String(s) => NodeKind::Scalar(s.clone()),
+            other => NodeKind::Scalar(other.to_string()),
+        };
+        Node {
+            kind,
+            location: None,
+        }
+    }
+
+    fn scalar(value: &str) -> Self {
+        Node {
+            kind: NodeKind::Scalar(value.to_string()),
+            location: None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self.kind {
+            NodeKind::Null => "null",
+            NodeKind::Scalar(_) => "a string",
+            NodeKind::Seq(_) => "a list",
+            NodeKind::Map(_) => "a mapping",
+        }
+    }
+
+    /// Mapping entries with `<<` merge keys resolved (own keys win)
+    fn entries(&self) -> Vec<(&Node, &Node)> {
+        let NodeKind::Map(pairs) = &self.kind else {
+            return Vec::new();
+        };
+
+        let mut own: Vec<(&Node, &Node)> = Vec::new();
+        let mut merged: Vec<(&Node, &Node)> = Vec::new();
+        for (key, value) in pairs {
+            if key.as_str() != Some("<<") {
+                own.push((key, value));
+                continue;
+            }
+            let sources: Vec<&Node> = match &value.kind {
+                NodeKind::Seq(items) => items.iter().collect(),
+                _ => vec![value],
+            };
+            for source in sources {
+                merged.extend(source.entries());
+            }
+        }
+
+        for (key, value) in merged {
+            let name = key.as_str();
+            if !own.iter().any(|(k, _)| k.as_str() == name) {
+                own.push((key, value));
+            }
+        }
+        own
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        self.entries()
+            .into_iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    /// Canonical text of the value, used to compare include entries
+    fn canonical(&self) -> String {
+        match &self.kind {
+            NodeKind::Null => "~".to_string(),
+            NodeKind::Scalar(s) => format!("{:?}", s),
+            NodeKind::Seq(items) => {
+                let items: Vec<String> = items.iter().map(Node::canonical).collect();
+                format!("[{}]", items.join(","))
+            }
+            NodeKind::Map(_) => {
+                let mut entries: Vec<String> = self
+                    .entries()
+                    .iter()
+                    .map(|(k, v)| format!("{}:{}", k.canonical(), v.canonical()))
+                    .collect();
+                entries.sort();
+                format!("{{{}}}", entries.join(","))
+            }
+        }
+    }
+}
+
+/// Parse YAML (or JSON) into a position-annotated tree
+fn parse_yaml(content: &str) -> Result<Node, Diagnostic> {
+    let mut builder = TreeBuilder::default();
+    Parser::new_from_str(content)
+        .load(&mut builder, false)
+        .map_err(|e| Diagnostic::error(Some(location(e.marker())), e.info()))?;
+
+    Ok(builder.root.unwrap_or(Node {
+        kind: NodeKind::Null,
+        location: Some((1, 1)),
+    }))
+}
+
+fn location(mark: &Marker) -> (usize, usize) {
+    (mark.line(), mark.col() + 1)
+}
+
+/// Event receiver assembling a [`Node`] tree, expanding aliases in place
+#[derive(Default)]
+struct TreeBuilder {
+    stack: Vec<Frame>,
+    anchors: HashMap<usize, Node>,
+    root: Option<Node>,
+}
+
+struct Frame {
+    node: Node,
+    anchor: usize,
+    pending_key: Option<Node>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, kind: NodeKind, anchor: usize, mark: Marker) {
+        self.stack.push(Frame {
+            node: Node {
+                kind,
+                location: Some(location(&mark)),
+            },
+            anchor,
+            pending_key: None,
+        });
+    }
+
+    fn insert(&mut self, node: Node, anchor: usize) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+
+        let Some(frame) = self.stack.last_mut() else {
+            self.root.get_or_insert(node);
+            return;
+        };
+        match &mut frame.node.kind {
+            NodeKind::Seq(items) => items.push(node),
+            NodeKind::Map(pairs) => match frame.pending_key.take() {
+                Some(key) => pairs.push((key, node)),
+                None => frame.pending_key = Some(node),
+            },
+            NodeKind::Null | NodeKind::Scalar(_) => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for TreeBuilder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::Scalar(value, style, anchor, _) => {
+                let is_null = style == TScalarStyle::Plain
+                    && matches!(value.as_str(), "" | "~" | "null" | "Null" | "NULL");
+                let kind = if is_null {
+                    NodeKind::Null
+                } else {
+                    NodeKind::Scalar(value)
+                };
+                let node = Node {
+                    kind,
+                    location: Some(location(&mark)),
+                };
+                self.insert(node, anchor);
+            }
+            Event::SequenceStart(anchor, _) => self.push(NodeKind::Seq(Vec::new()), anchor, mark),
+            Event::MappingStart(anchor, _) => self.push(NodeKind::Map(Vec::new()), anchor, mark),
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some(mut frame) = self.stack.pop() {
+                    // Block mappings are marked at their first ':', so
+                    // point at the first key instead when it comes earlier
+                    if let NodeKind::Map(pairs) = &frame.node.kind {
+                        if let Some((key, _)) = pairs.first() {
+                            frame.node.location = frame.node.location.min(key.location);
+                        }
+                    }
+                    self.insert(frame.node, frame.anchor);
+                }
+            }
+            Event::Alias(id) => {
+                let node = self.anchors.get(&id).cloned().unwrap_or(Node {
+                    kind: NodeKind::Null,
+                    location: Some(location(&mark)),
+                });
+                self.insert(node, 0);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects diagnostics while walking a tree
+#[derive(Default)]
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn error(&mut self, location: Option<(usize, usize)>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::error(location, message));
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    /// Check a mapping's keys, returning its entries when it is one
+    ///
+    /// Unknown keys are errors, or warnings when `unknown_is_warning` is set.
+    fn mapping<'a>(
+        &mut self,
+        node: &'a Node,
+        what: &str,
+        allowed: &[&str],
+        unknown_is_warning: bool,
+    ) -> Option<Vec<(&'a Node, &'a Node)>> {
+        let NodeKind::Map(pairs) = &node.kind else {
+            self.error(
+                node.location,
+                format!("{} must be a mapping, found {}", what, node.type_name()),
+            );
+            return None;
+        };
+
+        let mut seen: HashMap<&str, Option<(usize, usize)>> = HashMap::new();
+        for (key, _) in pairs {
+            let Some(name) = key.as_str() else {
+                self.error(key.location, format!("keys in {} must be strings", what));
+                continue;
+            };
+            if name == "<<" {
+                continue;
+            }
+            if let Some(first) = seen.insert(name, key.location) {
+                self.error(
+                    key.location,
+                    format!("duplicate key '{}' in {}{}", name, what, first_at(first)),
+                );
+            }
+            if !allowed.contains(&name) {
+                let mut message = format!("unknown key '{}' in {}", name, what);
+                match suggest(name, allowed) {
+                    Some(s) => message.push_str(&format!("; did you mean '{}'?", s)),
+                    None => message.push_str(&format!("; expected one of: {}", allowed.join(", "))),
+                }
+                if unknown_is_warning {
+                    message.push_str(" (ignored)");
+                    self.diagnostics
+                        .push(Diagnostic::warning(key.location, message));
+                } else {
+                    self.error(key.location, message);
+                }
+            }
+        }
+
+        // Keys pulled in through merge keys are checked where they are defined
+        Some(node.entries())
+    }
+
+    fn string(&mut self, key: &str, node: &Node) {
+        if !matches!(node.kind, NodeKind::Scalar(_)) {
+            self.error(
+                node.location,
+                format!("'{}' must be a string, found {}", key, node.type_name()),
+            );
+        }
+    }
+
+    fn string_list(&mut self, key: &str, node: &Node) {
+        let NodeKind::Seq(items) = &node.kind else {
+            self.error(
+                node.location,
+                format!(
+                    "'{}' must be a list of strings, found {}",
+                    key,
+                    node.type_name()
+                ),
+            );
+            return;
+        };
+        for item in items {
+            if !matches!(item.kind, NodeKind::Scalar(_)) {
+                self.error(
+                    item.location,
+                    format!(
+                        "items of '{}' must be strings, found {}",
+                        key,
+                        item.type_name()
+                    ),
+                );
+            }
+        }
+    }
+
+    /// A string, or a possibly nested list of strings (`cmake-args`, `snippet`)
+    fn args(&mut self, key: &str, node: &Node) {
+        match &node.kind {
+            NodeKind::Null | NodeKind::Scalar(_) => {}
+            NodeKind::Seq(items) => {
+                for item in items {
+                    self.args(key, item);
+                }
+            }
+            NodeKind::Map(_) => self.error(
+                node.location,
+                format!(
+                    "'{}' must be a string or a list of strings, found a mapping",
+                    key
+                ),
+            ),
+        }
+    }
+
+    /// A mapping of environment variable names to scalar values
+    fn env(&mut self, node: &Node) {
+        let NodeKind::Map(pairs) = &node.kind else {
+            self.error(
+                node.location,
+                format!("'env' must be a mapping, found {}", node.type_name()),
+            );
+            return;
+        };
+        for (key, value) in pairs {
+            let valid_name = key.as_str().is_some_and(|k| {
+                !k.is_empty()
+                    && !k.starts_with(|c: char| c.is_ascii_digit())
+                    && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if !valid_name {
+                self.error(
+                    key.location,
+                    format!(
+                        "'{}' is not a valid environment variable name",
+                        key.as_str().unwrap_or_default()
+                    ),
+                );
+            }
+            if matches!(value.kind, NodeKind::Seq(_) | NodeKind::Map(_)) {
+                self.error(
+                    value.location,
+                    format!("env values must be strings, found {}", value.type_name()),
+                );
+            }
+        }
+    }
+
+    fn build_config(&mut self, root: &Node) {
+        let Some(entries) = self.mapping(root, "build config", BUILD_KEYS, true) else {
+            return;
+        };
+
+        for (key, value) in entries {
+            match key.as_str() {
+                Some(k @ ("board" | "shield")) => self.string_list(k, value),
+                Some("include") => self.includes(value),
+                _ => {}
+            }
+        }
+    }
+
+    fn includes(&mut self, node: &Node) {
+        let NodeKind::Seq(items) = &node.kind else {
+            self.error(
+                node.location,
+                format!("'include' must be a list, found {}", node.type_name()),
+            );
+            return;
+        };
+
+        // artifact name -> (location, canonical entry) of its first include
+        let mut artifacts: HashMap<String, (Option<(usize, usize)>, String)> = HashMap::new();
+
+        for item in items {
+            let Some(entries) = self.mapping(item, "include entry", INCLUDE_KEYS, false) else {
+                continue;
+            };
+
+            for (key, value) in &entries {
+                match key.as_str() {
+                    Some(k @ ("board" | "shield" | "artifact-name" | "group")) => {
+                        self.string(k, value)
+                    }
+                    Some(k @ ("cmake-args" | "snippet")) => self.args(k, value),
+                    Some(k @ ("tags" | "extra-modules")) => self.string_list(k, value),
+                    Some("env") => self.env(value),
+                    Some("timeout")
+                        if value.as_str().is_none_or(|t| parse_duration(t).is_err()) =>
+                    {
+                        self.error(
+                            value.location,
+                            "'timeout' must be a duration like \"20m\" or a number of seconds",
+                        );
+                    }
+                    Some("priority")
+                        if value.as_str().is_none_or(|p| p.parse::<i32>().is_err()) =>
+                    {
+                        self.error(value.location, "'priority' must be an integer");
+                    }
+                    Some(k @ ("max-flash" | "max-ram"))
+                        if value
+                            .as_str()
+                            .is_none_or(|p| p.parse::<u8>().map_or(true, |p| p > 100)) =>
+                    {
+                        self.error(
+                            value.location,
+                            format!("'{}' must be a percentage from 0 to 100", k),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            let Some(board) = item.get("board") else {
+                self.error(
+                    item.location,
+                    "include entry is missing required key 'board'",
+                );
+                continue;
+            };
+            let (Some(board), shield) = (board.as_str(), item.get("shield")) else {
+                continue;
+            };
+            let artifact = match item.get("artifact-name").and_then(Node::as_str) {
+                Some(name) => name.to_string(),
+                None => BuildTarget::generate_artifact_name(board, shield.and_then(Node::as_str)),
+            };
+
+            let canonical = item.canonical();
+            match artifacts.get(&artifact) {
+                Some((first, existing)) if *existing == canonical => {
+                    self.diagnostics.push(Diagnostic::warning(
+                        item.location,
+                        format!(
+                            "duplicate include entry for '{}'{}",
+                            artifact,
+                            first_at(*first)
+                        ),
+                    ));
+                }
+                Some((first, _)) => self.error(
+                    item.location,
+                    format!(
+                        "duplicate artifact name '{}'{}; set a unique artifact-name",
+                        artifact,
+                        first_at(*first)
+                    ),
+                ),
+                None => {
+                    artifacts.insert(artifact, (item.location, canonical));
+                }
+            }
+        }
+    }
+
+    fn west_yml(&mut self, root: &Node) {
+        let Some(entries) = self.mapping(root, "west.yml", &["manifest"], false) else {
+            return;
+        };
+        let Some((_, manifest)) = entries.iter().find(|(k, _)| k.as_str() == Some("manifest"))
+        else {
+            self.error(root.location, "west.yml is missing required key 'manifest'");
+            return;
+        };
+        let Some(entries) = self.mapping(manifest, "manifest", MANIFEST_KEYS, false) else {
+            return;
+        };
+
+        let mut remotes: Vec<String> = Vec::new();
+        let mut default_remote = None;
+        let mut projects = None;
+
+        for (key, value) in entries {
+            match key.as_str() {
+                Some("remotes") => remotes = self.remotes(value),
+                Some("defaults") => {
+                    let Some(entries) = self.mapping(value, "defaults", DEFAULTS_KEYS, false)
+                    else {
+                        continue;
+                    };
+                    for (key, value) in entries {
+                        if let Some(k) = key.as_str() {
+                            self.string(k, value);
+                        }
+                        if key.as_str() == Some("remote") {
+                            default_remote = Some(value);
+                        }
+                    }
+                }
+                Some("projects") => projects = Some(value),
+                Some("self") => {
+                    if let Some(entries) = self.mapping(value, "self", SELF_KEYS, false) {
+                        for (key, value) in entries {
+                            if key.as_str() == Some("path") {
+                                self.string("path", value);
+                            }
+                        }
+                    }
+                }
+                Some("group-filter") => self.string_list("group-filter", value),
+                _ => {}
+            }
+        }
+
+        if let Some(remote) = default_remote.filter(|r| !remote_known(r, &remotes)) {
+            self.error(
+                remote.location,
+                format!(
+                    "default remote '{}' is not defined in 'remotes'",
+                    remote.as_str().unwrap_or_default()
+                ),
+            );
+        }
+
+        if let Some(projects) = projects {
+            self.projects(projects, &remotes, default_remote.is_some());
+        }
+    }
+
+    fn remotes(&mut self, node: &Node) -> Vec<String> {
+        let NodeKind::Seq(items) = &node.kind else {
+            self.error(
+                node.location,
+                format!("'remotes' must be a list, found {}", node.type_name()),
+            );
+            return Vec::new();
+        };
+
+        let mut names: Vec<String> = Vec::new();
+        for item in items {
+            let Some(entries) = self.mapping(item, "remote", REMOTE_KEYS, false) else {
+                continue;
+            };
+            for (key, value) in &entries {
+                if let Some(k) = key.as_str() {
+                    self.string(k, value);
+                }
+            }
+            for required in REMOTE_KEYS {
+                if item.get(required).is_none() {
+                    self.error(
+                        item.location,
+                        format!("remote is missing required key '{}'", required),
+                    );
+                }
+            }
+            if let Some(name) = item.get("name").and_then(Node::as_str) {
+                if names.iter().any(|n| n == name) {
+                    self.error(item.location, format!("duplicate remote '{}'", name));
+                }
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    fn projects(&mut self, node: &Node, remotes: &[String], has_default_remote: bool) {
+        let NodeKind::Seq(items) = &node.kind else {
+            self.error(
+                node.location,
+                format!("'projects' must be a list, found {}", node.type_name()),
+            );
+            return;
+        };
+
+        let mut names: HashMap<String, Option<(usize, usize)>> = HashMap::new();
+        for item in items {
+            let Some(entries) = self.mapping(item, "project", PROJECT_KEYS, false) else {
+                continue;
+            };
+            for (key, value) in &entries {
+                match key.as_str() {
+                    Some(
+                        k @ ("name" | "description" | "url" | "remote" | "repo-path" | "revision"
+                        | "path"),
+                    ) => self.string(k, value),
+                    Some("clone-depth")
+                        if value.as_str().and_then(|s| s.parse::<u32>().ok()).is_none() =>
+                    {
+                        self.error(
+                            value.location,
+                            format!(
+                                "'clone-depth' must be a positive integer, found {}",
+                                value.type_name()
+                            ),
+                        );
+                    }
+                    Some("groups") => self.string_list("groups", value),
+                    _ => {}
+                }
+            }
+
+            let Some(name) = item.get("name").and_then(Node::as_str) else {
+                self.error(item.location, "project is missing required key 'name'");
+                continue;
+            };
+            if let Some(first) = names.insert(name.to_string(), item.location) {
+                self.error(
+                    item.location,
+                    format!("duplicate project '{}'{}", name, first_at(first)),
+                );
+            }
+
+            match (item.get("remote"), item.get("url")) {
+                (Some(remote), Some(_)) => self.error(
+                    remote.location,
+                    format!("project '{}' sets both 'remote' and 'url'", name),
+                ),
+                (Some(remote), None) if !remote_known(remote, remotes) => self.error(
+                    remote.location,
+                    format!(
+                        "project '{}' uses remote '{}', which is not defined in 'remotes'",
+                        name,
+                        remote.as_str().unwrap_or_default()
+                    ),
+                ),
+                (None, None) if !has_default_remote => self.error(
+                    item.location,
+                    format!(
+                        "project '{}' needs a 'remote' or 'url' (no default remote is set)",
+                        name
+                    ),
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn remote_known(remote: &Node, remotes: &[String]) -> bool {
+    remote
+        .as_str()
+        .is_some_and(|r| remotes.iter().any(|n| n == r))
+}
+
+fn first_at(location: Option<(usize, usize)>) -> String {
+    match location {
+        Some((line, _)) => format!(" (first defined on line {})", line),
+        None => String::new(),
+    }
+}
+
+/// Suggest the closest allowed key for a likely typo
+fn suggest<'a>(key: &str, allowed: &[&'a str]) -> Option<&'a str> {
+    allowed
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_build_config() {
+        let yaml = r#"
+common: &common
+  group: central
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+    <<: *common
+  - board: nice_nano_v2
+    shield: corne_right
+    cmake-args: [-DFOO=y, [-DBAR=y]]
+    tags: [split]
+"#;
+        let diagnostics = validate_build_config(BuildConfigFormat::Yaml, yaml);
+        assert!(errors(&diagnostics).is_empty(), "{:?}", diagnostics);
+        // The anchor holder is reported but does not fail validation
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].location, Some((2, 1)));
+    }
+
+    #[test]
+    fn test_unknown_key_and_wrong_type() {
+        let yaml = "include:\n  - board: nice_nano_v2\n    shild: corne_left\n    tags: split\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(
+            errors,
+            vec![
+                "3:5: error: unknown key 'shild' in include entry; did you mean 'shield'?",
+                "4:11: error: 'tags' must be a list of strings, found a string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_priority() {
+        let yaml = "include:\n  - board: a\n    priority: 10\n  - board: b\n    priority: high\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(errors, vec!["5:15: error: 'priority' must be an integer"]);
+    }
+
+    #[test]
+    fn test_memory_thresholds() {
+        let yaml = "include:\n  - board: a\n    max-flash: 95\n    max-ram: 0\n  - board: b\n    max-flash: 101\n    max-ram: most\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(
+            errors,
+            vec![
+                "6:16: error: 'max-flash' must be a percentage from 0 to 100",
+                "7:14: error: 'max-ram' must be a percentage from 0 to 100",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_entries() {
+        let yaml = "include:\n  - board: nice_nano_v2\n    env:\n      1BAD: x\n      OK: [a]\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(
+            errors,
+            vec![
+                "4:7: error: '1BAD' is not a valid environment variable name",
+                "5:11: error: env values must be strings, found a list",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_board() {
+        let yaml = "include:\n  - shield: corne_left\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(
+            errors,
+            vec!["2:5: error: include entry is missing required key 'board'"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_artifact_names() {
+        let yaml = r#"
+include:
+  - board: nice_nano_v2
+    shield: corne_left
+  - board: nice_nano_v2
+    shield: corne_left
+    cmake-args: -DFOO=y
+  - board: xiao
+    artifact-name: corne_left-nice_nano_v2-zmk
+"#;
+        let errors = errors(&validate_build_config(BuildConfigFormat::Yaml, yaml));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("5:5: error: duplicate artifact name"));
+        assert!(errors[0].contains("first defined on line 3"));
+        assert!(errors[1].starts_with("8:5:"));
+    }
+
+    #[test]
+    fn test_syntax_error_location() {
+        let diagnostics = validate_build_config(BuildConfigFormat::Yaml, "include:\n  - [a\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].location.is_some());
+    }
+
+    #[test]
+    fn test_json_build_config() {
+        let json =
+            "{\n\t\"include\": [\n\t\t{\"board\": \"nice_nano_v2\", \"shild\": \"x\"}\n\t]\n}\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Json, json));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("3:29: error: unknown key 'shild'"));
+    }
+
+    #[test]
+    fn test_toml_build_config() {
+        let toml = "[[include]]\nboard = \"nice_nano_v2\"\nartifact = \"x\"\n";
+        let errors = errors(&validate_build_config(BuildConfigFormat::Toml, toml));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("error: unknown key 'artifact'"));
+
+        let diagnostics = validate_build_config(BuildConfigFormat::Toml, "include = [\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].location.map(|(line, _)| line), Some(2));
+    }
+
+    #[test]
+    fn test_valid_west_yml() {
+        let yaml = r#"
+manifest:
+  defaults:
+    revision: v0.3
+  remotes:
+    - name: zmkfirmware
+      url-base: https://github.com/zmkfirmware
+  projects:
+    - name: zmk
+      remote: zmkfirmware
+      import: app/west.yml
+  self:
+    path: config
+"#;
+        let diagnostics = validate_west_yml(yaml);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_west_yml_errors() {
+        let yaml = r#"
+manifest:
+  remotes:
+    - name: zmkfirmware
+      url-base: https://github.com/zmkfirmware
+  projects:
+    - name: zmk
+      remote: zmkfirmwre
+      revison: main
+    - name: zmk
+      remote: zmkfirmware
+    - name: helpers
+  self:
+    path: config
+"#;
+        let errors = errors(&validate_west_yml(yaml));
+        assert_eq!(
+            errors,
+            vec![
+                "9:7: error: unknown key 'revison' in project; did you mean 'revision'?",
+                "8:15: error: project 'zmk' uses remote 'zmkfirmwre', which is not defined in 'remotes'",
+                "10:7: error: duplicate project 'zmk' (first defined on line 7)",
+                "12:7: error: project 'helpers' needs a 'remote' or 'url' (no default remote is set)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_west_yml_missing_manifest() {
+        let errors = errors(&validate_west_yml("projects: []\n"));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[1].contains("missing required key 'manifest'"));
+    }
+}
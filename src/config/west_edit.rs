@@ -0,0 +1,208 @@
+//! In-place edits of west.yml that keep its formatting and comments.
+//!
+//! Edits work line by line on the block-style layout west.yml files use,
+//! so everything but the changed value is written back untouched.
+
+use anyhow::Result;
+
+/// Set a project's `revision:` in west.yml contents
+///
+/// An existing value is replaced (keeping its quotes and any trailing
+/// comment); otherwise a `revision:` line is added below the project's name.
+pub fn set_revision(content: &str, project: &str, revision: &str) -> Result<String> {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let Some(item) = find_project(&lines, project) else {
+        anyhow::bail!("Project '{}' not found in west.yml", project);
+    };
+
+    match item.revision_line {
+        Some(index) => {
+            let line = &lines[index];
+            let (key, rest) = line.split_at(line.find(':').expect("revision key") + 1);
+            let value_start = rest.len() - rest.trim_start().len();
+            let (value, comment) = split_comment(rest.trim_start());
+            let new_value = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => format!("{}{}{}", q, revision, q),
+                _ => quote_if_needed(revision),
+            };
+            lines[index] = format!("{}{}{}{}", key, &rest[..value_start], new_value, comment);
+        }
+        None => {
+            let line = format!(
+                "{}revision: {}",
+                " ".repeat(item.key_column),
+                quote_if_needed(revision)
+            );
+            lines.insert(item.name_line + 1, line);
+        }
+    }
+
+    let mut edited = lines.join("\n");
+    if content.ends_with('\n') {
+        edited.push('\n');
+    }
+    Ok(edited)
+}
+
+/// Where a project's keys live in the file
+struct ProjectItem {
+    name_line: usize,
+    revision_line: Option<usize>,
+    /// Column of the item's keys
+    key_column: usize,
+}
+
+fn find_project(lines: &[String], project: &str) -> Option<ProjectItem> {
+    let projects_line = lines
+        .iter()
+        .position(|l| uncommented(l).trim_start().starts_with("projects:"))?;
+
+    // Split the block under `projects:` into `- ` items
+    let mut item_indent = None;
+    let mut items: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate().skip(projects_line + 1) {
+        let text = uncommented(line);
+        if text.trim().is_empty() {
+            continue;
+        }
+        let indent = text.len() - text.trim_start().len();
+        let is_item = text.trim_start().starts_with('-');
+        let item_indent = *item_indent.get_or_insert(indent);
+        if indent < item_indent || (indent == item_indent && !is_item) {
+            break;
+        }
+        if indent == item_indent {
+            let key_column =
+                indent + 1 + (text[indent + 1..].len() - text[indent + 1..].trim_start().len());
+            items.push((index, key_column));
+        }
+    }
+
+    items
+        .iter()
+        .enumerate()
+        .find_map(|(i, &(start, key_column))| {
+            let end = items
+                .get(i + 1)
+                .map(|&(next, _)| next)
+                .unwrap_or_else(|| block_end(lines, start, key_column));
+            let key_at = |index: usize, key: &str| {
+                let line = uncommented(&lines[index]);
+                let in_column = line.get(key_column..)?;
+                let after = in_column.strip_prefix(key)?.strip_prefix(':')?;
+                (index == start || line[..key_column].trim().is_empty()).then_some(after)
+            };
+
+            let name_line = (start..end).find(|&index| {
+                key_at(index, "name").is_some_and(|value| unquote(value.trim()) == project)
+            })?;
+            Some(ProjectItem {
+                name_line,
+                revision_line: (start..end).find(|&index| key_at(index, "revision").is_some()),
+                key_column,
+            })
+        })
+}
+
+/// First line after the last project item
+fn block_end(lines: &[String], start: usize, key_column: usize) -> usize {
+    (start + 1..lines.len())
+        .find(|&index| {
+            let text = uncommented(&lines[index]);
+            !text.trim().is_empty() && text.len() - text.trim_start().len() < key_column
+        })
+        .unwrap_or(lines.len())
+}
+
+/// A line without its comment
+fn uncommented(line: &str) -> &str {
+    split_comment(line).0
+}
+
+/// Split a trailing ` # comment` off a line (outside of quotes)
+fn split_comment(line: &str) -> (&str, &str) {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if i == 0 || line[..i].ends_with(char::is_whitespace) => {
+                let value = line[..i].trim_end();
+                return (value, &line[value.len()..]);
+            }
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// Quote values YAML would otherwise read as numbers (e.g. `1234567` or `1e10`)
+fn quote_if_needed(value: &str) -> String {
+    if value.parse::<f64>().is_ok() || value.contains(": ") {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEST_YML: &str = "\
+manifest:
+  remotes:
+    - name: zmkfirmware
+      url-base: https://github.com/zmkfirmware
+  projects:
+    - name: zmk
+      remote: zmkfirmware
+      revision: v0.2  # pinned for studio
+      import: app/west.yml
+    - name: helpers
+      url: https://github.com/example/helpers
+  self:
+    path: config
+";
+
+    #[test]
+    fn test_replace_revision() {
+        let edited = set_revision(WEST_YML, "zmk", "v0.3").unwrap();
+        assert!(edited.contains("      revision: v0.3  # pinned for studio\n"));
+        assert_eq!(edited.lines().count(), WEST_YML.lines().count());
+        assert_eq!(
+            edited.replace("v0.3", "v0.2"),
+            WEST_YML,
+            "only the revision changes"
+        );
+    }
+
+    #[test]
+    fn test_insert_revision() {
+        let edited = set_revision(WEST_YML, "helpers", "1234567").unwrap();
+        assert!(edited.contains(
+            "    - name: helpers\n      revision: \"1234567\"\n      url: https://github.com/example/helpers\n"
+        ));
+    }
+
+    #[test]
+    fn test_keeps_quotes() {
+        let content = "manifest:\n  projects:\n  - name: \"zmk\"\n    revision: 'main'\n";
+        let edited = set_revision(content, "zmk", "v0.3").unwrap();
+        assert_eq!(
+            edited,
+            "manifest:\n  projects:\n  - name: \"zmk\"\n    revision: 'v0.3'\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_project() {
+        assert!(set_revision(WEST_YML, "nope", "main").is_err());
+        // `name` of a remote is not a project
+        assert!(set_revision(WEST_YML, "zmkfirmware", "main").is_err());
+    }
+}
@@ -1,8 +1,313 @@
 use anyhow::{Context, Result};
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use super::lfz_toml::WorkspaceKey;
+
+/// How deep `import:` chains are followed when resolving projects
+const MAX_IMPORT_DEPTH: usize = 4;
+
+/// Container directory local projects are mounted under for `west update`
+const LOCAL_PROJECTS_DIR: &str = "/lfz/local";
+
+/// The `manifest` section of a west.yml
+#[derive(Debug, Default, Deserialize)]
+pub struct WestManifest {
+    #[serde(default)]
+    pub remotes: Vec<WestRemote>,
+
+    #[serde(default)]
+    pub defaults: WestDefaults,
+
+    #[serde(default)]
+    pub projects: Vec<WestProject>,
+}
+
+/// A named remote that projects can fetch from
+#[derive(Debug, Clone, Deserialize)]
+pub struct WestRemote {
+    pub name: String,
+
+    #[serde(rename = "url-base")]
+    pub url_base: String,
+}
+
+/// Manifest-wide defaults for projects
+#[derive(Debug, Default, Deserialize)]
+pub struct WestDefaults {
+    pub remote: Option<String>,
+
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WestManifestFile {
+    manifest: WestManifest,
+}
+
+/// A single west project
+#[derive(Debug, Clone, Deserialize)]
+pub struct WestProject {
+    pub name: String,
+
+    /// Checkout path relative to the workspace (defaults to the name)
+    pub path: Option<String>,
+
+    pub remote: Option<String>,
+
+    #[serde(rename = "repo-path")]
+    pub repo_path: Option<String>,
+
+    pub url: Option<String>,
+
+    /// Branch, tag or commit to check out
+    pub revision: Option<String>,
+
+    /// `true`, a manifest file path, or a mapping with a `file` key
+    pub import: Option<serde_yaml::Value>,
+}
+
+impl WestProject {
+    /// Checkout path relative to the workspace root
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Manifest file (relative to the project) this project imports, if any
+    pub fn import_file(&self) -> Option<&str> {
+        match self.import.as_ref()? {
+            serde_yaml::Value::Bool(true) => Some("west.yml"),
+            serde_yaml::Value::String(file) => Some(file),
+            serde_yaml::Value::Mapping(map) => Some(
+                map.get("file")
+                    .and_then(|f| f.as_str())
+                    .unwrap_or("west.yml"),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Whether a project of the manifest this project imports is taken
+    /// in, as the import's `name-allowlist` and `name-blocklist` say
+    fn imports(&self, name: &str) -> bool {
+        let Some(serde_yaml::Value::Mapping(map)) = &self.import else {
+            return true;
+        };
+        let listed = |key: &str| match map.get(key) {
+            Some(serde_yaml::Value::String(only)) => Some(only == name),
+            Some(serde_yaml::Value::Sequence(names)) => {
+                Some(names.iter().any(|n| n.as_str() == Some(name)))
+            }
+            _ => None,
+        };
+        listed("name-allowlist").unwrap_or(true) && !listed("name-blocklist").unwrap_or(false)
+    }
+}
+
+impl WestManifest {
+    /// URL west fetches a project from (explicit `url`, or remote + repo path)
+    pub fn fetch_url(&self, project: &WestProject) -> Option<String> {
+        if let Some(url) = &project.url {
+            return Some(url.clone());
+        }
+        let remote_name = project.remote.as_ref().or(self.defaults.remote.as_ref())?;
+        let remote = self.remotes.iter().find(|r| &r.name == remote_name)?;
+        Some(format!(
+            "{}/{}",
+            remote.url_base.trim_end_matches('/'),
+            project.repo_path.as_deref().unwrap_or(&project.name)
+        ))
+    }
+
+    /// Revision west checks out for a project (west's own default is `master`)
+    pub fn revision<'a>(&'a self, project: &'a WestProject) -> &'a str {
+        project
+            .revision
+            .as_deref()
+            .or(self.defaults.revision.as_deref())
+            .unwrap_or("master")
+    }
+
+    /// Whether a project is fetched from a directory on this machine
+    pub fn is_local(&self, project: &WestProject) -> bool {
+        self.fetch_url(project)
+            .is_some_and(|url| local_path(&url).is_some())
+    }
+
+    /// Projects whose URL points at a directory on this machine
+    ///
+    /// Relative paths are resolved against `manifest_dir` (the directory
+    /// containing west.yml). Such projects can't be fetched inside the
+    /// container unless they are mounted into it.
+    pub fn local_projects(&self, manifest_dir: &Path) -> Vec<LocalProject> {
+        self.projects
+            .iter()
+            .filter_map(|project| {
+                let url = self.fetch_url(project)?;
+                let path = local_path(&url)?;
+                let host_path = manifest_dir.join(path).canonicalize().ok()?;
+                host_path.is_dir().then(|| LocalProject {
+                    name: project.name.clone(),
+                    url,
+                    host_path,
+                    workspace_path: project.path().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Load the manifest from a west.yml file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse west.yml contents
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: WestManifestFile = serde_yaml::from_str(content)?;
+        Ok(file.manifest)
+    }
+
+    /// All projects, including those pulled in through `import:` from
+    /// manifests already checked out in the workspace
+    ///
+    /// Earlier definitions win, like in west itself.
+    pub fn resolved_projects(&self, workspace: &Path) -> Vec<WestProject> {
+        let mut projects = Vec::new();
+        self.collect_projects(workspace, 0, &mut projects);
+        projects
+    }
+
+    fn collect_projects(&self, workspace: &Path, depth: usize, out: &mut Vec<WestProject>) {
+        for project in &self.projects {
+            if !out.iter().any(|p| p.name == project.name) {
+                out.push(project.clone());
+            }
+        }
+
+        if depth >= MAX_IMPORT_DEPTH {
+            return;
+        }
+        for project in &self.projects {
+            let Some(file) = project.import_file() else {
+                continue;
+            };
+            let path = workspace.join(project.path()).join(file);
+            // Imports are only resolvable once the workspace is populated
+            let Ok(imported) = Self::load(&path) else {
+                continue;
+            };
+            let mut found = Vec::new();
+            imported.collect_projects(workspace, depth + 1, &mut found);
+            for imported in found {
+                if project.imports(&imported.name) && !out.iter().any(|p| p.name == imported.name) {
+                    out.push(imported);
+                }
+            }
+        }
+    }
+}
+
+/// Interpret a west URL as a local path, if it is one
+fn local_path(url: &str) -> Option<&Path> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(Path::new(path));
+    }
+    (url.starts_with('/') || url.starts_with("./") || url.starts_with("../"))
+        .then(|| Path::new(url))
+}
+
+/// A west project whose sources live in a local directory
+#[derive(Debug, Clone)]
+pub struct LocalProject {
+    pub name: String,
+
+    /// URL as written in west.yml
+    pub url: String,
+
+    pub host_path: PathBuf,
+
+    /// Project path relative to the workspace root
+    pub workspace_path: String,
+}
+
+impl LocalProject {
+    /// Where the project is mounted for `west init`/`west update`
+    pub fn container_path(&self) -> String {
+        format!("{}/{}", LOCAL_PROJECTS_DIR, self.name)
+    }
+
+    /// Git config entries redirecting fetches of the URL to the mount
+    pub fn git_config(&self) -> Vec<(String, String)> {
+        vec![(
+            format!("url.{}.insteadOf", self.container_path()),
+            self.url.clone(),
+        )]
+    }
+
+    /// Mount the working tree over the checkout for builds, so
+    /// uncommitted changes are picked up without re-running west update
+    pub fn as_override(&self) -> ProjectOverride {
+        ProjectOverride {
+            name: self.name.clone(),
+            host_path: self.host_path.clone(),
+            workspace_path: self.workspace_path.clone(),
+        }
+    }
+}
+
+/// A west project replaced by a local directory for one build
+#[derive(Debug, Clone)]
+pub struct ProjectOverride {
+    pub name: String,
+
+    /// Local directory mounted in place of the project
+    pub host_path: PathBuf,
+
+    /// Project path relative to the workspace root
+    pub workspace_path: String,
+}
+
+impl ProjectOverride {
+    /// Parse a `name=path` spec and locate the project in the workspace
+    pub fn resolve(spec: &str, projects: &[WestProject]) -> Result<Self> {
+        let Some((name, path)) = spec
+            .split_once('=')
+            .map(|(n, p)| (n.trim(), p.trim()))
+            .filter(|(n, p)| !n.is_empty() && !p.is_empty())
+        else {
+            anyhow::bail!("Invalid module override '{}': expected 'name=path'", spec);
+        };
+
+        let Some(project) = projects.iter().find(|p| p.name == name) else {
+            let mut names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+            names.sort();
+            anyhow::bail!(
+                "Unknown west project '{}'. Known projects: {}",
+                name,
+                names.join(", ")
+            );
+        };
+
+        let host_path = Path::new(path)
+            .canonicalize()
+            .with_context(|| format!("Override path '{}' for '{}' not found", path, name))?;
+        if !host_path.is_dir() {
+            anyhow::bail!("Override path '{}' for '{}' is not a directory", path, name);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            host_path,
+            workspace_path: project.path().to_string(),
+        })
+    }
+}
+
 /// Get git repository info for cache keying
 /// Returns (remote_url or repo_path, branch_or_commit)
 pub fn get_git_info(config_dir: &Path) -> Result<(String, String)> {
@@ -81,10 +386,21 @@ pub fn get_git_info(config_dir: &Path) -> Result<(String, String)> {
     Ok((repo_id, branch_or_commit))
 }
 
-/// Compute a workspace hash based on git repo + branch
-pub fn hash_workspace_key(config_dir: &Path) -> Result<String> {
-    let (repo_id, branch) = get_git_info(config_dir)?;
-    let key = format!("{}:{}", repo_id, branch);
+/// Compute a workspace hash from what workspaces are keyed by
+pub fn hash_workspace_key(config_dir: &Path, key: WorkspaceKey) -> Result<String> {
+    let key = match key {
+        WorkspaceKey::Manifest => {
+            let path = config_dir.join("west.yml");
+            let contents =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            format!("west.yml:{}", hex::encode(Sha256::digest(contents)))
+        }
+        WorkspaceKey::Branch => {
+            let (repo_id, branch) = get_git_info(config_dir)?;
+            format!("{}:{}", repo_id, branch)
+        }
+        WorkspaceKey::Repo => format!("repo:{}", get_git_info(config_dir)?.0),
+    };
 
     let mut hasher = Sha256::new();
     hasher.update(key.as_bytes());
@@ -109,7 +425,7 @@ pub fn format_project_display(config_dir: &Path) -> Result<String> {
 }
 
 /// Extract repository name from a git URL or path
-fn extract_repo_name(repo_id: &str) -> String {
+pub fn extract_repo_name(repo_id: &str) -> String {
     // Remove trailing .git if present
     let cleaned = repo_id.trim_end_matches(".git");
 
@@ -172,4 +488,167 @@ mod tests {
         );
         assert_eq!(extract_repo_name("/home/user/zmk-config"), "zmk-config");
     }
+
+    const WEST_YML: &str = r#"
+manifest:
+  remotes:
+    - name: zmkfirmware
+      url-base: https://github.com/zmkfirmware
+  projects:
+    - name: zmk
+      remote: zmkfirmware
+      revision: main
+      import: app/west.yml
+    - name: helpers
+      path: modules/helpers
+      url: https://example.com/helpers
+"#;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = WestManifest::parse(WEST_YML).unwrap();
+        assert_eq!(manifest.projects[0].path(), "zmk");
+        assert_eq!(manifest.projects[0].import_file(), Some("app/west.yml"));
+        assert_eq!(manifest.projects[1].path(), "modules/helpers");
+        assert_eq!(manifest.projects[1].import_file(), None);
+    }
+
+    #[test]
+    fn test_resolved_projects_follow_imports() {
+        let workspace = tempfile::tempdir().unwrap();
+        let app = workspace.path().join("zmk/app");
+        fs::create_dir_all(&app).unwrap();
+        fs::write(
+            app.join("west.yml"),
+            "manifest:\n  projects:\n    - name: zephyr\n      url: https://x/zephyr\n    - name: helpers\n      url: https://y\n",
+        )
+        .unwrap();
+
+        let manifest = WestManifest::parse(WEST_YML).unwrap();
+        let projects = manifest.resolved_projects(workspace.path());
+        let names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["zmk", "helpers", "zephyr"]);
+        // The top-level definition wins
+        assert_eq!(projects[1].path(), "modules/helpers");
+    }
+
+    #[test]
+    fn test_resolved_projects_honor_allowlists() {
+        let workspace = tempfile::tempdir().unwrap();
+        let app = workspace.path().join("zmk/app");
+        let zephyr = workspace.path().join("zephyr");
+        fs::create_dir_all(&app).unwrap();
+        fs::create_dir_all(&zephyr).unwrap();
+        fs::write(
+            app.join("west.yml"),
+            "manifest:\n  projects:\n    - name: zephyr\n      url: https://x/zephyr\n      import:\n        name-allowlist: [cmsis, hal_nordic, lvgl]\n        name-blocklist: lvgl\n",
+        )
+        .unwrap();
+        fs::write(
+            zephyr.join("west.yml"),
+            "manifest:\n  projects:\n    - name: cmsis\n      url: https://x/cmsis\n    - name: hal_nordic\n      url: https://x/hal_nordic\n    - name: hal_stm32\n      url: https://x/hal_stm32\n    - name: lvgl\n      url: https://x/lvgl\n",
+        )
+        .unwrap();
+
+        let manifest = WestManifest::parse(WEST_YML).unwrap();
+        let projects = manifest.resolved_projects(workspace.path());
+        let names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["zmk", "helpers", "zephyr", "cmsis", "hal_nordic"]
+        );
+    }
+
+    #[test]
+    fn test_project_override() {
+        let manifest = WestManifest::parse(WEST_YML).unwrap();
+        let local = tempfile::tempdir().unwrap();
+        let spec = format!("helpers={}", local.path().display());
+
+        let over = ProjectOverride::resolve(&spec, &manifest.projects).unwrap();
+        assert_eq!(over.workspace_path, "modules/helpers");
+        assert_eq!(over.host_path, local.path().canonicalize().unwrap());
+
+        assert!(ProjectOverride::resolve("helpers", &manifest.projects).is_err());
+        let err = ProjectOverride::resolve("nope=/tmp", &manifest.projects).unwrap_err();
+        assert!(err.to_string().contains("Known projects: helpers, zmk"));
+    }
+
+    #[test]
+    fn test_local_projects() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config");
+        let module = root.path().join("my-module");
+        fs::create_dir_all(&config).unwrap();
+        fs::create_dir_all(&module).unwrap();
+
+        let manifest = WestManifest::parse(&format!(
+            r#"
+manifest:
+  defaults:
+    remote: local
+  remotes:
+    - name: local
+      url-base: file://{}
+  projects:
+    - name: relative
+      url: ../my-module
+      path: modules/relative
+    - name: my-module
+    - name: missing
+      url: ../does-not-exist
+    - name: zmk
+      url: https://github.com/zmkfirmware/zmk
+"#,
+            root.path().display()
+        ))
+        .unwrap();
+
+        let local = manifest.local_projects(&config);
+        let names: Vec<&str> = local.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["relative", "my-module"]);
+        assert_eq!(local[0].host_path, module.canonicalize().unwrap());
+        assert_eq!(local[0].workspace_path, "modules/relative");
+        assert_eq!(
+            local[0].git_config(),
+            vec![(
+                "url./lfz/local/relative.insteadOf".to_string(),
+                "../my-module".to_string()
+            )]
+        );
+        assert_eq!(
+            local[1].url,
+            format!("file://{}/my-module", root.path().display())
+        );
+    }
+
+    #[test]
+    fn test_hash_workspace_key() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("west.yml"), "manifest:\n  projects: []").unwrap();
+        fs::write(second.path().join("west.yml"), "manifest:\n  projects: []").unwrap();
+
+        let key = |dir: &tempfile::TempDir, key| hash_workspace_key(dir.path(), key).unwrap();
+        // Same modules, different repositories
+        assert_eq!(
+            key(&first, WorkspaceKey::Manifest),
+            key(&second, WorkspaceKey::Manifest)
+        );
+        assert_ne!(
+            key(&first, WorkspaceKey::Branch),
+            key(&second, WorkspaceKey::Branch)
+        );
+        assert_ne!(
+            key(&first, WorkspaceKey::Branch),
+            key(&first, WorkspaceKey::Repo)
+        );
+        assert_eq!(key(&first, WorkspaceKey::Manifest).len(), 16);
+
+        fs::write(second.path().join("west.yml"), "manifest:\n  remotes: []").unwrap();
+        assert_ne!(
+            key(&first, WorkspaceKey::Manifest),
+            key(&second, WorkspaceKey::Manifest)
+        );
+    }
 }
@@ -1,38 +1,167 @@
 mod command;
+pub mod project_image;
+pub mod remote;
+pub mod resources;
+pub mod shares;
+pub mod vm;
 
 pub use command::ContainerCommand;
+pub use resources::ResourceLimits;
 
 use anyhow::{Context, Result};
-use std::process::Command;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use crate::paths;
 
 /// Default ZMK build image
 pub const DEFAULT_IMAGE: &str = "zmkfirmware/zmk-build-arm:stable";
 
+/// Images `lfz image` manages: ZMK's build images
+pub const BUILD_IMAGES: &str = "zmkfirmware/zmk-build-*";
+
+/// A local image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub id: String,
+    /// `repository:tag` names; none for an image a later pull untagged
+    pub tags: Vec<String>,
+    /// `repository@sha256:...` digests of the registries it was pulled from
+    pub digests: Vec<String>,
+    pub size: u64,
+}
+
+impl Image {
+    /// Format `image inspect` prints one image per line in. The lists are
+    /// printed as JSON: nerdctl's templates have no `join`.
+    const INSPECT_FORMAT: &'static str =
+        "{{.Id}}\t{{.Size}}\t{{json .RepoTags}}\t{{json .RepoDigests}}";
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let id = fields.next()?.trim().to_string();
+        let size = fields.next()?.trim().parse().ok()?;
+        // `null` without any
+        let list = |field: Option<&str>| -> Vec<String> {
+            serde_json::from_str::<Option<Vec<String>>>(field.unwrap_or_default().trim())
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|s| !s.is_empty() && !s.contains("<none>"))
+                .collect()
+        };
+        let tags = list(fields.next());
+        let digests = list(fields.next());
+        (!id.is_empty()).then_some(Self {
+            id,
+            tags,
+            digests,
+            size,
+        })
+    }
+
+    /// Whether an ID, digest or tag (as `image_digest` returns them) names
+    /// this image
+    pub fn matches(&self, reference: &str) -> bool {
+        self.id == reference
+            || self.digests.iter().any(|d| d == reference)
+            || self.tags.iter().any(|t| t == reference)
+    }
+
+    /// Its first tag, or its ID shortened as `docker images` shows it
+    pub fn name(&self) -> String {
+        match self.tags.first() {
+            Some(tag) => tag.clone(),
+            None => {
+                let id = self.id.trim_start_matches("sha256:");
+                format!("<untagged> {}", &id[..id.len().min(12)])
+            }
+        }
+    }
+}
+
 /// Supported container runtimes
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Runtime {
     Docker,
     Podman,
+    /// containerd's Docker-compatible CLI (e.g. Rancher Desktop in
+    /// containerd mode)
+    Nerdctl,
+    /// Another Docker-compatible CLI, named with `--runtime-command`
+    Oci,
+}
+
+/// Runtime chosen with `--runtime` or lfz.toml's `runtime`
+static PREFERRED: Mutex<Option<Runtime>> = Mutex::new(None);
+
+/// Command of the `oci` runtime
+static OCI_COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Use this runtime instead of detecting one
+pub fn set_preferred(runtime: Option<Runtime>) {
+    *PREFERRED.lock().unwrap() = runtime;
+}
+
+/// Run this command as the `oci` runtime
+pub fn set_oci_command(command: String) {
+    let _ = OCI_COMMAND.set(command);
 }
 
 impl Runtime {
-    /// Detect available container runtime
-    /// Prefers Podman over Docker as it's daemonless
+    /// Runtimes in the order detection tries them: Podman first, as it's
+    /// daemonless. A generic CLI is only used when chosen.
+    const ALL: [Runtime; 3] = [Runtime::Podman, Runtime::Docker, Runtime::Nerdctl];
+
+    /// The container runtime to use: the one chosen with `--runtime` or in
+    /// lfz.toml, else the last one that worked on this machine, else the
+    /// first installed one that is running (Podman before Docker)
     pub fn detect() -> Result<Self> {
-        // Try podman first
-        if Self::is_available("podman") {
-            return Ok(Runtime::Podman);
+        if let Some(runtime) = *PREFERRED.lock().unwrap() {
+            if runtime == Runtime::Oci && OCI_COMMAND.get().is_none() {
+                anyhow::bail!("The oci runtime needs its command: pass --runtime-command");
+            }
+            if !Self::is_available(runtime.command_name()) {
+                anyhow::bail!(
+                    "{} was chosen as the container runtime, but '{}' isn't installed",
+                    runtime.name(),
+                    runtime.command_name()
+                );
+            }
+            return Ok(runtime);
+        }
+
+        if let Some(runtime) =
+            Self::remembered().filter(|runtime| Self::is_available(runtime.command_name()))
+        {
+            return Ok(runtime);
         }
 
-        // Fall back to docker
-        if Self::is_available("docker") {
-            return Ok(Runtime::Docker);
+        let installed: Vec<Runtime> = Self::ALL
+            .into_iter()
+            .filter(|runtime| Self::is_available(runtime.command_name()))
+            .collect();
+        // With both installed, skip one that isn't set up
+        match installed[..] {
+            [] => {}
+            [runtime] => return Ok(runtime),
+            _ => {
+                let running = installed.iter().find(|runtime| runtime.is_running());
+                return Ok(*running.unwrap_or(&installed[0]));
+            }
         }
 
         anyhow::bail!(
             "No container runtime found. Please install Docker or Podman.\n\
              - Docker: https://docs.docker.com/get-docker/\n\
-             - Podman: https://podman.io/getting-started/installation"
+             - Podman: https://podman.io/getting-started/installation\n\
+             (nerdctl works too, and other Docker-compatible CLIs with --runtime-command)"
         )
     }
 
@@ -54,24 +183,91 @@ impl Runtime {
             .unwrap_or(false)
     }
 
-    /// Ensure the runtime is available and running
+    /// Whether this is Podman running without root, where containers run
+    /// as the host user (`--userns=keep-id`) so the files they write are
+    /// the user's rather than a subordinate UID's
+    pub fn is_rootless(&self) -> bool {
+        static ROOTLESS: OnceLock<bool> = OnceLock::new();
+        *self == Runtime::Podman
+            && *ROOTLESS.get_or_init(|| {
+                self.command()
+                    .args(["info", "--format", "{{.Host.Security.Rootless}}"])
+                    .output()
+                    .is_ok_and(|o| o.status.success() && o.stdout.trim_ascii() == b"true")
+            })
+    }
+
+    /// Ensure the runtime is available and running, and remember it as
+    /// the one that works on this machine
     pub fn ensure_running(&self) -> Result<()> {
+        if !self.is_running() && remote::host(self).is_none() {
+            if let Some(vm) = vm::Vm::stopped(self) {
+                if !console::user_attended() {
+                    anyhow::bail!(
+                        "{} isn't running. Start it with '{}' and try again.",
+                        vm.describe(),
+                        vm.start_command()
+                    );
+                }
+                if crate::output::confirm(&format!("{} isn't running. Start it?", vm.describe()))? {
+                    vm.start(self)?;
+                }
+            }
+        }
         if !self.is_running() {
+            if Self::remembered() == Some(*self) {
+                Self::forget();
+            }
+            if let Some(host) = remote::host(self) {
+                anyhow::bail!(
+                    "{} can't reach the daemon at {}.\n\
+                     Check the connection, or unset DOCKER_HOST (CONTAINER_HOST for Podman) \
+                     or switch the context to build with a local daemon.",
+                    self.name(),
+                    host
+                );
+            }
             anyhow::bail!(
                 "{} is installed but not running.\n\
-                 Please start {} and try again.",
+                 Please start {} and try again, or choose another runtime with --runtime.",
                 self.name(),
                 self.name()
             );
         }
+        // A generic CLI is only ever used when chosen
+        if *self != Runtime::Oci && Self::remembered() != Some(*self) {
+            self.remember();
+        }
         Ok(())
     }
 
+    /// The runtime that last worked on this machine
+    fn remembered() -> Option<Self> {
+        let name = fs::read_to_string(paths::runtime_file().ok()?).ok()?;
+        Self::from_str(name.trim(), true).ok()
+    }
+
+    fn remember(&self) {
+        // Only a hint for the next detection
+        if let Ok(path) = paths::runtime_file() {
+            let _ = fs::create_dir_all(path.parent().unwrap_or(Path::new(".")));
+            let _ = fs::write(path, self.command_name());
+        }
+    }
+
+    fn forget() {
+        if let Ok(path) = paths::runtime_file() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
     /// Get the command name for this runtime
     pub fn command_name(&self) -> &'static str {
         match self {
             Runtime::Docker => "docker",
             Runtime::Podman => "podman",
+            Runtime::Nerdctl => "nerdctl",
+            Runtime::Oci => OCI_COMMAND.get().map_or("oci", String::as_str),
         }
     }
 
@@ -80,6 +276,18 @@ impl Runtime {
         match self {
             Runtime::Docker => "Docker",
             Runtime::Podman => "Podman",
+            Runtime::Nerdctl => "nerdctl",
+            Runtime::Oci => self.command_name(),
+        }
+    }
+
+    /// `run` flags keeping a container off the network and the registry.
+    /// Not every Docker-compatible CLI has `--pull`; images are checked
+    /// before containers start anyway.
+    pub fn offline_args(&self) -> &'static [&'static str] {
+        match self {
+            Runtime::Oci => &["--network", "none"],
+            _ => &["--pull", "never", "--network", "none"],
         }
     }
 
@@ -116,6 +324,141 @@ impl Runtime {
         Ok(())
     }
 
+    /// Save local images to a tarball
+    pub fn save_images(&self, images: &[&str], path: &Path) -> Result<()> {
+        let status = self
+            .command()
+            .args(["save", "-o"])
+            .arg(path)
+            .args(images)
+            .status()
+            .context("Failed to save image")?;
+        if !status.success() {
+            anyhow::bail!("Failed to save image: {}", images.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Load images from a tarball written by `save_images`
+    pub fn load_image(&self, path: &Path) -> Result<()> {
+        let status = self
+            .command()
+            .args(["load", "-i"])
+            .arg(path)
+            .stdout(Stdio::null())
+            .status()
+            .context("Failed to load image")?;
+        if !status.success() {
+            anyhow::bail!("Failed to load image from {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Remove an image by its tags (its ID if it has none), without
+    /// `--force`: an image a container still uses is kept
+    pub fn untag_image(&self, image: &Image) -> Result<()> {
+        let names = if image.tags.is_empty() {
+            std::slice::from_ref(&image.id)
+        } else {
+            image.tags.as_slice()
+        };
+        let output = self
+            .command()
+            .arg("rmi")
+            .args(names)
+            .output()
+            .context("Failed to remove image")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to remove image {}: {}",
+                image.name(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// The local images matching a reference pattern (e.g. `repo/name-*`)
+    pub fn images(&self, reference: &str) -> Result<Vec<Image>> {
+        let output = self
+            .command()
+            .args(["images", "--quiet", "--no-trunc", "--filter"])
+            .arg(format!("reference={}", reference))
+            .output()
+            .context("Failed to list images")?;
+        if !output.status.success() {
+            anyhow::bail!("Failed to list images of {}", reference);
+        }
+        let mut ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let output = self
+            .command()
+            .args(["image", "inspect", "--format", Image::INSPECT_FORMAT])
+            .args(&ids)
+            .output()
+            .context("Failed to inspect images")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to inspect images: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(Image::parse)
+            .collect())
+    }
+
+    /// Remove a local image by ID, with all its tags
+    pub fn remove_image(&self, id: &str) -> Result<()> {
+        let output = self
+            .command()
+            .args(["rmi", "--force", id])
+            .output()
+            .context("Failed to remove image")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to remove image {}: {}",
+                id,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Content digest of a local image (registry digest if pulled, else image ID)
+    pub fn image_digest(&self, image: &str) -> Option<String> {
+        let inspect = |format: &str| {
+            self.command()
+                .args(["image", "inspect", "--format", format, image])
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .filter(|digest| !digest.is_empty() && digest != "<no value>")
+        };
+        inspect("{{index .RepoDigests 0}}").or_else(|| inspect("{{.Id}}"))
+    }
+
+    /// Kill a running container by name, ignoring failures (it may have exited)
+    pub fn kill_container(&self, name: &str) {
+        let _ = self
+            .command()
+            .args(["kill", name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
     /// Ensure an image is available (pull if necessary)
     pub fn ensure_image(&self, image: &str) -> Result<()> {
         if !self.image_exists(image)? {
@@ -129,10 +472,44 @@ impl Runtime {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_image() {
+        let image = Image::parse(
+            "sha256:0123456789abcdef\t3221225472\t[\"zmkfirmware/zmk-build-arm:stable\"]\t\
+             [\"zmkfirmware/zmk-build-arm@sha256:feed\"]",
+        )
+        .unwrap();
+        assert_eq!(image.size, 3 << 30);
+        assert_eq!(image.name(), "zmkfirmware/zmk-build-arm:stable");
+        assert!(image.matches("zmkfirmware/zmk-build-arm@sha256:feed"));
+        assert!(image.matches("sha256:0123456789abcdef"));
+        assert!(image.matches("zmkfirmware/zmk-build-arm:stable"));
+        assert!(!image.matches("sha256:feed"));
+
+        let untagged = Image::parse("sha256:0123456789abcdef\t1024\t[]\tnull").unwrap();
+        assert!(untagged.tags.is_empty() && untagged.digests.is_empty());
+        assert_eq!(untagged.name(), "<untagged> 0123456789ab");
+        // Podman lists untagged images as <none>
+        let untagged = Image::parse("sha256:0123\t1024\t[\"<none>:<none>\"]\t[]").unwrap();
+        assert!(untagged.tags.is_empty());
+        assert_eq!(Image::parse("garbage"), None);
+    }
+
     #[test]
     fn test_command_name() {
         assert_eq!(Runtime::Docker.command_name(), "docker");
         assert_eq!(Runtime::Podman.command_name(), "podman");
+        assert_eq!(Runtime::Nerdctl.command_name(), "nerdctl");
+    }
+
+    #[test]
+    fn test_offline_args() {
+        assert_eq!(
+            Runtime::Docker.offline_args(),
+            ["--pull", "never", "--network", "none"]
+        );
+        // Other CLIs may not have --pull
+        assert_eq!(Runtime::Oci.offline_args(), ["--network", "none"]);
     }
 
     #[test]
@@ -140,9 +517,8 @@ mod tests {
         // This test will pass if either docker or podman is installed
         // It will fail if neither is installed, which is expected behavior
         let result = Runtime::detect();
-        if result.is_ok() {
-            let runtime = result.unwrap();
-            assert!(runtime == Runtime::Docker || runtime == Runtime::Podman);
+        if let Ok(runtime) = result {
+            assert!(Runtime::ALL.contains(&runtime));
         }
     }
 }
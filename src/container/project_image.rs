@@ -0,0 +1,159 @@
+//! A project's own build image, derived from the default one by an
+//! `lfz.Dockerfile` in the project root (to add pip packages or another
+//! toolchain, say).
+//!
+//! The image is tagged with a hash of the Dockerfile, so editing it makes
+//! the next build build a new image; older ones stay until `lfz image
+//! prune`.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Runtime, DEFAULT_IMAGE};
+use crate::config::project::Project;
+use crate::output;
+
+/// The Dockerfile lfz looks for in the project root
+pub const DOCKERFILE: &str = "lfz.Dockerfile";
+
+/// Repository project images are tagged in, as a reference pattern
+pub const PROJECT_IMAGES: &str = "lfz/*";
+
+/// An image built from a project's `lfz.Dockerfile`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectImage {
+    dockerfile: PathBuf,
+    tag: String,
+}
+
+impl ProjectImage {
+    /// The project's image, if it has an `lfz.Dockerfile`
+    pub fn detect(root: &Path) -> Result<Option<Self>> {
+        let dockerfile = root.join(DOCKERFILE);
+        if !dockerfile.is_file() {
+            return Ok(None);
+        }
+        let contents = fs::read(&dockerfile)
+            .with_context(|| format!("Failed to read {}", dockerfile.display()))?;
+        let hash = hex::encode(&Sha256::digest(&contents)[..6]);
+        let tag = format!("lfz/{}:{}", repository_name(root), hash);
+        Ok(Some(Self { dockerfile, tag }))
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Build the image, with the project root as build context
+    pub fn build(&self, runtime: &Runtime) -> Result<()> {
+        let context = self.dockerfile.parent().unwrap_or(Path::new("."));
+        let status = runtime
+            .command()
+            .arg("build")
+            .arg("--file")
+            .arg(&self.dockerfile)
+            .args(["--tag", &self.tag])
+            .arg(context)
+            .status()
+            .context("Failed to build image")?;
+        if !status.success() {
+            anyhow::bail!("Failed to build {} from {}", self.tag, DOCKERFILE);
+        }
+        Ok(())
+    }
+
+    /// Build the image unless this version of the Dockerfile was built
+    /// already. Returns whether it built.
+    pub fn ensure(&self, runtime: &Runtime) -> Result<bool> {
+        if runtime.image_exists(&self.tag)? {
+            return Ok(false);
+        }
+        self.build(runtime)?;
+        Ok(true)
+    }
+}
+
+/// The image a project's builds run in: its own if it has an
+/// `lfz.Dockerfile` (built first if the Dockerfile changed), the default
+/// otherwise
+pub fn build_image(project: &Project, runtime: &Runtime) -> Result<String> {
+    match ProjectImage::detect(&project.root)? {
+        Some(image) => {
+            output::status("Image", &image.tag);
+            if image.ensure(runtime)? {
+                output::success(&format!("Built {} from {}", image.tag, DOCKERFILE));
+            }
+            Ok(image.tag)
+        }
+        None => Ok(DEFAULT_IMAGE.to_string()),
+    }
+}
+
+/// The image a project's builds run in, without building it
+pub fn image_name(project: &Project) -> Result<String> {
+    Ok(ProjectImage::detect(&project.root)?
+        .map_or_else(|| DEFAULT_IMAGE.to_string(), |image| image.tag))
+}
+
+/// A project directory's name as an image repository component: lowercase
+/// letters, digits and single separators
+fn repository_name(root: &Path) -> String {
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut sanitized = String::new();
+    for c in name.chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_end_matches('-');
+    if sanitized.is_empty() {
+        "project".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("Corne_Config");
+        fs::create_dir(&root).unwrap();
+        assert_eq!(ProjectImage::detect(&root).unwrap(), None);
+
+        fs::write(root.join(DOCKERFILE), format!("FROM {}\n", DEFAULT_IMAGE)).unwrap();
+        let image = ProjectImage::detect(&root).unwrap().unwrap();
+        assert!(image.tag().starts_with("lfz/corne-config:"));
+        assert_eq!(image, ProjectImage::detect(&root).unwrap().unwrap());
+
+        // Editing the Dockerfile asks for a new image
+        fs::write(
+            root.join(DOCKERFILE),
+            format!("FROM {}\nRUN pip install keymap-drawer\n", DEFAULT_IMAGE),
+        )
+        .unwrap();
+        let edited = ProjectImage::detect(&root).unwrap().unwrap();
+        assert_ne!(edited.tag(), image.tag());
+    }
+
+    #[test]
+    fn test_repository_name() {
+        assert_eq!(repository_name(Path::new("/src/zmk-config")), "zmk-config");
+        assert_eq!(
+            repository_name(Path::new("/src/My Keyboard!")),
+            "my-keyboard"
+        );
+        assert_eq!(repository_name(Path::new("/src/__")), "project");
+    }
+}
@@ -0,0 +1,229 @@
+//! Daemons on another machine (`DOCKER_HOST=ssh://...`, or a Docker
+//! context or Podman connection pointing elsewhere).
+//!
+//! A remote daemon can't see this machine's directories: a bind mount
+//! would mount an empty directory of the remote machine. Instead each
+//! mounted directory is copied into a named volume before the container
+//! runs, and the directories it may write are copied back once it exits.
+//! Only files changed since the last copy travel, in a tar stream through
+//! the runtime's own connection. Deletions aren't copied either way.
+
+use sha2::{Digest, Sha256};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use super::Runtime;
+
+/// Prefix of the markers in each volume, one per run copying back from it:
+/// files newer than a run's marker were written after its copy in. Runs
+/// sharing a volume (parallel targets) each keep their own.
+const VOLUME_MARKER: &str = ".lfz-synced";
+
+static REMOTE_HOST: OnceLock<Option<String>> = OnceLock::new();
+
+/// The remote daemon's address, if the runtime talks to one
+pub fn host(runtime: &Runtime) -> Option<&'static str> {
+    REMOTE_HOST
+        .get_or_init(|| endpoint(runtime).filter(|uri| is_remote(uri)))
+        .as_deref()
+}
+
+/// The daemon endpoint the runtime's client is pointed at
+pub(super) fn endpoint(runtime: &Runtime) -> Option<String> {
+    let var = match runtime {
+        Runtime::Podman => "CONTAINER_HOST",
+        _ => "DOCKER_HOST",
+    };
+    if let Some(uri) = env::var(var).ok().filter(|uri| !uri.is_empty()) {
+        return Some(uri);
+    }
+    let output = match runtime {
+        Runtime::Docker => runtime
+            .command()
+            .args([
+                "context",
+                "inspect",
+                "--format",
+                "{{.Endpoints.docker.Host}}",
+            ])
+            .output(),
+        Runtime::Podman => runtime
+            .command()
+            .args([
+                "system",
+                "connection",
+                "list",
+                "--format",
+                "{{.Default}}\t{{.URI}}",
+            ])
+            .output(),
+        _ => return None,
+    }
+    .ok()
+    .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match runtime {
+        Runtime::Podman => stdout.lines().find_map(|line| {
+            let (default, uri) = line.split_once('\t')?;
+            (default.trim() == "true").then(|| uri.trim().to_string())
+        }),
+        _ => Some(stdout.trim().to_string()),
+    }
+}
+
+/// Whether a daemon endpoint is on another machine. Podman machines and
+/// Docker Desktop's VM listen on this one and share its directories.
+pub fn is_remote(uri: &str) -> bool {
+    let Some((scheme, rest)) = uri.split_once("://") else {
+        return false;
+    };
+    if !matches!(scheme, "tcp" | "ssh" | "http" | "https") {
+        return false;
+    }
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    !matches!(host, "localhost" | "127.0.0.1" | "::1" | "")
+}
+
+/// An id for one container run, unique among the runs of this machine
+pub fn run_id() -> String {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The volume a host directory is copied into
+pub fn volume_name(host_path: &str) -> String {
+    let hash = Sha256::digest(host_path.as_bytes());
+    format!("lfz-{}", hex::encode(&hash[..6]))
+}
+
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Copying directories between this machine and volumes, as shell
+/// commands run around the container
+pub struct VolumeSync<'a> {
+    /// The runtime's command
+    pub runtime: &'a str,
+    /// Image the copying containers run (anything with tar)
+    pub image: &'a str,
+    /// Where this machine records when each volume was last filled
+    pub markers: PathBuf,
+    /// The run's id (`run_id`), naming its markers
+    pub run: String,
+}
+
+impl VolumeSync<'_> {
+    fn marker(&self, volume: &str) -> String {
+        shell_quote(&self.markers.join(volume).to_string_lossy())
+    }
+
+    /// Copy the files of `host_path` changed since the last copy into its
+    /// volume; for a `writable` volume mark when, for `copy_out`
+    pub fn copy_in(&self, host_path: &Path, volume: &str, writable: bool) -> String {
+        let marker = self.marker(volume);
+        let files = "\\( -type f -o -type l \\) -print";
+        let mark = if writable {
+            format!(" && touch /data/{}-{}", VOLUME_MARKER, self.run)
+        } else {
+            String::new()
+        };
+        format!(
+            "mkdir -p {markers} && touch {marker}.{run} && \
+             (cd {host} && if [ -e {marker} ]; then find . -newer {marker} {files}; \
+             else find . {files}; fi | tar -cf - -T -) | \
+             {runtime} run --rm -i -v {volume}:/data {image} \
+             sh -c 'tar -C /data -xf -{mark}'",
+            markers = shell_quote(&self.markers.to_string_lossy()),
+            run = self.run,
+            host = shell_quote(&host_path.to_string_lossy()),
+            runtime = shell_quote(self.runtime),
+            image = shell_quote(self.image),
+        )
+    }
+
+    /// Copy the files the container wrote in a volume back to `host_path`,
+    /// and drop the run's marker
+    pub fn copy_out(&self, host_path: &Path, volume: &str) -> String {
+        format!(
+            "{runtime} run --rm -v {volume}:/data {image} \
+             sh -c 'cd /data && find . -newer {VOLUME_MARKER}-{run} \\( -type f -o -type l \\) \
+             ! -name \"{VOLUME_MARKER}-*\" -print | tar -cf - -T - && rm -f {VOLUME_MARKER}-{run}' \
+             | tar -C {host} -xf -",
+            runtime = shell_quote(self.runtime),
+            image = shell_quote(self.image),
+            run = self.run,
+            host = shell_quote(&host_path.to_string_lossy()),
+        )
+    }
+
+    /// Record that a volume holds what its directory held when `copy_in`
+    /// started
+    pub fn commit(&self, volume: &str) -> String {
+        let marker = self.marker(volume);
+        format!("mv {marker}.{} {marker}", self.run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_remote() {
+        assert!(is_remote("ssh://me@buildbox"));
+        assert!(is_remote("tcp://10.0.0.5:2376"));
+        assert!(is_remote("ssh://core@[2001:db8::1]:22/run/podman.sock"));
+        assert!(!is_remote("unix:///var/run/docker.sock"));
+        assert!(!is_remote(
+            "ssh://core@127.0.0.1:53214/run/user/501/podman/podman.sock"
+        ));
+        assert!(!is_remote("tcp://localhost:2375"));
+        assert!(!is_remote("npipe:////./pipe/docker_engine"));
+        assert!(!is_remote(""));
+    }
+
+    #[test]
+    fn test_volume_sync() {
+        let sync = VolumeSync {
+            runtime: "docker",
+            image: "zmk",
+            markers: PathBuf::from("/cache/remote"),
+            run: "42-0".to_string(),
+        };
+        let volume = volume_name("/cache/workspaces/0a1b");
+        assert!(volume.starts_with("lfz-") && volume.len() == 16);
+        assert_ne!(volume, volume_name("/cache/workspaces/2c3d"));
+
+        let copy_in = sync.copy_in(Path::new("/it's/here"), &volume, true);
+        assert!(copy_in.contains(r"cd '/it'\''s/here'"));
+        assert!(copy_in.contains(&format!("'docker' run --rm -i -v {}:/data 'zmk'", volume)));
+        assert!(copy_in.contains(&format!("find . -newer '/cache/remote/{}'", volume)));
+        assert!(copy_in.contains("tar -C /data -xf - && touch /data/.lfz-synced-42-0'"));
+        assert!(!sync
+            .copy_in(Path::new("/ro"), &volume, false)
+            .contains("touch /data"));
+        let copy_out = sync.copy_out(Path::new("/out"), &volume);
+        assert!(copy_out.contains("find . -newer .lfz-synced-42-0 "));
+        assert!(copy_out.contains("rm -f .lfz-synced-42-0'"));
+        assert!(copy_out.ends_with("| tar -C '/out' -xf -"));
+        assert_eq!(
+            sync.commit(&volume),
+            format!("mv '/cache/remote/{0}'.42-0 '/cache/remote/{0}'", volume)
+        );
+        assert_ne!(run_id(), run_id());
+    }
+}
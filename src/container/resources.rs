@@ -0,0 +1,222 @@
+//! Host resources: how many targets to build at once by default, and the
+//! share of the host each build container gets (`--cpu-limit`,
+//! `--memory-limit`, `--build-jobs`)
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::process::Command;
+use std::thread;
+
+const MIB: u64 = 1024 * 1024;
+const GIB: u64 = 1024 * MIB;
+
+/// Memory set aside per build: the least a container is given by default,
+/// and what the default number of parallel builds is planned with. ZMK
+/// links need a few hundred MiB and less invites OOM kills.
+const MEMORY_PER_BUILD: u64 = GIB;
+
+/// CPUs per build the default number of parallel builds is planned with;
+/// fewer makes compiling the first targets slower than building in turn
+const CPUS_PER_BUILD: usize = 2;
+
+/// CPU and memory caps for a build container
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// CPUs, possibly fractional
+    pub cpus: Option<f64>,
+    /// Memory in bytes
+    pub memory: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Limits for each of `jobs` containers running side by side: the given
+    /// ones (0 meaning none), or else an equal share of the host. A single
+    /// container gets the whole host.
+    pub fn for_jobs(cpus: Option<f64>, memory: Option<u64>, jobs: usize) -> Self {
+        let shared = jobs > 1;
+        Self {
+            cpus: match cpus {
+                Some(cpus) => Some(cpus).filter(|c| *c > 0.0),
+                None if shared => Some(cpu_share(host_cpus(), jobs)),
+                None => None,
+            },
+            memory: match memory {
+                Some(memory) => Some(memory).filter(|m| *m > 0),
+                None if shared => host_memory().map(|total| memory_share(total, jobs)),
+                None => None,
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none() && self.memory.is_none()
+    }
+
+    /// Runtime arguments applying the limits
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(cpus) = self.cpus {
+            args.extend(["--cpus".to_string(), cpus.to_string()]);
+        }
+        if let Some(memory) = self.memory {
+            args.extend(["--memory".to_string(), format!("{}m", memory / MIB)]);
+        }
+        args
+    }
+
+    /// e.g. "2.5 CPUs, 3.0 GiB"
+    pub fn describe(&self) -> String {
+        let cpus = self.cpus.map(|c| format!("{} CPUs", c));
+        let memory = self
+            .memory
+            .map(|m| format!("{:.1} GiB", m as f64 / GIB as f64));
+        [cpus, memory]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// An equal share of the host's CPUs, at least one, rounded down to a tenth
+fn cpu_share(host_cpus: usize, jobs: usize) -> f64 {
+    let share = host_cpus as f64 / jobs as f64;
+    ((share * 10.0).floor() / 10.0).max(1.0)
+}
+
+/// An equal share of three quarters of the host's memory (leaving the rest
+/// for the host and the runtime), rounded down to a MiB
+fn memory_share(host_memory: u64, jobs: usize) -> u64 {
+    let share = host_memory / 4 * 3 / jobs as u64;
+    (share / MIB * MIB).max(MEMORY_PER_BUILD)
+}
+
+/// How many of `targets` to build at once by default: as many as there are
+/// CPUs and free memory for
+pub fn default_jobs(targets: usize) -> usize {
+    plan_jobs(targets, host_cpus(), available_memory())
+}
+
+fn plan_jobs(targets: usize, cpus: usize, memory: Option<u64>) -> usize {
+    let by_cpus = cpus / CPUS_PER_BUILD;
+    let by_memory = memory.map_or(usize::MAX, |m| (m / MEMORY_PER_BUILD) as usize);
+    targets.min(by_cpus).min(by_memory).max(1)
+}
+
+/// Compile jobs for each of `jobs` builds running side by side, if they
+/// have to share the CPUs
+pub fn build_jobs_share(jobs: usize) -> Option<usize> {
+    (jobs > 1).then(|| (host_cpus() / jobs).max(1))
+}
+
+/// Number of CPUs available to lfz
+pub fn host_cpus() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Total physical memory in bytes, if it can be determined
+pub fn host_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        meminfo("MemTotal")
+    } else if cfg!(target_os = "macos") {
+        let output = Command::new("sysctl")
+            .args(["-n", "hw.memsize"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Memory available for new processes in bytes, if it can be determined
+/// (elsewhere than Linux, half the total)
+pub fn available_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        meminfo("MemAvailable")
+    } else {
+        host_memory().map(|total| total / 2)
+    }
+}
+
+/// A value from /proc/meminfo, in bytes
+fn meminfo(key: &str) -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let kib = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Parse a memory size like "4g", "512m" or "1.5GiB" into bytes; plain
+/// numbers are bytes
+pub fn parse_memory(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let lower = input.to_ascii_lowercase();
+    let number_end = lower
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(number_end);
+    let unit = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => MIB,
+        "g" | "gb" | "gib" => GIB,
+        _ => anyhow::bail!(
+            "Invalid memory size '{}' (expected e.g. \"4g\" or \"512m\")",
+            input
+        ),
+    };
+    let number: f64 = number.parse().with_context(|| {
+        format!(
+            "Invalid memory size '{}' (expected e.g. \"4g\" or \"512m\")",
+            input
+        )
+    })?;
+    Ok((number * unit as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("4g").unwrap(), 4 * GIB);
+        assert_eq!(parse_memory("512M").unwrap(), 512 * MIB);
+        assert_eq!(parse_memory("1.5GiB").unwrap(), 3 * GIB / 2);
+        assert_eq!(parse_memory("0").unwrap(), 0);
+        assert!(parse_memory("4 pages").is_err());
+        assert!(parse_memory("g").is_err());
+    }
+
+    #[test]
+    fn test_shares() {
+        assert_eq!(cpu_share(8, 3), 2.6);
+        assert_eq!(cpu_share(4, 10), 1.0);
+        assert_eq!(memory_share(16 * GIB, 4), 3 * GIB);
+        assert_eq!(memory_share(4 * GIB, 6), GIB);
+    }
+
+    #[test]
+    fn test_plan_jobs() {
+        assert_eq!(plan_jobs(10, 4, Some(16 * GIB)), 2);
+        assert_eq!(plan_jobs(10, 32, Some(3 * GIB)), 3);
+        assert_eq!(plan_jobs(3, 32, None), 3);
+        assert_eq!(plan_jobs(4, 1, Some(GIB / 2)), 1);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ResourceLimits::for_jobs(Some(1.5), Some(2 * GIB), 4);
+        assert_eq!(limits.args(), ["--cpus", "1.5", "--memory", "2048m"]);
+        assert_eq!(limits.describe(), "1.5 CPUs, 2.0 GiB");
+        assert!(ResourceLimits::for_jobs(Some(0.0), Some(0), 4).is_empty());
+        assert!(ResourceLimits::for_jobs(None, None, 1).is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! The Lima-based VMs (Colima, Lima itself) that run Docker on macOS share
+//! only some host directories with the VM, by default the home directory
+//! and (Lima) only read-only. A mount outside them isn't an error: the
+//! container sees an empty directory and the build fails far from the
+//! cause. So lfz checks the directories it mounts against the VM's
+//! configuration first. OrbStack shares the whole filesystem.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{remote, Runtime};
+use crate::config::lfz_toml::expand_home;
+use crate::paths;
+
+/// The VM a runtime's daemon runs in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DockerVm {
+    /// A Colima profile
+    Colima(String),
+    /// A Lima instance
+    Lima(String),
+    OrbStack,
+}
+
+/// A host directory the VM shares
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Share {
+    pub location: PathBuf,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// The part of `colima.yaml` and `lima.yaml` lfz reads
+#[derive(Debug, Default, Deserialize)]
+struct VmConfig {
+    #[serde(default)]
+    mounts: Option<Vec<Share>>,
+}
+
+impl DockerVm {
+    /// The VM behind the daemon the runtime talks to, recognized by where
+    /// its socket lives
+    pub fn detect(runtime: &Runtime) -> Option<Self> {
+        if !cfg!(target_os = "macos") || *runtime == Runtime::Podman {
+            return None;
+        }
+        from_endpoint(&remote::endpoint(runtime)?)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            DockerVm::Colima(profile) => format!("Colima (profile {})", profile),
+            DockerVm::Lima(instance) => format!("Lima (instance {})", instance),
+            DockerVm::OrbStack => "OrbStack".to_string(),
+        }
+    }
+
+    /// The file configuring the VM's shares
+    fn config_file(&self) -> Option<PathBuf> {
+        let home = |var: &str, default: &str| {
+            env::var_os(var)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| expand_home(Path::new(default)))
+        };
+        match self {
+            DockerVm::Colima(profile) => Some(
+                home("COLIMA_HOME", "~/.colima")
+                    .join(profile)
+                    .join("colima.yaml"),
+            ),
+            DockerVm::Lima(instance) => Some(
+                home("LIMA_HOME", "~/.lima")
+                    .join(instance)
+                    .join("lima.yaml"),
+            ),
+            DockerVm::OrbStack => None,
+        }
+    }
+
+    /// The directories the VM shares; `None` when it shares everything
+    pub fn shares(&self) -> Option<Vec<Share>> {
+        let file = self.config_file()?;
+        let config: VmConfig = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default();
+        let home = expand_home(Path::new("~"));
+        let shares = match (self, config.mounts) {
+            (_, Some(mounts)) if !mounts.is_empty() => mounts,
+            // Colima shares the home directory when nothing is configured
+            (DockerVm::Colima(_), _) => vec![Share {
+                location: home,
+                writable: true,
+            }],
+            _ => vec![
+                Share {
+                    location: home,
+                    writable: false,
+                },
+                Share {
+                    location: PathBuf::from("/tmp/lima"),
+                    writable: true,
+                },
+            ],
+        };
+        Some(
+            shares
+                .into_iter()
+                .map(|share| Share {
+                    location: canonical(&expand_home(&share.location)),
+                    ..share
+                })
+                .collect(),
+        )
+    }
+
+    /// Fail unless the VM shares each directory, writable where asked
+    pub fn check(&self, dirs: &[(&Path, bool)]) -> Result<()> {
+        let Some(shares) = self.shares() else {
+            return Ok(());
+        };
+        let missing: Vec<(PathBuf, bool)> = dirs
+            .iter()
+            .map(|(dir, writable)| (canonical(dir), *writable))
+            .filter(|(dir, writable)| !is_shared(&shares, dir, *writable))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = format!(
+            "{} doesn't share these directories with its VM, so containers would see them empty:\n",
+            self.name()
+        );
+        for (dir, writable) in &missing {
+            message.push_str(&format!(
+                "  {}{}\n",
+                paths::anonymize_path(dir),
+                if *writable {
+                    " (needs to be writable)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        let file = self
+            .config_file()
+            .map(|file| paths::anonymize_path(&file))
+            .unwrap_or_default();
+        message.push_str(&format!("Add them to `mounts` in {}:\nmounts:\n", file));
+        for (dir, _) in &missing {
+            message.push_str(&format!(
+                "  - location: \"{}\"\n    writable: true\n",
+                dir.display()
+            ));
+        }
+        message.push_str(&match self {
+            DockerVm::Colima(profile) => {
+                format!("then restart it with 'colima restart {}'.", profile)
+            }
+            _ => "then restart the VM.".to_string(),
+        });
+        anyhow::bail!(message)
+    }
+}
+
+/// The VM a daemon socket belongs to
+fn from_endpoint(uri: &str) -> Option<DockerVm> {
+    let path = uri.strip_prefix("unix://")?;
+    let mut parts = Path::new(path).iter().map(|part| part.to_string_lossy());
+    while let Some(part) = parts.next() {
+        match part.as_ref() {
+            ".colima" => {
+                return parts
+                    .next()
+                    .map(|profile| DockerVm::Colima(profile.to_string()))
+            }
+            ".lima" => {
+                return parts
+                    .next()
+                    .map(|instance| DockerVm::Lima(instance.to_string()))
+            }
+            ".orbstack" => return Some(DockerVm::OrbStack),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether a share covers a directory (`/private/tmp` and `/tmp` are one)
+fn is_shared(shares: &[Share], dir: &Path, writable: bool) -> bool {
+    shares
+        .iter()
+        .any(|share| dir.starts_with(&share.location) && (share.writable || !writable))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Check that the runtime's VM (if it is one of those above) shares lfz's
+/// cache and the project
+pub fn check(runtime: &Runtime, project_root: &Path) -> Result<()> {
+    let Some(vm) = DockerVm::detect(runtime) else {
+        return Ok(());
+    };
+    let cache = paths::cache_dir()?;
+    // Mountable only once it exists
+    fs::create_dir_all(&cache)?;
+    vm.check(&[(&cache, true), (project_root, false)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_endpoint() {
+        assert_eq!(
+            from_endpoint("unix:///Users/me/.colima/default/docker.sock"),
+            Some(DockerVm::Colima("default".to_string()))
+        );
+        assert_eq!(
+            from_endpoint("unix:///Users/me/.lima/docker/sock/docker.sock"),
+            Some(DockerVm::Lima("docker".to_string()))
+        );
+        assert_eq!(
+            from_endpoint("unix:///Users/me/.orbstack/run/docker.sock"),
+            Some(DockerVm::OrbStack)
+        );
+        assert_eq!(from_endpoint("unix:///var/run/docker.sock"), None);
+        assert_eq!(from_endpoint("ssh://me@buildbox"), None);
+    }
+
+    #[test]
+    fn test_is_shared() {
+        let shares = [
+            Share {
+                location: PathBuf::from("/Users/me"),
+                writable: false,
+            },
+            Share {
+                location: PathBuf::from("/tmp/lima"),
+                writable: true,
+            },
+        ];
+        assert!(is_shared(&shares, Path::new("/Users/me/zmk-config"), false));
+        assert!(!is_shared(
+            &shares,
+            Path::new("/Users/me/Library/Caches/lfz"),
+            true
+        ));
+        assert!(is_shared(&shares, Path::new("/tmp/lima/cache"), true));
+        assert!(!is_shared(
+            &shares,
+            Path::new("/Volumes/work/zmk-config"),
+            false
+        ));
+        assert!(!is_shared(&shares, Path::new("/Users/meg"), false));
+    }
+}
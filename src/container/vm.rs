@@ -0,0 +1,150 @@
+//! The virtual machines Podman and Docker Desktop run containers in on
+//! macOS. A stopped one looks like a missing socket to the client, so lfz
+//! recognizes it, offers to start it and waits until the daemon answers.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Runtime;
+use crate::output;
+
+/// How long a started machine may take to answer
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Where Docker Desktop is installed
+const DOCKER_DESKTOP_APP: &str = "/Applications/Docker.app";
+
+/// A machine a runtime's daemon runs in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vm {
+    /// `podman machine`, by name
+    PodmanMachine(String),
+    DockerDesktop,
+}
+
+impl Vm {
+    /// The stopped machine keeping the runtime from running, if that's why
+    pub fn stopped(runtime: &Runtime) -> Option<Self> {
+        if !cfg!(target_os = "macos") {
+            return None;
+        }
+        match runtime {
+            Runtime::Podman => {
+                let output = runtime
+                    .command()
+                    .args([
+                        "machine",
+                        "list",
+                        "--format",
+                        "{{.Name}}\t{{.Default}}\t{{.Running}}",
+                    ])
+                    .output()
+                    .ok()
+                    .filter(|output| output.status.success())?;
+                stopped_machine(&String::from_utf8_lossy(&output.stdout)).map(Vm::PodmanMachine)
+            }
+            Runtime::Docker if Path::new(DOCKER_DESKTOP_APP).exists() => Some(Vm::DockerDesktop),
+            _ => None,
+        }
+    }
+
+    /// The command that starts the machine, to show
+    pub fn start_command(&self) -> String {
+        match self {
+            Vm::PodmanMachine(name) => format!("podman machine start {}", name),
+            Vm::DockerDesktop => "open -a Docker".to_string(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Vm::PodmanMachine(name) => format!("Podman machine '{}'", name),
+            Vm::DockerDesktop => "Docker Desktop".to_string(),
+        }
+    }
+
+    /// Start the machine and wait until the runtime's daemon answers
+    pub fn start(&self, runtime: &Runtime) -> Result<()> {
+        let spinner = output::spinner(&format!("Starting {}", self.describe()));
+        let status = match self {
+            Vm::PodmanMachine(name) => runtime
+                .command()
+                .args(["machine", "start", name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status(),
+            // Returns once the app launches; the daemon comes up later
+            Vm::DockerDesktop => Command::new("open").args(["-a", "Docker"]).status(),
+        }
+        .with_context(|| format!("Failed to run '{}'", self.start_command()))?;
+        if !status.success() {
+            spinner.finish_and_clear();
+            anyhow::bail!(
+                "'{}' failed. Start {} yourself and try again.",
+                self.start_command(),
+                self.describe()
+            );
+        }
+
+        let started = Instant::now();
+        while !runtime.is_running() {
+            if started.elapsed() > STARTUP_TIMEOUT {
+                spinner.finish_and_clear();
+                anyhow::bail!(
+                    "{} didn't become ready within {} seconds",
+                    self.describe(),
+                    STARTUP_TIMEOUT.as_secs()
+                );
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        spinner.finish_and_clear();
+        output::status("Started", &self.describe());
+        Ok(())
+    }
+}
+
+/// The default machine (else the first) in `podman machine list` output,
+/// if it isn't running
+fn stopped_machine(list: &str) -> Option<String> {
+    let machines: Vec<(&str, bool, bool)> = list
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let default = fields.next()? == "true";
+            let running = fields.next()? == "true";
+            Some((name, default, running))
+        })
+        .collect();
+    let (name, _, running) = machines
+        .iter()
+        .find(|(_, default, _)| *default)
+        .or_else(|| machines.first())?;
+    (!running).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopped_machine() {
+        assert_eq!(
+            stopped_machine("podman-machine-default\ttrue\tfalse\n"),
+            Some("podman-machine-default".to_string())
+        );
+        assert_eq!(
+            stopped_machine("podman-machine-default\ttrue\ttrue\n"),
+            None
+        );
+        assert_eq!(
+            stopped_machine("dev\tfalse\ttrue\nbuilds\ttrue\tfalse\n"),
+            Some("builds".to_string())
+        );
+        assert_eq!(stopped_machine(""), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Machine-readable build events (`--log-json`)
+//!
+//! Each event is one JSON object per line, so dashboards and wrapper
+//! scripts can follow a build without scraping terminal output.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::build::memory::MemoryRegion;
+use crate::output;
+
+/// The event log, if one was requested
+static LOG: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Something that happened during a build
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    BuildStarted {
+        targets: Vec<&'a str>,
+        jobs: usize,
+    },
+    TargetStarted {
+        target: &'a str,
+    },
+    /// A ninja step (`[current/total]`)
+    Progress {
+        target: &'a str,
+        current: usize,
+        total: usize,
+    },
+    ArtifactCollected {
+        target: &'a str,
+        path: &'a Path,
+    },
+    TargetFinished {
+        target: &'a str,
+        success: bool,
+        timed_out: bool,
+        cancelled: bool,
+        retries: u32,
+        duration_ms: u128,
+        /// Flash and RAM usage, when the build got as far as linking
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        memory: &'a [MemoryRegion],
+    },
+    Error {
+        target: &'a str,
+        message: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<PathBuf>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line: Option<u32>,
+    },
+    BuildFinished {
+        succeeded: usize,
+        failed: usize,
+        duration_ms: u128,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Start writing events to a file, replacing its contents, or to stdout
+/// for `-` (moving all other output to stderr)
+pub fn open(path: &Path) -> Result<()> {
+    let log: Box<dyn Write + Send> = if path == Path::new("-") {
+        output::set_json_stdout(true);
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+        )
+    };
+    *LOG.lock().unwrap() = Some(log);
+    Ok(())
+}
+
+/// Write an event, if there is an event log
+pub fn emit(event: Event) {
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+    let Some(file) = log.as_mut() else {
+        return;
+    };
+    let _ = writeln!(file, "{}", to_json(&event, SystemTime::now()));
+    let _ = file.flush();
+}
+
+fn to_json(event: &Event, time: SystemTime) -> String {
+    let record = Record {
+        time: humantime::format_rfc3339_millis(time).to_string(),
+        event,
+    };
+    serde_json::to_string(&record).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_json() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let event = Event::Progress {
+            target: "corne_left-nice_nano_v2-zmk",
+            current: 12,
+            total: 345,
+        };
+        assert_eq!(
+            to_json(&event, time),
+            r#"{"time":"2023-11-14T22:13:20.123Z","event":"progress","target":"corne_left-nice_nano_v2-zmk","current":12,"total":345}"#
+        );
+
+        let event = Event::Error {
+            target: "corne_left-nice_nano_v2-zmk",
+            message: "syntax error",
+            file: None,
+            line: None,
+        };
+        assert_eq!(
+            to_json(&event, time),
+            r#"{"time":"2023-11-14T22:13:20.123Z","event":"error","target":"corne_left-nice_nano_v2-zmk","message":"syntax error"}"#
+        );
+    }
+}
@@ -1,13 +1,28 @@
 mod build;
+mod cache;
 mod cli;
 mod config;
 mod container;
+mod events;
+mod notify;
 mod output;
 mod paths;
 mod workspace;
 
 use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use build::sbom::SbomFormat;
+use build::size_report::ReportKind;
+use cli::report::ReportSort;
+use config::build_config::parse_duration;
+use container::resources::parse_memory;
+
+/// Exit status when the workspace lacks modules and `--no-update` (or
+/// `--offline`) kept lfz from fetching them
+const MISSING_MODULES_EXIT: i32 = 3;
 
 /// Build mode determines whether to use pristine or incremental builds
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,21 +46,29 @@ struct BuildArgs {
     #[arg(short, long)]
     shield: Option<String>,
 
+    /// Build a board:shield combination (repeatable, skips build.yaml)
+    #[arg(long = "pair", value_name = "BOARD:SHIELD", conflicts_with_all = ["board", "shield"])]
+    pairs: Vec<String>,
+
     /// Output directory for firmware files
     #[arg(short, long, default_value = "zmk-target")]
     output: String,
 
-    /// Number of parallel builds (default: number of targets)
+    /// Number of parallel builds (default: as many as the host's CPUs and free
+    /// memory allow)
     #[arg(short, long)]
     jobs: Option<usize>,
 
-    /// Suppress build output
-    #[arg(long)]
+    /// Print only the final summary on stdout; progress, errors and build
+    /// output go to stderr (full logs are still saved, see `lfz logs`)
+    #[arg(short, long)]
     quiet: bool,
 
-    /// Stream real-time build output for each target
-    #[arg(short, long)]
-    verbose: bool,
+    /// Stream real-time build output for each target (-vv: also show container
+    /// commands with their mounts and environment; -vvv: also enable west and
+    /// CMake diagnostics)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
 
     /// Incremental build (faster, but may have stale artifacts if configs changed)
     #[arg(short, long, conflicts_with = "pristine")]
@@ -55,9 +78,132 @@ struct BuildArgs {
     #[arg(short, long, conflicts_with = "incremental")]
     pristine: bool,
 
-    /// Build only targets in this group (e.g., "central", "peripheral", or "all")
-    #[arg(short, long, default_value = "all")]
-    group: String,
+    /// Build only targets in these groups (e.g., "central,dongle" or "!peripheral";
+    /// defaults to `default-group` from lfz.toml, or "all")
+    #[arg(short, long)]
+    group: Option<String>,
+
+    /// Build only targets whose tags match this expression (e.g., "central & !dongle")
+    #[arg(short, long)]
+    tags: Option<String>,
+
+    /// Build only targets whose artifact name matches this glob (repeatable, e.g., 'corne_*')
+    #[arg(long = "target", value_name = "GLOB")]
+    targets: Vec<String>,
+
+    /// Build only the targets that failed or were cancelled in the last build
+    #[arg(long, conflicts_with_all = ["board", "pairs"])]
+    failed: bool,
+
+    /// Interactively choose which targets to build
+    #[arg(long, conflicts_with = "board")]
+    pick: bool,
+
+    /// Kill a target's build after this long (e.g., "20m"; a target's
+    /// `timeout` in build.yaml takes precedence)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Replace a west project with a local directory for this build (repeatable)
+    #[arg(long = "override-module", value_name = "NAME=PATH")]
+    override_modules: Vec<String>,
+
+    /// Parallel compile jobs within each target's build (ninja -j; default:
+    /// the host's CPUs divided between the targets building at once)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    build_jobs: Option<u16>,
+
+    /// CPUs each build container may use (e.g. "2" or "1.5"; 0 for no limit;
+    /// default: an equal share of the host when building in parallel)
+    #[arg(long, value_name = "CPUS")]
+    cpu_limit: Option<f64>,
+
+    /// Memory each build container may use (e.g. "4g"; 0 for no limit;
+    /// default: an equal share of the host when building in parallel)
+    #[arg(long, value_name = "SIZE", value_parser = parse_memory)]
+    memory_limit: Option<u64>,
+
+    /// Build without network access, using only the cached image and workspace
+    #[arg(long)]
+    offline: bool,
+
+    /// Don't update the workspace when west.yml changed (exits with status
+    /// 3 if it lacks modules west.yml needs)
+    #[arg(long)]
+    no_update: bool,
+
+    /// Build from the module commits recorded in config/west.lock
+    #[arg(long)]
+    locked: bool,
+
+    /// Build against the head of a ZMK pull request, in a scratch workspace
+    #[arg(long, value_name = "N", conflicts_with_all = ["locked", "offline"])]
+    zmk_pr: Option<u32>,
+
+    /// Build from west.lock with fixed timestamps, twice, and check the firmware is identical
+    #[arg(long, conflicts_with_all = ["zmk_pr", "incremental"])]
+    reproducible: bool,
+
+    /// Write an SBOM of the west projects and build image next to each artifact
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "cyclonedx")]
+    sbom: Option<SbomFormat>,
+
+    /// Stamp the config commit, build time and lfz version into the firmware
+    /// and write <artifact>.build-info.json beside each artifact
+    #[arg(long)]
+    stamp: bool,
+
+    /// Fail the build if any target printed compiler or devicetree warnings
+    #[arg(long)]
+    warnings_as_errors: bool,
+
+    /// Stop at the first failed target: queued targets are skipped and running
+    /// builds are killed
+    #[arg(long, conflicts_with = "keep_going")]
+    fail_fast: bool,
+
+    /// Build every target even if some fail (the default, unless lfz.toml
+    /// sets fail-fast)
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Rebuild a failed target up to N more times, for transient errors such
+    /// as network failures or the compiler being killed for lack of memory
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// Build pristine when retrying a failed target
+    #[arg(long, requires = "retries")]
+    retry_pristine: bool,
+
+    /// Rebuild every target instead of reusing firmware built before from
+    /// the same inputs
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Build only the targets whose inputs changed since their last
+    /// successful build; the others keep their firmware
+    #[arg(long)]
+    changed_only: bool,
+
+    /// Upload firmware built by this run to the remote artifact cache
+    /// configured in lfz.toml
+    #[arg(long, conflicts_with = "no_cache")]
+    push_cache: bool,
+
+    /// If another lfz build is using the workspace, wait for it to finish
+    /// instead of failing
+    #[arg(long)]
+    wait: bool,
+
+    /// Show a desktop notification when the build finishes
+    #[arg(long)]
+    notify: bool,
+
+    /// Write build events (targets started, progress, results, errors) to a file
+    /// as JSON lines, or to stdout with `-` (moving all other output to stderr)
+    #[arg(long, value_name = "FILE")]
+    log_json: Option<PathBuf>,
 }
 
 impl BuildArgs {
@@ -81,6 +227,42 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
+    /// Plain output with GitHub Actions annotations and log groups instead of
+    /// progress bars (automatic on GitHub Actions)
+    #[arg(long, global = true)]
+    ci: bool,
+
+    /// Prefix status and build output lines with the time since lfz started,
+    /// or the wall-clock time with `--timestamps=wall`
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "elapsed"
+    )]
+    timestamps: Option<output::Timestamps>,
+
+    /// Container runtime to use instead of detecting one (also `runtime` in
+    /// lfz.toml)
+    #[arg(long, global = true, value_name = "RUNTIME")]
+    runtime: Option<container::Runtime>,
+
+    /// Command of a Docker-compatible CLI to use as the container runtime
+    /// (implies `--runtime oci`; also `runtime-command` in lfz.toml)
+    #[arg(long, global = true, value_name = "COMMAND")]
+    runtime_command: Option<String>,
+
+    /// When to use colors (auto: on terminals, unless NO_COLOR is set)
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    color: output::ColorChoice,
+
+    /// Plain output without spinners, progress bars or colors, for dumb
+    /// terminals and log files
+    #[arg(long, global = true)]
+    plain: bool,
+
     /// Top-level build options (used when no subcommand is given)
     #[command(flatten)]
     build_args: BuildArgs,
@@ -91,28 +273,476 @@ enum Commands {
     /// Build ZMK firmware (default if no subcommand given)
     Build(BuildArgs),
 
+    /// Rebuild only the targets that failed in the last build (`build --failed`)
+    Retry(BuildArgs),
+
     /// List available build targets and groups
     List {
-        /// Filter targets by group
+        /// Filter targets by group (e.g., "central,dongle" or "!peripheral")
         #[arg(short, long)]
         group: Option<String>,
+
+        /// Filter targets by tag expression (e.g., "central & !dongle")
+        #[arg(short, long)]
+        tags: Option<String>,
+    },
+
+    /// List boards available in the cached workspace
+    Boards {
+        /// Only show boards whose name contains this text
+        filter: Option<String>,
     },
 
+    /// List shields available from ZMK and modules in the cached workspace
+    Shields {
+        /// Only show shields whose name contains this text
+        filter: Option<String>,
+    },
+
+    /// Check build config, west.yml and lfz.toml for schema errors
+    Validate,
+
     /// Refresh west workspace (re-run west update)
-    Update,
+    Update {
+        /// Only fetch these west projects again (repeatable), keeping the
+        /// rest of the workspace
+        #[arg(long = "project", value_name = "NAME")]
+        projects: Vec<String>,
+    },
+
+    /// Show west projects whose pinned revision is behind upstream
+    Outdated,
+
+    /// Pull the build image and set up the workspace without building
+    /// (e.g. before going offline)
+    Prefetch {
+        /// Check out the module commits recorded in config/west.lock
+        #[arg(long)]
+        locked: bool,
+
+        /// If another lfz build is using the workspace, wait for it to
+        /// finish instead of failing
+        #[arg(long)]
+        wait: bool,
+    },
+
+    /// Find the ZMK commit that broke a target by bisecting between two commits
+    Bisect {
+        /// Last known good ZMK commit or tag
+        #[arg(long)]
+        good: String,
+
+        /// First known bad ZMK commit or tag
+        #[arg(long)]
+        bad: String,
+
+        /// Board to build (skips build.yaml)
+        #[arg(short, long)]
+        board: Option<String>,
+
+        /// Shield to build
+        #[arg(short, long, requires = "board")]
+        shield: Option<String>,
+
+        /// build.yaml target to build (artifact name or glob matching one target)
+        #[arg(long, conflicts_with = "board")]
+        target: Option<String>,
+
+        /// Ask whether each built firmware works instead of only checking that it builds
+        #[arg(long)]
+        test: bool,
+
+        /// Output directory for each step's firmware
+        #[arg(short, long, default_value = "zmk-target")]
+        output: String,
+    },
+
+    /// Update west.yml revisions to the latest upstream tag (or a given ref)
+    Bump {
+        /// Projects to bump (default: all)
+        projects: Vec<String>,
+
+        /// Ref to pin instead of the latest tag
+        #[arg(long, value_name = "REF", requires = "projects")]
+        to: Option<String>,
+
+        /// Show the changes without writing west.yml
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Generate CI configuration from build.yaml
+    Ci {
+        #[command(subcommand)]
+        command: CiCommand,
+    },
+
+    /// Publish built firmware, checksums and a manifest as a GitHub release
+    /// (authenticated via GITHUB_TOKEN)
+    Release {
+        /// Tag to release (created on GitHub if it doesn't exist)
+        tag: String,
+
+        /// Directory containing the firmware to release
+        #[arg(short, long, default_value = "zmk-target")]
+        output: String,
+
+        /// GitHub repository as owner/name (default: the 'origin' remote)
+        #[arg(long)]
+        repo: Option<String>,
+
+        /// Release notes (default: generated by GitHub)
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// Create the release as a draft
+        #[arg(long)]
+        draft: bool,
+
+        /// Mark the release as a pre-release
+        #[arg(long)]
+        prerelease: bool,
+    },
+
+    /// Show the most recent build log of a target
+    Logs {
+        /// Target, or part of its name (default: the first failure of the last
+        /// build, or the most recently built)
+        target: Option<String>,
+
+        /// Keep printing output as it is written to the log
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Only show the last N lines
+        #[arg(short = 'n', long, value_name = "N")]
+        lines: Option<usize>,
+
+        /// List the available logs instead
+        #[arg(long, conflicts_with_all = ["target", "follow", "lines"])]
+        list: bool,
+    },
+
+    /// Show recent build runs and per-target statistics
+    History {
+        /// Show the recent builds of this target instead
+        target: Option<String>,
+
+        /// Number of runs (or builds) to show
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 10)]
+        count: usize,
+    },
+
+    /// Show what a target's flash or RAM is used by (Zephyr's rom_report/ram_report)
+    Report {
+        /// build.yaml target (artifact name or glob matching one target)
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Report RAM instead of flash
+        #[arg(long)]
+        ram: bool,
+
+        /// Levels of the path tree to show
+        #[arg(short, long, value_name = "N", default_value_t = 3)]
+        depth: usize,
+
+        /// Order of the entries under each path
+        #[arg(long, value_enum, default_value = "size")]
+        sort: ReportSort,
+    },
+
+    /// List the largest symbols in a target's flash or RAM, from its linker map
+    Symbols {
+        /// build.yaml target (artifact name or glob matching one target)
+        #[arg(long, conflicts_with = "map")]
+        target: Option<String>,
+
+        /// Linker map to read instead of the target's
+        #[arg(long, value_name = "FILE")]
+        map: Option<PathBuf>,
+
+        /// List RAM instead of flash
+        #[arg(long)]
+        ram: bool,
+
+        /// List output sections instead of symbols
+        #[arg(long)]
+        sections: bool,
+
+        /// Number of entries to show
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+        count: usize,
+    },
+
+    /// Print a target's final devicetree (zephyr.dts), with all overlays merged in
+    Dts {
+        /// build.yaml target (artifact name or glob matching one target)
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Only print this node: a path, node name or label (e.g. /soc/i2c@40003000, &kscan0)
+        node: Option<String>,
+    },
+
+    /// Print a target's resolved Kconfig (.config), to check which options took effect
+    #[command(args_conflicts_with_subcommands = true)]
+    Kconfig {
+        #[command(subcommand)]
+        command: Option<KconfigCommand>,
+
+        /// build.yaml target (artifact name or glob matching one target)
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Only print symbols whose name contains this (case-insensitive)
+        #[arg(short, long, value_name = "PATTERN")]
+        grep: Option<String>,
+    },
+
+    /// Compare firmware and memory usage between two builds
+    Compare {
+        /// Earlier build: an output directory, or a history entry (@0 is the latest run, @1 the one before)
+        before: String,
+
+        /// Later build, like BEFORE
+        #[arg(default_value = "zmk-target")]
+        after: String,
+
+        /// Print the comparison as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Remove cached workspace for this config
     Clean {
-        /// Remove all cached workspaces
+        /// Remove all cached workspaces (except those another lfz run is using)
         #[arg(long)]
         all: bool,
+
+        /// Remove one workspace, by id (or its start), repository, branch or
+        /// repo:branch as `lfz workspaces` shows them
+        #[arg(long, value_name = "SPEC", conflicts_with = "all", group = "selective")]
+        workspace: Option<String>,
+
+        /// Remove the least recently used workspaces until they fit in the
+        /// quota (workspaces.max-size in lfz.toml, or --max-size)
+        #[arg(long, conflicts_with = "all", group = "selective")]
+        evict: bool,
+
+        /// Quota for --evict (e.g. "20g")
+        #[arg(long, value_name = "SIZE", value_parser = parse_memory, requires = "evict")]
+        max_size: Option<u64>,
+
+        /// Remove the workspaces not used within this long (e.g. "30d")
+        #[arg(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_duration,
+            conflicts_with = "all",
+            group = "selective"
+        )]
+        older_than: Option<Duration>,
+
+        /// With --older-than, also remove build logs that old
+        #[arg(long, requires = "older_than")]
+        logs: bool,
+
+        /// With --older-than, also remove cached firmware not used that long
+        #[arg(long, requires = "older_than")]
+        artifacts: bool,
+
+        /// Only report what --workspace, --evict or --older-than would remove
+        #[arg(long, requires = "selective")]
+        dry_run: bool,
+    },
+
+    /// Remove all caches (workspaces + ccache), or only those selected
+    Purge {
+        /// Remove the compiler caches (ccache and sccache)
+        #[arg(long)]
+        ccache: bool,
+
+        /// Remove the workspaces no build is using
+        #[arg(long)]
+        workspaces: bool,
+
+        /// Remove the build logs
+        #[arg(long)]
+        logs: bool,
+
+        /// Remove the build images lfz uses: the default image, project
+        /// images, and those workspaces were built with (not part of the
+        /// cache otherwise)
+        #[arg(long)]
+        images: bool,
+    },
+
+    /// Apply the retention policies in lfz.toml: [gc] ages for workspaces,
+    /// logs and cached firmware, the workspace quota and the ccache cap
+    Gc {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show disk space used by caches, biggest workspaces first
+    Size {
+        /// Firmware output directory to include
+        #[arg(short, long, default_value = "zmk-target")]
+        output: String,
+
+        /// Print the sizes (in bytes) as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect the shared compiler cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// List, update and prune the build images
+    Image {
+        #[command(subcommand)]
+        command: ImageCommand,
+    },
+
+    /// List cached workspaces with the project and branch each belongs to
+    Workspaces,
+
+    /// Pack the project's workspace into a tarball, to build on a machine
+    /// without internet access
+    ExportWorkspace {
+        /// Where to write it (default: lfz-<repo>-<branch>.tar.gz)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Include the build image
+        #[arg(long)]
+        image: bool,
+    },
+
+    /// Write the build environment out for other tools
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+
+    /// Work with the project's cached workspace
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand,
+    },
+
+    /// Restore a workspace from `lfz export-workspace`
+    ImportWorkspace {
+        /// The tarball
+        archive: PathBuf,
+
+        /// Replace the workspace if there already is one, and import it
+        /// even if it was exported from another repository
+        #[arg(long)]
+        force: bool,
     },
+}
+
+#[derive(Subcommand)]
+enum CiCommand {
+    /// Write a GitHub Actions workflow building the build.yaml targets
+    Generate {
+        /// Only include targets in these groups (e.g., "central,dongle" or "!peripheral")
+        #[arg(short, long)]
+        group: Option<String>,
 
-    /// Remove all caches (workspaces + ccache)
-    Purge,
+        /// Only include targets whose tags match this expression
+        #[arg(short, long)]
+        tags: Option<String>,
+
+        /// Where to write the workflow (default: .github/workflows/build.yml)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Print the workflow instead of writing it
+        #[arg(long, conflicts_with_all = ["output", "check", "force"])]
+        stdout: bool,
+
+        /// Fail if the workflow file is out of date with build.yaml
+        #[arg(long, conflicts_with = "force")]
+        check: bool,
+
+        /// Overwrite an existing workflow file
+        #[arg(long)]
+        force: bool,
+    },
+}
 
-    /// Show disk space used by caches
-    Size,
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show ccache's hit rate, size and evictions (sccache only its size)
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum ImageCommand {
+    /// List the build images with their size and the workspaces using them
+    List,
+
+    /// Pull the build image again, to update it
+    Pull,
+
+    /// Build the project's image from its lfz.Dockerfile
+    Build,
+
+    /// Remove the build images no workspace references
+    Prune {
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Write a devcontainer.json that opens the project's workspace in its
+    /// build image, with lfz's mounts and environment (e.g. for VS Code)
+    Devcontainer {
+        /// Where to write it (default: .devcontainer/devcontainer.json)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommand {
+    /// Fetch the full history of shallow cloned projects, to check out
+    /// other revisions or dig through git log
+    Unshallow {
+        /// west projects (default: all of them)
+        projects: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KconfigCommand {
+    /// Show the symbols that differ between two targets, or between two
+    /// builds' output directories
+    Diff {
+        /// Target (artifact name or glob), or output directory
+        a: String,
+
+        /// Target (artifact name or glob), or output directory
+        b: String,
+
+        /// Only compare these targets of the output directories (glob)
+        #[arg(long)]
+        target: Option<String>,
+    },
 }
 
 fn run_build(args: BuildArgs) -> Result<()> {
@@ -120,26 +750,215 @@ fn run_build(args: BuildArgs) -> Result<()> {
     cli::build::run(
         args.board,
         args.shield,
+        args.pairs,
         args.output,
         args.jobs,
         args.quiet,
         args.verbose,
         build_mode,
         args.group,
+        args.tags,
+        args.targets,
+        args.failed,
+        args.pick,
+        args.timeout,
+        args.build_jobs.map(usize::from),
+        args.cpu_limit,
+        args.memory_limit,
+        args.override_modules,
+        args.offline,
+        args.no_update,
+        args.locked,
+        args.zmk_pr,
+        args.reproducible,
+        args.sbom,
+        args.stamp,
+        args.warnings_as_errors,
+        args.log_json,
+        args.notify,
+        // `--keep-going` overrides lfz.toml's fail-fast
+        match (args.fail_fast, args.keep_going) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        args.retries,
+        args.retry_pristine,
+        args.no_cache,
+        args.changed_only,
+        args.push_cache,
+        args.wait,
     )
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    output::set_ci(cli.ci || output::ci_detected());
+    output::set_timestamps(cli.timestamps);
+    output::set_plain(cli.plain);
+    output::set_color(cli.color);
+    // An unreadable lfz.toml fails the command itself later
+    let lfz_config = config::project::Project::detect()
+        .ok()
+        .and_then(|project| config::lfz_toml::LfzConfig::load(&project.root).ok())
+        .unwrap_or_default();
+    let runtime_command = cli.runtime_command.or(lfz_config.runtime_command);
+    let runtime = cli
+        .runtime
+        .or(lfz_config.runtime)
+        .or(runtime_command.as_ref().map(|_| container::Runtime::Oci));
+    if let Some(command) = runtime_command {
+        container::set_oci_command(command);
+    }
+    container::set_preferred(runtime);
 
-    match cli.command {
+    let result = match cli.command {
         Some(Commands::Build(args)) => run_build(args),
-        Some(Commands::List { group }) => cli::list::run(group),
-        Some(Commands::Update) => cli::update::run(),
-        Some(Commands::Clean { all }) => cli::clean::run(all),
-        Some(Commands::Purge) => cli::purge::run(),
-        Some(Commands::Size) => cli::size::run(),
+        Some(Commands::Retry(args)) => run_build(BuildArgs {
+            failed: true,
+            ..args
+        }),
+        Some(Commands::List { group, tags }) => cli::list::run(group, tags),
+        Some(Commands::Boards { filter }) => cli::boards::run(filter),
+        Some(Commands::Shields { filter }) => cli::shields::run(filter),
+        Some(Commands::Validate) => cli::validate::run(),
+        Some(Commands::Update { projects }) => cli::update::run(projects),
+        Some(Commands::Outdated) => cli::outdated::run(),
+        Some(Commands::Prefetch { locked, wait }) => cli::prefetch::run(locked, wait),
+        Some(Commands::Bisect {
+            good,
+            bad,
+            board,
+            shield,
+            target,
+            test,
+            output,
+        }) => cli::bisect::run(good, bad, board, shield, target, test, output),
+        Some(Commands::Bump {
+            projects,
+            to,
+            dry_run,
+        }) => cli::bump::run(projects, to, dry_run),
+        Some(Commands::Ci {
+            command:
+                CiCommand::Generate {
+                    group,
+                    tags,
+                    output,
+                    stdout,
+                    check,
+                    force,
+                },
+        }) => cli::ci::generate(group, tags, output, stdout, check, force),
+        Some(Commands::Release {
+            tag,
+            output,
+            repo,
+            notes,
+            draft,
+            prerelease,
+        }) => cli::release::run(tag, output, repo, notes, draft, prerelease),
+        Some(Commands::Logs {
+            target,
+            follow,
+            lines,
+            list,
+        }) => cli::logs::run(target, follow, lines, list),
+        Some(Commands::History { target, count }) => cli::history::run(target, count),
+        Some(Commands::Report {
+            target,
+            ram,
+            depth,
+            sort,
+        }) => cli::report::run(
+            target,
+            if ram {
+                ReportKind::Ram
+            } else {
+                ReportKind::Rom
+            },
+            depth,
+            sort,
+        ),
+        Some(Commands::Symbols {
+            target,
+            map,
+            ram,
+            sections,
+            count,
+        }) => cli::symbols::run(target, map, ram, sections, count),
+        Some(Commands::Dts { target, node }) => cli::dts::run(target, node),
+        Some(Commands::Kconfig {
+            command: Some(KconfigCommand::Diff { a, b, target }),
+            ..
+        }) => cli::kconfig::diff(a, b, target),
+        Some(Commands::Kconfig {
+            command: None,
+            target,
+            grep,
+        }) => cli::kconfig::run(target, grep),
+        Some(Commands::Compare {
+            before,
+            after,
+            json,
+        }) => cli::compare::run(before, after, json),
+        Some(Commands::Clean {
+            all,
+            workspace,
+            evict,
+            max_size,
+            older_than,
+            logs,
+            artifacts,
+            dry_run,
+        }) => cli::clean::run(
+            all, workspace, evict, max_size, older_than, logs, artifacts, dry_run,
+        ),
+        Some(Commands::Purge {
+            ccache,
+            workspaces,
+            logs,
+            images,
+        }) => cli::purge::run(cli::purge::Selection {
+            ccache,
+            workspaces,
+            logs,
+            images,
+        }),
+        Some(Commands::Gc { dry_run }) => cli::gc::run(dry_run),
+        Some(Commands::Size { output, json }) => cli::size::run(output, json),
+        Some(Commands::Cache {
+            command: CacheCommand::Stats,
+        }) => cli::cache::stats(),
+        Some(Commands::Image { command }) => match command {
+            ImageCommand::List => cli::image::list(),
+            ImageCommand::Pull => cli::image::pull(),
+            ImageCommand::Build => cli::image::build(),
+            ImageCommand::Prune { dry_run } => cli::image::prune(dry_run),
+        },
+        Some(Commands::Workspaces) => cli::workspaces::run(),
+        Some(Commands::ExportWorkspace { output, image }) => {
+            cli::workspace_archive::export(output, image)
+        }
+        Some(Commands::Export {
+            command: ExportCommand::Devcontainer { output, force },
+        }) => cli::devcontainer::export(output, force),
+        Some(Commands::Workspace {
+            command: WorkspaceCommand::Unshallow { projects },
+        }) => cli::unshallow::run(projects),
+        Some(Commands::ImportWorkspace { archive, force }) => {
+            cli::workspace_archive::import(archive, force)
+        }
         // Default to build with top-level args
         None => run_build(cli.build_args),
+    };
+
+    // Scripts tell a workspace that needs updating from a failed build
+    if let Err(e) = &result {
+        if e.downcast_ref::<workspace::MissingModules>().is_some() {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(MISSING_MODULES_EXIT);
+        }
     }
+    result
 }
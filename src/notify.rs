@@ -0,0 +1,261 @@
+//! Notifications when a build run finishes (`--notify`, `[notify]` in lfz.toml)
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::build::memory::MemoryRegion;
+use crate::build::orchestrator::BuildResult;
+use crate::config::lfz_toml::NotifyConfig;
+use crate::output;
+
+/// How long a webhook may take before it is given up on
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A finished run, as sent to webhooks and notification commands
+#[derive(Debug, Serialize)]
+pub struct Summary<'a> {
+    /// One-line description (also what Slack-compatible webhooks display)
+    pub text: String,
+    pub success: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub duration_ms: u128,
+    pub targets: Vec<TargetSummary<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetSummary<'a> {
+    pub target: &'a str,
+    pub success: bool,
+    pub timed_out: bool,
+    /// Failed attempts before the final one (`--retries`)
+    pub retries: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<&'a Path>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub memory: &'a [MemoryRegion],
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(results: &'a [BuildResult], duration: Duration) -> Self {
+        let failed = results.iter().filter(|r| !r.success).count();
+        let succeeded = results.len() - failed;
+        let (title, message) = build_message(succeeded, failed, duration);
+        Self {
+            text: format!("{}: {}", title, message),
+            success: failed == 0,
+            succeeded,
+            failed,
+            duration_ms: duration.as_millis(),
+            targets: results
+                .iter()
+                .map(|r| TargetSummary {
+                    target: &r.target_name,
+                    success: r.success,
+                    timed_out: r.timed_out,
+                    retries: r.retries,
+                    error: r.error.as_deref(),
+                    artifact: r.artifact_path.as_deref(),
+                    memory: &r.memory,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Send a finished run to the webhook and command configured in lfz.toml.
+/// Like the desktop notification, failures only warn.
+pub fn hooks(config: &NotifyConfig, summary: &Summary) {
+    let json = serde_json::to_string(summary).unwrap_or_default();
+    if let Some(url) = &config.webhook {
+        if let Err(e) = post(url, &json) {
+            output::warning(&format!("Notification webhook failed: {}", e));
+        }
+    }
+    if let Some(command) = &config.command {
+        if let Err(e) = run_command(command, &json) {
+            output::warning(&format!("Notification command failed: {}", e));
+        }
+    }
+}
+
+fn post(url: &str, json: &str) -> Result<()> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(WEBHOOK_TIMEOUT))
+        .build()
+        .into();
+    let response = agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(json)
+        .context("Failed to send request")?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} responded with {}", url, response.status());
+    }
+    Ok(())
+}
+
+/// Run a shell command with the summary on stdin
+fn run_command(command: &str, json: &str) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may not read its input; that's not an error
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("'{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+/// Show a desktop notification with a run's result; failures only warn,
+/// since the build itself is done
+pub fn desktop(succeeded: usize, failed: usize, duration: Duration) {
+    let (title, message) = build_message(succeeded, failed, duration);
+    if let Err(e) = show(&title, &message) {
+        output::warning(&format!("Failed to show desktop notification: {}", e));
+    }
+}
+
+/// Title and body of the notification for a finished run
+fn build_message(succeeded: usize, failed: usize, duration: Duration) -> (String, String) {
+    let title = if failed == 0 {
+        "lfz: build complete".to_string()
+    } else {
+        "lfz: build failed".to_string()
+    };
+    let message = format!(
+        "{} succeeded, {} failed in {}",
+        succeeded,
+        failed,
+        output::format_duration(duration)
+    );
+    (title, message)
+}
+
+fn show(title: &str, message: &str) -> Result<()> {
+    let mut cmd = notification_command(title, message);
+    let name = cmd.get_program().to_string_lossy().to_string();
+    let status = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {}", name))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", name, status);
+    }
+    Ok(())
+}
+
+/// The platform's notification command: `osascript` on macOS, PowerShell on
+/// Windows and `notify-send` (libnotify) elsewhere
+fn notification_command(title: &str, message: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(message),
+            applescript_string(title)
+        ));
+        cmd
+    } else if cfg!(windows) {
+        let mut cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg(format!(
+                "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             $n.ShowBalloonTip(10000, {}, {}, 'Info'); \
+             Start-Sleep -Seconds 5; $n.Dispose()",
+                powershell_string(title),
+                powershell_string(message)
+            ));
+        cmd
+    } else {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name", "lfz", title, message]);
+        cmd
+    }
+}
+
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn powershell_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_message() {
+        let (title, message) = build_message(3, 1, Duration::from_secs(95));
+        assert_eq!(title, "lfz: build failed");
+        assert_eq!(message, "3 succeeded, 1 failed in 1m 35s");
+        assert_eq!(applescript_string("a \"b\""), r#""a \"b\"""#);
+        assert_eq!(powershell_string("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let results = vec![
+            BuildResult {
+                target_name: "corne_left".to_string(),
+                success: true,
+                artifact_path: Some("zmk-target/corne_left.uf2".into()),
+                ..Default::default()
+            },
+            BuildResult {
+                target_name: "corne_right".to_string(),
+                error: Some("Build timed out after 1m 0s".to_string()),
+                timed_out: true,
+                ..Default::default()
+            },
+        ];
+        let summary = Summary::new(&results, Duration::from_secs(61));
+        let json: serde_json::Value = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            json["text"],
+            "lfz: build failed: 1 succeeded, 1 failed in 1m 1s"
+        );
+        assert_eq!(json["success"], false);
+        assert_eq!(json["duration_ms"], 61_000);
+        assert_eq!(json["targets"][0]["artifact"], "zmk-target/corne_left.uf2");
+        assert!(json["targets"][0].get("error").is_none());
+        assert_eq!(json["targets"][1]["timed_out"], true);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_receives_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("summary.json");
+        run_command(&format!("cat > '{}'", out.display()), "{\"success\":true}").unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"success\":true}");
+        assert!(run_command("exit 3", "{}").is_err());
+    }
+}
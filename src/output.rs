@@ -1,12 +1,176 @@
 //! Terminal output utilities using indicatif and console
 
+use anyhow::{Context, Result};
+use clap::ValueEnum;
 use console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+
+/// `println!`, unless stdout is reserved for the summary (`--quiet`) or
+/// build events (`--log-json -`), in which case it goes to stderr
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if stdout_reserved() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// `println!` for the final summary, which stays on stdout in quiet mode
+macro_rules! summary_line {
+    ($($arg:tt)*) => {
+        if JSON_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Stdout carries only the final summary (`--quiet`)
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Stdout carries only build events as JSON lines (`--log-json -`)
+static JSON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// Switch quiet mode on or off
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+/// Reserve stdout for build events
+pub fn set_json_stdout(enabled: bool) {
+    JSON_STDOUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether everything but the summary (or events) goes to stderr
+fn stdout_reserved() -> bool {
+    QUIET.load(Ordering::Relaxed) || JSON_STDOUT.load(Ordering::Relaxed)
+}
+
+/// Print a line of plain text, to stderr if stdout is reserved
+pub fn line(text: &str) {
+    say!("{}", text);
+}
+
+/// Plain output with GitHub Actions workflow commands (`--ci`)
+static CI: AtomicBool = AtomicBool::new(false);
+
+/// Switch CI output mode on or off
+pub fn set_ci(enabled: bool) {
+    CI.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether CI output mode is on
+pub fn ci() -> bool {
+    CI.load(Ordering::Relaxed)
+}
+
+/// No spinners or progress bars (`--plain`)
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Switch plain output mode on or off
+pub fn set_plain(enabled: bool) {
+    PLAIN.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether output is plain lines only, as in `--plain` or CI mode
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed) || ci()
+}
+
+/// When to color output (`--color`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color terminals, unless NO_COLOR is set or output is plain
+    Auto,
+    Always,
+    Never,
+}
+
+/// Decide whether stdout and stderr are colored. Call after `set_plain`.
+pub fn set_color(choice: ColorChoice) {
+    let no_color = std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+    let plain = PLAIN.load(Ordering::Relaxed);
+    console::set_colors_enabled(colors_enabled(
+        choice,
+        no_color,
+        plain,
+        console::Term::stdout().is_term(),
+    ));
+    console::set_colors_enabled_stderr(colors_enabled(
+        choice,
+        no_color,
+        plain,
+        console::Term::stderr().is_term(),
+    ));
+}
+
+fn colors_enabled(choice: ColorChoice, no_color: bool, plain: bool, is_term: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && !plain && is_term,
+    }
+}
+
+/// Whether lfz runs inside GitHub Actions
+pub fn ci_detected() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Time shown at the start of status and build output lines (`--timestamps`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Timestamps {
+    /// Time since lfz started
+    Elapsed,
+    /// Wall-clock time (UTC)
+    Wall,
+}
+
+/// Timestamp mode and when lfz started
+static TIMESTAMPS: Mutex<Option<(Timestamps, Instant)>> = Mutex::new(None);
+
+/// Switch timestamps on or off, counting elapsed time from now
+pub fn set_timestamps(mode: Option<Timestamps>) {
+    *TIMESTAMPS.lock().unwrap() = mode.map(|mode| (mode, Instant::now()));
+}
+
+/// Prefix for an output line: the time followed by a space, or nothing
+pub fn timestamp() -> String {
+    let Some((mode, start)) = *TIMESTAMPS.lock().unwrap() else {
+        return String::new();
+    };
+    let time = format_timestamp(mode, start.elapsed(), SystemTime::now());
+    format!("{} ", style(format!("[{}]", time)).dim())
+}
+
+/// `01:02.3` (elapsed) or `14:03:22` (wall clock)
+fn format_timestamp(mode: Timestamps, elapsed: Duration, now: SystemTime) -> String {
+    match mode {
+        Timestamps::Elapsed => {
+            let secs = elapsed.as_secs_f64();
+            format!("{:02}:{:04.1}", elapsed.as_secs() / 60, secs % 60.0)
+        }
+        Timestamps::Wall => humantime::format_rfc3339_seconds(now).to_string()[11..19].to_string(),
+    }
+}
 
 /// Create a spinner for long-running operations
+///
+/// In plain and CI mode the message is printed once instead.
 pub fn spinner(message: &str) -> ProgressBar {
+    if plain() {
+        say!("{}", message);
+        return ProgressBar::hidden();
+    }
     let pb = ProgressBar::new_spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
@@ -19,6 +183,178 @@ pub fn spinner(message: &str) -> ProgressBar {
     pb
 }
 
+/// Interactively pick a subset of items (e.g., build targets)
+///
+/// Prompts for an optional fuzzy search query to narrow the list, then shows
+/// a multi-select of the matching items. Returns the indices of the chosen
+/// items in the original slice.
+pub fn pick(prompt: &str, items: &[String]) -> Result<Vec<usize>> {
+    if !console::user_attended() {
+        anyhow::bail!("Interactive selection requires a terminal");
+    }
+
+    let theme = ColorfulTheme::default();
+    let query: String = Input::with_theme(&theme)
+        .with_prompt("Filter (fuzzy, empty for all)")
+        .allow_empty(true)
+        .interact_text()
+        .context("Failed to read filter")?;
+
+    let matches = fuzzy_filter(items, &query);
+    if matches.is_empty() {
+        anyhow::bail!("Nothing matches '{}'", query);
+    }
+
+    let labels: Vec<&str> = matches.iter().map(|&i| items[i].as_str()).collect();
+    let chosen = MultiSelect::with_theme(&theme)
+        .with_prompt(format!("{} (space to toggle, enter to confirm)", prompt))
+        .items(&labels)
+        .defaults(&vec![true; labels.len()])
+        .interact()
+        .context("Failed to read selection")?;
+
+    Ok(chosen.into_iter().map(|i| matches[i]).collect())
+}
+
+/// Interactively choose one of several answers, returning its index
+pub fn select(prompt: &str, items: &[&str]) -> Result<usize> {
+    if !console::user_attended() {
+        anyhow::bail!("Interactive selection requires a terminal");
+    }
+
+    Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact()
+        .context("Failed to read selection")
+}
+
+/// Interactively answer a yes/no question, yes by default
+pub fn confirm(prompt: &str) -> Result<bool> {
+    if !console::user_attended() {
+        anyhow::bail!("Confirmation requires a terminal");
+    }
+
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(true)
+        .interact()
+        .context("Failed to read answer")
+}
+
+/// Return indices of items fuzzy-matching a query, best match first
+fn fuzzy_filter(items: &[String], query: &str) -> Vec<usize> {
+    if query.trim().is_empty() {
+        return (0..items.len()).collect();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(i64, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| matcher.fuzzy_match(item, query.trim()).map(|s| (s, i)))
+        .collect();
+    // Highest score first, original order for ties
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Progress of west fetching the projects of a workspace
+///
+/// In plain and CI mode each project is printed as a line instead.
+pub struct WorkspaceProgress {
+    bar: ProgressBar,
+    /// Projects west will fetch, if known
+    total: Option<usize>,
+    /// Projects west started on so far, and the latest of them
+    current: Mutex<(usize, String)>,
+}
+
+impl WorkspaceProgress {
+    pub fn new(total: Option<usize>) -> Self {
+        let bar = if plain() {
+            ProgressBar::hidden()
+        } else {
+            let (bar, template) = match total {
+                Some(total) => (
+                    ProgressBar::new(total as u64),
+                    "{spinner:.cyan} [{bar:25.cyan/blue}] {pos}/{len} projects {msg}",
+                ),
+                None => (
+                    ProgressBar::new_spinner(),
+                    "{spinner:.cyan} {pos} projects {msg}",
+                ),
+            };
+            bar.set_draw_target(ProgressDrawTarget::stderr_with_hz(10));
+            bar.set_style(
+                ProgressStyle::default_spinner()
+                    .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+                    .template(template)
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        };
+        Self {
+            bar,
+            total,
+            current: Mutex::new((0, String::new())),
+        }
+    }
+
+    /// west started on the next project
+    pub fn project(&self, name: &str) {
+        let Ok(mut current) = self.current.lock() else {
+            return;
+        };
+        current.0 += 1;
+        current.1 = name.to_string();
+        let count = current.0;
+
+        if plain() {
+            match self.total {
+                Some(total) => say!("  [{}/{}] {}", count, total.max(count), name),
+                None => say!("  [{}] {}", count, name),
+            }
+            return;
+        }
+        // The total is an estimate from the manifest
+        if self.bar.length().is_some_and(|len| count as u64 > len) {
+            self.bar.set_length(count as u64);
+        }
+        self.bar.set_position(count as u64);
+        self.bar.set_message(name.to_string());
+    }
+
+    /// git got this far with a phase of fetching the current project
+    pub fn fetch(&self, phase: &str, percent: u8) {
+        if let Ok(current) = self.current.lock() {
+            self.bar.set_message(format!(
+                "{} {}",
+                current.1,
+                style(format!("{} {}%", phase.to_lowercase(), percent)).dim()
+            ));
+        }
+    }
+
+    /// Print a line above the progress bar
+    pub fn println(&self, line: &str) {
+        self.bar.suspend(|| say!("{}", line));
+    }
+
+    /// Print a line to stderr above the progress bar
+    pub fn eprintln(&self, line: &str) {
+        self.bar.suspend(|| eprintln!("{}", line));
+    }
+
+    /// Remove the progress bar
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
 /// Build progress tracker for parallel builds using indicatif MultiProgress
 pub struct BuildProgress {
     #[allow(dead_code)]
@@ -26,6 +362,11 @@ pub struct BuildProgress {
     bars: Vec<ProgressBar>,
     targets: Vec<String>,
     results: Mutex<Vec<(bool, String)>>, // (success, message) for each target
+    retries: Mutex<Vec<u32>>,
+    /// How long each target is expected to take, from earlier builds
+    estimates: Vec<Option<Duration>>,
+    /// When each target started building
+    started: Mutex<Vec<Option<Instant>>>,
 }
 
 impl BuildProgress {
@@ -52,15 +393,34 @@ impl BuildProgress {
         }
 
         let results = Mutex::new(vec![(false, String::new()); targets.len()]);
+        let retries = Mutex::new(vec![0; targets.len()]);
+        let started = Mutex::new(vec![None; targets.len()]);
 
         Self {
             multi,
             bars,
             targets: targets.to_vec(),
             results,
+            retries,
+            estimates: Vec::new(),
+            started,
         }
     }
 
+    /// Show the time remaining for targets with an expected duration
+    pub fn estimates(mut self, estimates: Vec<Option<Duration>>) -> Self {
+        self.estimates = estimates;
+        self
+    }
+
+    /// Expected time left for a running target, if it has an estimate and
+    /// hasn't overrun it
+    fn remaining(&self, index: usize) -> Option<Duration> {
+        let estimate = self.estimates.get(index).copied().flatten()?;
+        let started = self.started.lock().ok()?.get(index).copied().flatten()?;
+        estimate.checked_sub(started.elapsed())
+    }
+
     /// Update a target's status
     pub fn update(&self, index: usize, state: BuildState, message: &str) {
         if let Some(pb) = self.bars.get(index) {
@@ -72,9 +432,29 @@ impl BuildProgress {
                 BuildState::Failed => format!("{}", style("[XX]").red().bold()),
             };
 
+            if state == BuildState::Starting {
+                if let Ok(mut started) = self.started.lock() {
+                    if let Some(started) = started.get_mut(index) {
+                        started.get_or_insert_with(Instant::now);
+                    }
+                }
+            }
+            let message = match self.remaining(index) {
+                Some(remaining) if !message.is_empty() => {
+                    format!("{} · ~{} left", message, format_remaining(remaining))
+                }
+                Some(remaining) => format!("~{} left", format_remaining(remaining)),
+                None => message.to_string(),
+            };
+
+            let target = match self.retries_of(index) {
+                0 => target.to_string(),
+                n => format!("{} {}", target, style(format!("(retry {})", n)).yellow()),
+            };
+
             pb.set_prefix(prefix);
             if message.is_empty() {
-                pb.set_message(target.to_string());
+                pb.set_message(target);
             } else {
                 pb.set_message(format!("{} {}", target, style(message).dim()));
             }
@@ -91,9 +471,18 @@ impl BuildProgress {
     ) {
         if let Some(pb) = self.bars.get(index) {
             let target = self.targets.get(index).map(|s| s.as_str()).unwrap_or("");
-            let time_str = duration
-                .map(|d| format!("({})", format_duration(d)))
-                .unwrap_or_default();
+            let retries = match self.retries_of(index) {
+                0 => None,
+                1 => Some("1 retry".to_string()),
+                n => Some(format!("{} retries", n)),
+            };
+            let time_str = match (duration.map(format_duration), retries) {
+                (Some(time), Some(retries)) => format!("({}, {})", time, retries),
+                (time, retries) => time
+                    .or(retries)
+                    .map(|s| format!("({})", s))
+                    .unwrap_or_default(),
+            };
 
             let msg = if success {
                 if let Some(art) = artifact {
@@ -123,6 +512,48 @@ impl BuildProgress {
         }
     }
 
+    /// Show a failed target as building again (`--retries`)
+    pub fn retry(&self, index: usize) {
+        if let Ok(mut retries) = self.retries.lock() {
+            if let Some(count) = retries.get_mut(index) {
+                *count += 1;
+            }
+        }
+        if let Ok(mut started) = self.started.lock() {
+            if let Some(started) = started.get_mut(index) {
+                *started = None;
+            }
+        }
+        if let Some(pb) = self.bars.get(index) {
+            pb.reset();
+            pb.enable_steady_tick(Duration::from_millis(100));
+        }
+        self.update(index, BuildState::Starting, "retrying");
+    }
+
+    fn retries_of(&self, index: usize) -> u32 {
+        self.retries
+            .lock()
+            .ok()
+            .and_then(|r| r.get(index).copied())
+            .unwrap_or(0)
+    }
+
+    /// Mark a target as cancelled because another one failed
+    pub fn cancel(&self, index: usize) {
+        if let Some(pb) = self.bars.get(index) {
+            let target = self.targets.get(index).map(|s| s.as_str()).unwrap_or("");
+            let msg = format!("{} cancelled", target);
+            if let Ok(mut results) = self.results.lock() {
+                if index < results.len() {
+                    results[index] = (false, msg.clone());
+                }
+            }
+            pb.set_prefix(format!("{}", style("[--]").dim()));
+            pb.finish_with_message(msg);
+        }
+    }
+
     /// Print final results to stdout (call after all builds complete)
     pub fn print_results(&self) {
         // First, finish and clear all progress bars
@@ -137,9 +568,9 @@ impl BuildProgress {
                     continue;
                 }
                 if *success {
-                    println!("{} {}", style("[OK]").green().bold(), msg);
+                    say!("{} {}", style("[OK]").green().bold(), msg);
                 } else {
-                    println!("{} {}", style("[XX]").red().bold(), msg);
+                    say!("{} {}", style("[XX]").red().bold(), msg);
                 }
             }
         }
@@ -168,49 +599,64 @@ pub enum BuildState {
 
 /// Print a status message (cyan, bold prefix)
 pub fn status(prefix: &str, message: &str) {
-    println!("{} {}", style(prefix).cyan().bold(), message);
+    say!("{}{} {}", timestamp(), style(prefix).cyan().bold(), message);
 }
 
 /// Print an info message (blue)
 pub fn info(message: &str) {
-    println!("{}", style(message).blue());
+    say!("{}{}", timestamp(), style(message).blue());
 }
 
 /// Print a success message (green)
 pub fn success(message: &str) {
-    println!("{}", style(message).green());
+    say!("{}{}", timestamp(), style(message).green());
 }
 
 /// Print a warning message (yellow)
 #[allow(dead_code)]
 pub fn warning(message: &str) {
-    println!("{} {}", style("warning:").yellow(), message);
+    say!("{}{} {}", timestamp(), style("warning:").yellow(), message);
+}
+
+/// Print a hint on how to fix an error (cyan)
+pub fn hint(message: &str) {
+    say!("{} {}", style("hint:").cyan().bold(), message);
 }
 
 /// Print an error message (red)
 pub fn error(message: &str) {
-    eprintln!("{} {}", style("error:").red(), message);
+    eprintln!("{}{} {}", timestamp(), style("error:").red(), message);
 }
 
 /// Print a section header
 pub fn header(message: &str) {
-    println!("\n{}", style(format!("==> {}", message)).magenta().bold());
+    say!("\n{}", style(format!("==> {}", message)).magenta().bold());
 }
 
 /// Print a list item
 pub fn list_item(item: &str) {
-    println!("  {} {}", style("-").dim(), item);
+    say!("  {} {}", style("-").dim(), item);
 }
 
 /// Print a key-value pair
 #[allow(dead_code)]
 pub fn kv(key: &str, value: &str) {
-    println!("  {} {}", style(format!("{}:", key)).dim(), value);
+    say!("  {} {}", style(format!("{}:", key)).dim(), value);
 }
 
 /// Print command being executed (dimmed)
 pub fn command(cmd: &str) {
-    println!("{}", style(format!("$ {}", cmd)).dim());
+    say!("{}", style(format!("$ {}", cmd)).dim());
+}
+
+/// Format an estimate in whole seconds (e.g. "45s" or "1m 5s")
+fn format_remaining(duration: Duration) -> String {
+    let secs = duration.as_secs().max(1);
+    if secs >= 60 {
+        format_duration(Duration::from_secs(secs))
+    } else {
+        format!("{}s", secs)
+    }
 }
 
 /// Format a duration as human-readable string
@@ -226,26 +672,45 @@ pub fn format_duration(duration: Duration) -> String {
 }
 
 /// Print the final summary with optional timing
-pub fn summary(succeeded: usize, failed: usize, total_time: Option<Duration>) {
-    println!();
+pub fn summary(
+    succeeded: usize,
+    failed: usize,
+    cancelled: usize,
+    up_to_date: usize,
+    total_time: Option<Duration>,
+) {
+    say!();
     let time_str = total_time
         .map(|d| format!(" in {}", format_duration(d)))
         .unwrap_or_default();
+    let cancelled_str = if cancelled > 0 {
+        format!(", {} cancelled", cancelled)
+    } else {
+        String::new()
+    };
+    let up_to_date_str = if up_to_date > 0 {
+        format!(", {} up to date", up_to_date)
+    } else {
+        String::new()
+    };
 
     if failed == 0 {
-        println!(
-            "{} {} succeeded, {} failed{}",
+        summary_line!(
+            "{} {} succeeded, {} failed{}{}",
             style("Build complete:").green().bold(),
             succeeded,
             failed,
+            up_to_date_str,
             time_str
         );
     } else {
-        println!(
-            "{} {} succeeded, {}{}",
+        summary_line!(
+            "{} {} succeeded, {}{}{}{}",
             style("Build complete:").red().bold(),
             style(format!("{}", succeeded)).green(),
             style(format!("{} failed", failed)).red(),
+            cancelled_str,
+            up_to_date_str,
             time_str
         );
     }
@@ -253,29 +718,87 @@ pub fn summary(succeeded: usize, failed: usize, total_time: Option<Duration>) {
 
 /// Print build error output with formatting
 pub fn build_error_output(target: &str, output: &str) {
-    println!("{}", style(format!("--- Output for {} ---", target)).dim());
+    say!("{}", style(format!("--- Output for {} ---", target)).dim());
 
     for line in output.lines() {
         if line.contains("error:") || line.contains("Error") || line.contains("FATAL") {
-            println!("{}", style(line).red());
+            say!("{}", style(line).red());
         } else if line.contains("warning:") {
-            println!("{}", style(line).yellow());
+            say!("{}", style(line).yellow());
         } else {
-            println!("{}", style(line).dim());
+            say!("{}", style(line).dim());
         }
     }
 
-    println!("{}", style("--- End output ---").dim());
+    say!("{}", style("--- End output ---").dim());
+}
+
+// === CI output functions ===
+
+/// Print a collapsible log group (`::group::`) in one piece, so parallel
+/// builds don't interleave inside it
+pub fn ci_group(title: &str, body: &str) {
+    let mut group = format!("::group::{}\n", escape_data(title));
+    for line in body.lines() {
+        group.push_str(line);
+        group.push('\n');
+    }
+    group.push_str("::endgroup::");
+    say!("{}", group);
+}
+
+/// Location of a CI annotation in the repository
+pub struct CiLocation<'a> {
+    pub file: &'a str,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// Print an `::error` or `::warning` annotation
+pub fn ci_annotation(error: bool, title: &str, location: Option<CiLocation>, message: &str) {
+    let mut properties = vec![format!("title={}", escape_property(title))];
+    if let Some(location) = location {
+        properties.push(format!("file={}", escape_property(location.file)));
+        if let Some(line) = location.line {
+            properties.push(format!("line={}", line));
+        }
+        if let Some(column) = location.column {
+            properties.push(format!("col={}", column));
+        }
+    }
+    say!(
+        "::{} {}::{}",
+        if error { "error" } else { "warning" },
+        properties.join(","),
+        escape_data(message)
+    );
+}
+
+/// Escape a workflow command message
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
 }
 
 // === Verbose output functions ===
 
 /// Print a header for verbose build output (sequential mode)
 pub fn verbose_header(target: &str) {
+    if ci() {
+        say!("::group::Building {}", escape_data(target));
+        return;
+    }
     let separator = "=".repeat(60);
-    println!("\n{}", style(&separator).cyan().bold());
-    println!("{}", style(format!("Building: {}", target)).cyan().bold());
-    println!("{}\n", style(&separator).cyan().bold());
+    say!("\n{}", style(&separator).cyan().bold());
+    say!("{}", style(format!("Building: {}", target)).cyan().bold());
+    say!("{}\n", style(&separator).cyan().bold());
 }
 
 /// Print result after verbose build (sequential mode)
@@ -289,24 +812,27 @@ pub fn verbose_result(
         .map(|d| format!(" in {}", format_duration(d)))
         .unwrap_or_default();
 
-    println!();
+    if ci() {
+        say!("::endgroup::");
+    }
+    say!();
     if success {
-        println!(
+        say!(
             "{}{}",
             style(format!("✓ {} succeeded", target)).green().bold(),
             time_str
         );
         if let Some(path) = artifact {
-            println!("  Artifact: {}", path.display());
+            say!("  Artifact: {}", path.display());
         }
     } else {
-        println!(
+        say!(
             "{}{}",
             style(format!("✗ {} failed", target)).red().bold(),
             time_str
         );
     }
-    println!();
+    say!();
 }
 
 /// Color palette for target prefixes (rotating)
@@ -326,13 +852,14 @@ pub fn styled_target(target: &str, index: usize) -> String {
 
 /// Print a line with colored target prefix (for parallel verbose mode)
 pub fn verbose_line(target: &str, index: usize, line: &str) {
-    println!("{} {}", styled_target(target, index), line);
+    say!("{}{} {}", timestamp(), styled_target(target, index), line);
 }
 
 /// Print a start marker for parallel verbose mode
 pub fn verbose_start(target: &str, index: usize) {
-    println!(
-        "{} {}",
+    say!(
+        "{}{} {}",
+        timestamp(),
         styled_target(target, index),
         style("starting build...").dim()
     );
@@ -354,16 +881,18 @@ pub fn verbose_done(
         let artifact_str = artifact
             .map(|p| format!(" → {}", p.file_name().unwrap_or_default().to_string_lossy()))
             .unwrap_or_default();
-        println!(
-            "{} {}{}{}",
+        say!(
+            "{}{} {}{}{}",
+            timestamp(),
             styled_target(target, index),
             style("✓ succeeded").green(),
             time_str,
             artifact_str
         );
     } else {
-        println!(
-            "{} {}{}",
+        say!(
+            "{}{} {}{}",
+            timestamp(),
             styled_target(target, index),
             style("✗ failed").red(),
             time_str
@@ -382,8 +911,71 @@ pub fn build_status(target: &str, state: BuildState, message: &str) {
 
     let prefix = color_fn(format!("[{}]", symbol));
     if message.is_empty() {
-        println!("{} {}", prefix, target);
+        say!("{}{} {}", timestamp(), prefix, target);
     } else {
-        println!("{} {} {}", prefix, target, style(message).dim());
+        say!(
+            "{}{} {} {}",
+            timestamp(),
+            prefix,
+            target,
+            style(message).dim()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_enabled() {
+        assert!(colors_enabled(ColorChoice::Auto, false, false, true));
+        assert!(!colors_enabled(ColorChoice::Auto, true, false, true));
+        assert!(!colors_enabled(ColorChoice::Auto, false, true, true));
+        assert!(!colors_enabled(ColorChoice::Auto, false, false, false));
+        assert!(colors_enabled(ColorChoice::Always, true, true, false));
+        assert!(!colors_enabled(ColorChoice::Never, false, false, true));
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let elapsed = Duration::from_millis(62_345);
+        assert_eq!(
+            format_timestamp(Timestamps::Elapsed, elapsed, now),
+            "01:02.3"
+        );
+        assert_eq!(
+            format_timestamp(Timestamps::Elapsed, Duration::from_millis(4_000), now),
+            "00:04.0"
+        );
+        assert_eq!(format_timestamp(Timestamps::Wall, elapsed, now), "22:13:20");
+    }
+
+    #[test]
+    fn test_escape_workflow_commands() {
+        assert_eq!(escape_data("50% done\nnext"), "50%25 done%0Anext");
+        assert_eq!(escape_property("config/a,b:c"), "config/a%2Cb%3Ac");
+    }
+
+    #[test]
+    fn test_fuzzy_filter_empty_query_keeps_all() {
+        let items = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(fuzzy_filter(&items, ""), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_fuzzy_filter_matches_subsequence() {
+        let items = vec![
+            "corne_left-nice_nano_v2-zmk".to_string(),
+            "corne_right-nice_nano_v2-zmk".to_string(),
+            "settings_reset-nice_nano_v2-zmk".to_string(),
+        ];
+        let matches = fuzzy_filter(&items, "crnl");
+        assert_eq!(matches, vec![0]);
+
+        let matches = fuzzy_filter(&items, "corne");
+        assert_eq!(matches.len(), 2);
+        assert!(!matches.contains(&2));
     }
 }
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 
 /// Anonymize a path by replacing the user's home directory with ~
@@ -12,6 +13,26 @@ pub fn anonymize_path(path: &Path) -> String {
     path.display().to_string()
 }
 
+/// Calculate directory size recursively
+pub fn dir_size(path: &Path) -> u64 {
+    if !path.exists() {
+        return 0;
+    }
+
+    let mut size = 0;
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                size += dir_size(&path);
+            } else if let Ok(meta) = entry.metadata() {
+                size += meta.len();
+            }
+        }
+    }
+    size
+}
+
 /// Get the cache directory for lfz
 /// Uses platform-appropriate location:
 /// - Linux: ~/.cache/lfz
@@ -24,16 +45,55 @@ pub fn cache_dir() -> Result<PathBuf> {
     Ok(proj_dirs.cache_dir().to_path_buf())
 }
 
+/// Get the file remembering the container runtime that last worked
+pub fn runtime_file() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("runtime"))
+}
+
+/// Get the directory recording when directories were last copied to the
+/// volumes of a remote daemon
+pub fn remote_sync_dir(host: &str) -> Result<PathBuf> {
+    let hash = Sha256::digest(host.as_bytes());
+    Ok(cache_dir()?.join("remote").join(hex::encode(&hash[..6])))
+}
+
 /// Get the directory where west workspaces are cached
 pub fn workspaces_dir() -> Result<PathBuf> {
     Ok(cache_dir()?.join("workspaces"))
 }
 
+/// Get the directory where per-target build logs are kept
+pub fn logs_dir() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("logs"))
+}
+
+/// Get the directory where build run statistics are kept
+pub fn history_dir() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("history"))
+}
+
+/// Get the directory where built firmware is cached by its inputs
+pub fn artifacts_dir() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("artifacts"))
+}
+
 /// Get the shared ccache directory
 pub fn ccache_dir() -> Result<PathBuf> {
     Ok(cache_dir()?.join("ccache"))
 }
 
+/// Get the ccache directory of builds in one image (by digest, or tag if
+/// unknown). Images get a cache each, so their toolchains never mix.
+pub fn image_ccache_dir(image: &str) -> Result<PathBuf> {
+    let hash = Sha256::digest(image.as_bytes());
+    Ok(ccache_dir()?.join(hex::encode(&hash[..6])))
+}
+
+/// Get the shared sccache directory
+pub fn sccache_dir() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("sccache"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,4 +103,13 @@ mod tests {
         let dir = cache_dir().unwrap();
         assert!(dir.to_string_lossy().contains("lfz"));
     }
+
+    #[test]
+    fn test_image_ccache_dir() {
+        let stable = image_ccache_dir("zmkfirmware/zmk-build-arm@sha256:0a1b").unwrap();
+        let next = image_ccache_dir("zmkfirmware/zmk-build-arm@sha256:2c3d").unwrap();
+        assert_ne!(stable, next);
+        assert_eq!(stable.parent().unwrap(), ccache_dir().unwrap());
+        assert_eq!(stable.file_name().unwrap().len(), 12);
+    }
 }
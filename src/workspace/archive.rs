@@ -0,0 +1,177 @@
+//! Workspaces packed into a tarball, to build on a machine without internet
+//! access or to seed someone else's cache.
+//!
+//! ```text
+//! lfz-workspace.json   the workspace's id, project and build image
+//! image.tar            the build image itself (optional)
+//! <id>/...             the workspace, without its build directories
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use super::metadata::WorkspaceMetadata;
+
+/// Describes the archive's contents
+pub const MANIFEST_FILE: &str = "lfz-workspace.json";
+
+/// The build image, as saved by the container runtime
+pub const IMAGE_FILE: &str = "image.tar";
+
+/// What an archive holds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Directory name of the workspace in the cache
+    pub id: String,
+    /// What the workspace belongs to, if recorded
+    pub metadata: Option<WorkspaceMetadata>,
+    /// Image the workspace was built with
+    pub image: String,
+    /// Its digest on the exporting machine
+    pub image_digest: Option<String>,
+    /// Whether [`IMAGE_FILE`] is in the archive
+    pub includes_image: bool,
+}
+
+/// Write a gzipped tarball of a workspace along with the files in
+/// `staging` (the manifest, and the image if exported)
+pub fn pack(workspace: &Path, staging: &Path, output: &Path) -> Result<()> {
+    let id = workspace
+        .file_name()
+        .context("Workspace path has no directory name")?
+        .to_string_lossy()
+        .to_string();
+    let parent = workspace
+        .parent()
+        .context("Workspace path has no parent directory")?;
+    let mut extras: Vec<String> = fs::read_dir(staging)
+        .with_context(|| format!("Failed to read {}", staging.display()))?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    extras.sort();
+
+    let status = Command::new("tar")
+        // Build directories are specific to the machine's targets, and big
+        .arg(format!("--exclude={}/build", id))
+        .arg("-czf")
+        .arg(output)
+        .arg("-C")
+        .arg(staging)
+        .args(&extras)
+        .arg("-C")
+        .arg(parent)
+        .arg(&id)
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        anyhow::bail!("Failed to write {}", output.display());
+    }
+    Ok(())
+}
+
+/// Extract an archive into `staging` (which must exist), returning its
+/// manifest. The workspace ends up in `staging/<id>`.
+pub fn unpack(archive: &Path, staging: &Path) -> Result<ArchiveManifest> {
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}", archive.display());
+    }
+
+    let path = staging.join(MANIFEST_FILE);
+    let contents = fs::read_to_string(&path).with_context(|| {
+        format!(
+            "{} is not an lfz workspace archive (no {})",
+            archive.display(),
+            MANIFEST_FILE
+        )
+    })?;
+    let manifest: ArchiveManifest = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", MANIFEST_FILE))?;
+    if !is_workspace_name(&manifest.id) || !staging.join(&manifest.id).is_dir() {
+        anyhow::bail!("{} holds no workspace", archive.display());
+    }
+    Ok(manifest)
+}
+
+/// Whether an archive's id names a workspace directory: a hex key, with a
+/// scratch suffix like `-zmk-pr-1234`. Anything else (`..`, say) could
+/// point the import outside the workspaces directory.
+fn is_workspace_name(id: &str) -> bool {
+    let (key, scratch) = id.split_once('-').unwrap_or((id, ""));
+    !key.is_empty()
+        && key.chars().all(|c| c.is_ascii_hexdigit())
+        && scratch
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path().join("workspaces/a1b2c3");
+        fs::create_dir_all(workspace.join(".west")).unwrap();
+        fs::create_dir_all(workspace.join("zmk/app")).unwrap();
+        fs::create_dir_all(workspace.join("build/corne_left")).unwrap();
+        fs::write(workspace.join(".west/config"), "[manifest]\n").unwrap();
+        fs::write(workspace.join("zmk/app/west.yml"), "manifest:").unwrap();
+        fs::write(workspace.join("build/corne_left/zmk.uf2"), "uf2").unwrap();
+
+        let staging = dir.path().join("staging");
+        fs::create_dir(&staging).unwrap();
+        let manifest = ArchiveManifest {
+            id: "a1b2c3".to_string(),
+            metadata: None,
+            image: "zmkfirmware/zmk-build-arm:stable".to_string(),
+            image_digest: Some("sha256:abc".to_string()),
+            includes_image: false,
+        };
+        fs::write(
+            staging.join(MANIFEST_FILE),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        let archive = dir.path().join("workspace.tar.gz");
+        pack(&workspace, &staging, &archive).unwrap();
+
+        let extracted = dir.path().join("extracted");
+        fs::create_dir(&extracted).unwrap();
+        assert_eq!(unpack(&archive, &extracted).unwrap(), manifest);
+        let restored = extracted.join("a1b2c3");
+        assert!(restored.join(".west/config").is_file());
+        assert!(restored.join("zmk/app/west.yml").is_file());
+        assert!(!restored.join("build").exists());
+
+        // Not an lfz archive
+        let other = dir.path().join("other");
+        fs::create_dir(&other).unwrap();
+        fs::write(staging.join("README"), "").unwrap();
+        fs::remove_file(staging.join(MANIFEST_FILE)).unwrap();
+        pack(&workspace, &staging, &archive).unwrap();
+        assert!(unpack(&archive, &other).is_err());
+    }
+
+    #[test]
+    fn test_is_workspace_name() {
+        assert!(is_workspace_name("a1b2c3"));
+        assert!(is_workspace_name("a1b2c3-zmk-pr-1234"));
+        assert!(!is_workspace_name(".."));
+        assert!(!is_workspace_name(""));
+        assert!(!is_workspace_name("a1b2/../c3"));
+        assert!(!is_workspace_name("-zmk-pr-1"));
+    }
+}
@@ -0,0 +1,269 @@
+//! Access to private module repositories from west containers.
+
+use anyhow::Result;
+use std::env;
+use std::path::PathBuf;
+
+use crate::config::lfz_toml::{GitCredentials, SshConfig};
+use crate::config::west_yml::WestManifest;
+use crate::container::{ContainerCommand, Runtime};
+
+/// Agent socket path inside the container
+const CONTAINER_AGENT_SOCK: &str = "/run/lfz-ssh-agent.sock";
+
+/// Private key and known hosts paths inside the container, outside the
+/// home directory since ssh looks for it through the user database rather
+/// than `$HOME`
+const CONTAINER_KEY: &str = "/run/lfz-ssh-key";
+const CONTAINER_KNOWN_HOSTS: &str = "/run/lfz-known-hosts";
+
+/// Credential store path inside the container
+const CONTAINER_GIT_CREDENTIALS: &str = "/run/lfz-git-credentials";
+
+/// Environment variable holding an HTTPS token, on the host and in the container
+const TOKEN_ENV: &str = "LFZ_GIT_TOKEN";
+
+/// Docker Desktop's forwarded agent socket inside its VM (macOS)
+const DOCKER_DESKTOP_AGENT_SOCK: &str = "/run/host-services/ssh-auth.sock";
+
+/// Whether a git URL is fetched over SSH (`ssh://...` or scp-like `user@host:path`)
+pub fn is_ssh_url(url: &str) -> bool {
+    if url.starts_with("ssh://") || url.starts_with("git+ssh://") {
+        return true;
+    }
+    if url.contains("://") {
+        return false;
+    }
+    match (url.find('@'), url.find(':')) {
+        (Some(at), Some(colon)) => at < colon && !url[..colon].contains('/'),
+        _ => false,
+    }
+}
+
+/// Fail early if west.yml needs SSH but no SSH access is configured
+pub fn check_ssh_access(manifest: &WestManifest, ssh: &SshConfig) -> Result<()> {
+    if ssh.is_configured() {
+        return Ok(());
+    }
+
+    let ssh_projects: Vec<&str> = manifest
+        .projects
+        .iter()
+        .filter(|p| manifest.fetch_url(p).is_some_and(|url| is_ssh_url(&url)))
+        .map(|p| p.name.as_str())
+        .collect();
+
+    if !ssh_projects.is_empty() {
+        anyhow::bail!(
+            "west.yml fetches {} over SSH, but the build container has no SSH credentials.\n\
+             Add one of these to lfz.toml:\n\
+             \n  [ssh]\n  forward-agent = true            # use keys loaded in ssh-agent\n  \
+             key = \"~/.ssh/id_ed25519\"       # or mount a specific key read-only",
+            ssh_projects.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Forward the SSH agent and/or mount a key into a west container
+pub fn apply_ssh(
+    mut cmd: ContainerCommand,
+    ssh: &SshConfig,
+    runtime: &Runtime,
+) -> Result<ContainerCommand> {
+    if !ssh.is_configured() {
+        return Ok(cmd);
+    }
+
+    let known_hosts_option = format!("UserKnownHostsFile={}", CONTAINER_KNOWN_HOSTS);
+    let mut ssh_command = vec!["ssh", "-o", "StrictHostKeyChecking=accept-new"];
+
+    if ssh.forward_agent {
+        cmd = cmd
+            .mount(agent_socket(runtime)?, CONTAINER_AGENT_SOCK, false)
+            .env("SSH_AUTH_SOCK", CONTAINER_AGENT_SOCK);
+    }
+
+    if let Some(key) = ssh.key_path() {
+        if !key.is_file() {
+            anyhow::bail!("SSH key '{}' from lfz.toml does not exist", key.display());
+        }
+        cmd = cmd.mount(&key, CONTAINER_KEY, true);
+        ssh_command.extend(["-i", CONTAINER_KEY]);
+        if !ssh.forward_agent {
+            ssh_command.extend(["-o", "IdentitiesOnly=yes"]);
+        }
+    }
+
+    // Reuse the host's known hosts so existing trust carries over
+    if let Some(known_hosts) = dirs::home_dir()
+        .map(|home| home.join(".ssh").join("known_hosts"))
+        .filter(|path| path.is_file())
+    {
+        cmd = cmd.mount(known_hosts, CONTAINER_KNOWN_HOSTS, true);
+        ssh_command.extend(["-o", &known_hosts_option]);
+    }
+
+    Ok(cmd.env("GIT_SSH_COMMAND", ssh_command.join(" ")))
+}
+
+/// Host path of the SSH agent socket to mount
+fn agent_socket(runtime: &Runtime) -> Result<PathBuf> {
+    // Docker Desktop can't mount macOS sockets, but exposes the agent itself
+    if cfg!(target_os = "macos") && *runtime == Runtime::Docker {
+        return Ok(PathBuf::from(DOCKER_DESKTOP_AGENT_SOCK));
+    }
+
+    match env::var_os("SSH_AUTH_SOCK").map(PathBuf::from) {
+        Some(sock) if sock.exists() => Ok(sock),
+        Some(sock) => anyhow::bail!(
+            "SSH agent forwarding is enabled in lfz.toml, but SSH_AUTH_SOCK ({}) does not exist.\n\
+             Start ssh-agent and add your key with 'ssh-add'.",
+            sock.display()
+        ),
+        None => anyhow::bail!(
+            "SSH agent forwarding is enabled in lfz.toml, but SSH_AUTH_SOCK is not set.\n\
+             Start ssh-agent and add your key with 'ssh-add'."
+        ),
+    }
+}
+
+/// Pass HTTPS credentials into a west container
+///
+/// A token is exposed to git through a credential helper that reads it
+/// from the environment, so it never lands in git config, the command
+/// line or the workspace.
+pub fn apply_git_credentials(
+    cmd: ContainerCommand,
+    git: &GitCredentials,
+) -> Result<ContainerCommand> {
+    apply_with_env(cmd, git, |name| env::var(name).ok())
+}
+
+/// [`apply_git_credentials`], reading the token variables through `var`
+fn apply_with_env(
+    mut cmd: ContainerCommand,
+    git: &GitCredentials,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<ContainerCommand> {
+    let token = match &git.token_env {
+        Some(name) => match var(name) {
+            Some(token) => Some(token),
+            None => anyhow::bail!(
+                "lfz.toml reads the git token from ${}, but it is not set",
+                name
+            ),
+        },
+        None => var(TOKEN_ENV),
+    };
+
+    if let Some(token) = token.filter(|t| !t.is_empty()) {
+        cmd = cmd.secret_env(TOKEN_ENV, token).git_config(
+            format!("credential.https://{}.helper", git.token_host()),
+            format!(
+                "!f() {{ test \"$1\" = get && echo username=x-access-token && echo \"password=${}\"; }}; f",
+                TOKEN_ENV
+            ),
+        );
+    }
+
+    let home = dirs::home_dir();
+    if git.netrc {
+        let netrc = home.as_ref().map(|h| h.join(".netrc"));
+        match netrc.filter(|p| p.is_file()) {
+            Some(netrc) => cmd = cmd.mount(netrc, "/root/.netrc", true),
+            None => anyhow::bail!("lfz.toml enables [git] netrc, but ~/.netrc does not exist"),
+        }
+    }
+    if git.git_credentials {
+        let store = home.as_ref().map(|h| h.join(".git-credentials"));
+        match store.filter(|p| p.is_file()) {
+            Some(store) => {
+                cmd = cmd
+                    .mount(store, CONTAINER_GIT_CREDENTIALS, true)
+                    .git_config(
+                        "credential.helper",
+                        format!("store --file={}", CONTAINER_GIT_CREDENTIALS),
+                    );
+            }
+            None => anyhow::bail!(
+                "lfz.toml enables [git] git-credentials, but ~/.git-credentials does not exist"
+            ),
+        }
+    }
+
+    Ok(cmd)
+}
+
+/// A hint for git authentication failures in west output, if any
+pub fn auth_failure_hint(output: &str) -> Option<&'static str> {
+    if output.contains("Permission denied (publickey)") {
+        Some(
+            "SSH authentication failed. Check that the key in lfz.toml (or loaded in \
+             ssh-agent with [ssh] forward-agent = true) has access to the repository.",
+        )
+    } else if output.contains("Host key verification failed") {
+        Some("SSH host key verification failed. Connect once from the host (e.g. 'ssh -T git@github.com') to trust the host key.")
+    } else if output.contains("could not read Username")
+        || output.contains("Authentication failed for")
+    {
+        Some(
+            "HTTPS authentication failed. Export a token as LFZ_GIT_TOKEN (or name the \
+             variable with [git] token-env in lfz.toml), or enable [git] netrc.",
+        )
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ssh_url() {
+        assert!(is_ssh_url("git@github.com:user/repo.git"));
+        assert!(is_ssh_url("ssh://git@github.com/user/repo"));
+        assert!(!is_ssh_url("https://github.com/user/repo"));
+        assert!(!is_ssh_url("https://user@github.com/user/repo"));
+        assert!(!is_ssh_url("../local/module"));
+    }
+
+    #[test]
+    fn test_check_ssh_access() {
+        let manifest = WestManifest::parse(
+            "manifest:\n  projects:\n    - name: private\n      url: git@github.com:me/private\n",
+        )
+        .unwrap();
+
+        let err = check_ssh_access(&manifest, &SshConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("fetches private over SSH"));
+
+        let ssh = SshConfig {
+            forward_agent: true,
+            key: None,
+        };
+        assert!(check_ssh_access(&manifest, &ssh).is_ok());
+    }
+
+    #[test]
+    fn test_apply_git_token() {
+        let git = GitCredentials {
+            token_env: Some("LFZ_TEST_TOKEN_SET".to_string()),
+            ..Default::default()
+        };
+        let var = |name: &str| (name == "LFZ_TEST_TOKEN_SET").then(|| "s3cret".to_string());
+        let cmd = ContainerCommand::new(Runtime::Docker, "image");
+        let s = apply_with_env(cmd, &git, var).unwrap().as_string();
+        assert!(s.contains("-e LFZ_GIT_TOKEN "));
+        assert!(s.contains("credential.https://github.com.helper"));
+        assert!(!s.contains("s3cret"));
+
+        let missing = GitCredentials {
+            token_env: Some("LFZ_TEST_TOKEN_MISSING".to_string()),
+            ..Default::default()
+        };
+        let cmd = ContainerCommand::new(Runtime::Docker, "image");
+        assert!(apply_with_env(cmd, &missing, var).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+//! Discovers boards and shields available in a west workspace.
+//!
+//! Boards are defined in "board roots": Zephyr's own `boards/` directory,
+//! ZMK's `app/boards/`, and any Zephyr module that declares a `board_root`
+//! in its `zephyr/module.yml`. Both hardware model v2 (`board.yml`) and the
+//! legacy v1 layout (`boards/<arch>/<board>/Kconfig.board`) are supported.
+//! Shields live in `boards/shields/<shield>/` of the same roots.
+
+use anyhow::Result;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories inside a workspace that never contain modules
+const SKIPPED_DIRS: &[&str] = &["build", ".west", "config"];
+
+/// How deep to look for `zephyr/module.yml` below the workspace root
+const MODULE_SEARCH_DEPTH: usize = 3;
+
+/// A board found in one of the workspace board roots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardInfo {
+    /// Board identifier as passed to `west build -b`
+    pub name: String,
+    /// CPU architecture (e.g., "arm", "riscv"), if it could be determined
+    pub arch: Option<String>,
+    /// Where the board was found (e.g., "zephyr", "zmk", or a module name)
+    pub source: String,
+}
+
+/// A shield found in one of the workspace board roots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShieldInfo {
+    /// Shield identifier as passed to `-DSHIELD=` (e.g., "corne_left")
+    pub name: String,
+    /// Shield directory the variant belongs to (e.g., "corne")
+    pub family: String,
+    /// Where the shield was found (e.g., "zmk" or a module name)
+    pub source: String,
+}
+
+/// A directory that contains `boards/` (and possibly `boards/shields/`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardRoot {
+    /// Directory containing the `boards/` folder
+    pub path: PathBuf,
+    /// Display name for where this root comes from
+    pub source: String,
+}
+
+/// Collect all board roots for a workspace plus any extra (host) modules
+pub fn board_roots(workspace: &Path, extra_modules: &[PathBuf]) -> Vec<BoardRoot> {
+    let mut roots = vec![
+        BoardRoot {
+            path: workspace.join("zephyr"),
+            source: "zephyr".to_string(),
+        },
+        BoardRoot {
+            path: workspace.join("zmk").join("app"),
+            source: "zmk".to_string(),
+        },
+    ];
+
+    let mut modules = Vec::new();
+    find_modules(workspace, MODULE_SEARCH_DEPTH, &mut modules);
+    modules.sort();
+    modules.extend(extra_modules.iter().cloned());
+
+    for module in modules {
+        if let Some(path) = module_board_root(&module) {
+            let source = module
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| module.display().to_string());
+            roots.push(BoardRoot { path, source });
+        }
+    }
+
+    // The same root may be reachable both explicitly and through module.yml
+    let mut seen = Vec::new();
+    roots.retain(|root| {
+        let key = root
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| root.path.clone());
+        if root.path.join("boards").is_dir() && !seen.contains(&key) {
+            seen.push(key);
+            true
+        } else {
+            false
+        }
+    });
+
+    roots
+}
+
+/// Discover all boards in the given board roots, sorted by name
+pub fn discover_boards(roots: &[BoardRoot]) -> Result<Vec<BoardInfo>> {
+    // Keyed by name so a board defined in several roots is listed once
+    // (first root wins, matching Zephyr's BOARD_ROOT search order)
+    let mut boards: BTreeMap<String, BoardInfo> = BTreeMap::new();
+
+    for root in roots {
+        let mut found = Vec::new();
+        scan_boards_dir(&root.path.join("boards"), &root.source, &mut found)?;
+        for board in found {
+            boards.entry(board.name.clone()).or_insert(board);
+        }
+    }
+
+    Ok(boards.into_values().collect())
+}
+
+/// Discover all keyboard shields in the given board roots, sorted by name
+///
+/// Zephyr's own shields (displays, sensors, ...) are not keyboard shields
+/// and are skipped.
+pub fn discover_shields(roots: &[BoardRoot]) -> Result<Vec<ShieldInfo>> {
+    let mut shields: BTreeMap<String, ShieldInfo> = BTreeMap::new();
+
+    for root in roots.iter().filter(|r| r.source != "zephyr") {
+        let shields_dir = root.path.join("boards").join("shields");
+        let Ok(entries) = fs::read_dir(&shields_dir) else {
+            continue;
+        };
+
+        let mut dirs: Vec<_> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs {
+            let family = dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            for name in shield_names(&dir)? {
+                shields.entry(name.clone()).or_insert(ShieldInfo {
+                    name,
+                    family: family.clone(),
+                    source: root.source.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(shields.into_values().collect())
+}
+
+/// Find shield names in a single shield directory
+///
+/// Uses `shield.yml` if present, otherwise every top-level `<name>.overlay`
+/// is a shield (this is how split halves like `corne_left`/`corne_right`
+/// are defined).
+fn shield_names(dir: &Path) -> Result<Vec<String>> {
+    let shield_yml = dir.join("shield.yml");
+    if let Some(yaml) = fs::read_to_string(&shield_yml)
+        .ok()
+        .and_then(|c| serde_yaml::from_str::<Value>(&c).ok())
+    {
+        let mut names = Vec::new();
+        if let Some(name) = yaml.get("shield").and_then(|s| s.get("name")) {
+            names.extend(name.as_str().map(String::from));
+        }
+        if let Some(list) = yaml.get("shields").and_then(|s| s.as_sequence()) {
+            names.extend(
+                list.iter()
+                    .filter_map(|s| s.get("name")?.as_str().map(String::from)),
+            );
+        }
+        if !names.is_empty() {
+            return Ok(names);
+        }
+    }
+
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("overlay"))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Recursively find directories containing `zephyr/module.yml`
+fn find_modules(dir: &Path, depth: usize, modules: &mut Vec<PathBuf>) {
+    if depth == 0 {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if path.join("zephyr").join("module.yml").is_file() {
+            modules.push(path);
+        } else {
+            find_modules(&path, depth - 1, modules);
+        }
+    }
+}
+
+/// Read `build.settings.board_root` from a module's `zephyr/module.yml`
+fn module_board_root(module: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(module.join("zephyr").join("module.yml")).ok()?;
+    let yaml: Value = serde_yaml::from_str(&content).ok()?;
+    let board_root = yaml
+        .get("build")?
+        .get("settings")?
+        .get("board_root")?
+        .as_str()?;
+    Some(module.join(board_root))
+}
+
+/// Scan a `boards/` directory tree for board definitions
+fn scan_boards_dir(dir: &Path, source: &str, boards: &mut Vec<BoardInfo>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+
+    let board_yml = dir.join("board.yml");
+    if board_yml.is_file() {
+        // Hardware model v2: board.yml names one or more boards
+        let arch = twister_arch(dir);
+        for name in board_yml_names(&board_yml) {
+            boards.push(BoardInfo {
+                name,
+                arch: arch.clone(),
+                source: source.to_string(),
+            });
+        }
+        return Ok(());
+    }
+
+    if dir.join("Kconfig.board").is_file() {
+        // Hardware model v1: boards/<arch>/<board>/<name>_defconfig
+        let arch = dir
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string());
+        for entry in fs::read_dir(dir)?.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(name) = file_name.strip_suffix("_defconfig") {
+                boards.push(BoardInfo {
+                    name: name.to_string(),
+                    arch: arch.clone(),
+                    source: source.to_string(),
+                });
+            }
+        }
+        return Ok(());
+    }
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Shields live alongside boards but are not boards themselves
+        if path.is_dir() && entry.file_name() != "shields" {
+            scan_boards_dir(&path, source, boards)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract board names from a hardware model v2 `board.yml`
+fn board_yml_names(path: &Path) -> Vec<String> {
+    let Some(yaml) = fs::read_to_string(path)
+        .ok()
+        .and_then(|c| serde_yaml::from_str::<Value>(&c).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut names = Vec::new();
+    if let Some(name) = yaml.get("board").and_then(|b| b.get("name")) {
+        names.extend(name.as_str().map(String::from));
+    }
+    if let Some(list) = yaml.get("boards").and_then(|b| b.as_sequence()) {
+        names.extend(
+            list.iter()
+                .filter_map(|b| b.get("name")?.as_str().map(String::from)),
+        );
+    }
+    names
+}
+
+/// Find the architecture from a twister metadata file (`<board>.yaml` with `arch:`)
+fn twister_arch(dir: &Path) -> Option<String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("yaml"))
+        .collect();
+    files.sort();
+
+    files.iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        let yaml: Value = serde_yaml::from_str(&content).ok()?;
+        yaml.get("arch")?.as_str().map(String::from)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_hwmv2_board() {
+        let dir = tempdir().unwrap();
+        let board_dir = dir.path().join("zmk/app/boards/nicekeyboards/nice_nano");
+        fs::create_dir_all(&board_dir).unwrap();
+        fs::write(
+            board_dir.join("board.yml"),
+            "board:\n  name: nice_nano\n  vendor: nicekeyboards\n",
+        )
+        .unwrap();
+        fs::write(
+            board_dir.join("nice_nano.yaml"),
+            "identifier: nice_nano\narch: arm\n",
+        )
+        .unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        let boards = discover_boards(&roots).unwrap();
+        assert_eq!(
+            boards,
+            vec![BoardInfo {
+                name: "nice_nano".to_string(),
+                arch: Some("arm".to_string()),
+                source: "zmk".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_discover_hwmv1_board() {
+        let dir = tempdir().unwrap();
+        let board_dir = dir.path().join("zephyr/boards/arm/nice_nano");
+        fs::create_dir_all(&board_dir).unwrap();
+        fs::write(board_dir.join("Kconfig.board"), "").unwrap();
+        fs::write(board_dir.join("nice_nano_defconfig"), "").unwrap();
+        fs::write(board_dir.join("nice_nano_v2_defconfig"), "").unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        let names: Vec<_> = discover_boards(&roots)
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.name, b.arch))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("nice_nano".to_string(), Some("arm".to_string())),
+                ("nice_nano_v2".to_string(), Some("arm".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discover_module_board_root_skips_shields() {
+        let dir = tempdir().unwrap();
+        let module = dir.path().join("modules/my-module");
+        fs::create_dir_all(module.join("zephyr")).unwrap();
+        fs::write(
+            module.join("zephyr/module.yml"),
+            "build:\n  settings:\n    board_root: .\n",
+        )
+        .unwrap();
+        let board_dir = module.join("boards/custom/my_board");
+        fs::create_dir_all(&board_dir).unwrap();
+        fs::write(board_dir.join("board.yml"), "board:\n  name: my_board\n").unwrap();
+        let shield_dir = module.join("boards/shields/my_shield");
+        fs::create_dir_all(&shield_dir).unwrap();
+        fs::write(
+            shield_dir.join("board.yml"),
+            "board:\n  name: not_a_board\n",
+        )
+        .unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].source, "my-module");
+
+        let boards = discover_boards(&roots).unwrap();
+        assert_eq!(boards.len(), 1);
+        assert_eq!(boards[0].name, "my_board");
+        assert_eq!(boards[0].arch, None);
+    }
+
+    #[test]
+    fn test_discover_shields_from_overlays() {
+        let dir = tempdir().unwrap();
+        let corne = dir.path().join("zmk/app/boards/shields/corne");
+        fs::create_dir_all(corne.join("boards")).unwrap();
+        fs::write(corne.join("Kconfig.shield"), "").unwrap();
+        fs::write(corne.join("corne.dtsi"), "").unwrap();
+        fs::write(corne.join("corne_left.overlay"), "").unwrap();
+        fs::write(corne.join("corne_right.overlay"), "").unwrap();
+        // Board-specific overlays are not shields
+        fs::write(corne.join("boards/nice_nano.overlay"), "").unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        let shields = discover_shields(&roots).unwrap();
+        let names: Vec<_> = shields.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["corne_left", "corne_right"]);
+        assert!(shields
+            .iter()
+            .all(|s| s.family == "corne" && s.source == "zmk"));
+    }
+
+    #[test]
+    fn test_discover_shields_skips_zephyr() {
+        let dir = tempdir().unwrap();
+        let display = dir.path().join("zephyr/boards/shields/ssd1306");
+        fs::create_dir_all(&display).unwrap();
+        fs::write(display.join("ssd1306_128x32.overlay"), "").unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        assert!(discover_shields(&roots).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_discover_shields_from_shield_yml() {
+        let dir = tempdir().unwrap();
+        let module = dir.path().join("my-shields");
+        fs::create_dir_all(module.join("zephyr")).unwrap();
+        fs::write(
+            module.join("zephyr/module.yml"),
+            "build:\n  settings:\n    board_root: .\n",
+        )
+        .unwrap();
+        let shield_dir = module.join("boards/shields/chalk");
+        fs::create_dir_all(&shield_dir).unwrap();
+        fs::write(
+            shield_dir.join("shield.yml"),
+            "shields:\n  - name: chalk_left\n  - name: chalk_right\n",
+        )
+        .unwrap();
+
+        let roots = board_roots(dir.path(), &[]);
+        let shields = discover_shields(&roots).unwrap();
+        let names: Vec<_> = shields.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["chalk_left", "chalk_right"]);
+        assert_eq!(shields[0].source, "my-shields");
+    }
+}
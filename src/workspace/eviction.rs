@@ -0,0 +1,172 @@
+//! Least-recently-used eviction of workspaces under a disk quota.
+//!
+//! Every build records when it used its workspace (`.lfz_last_used`). When
+//! all workspaces together take more than the quota set in lfz.toml, the
+//! ones used longest ago are removed until the rest fit. `lfz clean
+//! --older-than` removes those not used for a while instead.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::paths;
+
+/// File in a workspace with the time it was last used (seconds since the epoch)
+const LAST_USED_FILE: &str = ".lfz_last_used";
+
+/// A workspace, its size and when it was last used
+#[derive(Debug, Clone)]
+pub struct WorkspaceUsage {
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// Record that a workspace is being used
+pub fn touch(workspace: &Path) -> Result<()> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = workspace.join(LAST_USED_FILE);
+    fs::write(&path, secs.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// When a workspace was last used; workspaces from before this was recorded
+/// count as last used when their directory last changed
+fn last_used(workspace: &Path) -> SystemTime {
+    fs::read_to_string(workspace.join(LAST_USED_FILE))
+        .ok()
+        .and_then(|contents| contents.trim().parse().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .or_else(|| fs::metadata(workspace).and_then(|m| m.modified()).ok())
+        .unwrap_or(UNIX_EPOCH)
+}
+
+/// Every workspace in a directory of workspaces
+pub fn scan(workspaces_dir: &Path) -> Result<Vec<WorkspaceUsage>> {
+    if !workspaces_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(workspaces_dir)
+        .with_context(|| format!("Failed to read {}", workspaces_dir.display()))?;
+    Ok(entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|path| WorkspaceUsage {
+            size: paths::dir_size(&path),
+            last_used: last_used(&path),
+            path,
+        })
+        .collect())
+}
+
+/// The workspaces to remove, least recently used first, so that the others
+/// take at most `max_size`. The workspaces in `keep` (those in use) are
+/// never among them.
+pub fn plan<'a>(
+    workspaces: &'a [WorkspaceUsage],
+    max_size: u64,
+    keep: &[&Path],
+) -> Vec<&'a WorkspaceUsage> {
+    let mut total: u64 = workspaces.iter().map(|w| w.size).sum();
+    let mut candidates: Vec<&WorkspaceUsage> = workspaces
+        .iter()
+        .filter(|w| !keep.contains(&w.path.as_path()))
+        .collect();
+    candidates.sort_by_key(|w| w.last_used);
+
+    let mut evicted = Vec::new();
+    for workspace in candidates {
+        if total <= max_size {
+            break;
+        }
+        total -= workspace.size;
+        evicted.push(workspace);
+    }
+    evicted
+}
+
+/// The workspaces not used within `max_age` before `now`
+pub fn unused_for(
+    workspaces: &[WorkspaceUsage],
+    max_age: Duration,
+    now: SystemTime,
+) -> Vec<&WorkspaceUsage> {
+    workspaces
+        .iter()
+        .filter(|w| {
+            now.duration_since(w.last_used)
+                .is_ok_and(|age| age > max_age)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn workspace(name: &str, size: u64, days_ago: u64) -> WorkspaceUsage {
+        WorkspaceUsage {
+            path: PathBuf::from(name),
+            size,
+            last_used: SystemTime::now() - Duration::from_secs(days_ago * 86400),
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let workspaces = [
+            workspace("a", 4, 1),
+            workspace("b", 3, 30),
+            workspace("c", 5, 10),
+        ];
+
+        let names = |max_size, keep: &[&str]| -> Vec<String> {
+            let keep: Vec<&Path> = keep.iter().map(Path::new).collect();
+            plan(&workspaces, max_size, &keep)
+                .iter()
+                .map(|w| w.path.display().to_string())
+                .collect()
+        };
+        assert!(names(12, &[]).is_empty());
+        assert_eq!(names(9, &[]), ["b"]);
+        assert_eq!(names(5, &[]), ["b", "c"]);
+        assert_eq!(names(0, &["a"]), ["b", "c"]);
+        assert_eq!(names(0, &["a", "b"]), ["c"]);
+
+        let week = Duration::from_secs(7 * 86400);
+        let unused: Vec<_> = unused_for(&workspaces, week, SystemTime::now())
+            .iter()
+            .map(|w| w.path.display().to_string())
+            .collect();
+        assert_eq!(unused, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_touch_and_scan() {
+        let dir = tempdir().unwrap();
+        let old = dir.path().join("old");
+        let new = dir.path().join("new");
+        fs::create_dir(&old).unwrap();
+        fs::create_dir(&new).unwrap();
+        fs::write(old.join(LAST_USED_FILE), "1000").unwrap();
+        fs::write(new.join("west.yml"), "manifest:").unwrap();
+        touch(&new).unwrap();
+
+        let mut workspaces = scan(dir.path()).unwrap();
+        workspaces.sort_by_key(|w| w.last_used);
+        assert_eq!(workspaces.len(), 2);
+        assert_eq!(workspaces[0].path, old);
+        assert_eq!(
+            workspaces[0].last_used,
+            UNIX_EPOCH + Duration::from_secs(1000)
+        );
+        assert_eq!(workspaces[1].path, new);
+        assert!(workspaces[1].size > 0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Keeps concurrent lfz runs (a watch session and a manual build, say) out
+//! of each other's workspace.
+//!
+//! A run holds an advisory lock on `<workspace>.lock`, next to the workspace
+//! directory so that removing or reinitializing the workspace leaves it in
+//! place. The file records the holder's pid for the error message; the lock
+//! itself goes away with the process, however it exits.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::output;
+
+/// Lock files this process holds
+static HELD: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// An lfz run's hold on a workspace, released when dropped
+pub struct WorkspaceGuard {
+    _file: File,
+    path: PathBuf,
+}
+
+impl WorkspaceGuard {
+    /// Take a workspace for this run. If another run holds it, fail, or
+    /// with `wait` block until it is done.
+    pub fn acquire(workspace: &Path, wait: bool) -> Result<Self> {
+        let path = lock_path(workspace);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = holder(&path);
+                if !wait {
+                    anyhow::bail!(
+                        "Another lfz build is running in this workspace{}.\n\
+                         Pass --wait to start once it is done.",
+                        holder
+                    );
+                }
+                output::info(&format!(
+                    "Waiting for another lfz build{} to finish...",
+                    holder
+                ));
+                file.lock()
+                    .with_context(|| format!("Failed to lock {}", path.display()))?;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        HELD.lock().unwrap().push(path.clone());
+        Ok(Self { _file: file, path })
+    }
+}
+
+impl Drop for WorkspaceGuard {
+    fn drop(&mut self) {
+        let mut held = HELD.lock().unwrap();
+        if let Some(i) = held.iter().position(|path| path == &self.path) {
+            held.remove(i);
+        }
+    }
+}
+
+/// Whether another lfz run holds a workspace
+pub fn in_use(workspace: &Path) -> bool {
+    File::open(lock_path(workspace))
+        .is_ok_and(|file| matches!(file.try_lock(), Err(TryLockError::WouldBlock)))
+}
+
+/// Whether a run other than this one holds a workspace
+pub fn in_use_elsewhere(workspace: &Path) -> bool {
+    let path = lock_path(workspace);
+    !HELD.lock().unwrap().contains(&path) && in_use(workspace)
+}
+
+/// Remove the lock file of a workspace that was removed
+pub fn remove_lock(workspace: &Path) -> Result<()> {
+    let path = lock_path(workspace);
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The lock file of a workspace, `<workspace>.lock`
+fn lock_path(workspace: &Path) -> PathBuf {
+    let mut name = workspace.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    workspace.with_file_name(name)
+}
+
+/// ` (pid N)` of the run holding a lock, if it recorded one
+fn holder(path: &Path) -> String {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .map(|pid| format!(" (pid {})", pid))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_acquire() {
+        let dir = tempdir().unwrap();
+        let workspace = dir.path().join("a1b2c3");
+        assert_eq!(lock_path(&workspace), dir.path().join("a1b2c3.lock"));
+        assert!(!in_use(&workspace));
+
+        let guard = WorkspaceGuard::acquire(&workspace, false).unwrap();
+        assert!(in_use(&workspace));
+        assert!(!in_use_elsewhere(&workspace));
+        let err = WorkspaceGuard::acquire(&workspace, false)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains(&format!("(pid {})", std::process::id())));
+
+        drop(guard);
+        assert!(!in_use(&workspace));
+        assert!(!in_use_elsewhere(&workspace));
+        drop(WorkspaceGuard::acquire(&workspace, false).unwrap());
+
+        remove_lock(&workspace).unwrap();
+        assert!(!lock_path(&workspace).exists());
+        remove_lock(&workspace).unwrap();
+    }
+}
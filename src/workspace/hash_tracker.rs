@@ -1,13 +1,18 @@
 //! Tracks build configuration hashes to determine if incremental builds are safe.
 //!
-//! When build.yaml, west.yml, or custom board/shield definitions change,
+//! When the build config, west.yml, or custom board/shield definitions change,
 //! incremental builds may have stale artifacts. This module tracks hashes
 //! of these files to automatically decide whether to use pristine (safe)
 //! or incremental (fast) builds.
+//!
+//! The files of the config directory are tracked one by one: when only
+//! keymaps and `.conf` files changed, existing build directories can be
+//! rebuilt in place, skipping the pristine checks.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -17,7 +22,7 @@ const HASH_FILE: &str = ".lfz_build_hashes.json";
 /// Hashes of configuration files that affect build output
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BuildHashes {
-    /// SHA256 hash of build.yaml contents
+    /// SHA256 hash of the build config contents (build.yaml, .toml or .json)
     pub build_yaml: String,
     /// SHA256 hash of west.yml contents
     pub west_yml: String,
@@ -27,17 +32,24 @@ pub struct BuildHashes {
     /// SHA256 hash of shields/ directory contents (if present)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub shields_dir: Option<String>,
+    /// SHA256 hash of each file of the config directory, by path relative to it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub config_files: BTreeMap<String, String>,
 }
 
+/// Extensions of the config files a build directory picks up when rebuilt
+/// in place (CMake reruns on its own for them)
+const REBUILD_ONLY_EXTENSIONS: [&str; 2] = ["keymap", "conf"];
+
 impl BuildHashes {
     /// Calculate hashes from the project's configuration files and directories
     pub fn calculate(
         project_root: &Path,
-        build_yaml_path: &Path,
+        build_file_path: &Path,
         west_yml_path: &Path,
     ) -> Result<Self> {
-        let build_yaml_hash = hash_file(build_yaml_path)
-            .with_context(|| format!("Failed to hash {}", build_yaml_path.display()))?;
+        let build_yaml_hash = hash_file(build_file_path)
+            .with_context(|| format!("Failed to hash {}", build_file_path.display()))?;
 
         let west_yml_hash = hash_file(west_yml_path)
             .with_context(|| format!("Failed to hash {}", west_yml_path.display()))?;
@@ -57,11 +69,20 @@ impl BuildHashes {
             None
         };
 
+        let mut config_files = BTreeMap::new();
+        if let Some(config_dir) = west_yml_path.parent() {
+            for path in collect_files(config_dir)? {
+                let relative = path.strip_prefix(config_dir).unwrap_or(&path);
+                config_files.insert(relative.to_string_lossy().to_string(), hash_file(&path)?);
+            }
+        }
+
         Ok(Self {
             build_yaml: build_yaml_hash,
             west_yml: west_yml_hash,
             boards_dir: boards_hash,
             shields_dir: shields_hash,
+            config_files,
         })
     }
 
@@ -95,9 +116,39 @@ impl BuildHashes {
         Ok(())
     }
 
-    /// Check if these hashes match stored hashes, indicating incremental build is safe
+    /// Check if these hashes match stored hashes, indicating incremental build is safe.
+    /// Config files are left out: CMake notices changes to them by itself.
     pub fn matches(&self, other: &Self) -> bool {
-        self == other
+        self.build_yaml == other.build_yaml
+            && self.west_yml == other.west_yml
+            && self.boards_dir == other.boards_dir
+            && self.shields_dir == other.shields_dir
+    }
+
+    /// The config files that changed since `stored`, if they are only edited
+    /// keymaps and `.conf` files. `None` when nothing changed, when anything
+    /// else changed, or when files were added or removed (which CMake only
+    /// notices when reconfiguring).
+    pub fn rebuild_only_changes(&self, stored: &Self) -> Option<Vec<String>> {
+        if !self.matches(stored) || stored.config_files.is_empty() {
+            return None;
+        }
+        if !self.config_files.keys().eq(stored.config_files.keys()) {
+            return None;
+        }
+
+        let changed: Vec<String> = self
+            .config_files
+            .iter()
+            .filter(|(path, hash)| stored.config_files.get(*path) != Some(*hash))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let rebuild_only = changed.iter().all(|path| {
+            Path::new(path)
+                .extension()
+                .is_some_and(|ext| REBUILD_ONLY_EXTENSIONS.iter().any(|e| ext == *e))
+        });
+        (!changed.is_empty() && rebuild_only).then_some(changed)
     }
 }
 
@@ -110,8 +161,17 @@ pub fn is_incremental_safe(workspace: &Path, current: &BuildHashes) -> bool {
     }
 }
 
+/// The config files changed since the last successful build, if only
+/// keymaps and `.conf` files changed (see [`BuildHashes::rebuild_only_changes`])
+pub fn rebuild_only_changes(workspace: &Path, current: &BuildHashes) -> Option<Vec<String>> {
+    match BuildHashes::load(workspace) {
+        Ok(Some(stored)) => current.rebuild_only_changes(&stored),
+        _ => None,
+    }
+}
+
 /// Calculate SHA256 hash of a file's contents
-fn hash_file(path: &Path) -> Result<String> {
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
     let contents =
         fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
 
@@ -150,7 +210,7 @@ fn hash_directory(dir: &Path) -> Result<String> {
 }
 
 /// Recursively collect all files in a directory
-fn collect_files(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+pub(crate) fn collect_files(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
     let mut files = Vec::new();
 
     if !dir.is_dir() {
@@ -267,6 +327,7 @@ mod tests {
             west_yml: "def456".to_string(),
             boards_dir: Some("boards789".to_string()),
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
 
         hashes.save(workspace).unwrap();
@@ -289,6 +350,7 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: None,
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
 
         assert!(!is_incremental_safe(dir.path(), &current));
@@ -302,6 +364,7 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: None,
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
 
         hashes.save(dir.path()).unwrap();
@@ -316,6 +379,7 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: None,
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
         stored.save(dir.path()).unwrap();
 
@@ -324,6 +388,7 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: None,
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
         assert!(!is_incremental_safe(dir.path(), &current));
     }
@@ -336,6 +401,7 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: Some("old_hash".to_string()),
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
         stored.save(dir.path()).unwrap();
 
@@ -344,7 +410,42 @@ mod tests {
             west_yml: "def".to_string(),
             boards_dir: Some("new_hash".to_string()), // Changed!
             shields_dir: None,
+            config_files: BTreeMap::new(),
         };
         assert!(!is_incremental_safe(dir.path(), &current));
     }
+
+    #[test]
+    fn test_rebuild_only_changes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let config = root.join("config");
+        fs::create_dir(&config).unwrap();
+        let build_yaml = root.join("build.yaml");
+        let west_yml = config.join("west.yml");
+        fs::write(&build_yaml, "board: [nice_nano_v2]").unwrap();
+        fs::write(&west_yml, "manifest:\n  projects: []").unwrap();
+        fs::write(config.join("corne.keymap"), "a").unwrap();
+        fs::write(config.join("corne.conf"), "a").unwrap();
+        fs::write(config.join("corne.overlay"), "a").unwrap();
+        let calculate = || BuildHashes::calculate(root, &build_yaml, &west_yml).unwrap();
+
+        let stored = calculate();
+        assert_eq!(calculate().rebuild_only_changes(&stored), None);
+
+        fs::write(config.join("corne.keymap"), "b").unwrap();
+        let current = calculate();
+        assert!(current.matches(&stored));
+        assert_eq!(
+            current.rebuild_only_changes(&stored),
+            Some(vec!["corne.keymap".to_string()])
+        );
+
+        fs::write(config.join("corne.overlay"), "b").unwrap();
+        assert_eq!(calculate().rebuild_only_changes(&stored), None);
+
+        fs::write(config.join("corne.overlay"), "a").unwrap();
+        fs::write(config.join("nice_nano_v2.conf"), "a").unwrap();
+        assert_eq!(calculate().rebuild_only_changes(&stored), None);
+    }
 }
@@ -0,0 +1,193 @@
+//! Checks for what interrupted west runs leave behind in a workspace.
+//!
+//! A `west update` cut short (Ctrl-C, a dropped connection, a full disk)
+//! can leave projects without a checked out commit, or git lock files that
+//! make every later git command in them fail. Both are found before
+//! building, so they can be repaired instead of failing mid-build.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::config::west_yml::{WestManifest, WestProject};
+
+use super::lock::head_commit;
+
+/// Lock files older than this are left over from an interrupted git command
+/// rather than held by a running one
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+/// The workspace lacks projects west.yml needs, and a flag keeps them
+/// from being fetched
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "The workspace is missing or only partly has {}, and {flag} prevents fetching them",
+    projects.join(", ")
+)]
+pub struct MissingModules {
+    pub projects: Vec<String>,
+    pub flag: &'static str,
+}
+
+/// Problems found in a workspace
+#[derive(Debug, Default)]
+pub struct Health {
+    /// Why the workspace as a whole is unusable and has to be initialized again
+    pub broken: Option<String>,
+    /// Git lock files no running command holds
+    pub stale_locks: Vec<PathBuf>,
+    /// Projects that are missing or were never completely cloned
+    pub incomplete: Vec<WestProject>,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.broken.is_none() && self.stale_locks.is_empty() && self.incomplete.is_empty()
+    }
+}
+
+/// Check a workspace against the manifest it was created from
+pub fn check(workspace: &Path, manifest: &WestManifest, now: SystemTime) -> Health {
+    let west_dir = workspace.join(".west");
+    if !west_dir.exists() {
+        // An empty directory (or one with only lfz's own files) is just
+        // a workspace that hasn't been initialized yet
+        let has_content = fs::read_dir(workspace).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| !entry.file_name().to_string_lossy().starts_with(".lfz_"))
+        });
+        return Health {
+            broken: has_content.then(|| ".west is missing".to_string()),
+            ..Default::default()
+        };
+    }
+    if !west_dir.join("config").is_file() {
+        return Health {
+            broken: Some(".west/config is missing".to_string()),
+            ..Default::default()
+        };
+    }
+
+    let mut health = Health::default();
+    for project in manifest.resolved_projects(workspace) {
+        let dir = workspace.join(project.path());
+        if !dir.exists() {
+            // Imported projects may be left out by name-allowlists, but
+            // west.yml's own are always cloned
+            if manifest.projects.iter().any(|p| p.name == project.name) {
+                health.incomplete.push(project);
+            }
+            continue;
+        }
+        let git_dir = dir.join(".git");
+        if head_commit(&dir).is_none() {
+            health.incomplete.push(project);
+        } else {
+            find_stale_locks(&git_dir, now, &mut health.stale_locks);
+        }
+    }
+    health
+}
+
+/// Lock files in a git directory (skipping the object store) older than
+/// [`STALE_LOCK_AGE`]
+fn find_stale_locks(dir: &Path, now: SystemTime, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if entry.file_name() != "objects" {
+                find_stale_locks(&path, now, out);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "lock") {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > STALE_LOCK_AGE);
+            if stale {
+                out.push(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MANIFEST: &str = r#"
+manifest:
+  projects:
+    - name: zmk
+      url: https://github.com/zmkfirmware/zmk
+      import: app/west.yml
+    - name: helpers
+      url: https://github.com/me/helpers
+"#;
+
+    fn clone(workspace: &Path, path: &str) {
+        let git_dir = workspace.join(path).join(".git");
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::write(git_dir.join("HEAD"), "2f0a3b1c\n").unwrap();
+    }
+
+    #[test]
+    fn test_check() {
+        let workspace = tempdir().unwrap();
+        let manifest = WestManifest::parse(MANIFEST).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(3600);
+
+        // Not initialized yet
+        assert!(check(workspace.path(), &manifest, later).is_healthy());
+        fs::write(workspace.path().join(".lfz_last_used"), "0").unwrap();
+        assert!(check(workspace.path(), &manifest, later).is_healthy());
+        // Left over without .west
+        clone(workspace.path(), "zmk");
+        let health = check(workspace.path(), &manifest, later);
+        assert_eq!(health.broken.as_deref(), Some(".west is missing"));
+        fs::create_dir(workspace.path().join(".west")).unwrap();
+        let health = check(workspace.path(), &manifest, later);
+        assert_eq!(health.broken.as_deref(), Some(".west/config is missing"));
+        fs::write(workspace.path().join(".west/config"), "[manifest]\n").unwrap();
+
+        // helpers missing, zmk's import (not there) not required
+        let health = check(workspace.path(), &manifest, later);
+        assert!(health.broken.is_none());
+        let names: Vec<&str> = health.incomplete.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["helpers"]);
+
+        // helpers cloned without a commit, a lock file left in zmk
+        let helpers_git = workspace.path().join("helpers/.git");
+        fs::create_dir_all(&helpers_git).unwrap();
+        fs::write(helpers_git.join("HEAD"), "ref: refs/heads/master\n").unwrap();
+        let lock = workspace.path().join("zmk/.git/refs/heads/main.lock");
+        fs::write(&lock, "").unwrap();
+        fs::create_dir_all(workspace.path().join("zmk/.git/objects/pack")).unwrap();
+        fs::write(workspace.path().join("zmk/.git/objects/pack/x.lock"), "").unwrap();
+        let health = check(workspace.path(), &manifest, later);
+        assert_eq!(health.incomplete.len(), 1);
+        assert_eq!(health.stale_locks, [lock]);
+
+        // A fresh lock may be held by a running command
+        let health = check(workspace.path(), &manifest, SystemTime::now());
+        assert!(health.stale_locks.is_empty());
+
+        let missing = MissingModules {
+            projects: vec!["helpers".to_string(), "zmk".to_string()],
+            flag: "--no-update",
+        };
+        assert_eq!(
+            missing.to_string(),
+            "The workspace is missing or only partly has helpers, zmk, and --no-update prevents fetching them"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! `west.lock` records the exact commit of every west project after a
+//! workspace init or update, so builds can be reproduced with `--locked`.
+//!
+//! ```yaml
+//! projects:
+//!   zmk:
+//!     path: zmk
+//!     revision: 2f0a3b1c...
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::west_yml::WestManifest;
+
+/// File name of the lockfile, next to west.yml
+pub const WEST_LOCK: &str = "west.lock";
+
+const HEADER: &str = "# Generated by lfz after each west update. Commit this file and build\n\
+                      # with `lfz --locked` to use exactly these module revisions.\n";
+
+/// Resolved commits of all west projects in a workspace
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WestLock {
+    pub projects: BTreeMap<String, LockedProject>,
+}
+
+/// A west project pinned to a commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedProject {
+    /// Project path relative to the workspace root
+    pub path: String,
+    /// Full commit SHA
+    pub revision: String,
+}
+
+impl WestLock {
+    /// Record the checked out commit of every project present in the workspace
+    pub fn capture(workspace: &Path, manifest: &WestManifest) -> Self {
+        let projects = manifest
+            .resolved_projects(workspace)
+            .into_iter()
+            .filter_map(|project| {
+                let revision = head_commit(&workspace.join(project.path()))?;
+                let locked = LockedProject {
+                    path: project.path().to_string(),
+                    revision,
+                };
+                Some((project.name, locked))
+            })
+            .collect();
+        Self { projects }
+    }
+
+    /// Load a lockfile, if it exists
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lock = serde_yaml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(lock))
+    }
+
+    /// Write the lockfile
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_yaml::to_string(self).context("Failed to serialize west.lock")?;
+        fs::write(path, format!("{}{}", HEADER, contents))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Locked projects whose checkout in `current` is at a different commit
+    pub fn drift<'a>(&'a self, current: &Self) -> Vec<(&'a String, &'a LockedProject)> {
+        self.projects
+            .iter()
+            .filter(|(name, locked)| {
+                current
+                    .projects
+                    .get(*name)
+                    .is_none_or(|c| c.revision != locked.revision)
+            })
+            .collect()
+    }
+}
+
+/// Commit checked out in a git repository, read without invoking git
+pub(super) fn head_commit(repo: &Path) -> Option<String> {
+    let git_dir = repo.join(".git");
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    // west leaves projects on a detached HEAD
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        return Some(head.to_string());
+    };
+    if let Ok(sha) = fs::read_to_string(git_dir.join(reference)) {
+        return Some(sha.trim().to_string());
+    }
+    let packed = fs::read_to_string(git_dir.join("packed-refs")).ok()?;
+    packed.lines().find_map(|line| {
+        let (sha, name) = line.split_once(' ')?;
+        (name == reference).then(|| sha.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const ZMK_SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn fake_repo(workspace: &Path, path: &str, head: &str) {
+        let git_dir = workspace.join(path).join(".git");
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), format!("{}\n", head)).unwrap();
+    }
+
+    #[test]
+    fn test_capture_and_roundtrip() {
+        let workspace = tempdir().unwrap();
+        fake_repo(workspace.path(), "zmk", ZMK_SHA);
+        fake_repo(workspace.path(), "modules/helpers", "ref: refs/heads/main");
+        fs::write(
+            workspace.path().join("modules/helpers/.git/packed-refs"),
+            "# pack-refs with: peeled\nfedcba9876543210fedcba9876543210fedcba98 refs/heads/main\n",
+        )
+        .unwrap();
+
+        let manifest = WestManifest::parse(
+            r#"
+manifest:
+  projects:
+    - name: zmk
+      url: https://github.com/zmkfirmware/zmk
+    - name: helpers
+      path: modules/helpers
+      url: https://github.com/example/helpers
+    - name: missing
+      url: https://github.com/example/missing
+"#,
+        )
+        .unwrap();
+
+        let lock = WestLock::capture(workspace.path(), &manifest);
+        assert_eq!(lock.projects.len(), 2);
+        assert_eq!(lock.projects["zmk"].revision, ZMK_SHA);
+        assert_eq!(lock.projects["helpers"].path, "modules/helpers");
+        assert!(lock.projects["helpers"].revision.starts_with("fedcba"));
+
+        let path = workspace.path().join(WEST_LOCK);
+        lock.save(&path).unwrap();
+        assert_eq!(WestLock::load(&path).unwrap(), Some(lock));
+    }
+
+    #[test]
+    fn test_drift() {
+        let locked = |revision: &str| LockedProject {
+            path: "zmk".to_string(),
+            revision: revision.to_string(),
+        };
+        let mut lock = WestLock::default();
+        lock.projects.insert("zmk".to_string(), locked(ZMK_SHA));
+        lock.projects.insert("other".to_string(), locked("abc"));
+
+        let mut current = lock.clone();
+        assert!(lock.drift(&current).is_empty());
+
+        current.projects.insert("zmk".to_string(), locked("def"));
+        current.projects.remove("other");
+        let names: Vec<_> = lock.drift(&current).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["other", "zmk"]);
+    }
+}
@@ -1,25 +1,92 @@
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::SystemTime;
 
+use crate::config::lfz_toml::{LfzConfig, WorkspaceKey};
 use crate::config::project::Project;
-use crate::config::west_yml;
+use crate::config::west_yml::{self, WestManifest, WestProject};
 use crate::container::{ContainerCommand, Runtime, DEFAULT_IMAGE};
-use crate::output;
+use crate::output::{self, WorkspaceProgress};
 use crate::paths;
 
+use super::credentials;
+use super::eviction;
+use super::guard::{self, WorkspaceGuard};
+use super::health::{self, MissingModules};
+use super::lock::{WestLock, WEST_LOCK};
+use super::metadata::WorkspaceMetadata;
+use super::progress::{self, WestEvent};
+
 /// File name for storing west.yml hash in the workspace
 const WEST_YML_HASH_FILE: &str = ".lfz_west_yml_hash";
 
+/// Sets `$jobs` to west's parallel update flag when workspaces.update-jobs
+/// asks for it and the image's west has one
+const WEST_JOBS_SCRIPT: &str = r#"
+jobs=""
+if [ -n "$LFZ_UPDATE_JOBS" ]; then
+    if west update --help | grep -q -- '--jobs'; then
+        jobs="--jobs $LFZ_UPDATE_JOBS"
+    else
+        echo "=== This west can't update projects in parallel, fetching one at a time"
+    fi
+fi
+"#;
+
+/// What keeps an existing workspace from being updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpdateBlocker {
+    Offline,
+    NoUpdate,
+    /// `workspaces.pinned` in lfz.toml
+    Pinned,
+}
+
+impl UpdateBlocker {
+    /// The flag or setting, as the user wrote it
+    fn flag(self) -> &'static str {
+        match self {
+            Self::Offline => "--offline",
+            Self::NoUpdate => "--no-update",
+            Self::Pinned => "workspaces.pinned",
+        }
+    }
+}
+
+/// Upstream ZMK repository, where pull request refs live
+const ZMK_REPO: &str = "https://github.com/zmkfirmware/zmk";
+
+/// A project to move to another revision
+struct Checkout {
+    name: String,
+    /// Project path relative to the workspace root
+    path: String,
+    /// Repository to fetch from (default: the project's URL in west.yml)
+    url: Option<String>,
+    /// Commit or ref to fetch
+    revision: String,
+}
+
 /// Manages west workspaces for building ZMK
 pub struct WorkspaceManager {
     /// Root directory for all cached workspaces
     workspaces_dir: PathBuf,
     /// Shared ccache directory
     ccache_dir: PathBuf,
+    /// Never touch the network (`--offline`)
+    offline: bool,
+    /// Leave the workspace as it is when west.yml changed (`--no-update`)
+    no_update: bool,
+    /// Check out the revisions from west.lock (`--locked`)
+    locked: bool,
+    /// Suffix of a scratch workspace next to the regular one
+    scratch: Option<String>,
+    /// Wait for other lfz runs using the workspace instead of failing
+    wait: bool,
 }
 
 impl WorkspaceManager {
@@ -34,13 +101,78 @@ impl WorkspaceManager {
         Ok(Self {
             workspaces_dir,
             ccache_dir,
+            offline: false,
+            no_update: false,
+            locked: false,
+            scratch: None,
+            wait: false,
         })
     }
 
-    /// Get the workspace path for a project (based on git repo + branch)
+    /// Use existing workspaces as-is instead of initializing or updating them
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Don't run `west update` when west.yml changed, only warn
+    pub fn no_update(mut self, no_update: bool) -> Self {
+        self.no_update = no_update;
+        self
+    }
+
+    /// What keeps an existing workspace from being updated, if anything
+    fn update_blocked_by(&self, project: &Project) -> Result<Option<UpdateBlocker>> {
+        Ok(if self.offline {
+            Some(UpdateBlocker::Offline)
+        } else if self.no_update {
+            Some(UpdateBlocker::NoUpdate)
+        } else if LfzConfig::load(&project.root)?.workspaces.pinned {
+            Some(UpdateBlocker::Pinned)
+        } else {
+            None
+        })
+    }
+
+    /// Use a separate scratch workspace (e.g. for a ZMK pull request), so
+    /// the project's regular workspace and west.lock stay untouched
+    pub fn scratch(mut self, name: Option<String>) -> Self {
+        self.scratch = name;
+        self
+    }
+
+    /// Check out projects at the revisions in west.lock instead of
+    /// recording the revisions west resolves
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Wait for other lfz runs to be done with the workspace (`--wait`)
+    pub fn wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Take the project's workspace for this run, until the guard is dropped
+    pub fn lock(&self, project: &Project) -> Result<WorkspaceGuard> {
+        WorkspaceGuard::acquire(&self.workspace_path(project)?, self.wait)
+    }
+
+    /// Get the workspace path for a project, keyed as its lfz.toml says
+    /// (by default on git repo + branch)
     pub fn workspace_path(&self, project: &Project) -> Result<PathBuf> {
-        let hash = west_yml::hash_workspace_key(&project.config_dir)?;
-        Ok(self.workspaces_dir.join(hash))
+        let key = LfzConfig::load(&project.root)?.workspaces.key;
+        self.workspace_path_for(project, key)
+    }
+
+    /// The workspace path for a project under a given key
+    fn workspace_path_for(&self, project: &Project, key: WorkspaceKey) -> Result<PathBuf> {
+        let hash = west_yml::hash_workspace_key(&project.config_dir, key)?;
+        Ok(match &self.scratch {
+            Some(scratch) => self.workspaces_dir.join(format!("{}-{}", hash, scratch)),
+            None => self.workspaces_dir.join(hash),
+        })
     }
 
     /// Find existing workspace for a project, if any
@@ -55,20 +187,61 @@ impl WorkspaceManager {
 
     /// Get or create a workspace for a project
     ///
-    /// If west.yml has changed since the workspace was created, this will
+    /// Stale git locks and partially cloned projects left by an interrupted
+    /// west run are repaired first. If west.yml has changed since the workspace was created, this will
     /// automatically run `west update` to sync the workspace with the new
-    /// module versions.
+    /// module versions. In locked mode, projects are then moved to the
+    /// revisions in west.lock.
     pub fn get_or_create(&self, project: &Project) -> Result<PathBuf> {
+        let workspace = self.prepare(project)?;
+        if self.locked {
+            self.apply_lock(&workspace, project)?;
+        }
+        if let Err(e) = eviction::touch(&workspace)
+            .and_then(|()| WorkspaceMetadata::record(&workspace, project, self.scratch.as_deref()))
+        {
+            // Non-fatal: the workspace just looks older to eviction, or
+            // anonymous to `lfz workspaces`
+            output::warning(&format!("Failed to record the workspace's use: {}", e));
+        }
+        Ok(workspace)
+    }
+
+    /// Initialize or update the workspace as west.yml requires
+    fn prepare(&self, project: &Project) -> Result<PathBuf> {
         let workspace = self.workspace_path(project)?;
+        if !workspace.exists() {
+            self.migrate(&workspace, project)?;
+        }
+        if workspace.exists() {
+            self.repair(&workspace, project)?;
+        }
 
         // Check if workspace already exists and is initialized
         if workspace.join(".west").exists() {
             // Check if west.yml has changed
             let west_yml_path = project.config_dir.join("west.yml");
-            if self.west_yml_changed(&workspace, &west_yml_path)? {
+            let changed = self.west_yml_changed(&workspace, &west_yml_path)?;
+            let blocked_by = if changed {
+                self.update_blocked_by(project)?
+            } else {
+                None
+            };
+            // Keep the stored hash so that the warning stays until an update
+            if blocked_by == Some(UpdateBlocker::Pinned) {
+                output::warning(
+                    "west.yml changed, but the workspace is pinned (workspaces.pinned in lfz.toml). \
+                     Run 'lfz update' to sync it.",
+                );
+            } else if let Some(blocker) = blocked_by {
+                output::warning(&format!(
+                    "west.yml changed, but skipping west update ({})",
+                    blocker.flag()
+                ));
+            } else if changed {
                 output::header("west.yml changed - updating workspace");
                 let runtime = Runtime::detect()?;
-                self.update_workspace(&workspace, project, &runtime)?;
+                self.update_workspace(&workspace, project, &runtime, &[])?;
                 // Save the new hash after successful update
                 self.save_west_yml_hash(&workspace, &west_yml_path)?;
             } else {
@@ -77,6 +250,13 @@ impl WorkspaceManager {
             return Ok(workspace);
         }
 
+        if self.offline {
+            anyhow::bail!(
+                "No workspace for this project yet, and --offline prevents creating one.\n\
+                 Run 'lfz build' once with network access first."
+            );
+        }
+
         // Need to initialize workspace
         output::header("Initializing new workspace");
         self.initialize_workspace(&workspace, project)?;
@@ -84,6 +264,124 @@ impl WorkspaceManager {
         Ok(workspace)
     }
 
+    /// Move the project's workspace created under another key (before
+    /// workspaces.key changed) to `workspace`, so it isn't initialized again.
+    /// Only a workspace recorded as this repository's and keyed the other
+    /// way moves: one found under another key through a shared west.yml
+    /// (or this branch's name) belongs to other projects as well.
+    fn migrate(&self, workspace: &Path, project: &Project) -> Result<()> {
+        let key = LfzConfig::load(&project.root)?.workspaces.key;
+        let (repo, _) = west_yml::get_git_info(&project.config_dir)?;
+        for other in WorkspaceKey::ALL.into_iter().filter(|k| *k != key) {
+            let old = self.workspace_path_for(project, other)?;
+            if !old.join(".west").exists() {
+                continue;
+            }
+            let Some(metadata) = WorkspaceMetadata::load(&old) else {
+                continue;
+            };
+            if metadata.key() != other || metadata.repo != repo || metadata.scratch != self.scratch
+            {
+                continue;
+            }
+            // Not while another run builds in it
+            let _guard = WorkspaceGuard::acquire(&old, self.wait)?;
+            output::status(
+                "Migrating",
+                &format!(
+                    "workspace keyed by {} to key by {}",
+                    other.as_str(),
+                    key.as_str()
+                ),
+            );
+            fs::rename(&old, workspace).with_context(|| {
+                format!(
+                    "Failed to move {} to {}",
+                    old.display(),
+                    workspace.display()
+                )
+            })?;
+            guard::remove_lock(&old)?;
+            break;
+        }
+        Ok(())
+    }
+
+    /// Fix what an interrupted west run left in a workspace: stale git locks
+    /// are removed and partially cloned projects fetched again. A workspace
+    /// beyond that is removed (after asking) to be initialized again.
+    fn repair(&self, workspace: &Path, project: &Project) -> Result<()> {
+        let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+        let health = health::check(workspace, &manifest, SystemTime::now());
+        if health.is_healthy() {
+            return Ok(());
+        }
+
+        if let Some(problem) = &health.broken {
+            output::warning(&format!("Workspace is broken: {}", problem));
+            if self.offline {
+                anyhow::bail!(
+                    "Workspace is broken ({}), and --offline prevents reinitializing it",
+                    problem
+                );
+            }
+            if !console::user_attended() {
+                anyhow::bail!(
+                    "Workspace is broken ({}). Run 'lfz update' to reinitialize it.",
+                    problem
+                );
+            }
+            let answer = output::select(
+                "Remove and reinitialize the workspace?",
+                &["Reinitialize", "Abort"],
+            )?;
+            if answer != 0 {
+                anyhow::bail!("Workspace is broken ({})", problem);
+            }
+            fs::remove_dir_all(workspace).context("Failed to remove broken workspace")?;
+            return Ok(());
+        }
+
+        // A git command of another lfz run may just be slow; its locks are
+        // only stale once no other run holds the workspace
+        if !health.stale_locks.is_empty() && guard::in_use_elsewhere(workspace) {
+            output::warning(
+                "Another lfz run is using the workspace; leaving its git lock files in place",
+            );
+        } else {
+            for lock in &health.stale_locks {
+                fs::remove_file(lock)
+                    .with_context(|| format!("Failed to remove {}", lock.display()))?;
+                let relative = lock.strip_prefix(workspace).unwrap_or(lock);
+                output::status("Removed", &format!("stale git lock {}", relative.display()));
+            }
+        }
+
+        if !health.incomplete.is_empty() {
+            let names: Vec<String> = health.incomplete.iter().map(|p| p.name.clone()).collect();
+            if let Some(blocker) = self.update_blocked_by(project)? {
+                return Err(MissingModules {
+                    projects: names,
+                    flag: blocker.flag(),
+                }
+                .into());
+            }
+            output::header(&format!(
+                "Repairing partially cloned project(s): {}",
+                names.join(", ")
+            ));
+            for incomplete in &health.incomplete {
+                let dir = workspace.join(incomplete.path());
+                if dir.exists() {
+                    fs::remove_dir_all(&dir)
+                        .with_context(|| format!("Failed to remove {}", dir.display()))?;
+                }
+            }
+            self.update_workspace(workspace, project, &Runtime::detect()?, &names)?;
+        }
+        Ok(())
+    }
+
     /// Force refresh the workspace (re-run west update)
     pub fn refresh(&self, project: &Project, runtime: &Runtime) -> Result<PathBuf> {
         let workspace = self.workspace_path(project)?;
@@ -103,6 +401,82 @@ impl WorkspaceManager {
         Ok(workspace)
     }
 
+    /// Fetch some projects of an existing workspace again, leaving the
+    /// others as they are
+    pub fn refresh_projects(
+        &self,
+        project: &Project,
+        runtime: &Runtime,
+        names: &[String],
+    ) -> Result<PathBuf> {
+        let workspace = self
+            .find_workspace(project)?
+            .context("No workspace for this project yet. Run 'lfz build' or 'lfz update' first.")?;
+        let paths: Vec<PathBuf> = find_projects(&workspace, project, names)?
+            .iter()
+            .map(|p| workspace.join(p.path()))
+            .collect();
+
+        output::header(&format!("Refreshing {}", names.join(", ")));
+        for path in &paths {
+            if path.exists() {
+                fs::remove_dir_all(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        self.update_workspace(&workspace, project, runtime, names)?;
+        Ok(workspace)
+    }
+
+    /// Fetch the full history of shallow cloned projects, all of them if
+    /// `names` is empty
+    pub fn unshallow(&self, project: &Project, runtime: &Runtime, names: &[String]) -> Result<()> {
+        let workspace = self
+            .find_workspace(project)?
+            .context("No workspace for this project yet. Run 'lfz build' or 'lfz update' first.")?;
+        let shallow: Vec<WestProject> = find_projects(&workspace, project, names)?
+            .into_iter()
+            .filter(|p| workspace.join(p.path()).join(".git/shallow").exists())
+            .collect();
+        if shallow.is_empty() {
+            output::success("Already full clones, nothing to fetch");
+            return Ok(());
+        }
+        runtime.ensure_image(DEFAULT_IMAGE)?;
+
+        let mut script = String::from("set -e\n");
+        for p in &shallow {
+            // Announced the way west update does, for the progress display
+            script.push_str(&format!(
+                "echo \"=== updating {name} ({path}):\"\n\
+                 git -C '/workspace/{path}' fetch --unshallow --progress \"$(west list -f '{{url}}' {name})\"\n",
+                name = p.name,
+                path = p.path(),
+            ));
+        }
+        let names: Vec<&str> = shallow.iter().map(|p| p.name.as_str()).collect();
+        output::header(&format!("Unshallowing {}", names.join(", ")));
+        output::command(&format!("git fetch --unshallow ({})", names.join(", ")));
+
+        let cmd = self
+            .west_container(runtime, &workspace, project)?
+            .shell_command(script)
+            .build();
+        let (status, _, stderr_output) = run_west(cmd, Some(shallow.len()))
+            .context("Failed to run container to unshallow projects")?;
+        if !status.success() {
+            if !stderr_output.trim().is_empty() {
+                eprintln!("\nErrors:\n{}", stderr_output);
+            }
+            if let Some(hint) = credentials::auth_failure_hint(&stderr_output) {
+                output::info(&format!("Tip: {}", hint));
+            }
+            anyhow::bail!("Failed to fetch the full history of {}", names.join(", "));
+        }
+        output::success(&format!("{} now full clones", names.join(", ")));
+        Ok(())
+    }
+
     /// Initialize a new workspace
     fn initialize_workspace(&self, workspace: &PathBuf, project: &Project) -> Result<()> {
         // Detect runtime for initialization
@@ -127,15 +501,17 @@ impl WorkspaceManager {
         // We mount the config as read-only and let west clone everything into the workspace
         // Use shallow clones (--depth 1) to save disk space and download time
         // Retry west update up to 3 times since network failures are common
-        let init_script = r#"
+        let init_script = format!(
+            r#"
 set -e
+{west_jobs}
 echo "Initializing west workspace..."
 west init -l /workspace/config
 
 echo "Updating west modules with shallow clones..."
 max_retries=3
 retry_count=0
-until west update --narrow --fetch-opt=--depth=1; do
+until west update --narrow --fetch-opt=--depth=1 --fetch-opt=--progress $jobs; do
     retry_count=$((retry_count + 1))
     if [ $retry_count -ge $max_retries ]; then
         echo "ERROR: west update failed after $max_retries attempts"
@@ -146,75 +522,20 @@ until west update --narrow --fetch-opt=--depth=1; do
 done
 
 echo "Workspace initialized successfully"
-"#;
+"#,
+            west_jobs = WEST_JOBS_SCRIPT
+        );
 
-        let mut cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
-            .mount(workspace, "/workspace", false)
-            .mount(&project.config_dir, "/workspace/config", true)
-            .mount(&self.ccache_dir, "/root/.ccache", false)
-            .workdir("/workspace")
+        let cmd = self
+            .west_container(runtime, workspace, project)?
             .shell_command(init_script)
             .build();
 
         output::command("west init -l config && west update --narrow --depth=1");
         output::info("This may take several minutes on first run...");
 
-        // Stream output so user can see progress
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let mut child = cmd
-            .spawn()
-            .context("Failed to run container for workspace initialization")?;
-
-        let stdout = child.stdout.take().expect("Failed to capture stdout");
-        let stderr = child.stderr.take().expect("Failed to capture stderr");
-
-        // Stream stdout in a separate thread
-        let stdout_handle = std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            let mut last_lines: Vec<String> = Vec::new();
-            for line in reader.lines().map_while(Result::ok) {
-                // Show progress lines (cloning, fetching, etc.)
-                if line.contains("Cloning")
-                    || line.contains("Fetching")
-                    || line.contains("Updating")
-                    || line.contains("=== ")
-                    || line.contains("initialized")
-                    || line.contains("ERROR")
-                    || line.contains("error:")
-                {
-                    println!("  {}", line);
-                }
-                // Keep last lines for error context
-                last_lines.push(line);
-                if last_lines.len() > 30 {
-                    last_lines.remove(0);
-                }
-            }
-            last_lines
-        });
-
-        // Capture stderr (only print on error)
-        let stderr_handle = std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            let mut error_output = String::new();
-            for line in reader.lines().map_while(Result::ok) {
-                // Only print actual errors, not duplicated progress
-                if line.contains("error:") || line.contains("ERROR") || line.contains("fatal:") {
-                    eprintln!("  {}", line);
-                }
-                error_output.push_str(&line);
-                error_output.push('\n');
-            }
-            error_output
-        });
-
-        let status = child
-            .wait()
-            .context("Failed to wait for workspace initialization")?;
-        let last_lines = stdout_handle.join().unwrap_or_default();
-        let stderr_output = stderr_handle.join().unwrap_or_default();
+        let (status, last_lines, stderr_output) =
+            run_west(cmd, None).context("Failed to run container for workspace initialization")?;
 
         if !status.success() {
             // Show last stdout lines for context
@@ -231,13 +552,17 @@ echo "Workspace initialized successfully"
             // Clean up failed workspace
             let _ = fs::remove_dir_all(workspace);
             output::error("Workspace initialization failed");
-            output::info(
-                "Tip: This is often a transient network error. Try running 'lfz build' again.",
-            );
+            match credentials::auth_failure_hint(&stderr_output) {
+                Some(hint) => output::info(&format!("Tip: {}", hint)),
+                None => output::info(
+                    "Tip: This is often a transient network error. Try running 'lfz build' again.",
+                ),
+            }
             anyhow::bail!("Workspace initialization failed");
         }
 
         output::success("Workspace initialized successfully");
+        self.record_lock(workspace, project)?;
 
         // Save west.yml hash for future change detection
         let west_yml_path = project.config_dir.join("west.yml");
@@ -246,6 +571,273 @@ echo "Workspace initialized successfully"
         Ok(())
     }
 
+    /// Base container for running west in a workspace
+    ///
+    /// Projects whose west.yml URL is a local directory are mounted
+    /// read-only, and git is pointed at the mount instead of the URL.
+    /// SSH and HTTPS credentials configured in lfz.toml are passed through.
+    fn west_container(
+        &self,
+        runtime: &Runtime,
+        workspace: &Path,
+        project: &Project,
+    ) -> Result<ContainerCommand> {
+        let lfz_config = LfzConfig::load(&project.root)?;
+        let mut cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
+            .proxy(&lfz_config.proxy)
+            .mount(workspace, "/workspace", false)
+            .mount(&project.config_dir, "/workspace/config", true)
+            .mount(&self.ccache_dir, "/root/.ccache", false)
+            .workdir("/workspace");
+        if let Some(jobs) = lfz_config.workspaces.update_jobs.filter(|jobs| *jobs > 1) {
+            cmd = cmd.env("LFZ_UPDATE_JOBS", jobs.to_string());
+        }
+
+        let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+        let local_projects = manifest.local_projects(&project.config_dir);
+        for local in &local_projects {
+            output::status(
+                "Local",
+                &format!(
+                    "{} from {}",
+                    local.name,
+                    paths::anonymize_path(&local.host_path)
+                ),
+            );
+            cmd = cmd.mount(&local.host_path, local.container_path(), true);
+            for (key, value) in local.git_config() {
+                cmd = cmd.git_config(key, value);
+            }
+        }
+        if !local_projects.is_empty() {
+            // Mounted repositories are owned by a different user in the container
+            cmd = cmd.git_config("safe.directory", "*");
+        }
+
+        // Credentials for private repositories
+        credentials::check_ssh_access(&manifest, &lfz_config.ssh)?;
+        let cmd = credentials::apply_ssh(cmd, &lfz_config.ssh, runtime)?;
+        credentials::apply_git_credentials(cmd, &lfz_config.git)
+    }
+
+    /// Write the revisions west checked out to west.lock
+    fn record_lock(&self, workspace: &Path, project: &Project) -> Result<()> {
+        if self.locked || self.scratch.is_some() {
+            return Ok(());
+        }
+        let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+        let lock = WestLock::capture(workspace, &manifest);
+        lock.save(&project.config_dir.join(WEST_LOCK))?;
+        output::info(&format!(
+            "Recorded {} project revisions in {}",
+            lock.projects.len(),
+            WEST_LOCK
+        ));
+        Ok(())
+    }
+
+    /// Move projects whose checkout differs from west.lock to the locked commit
+    fn apply_lock(&self, workspace: &Path, project: &Project) -> Result<()> {
+        let lock_path = project.config_dir.join(WEST_LOCK);
+        let lock = WestLock::load(&lock_path)?.with_context(|| {
+            format!(
+                "--locked needs {}, which doesn't exist yet. Run 'lfz update' to create it.",
+                paths::anonymize_path(&lock_path)
+            )
+        })?;
+
+        let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+        let drift = lock.drift(&WestLock::capture(workspace, &manifest));
+        if drift.is_empty() {
+            output::info(&format!("Workspace matches {}", WEST_LOCK));
+            return Ok(());
+        }
+
+        let names: Vec<&str> = drift.iter().map(|(name, _)| name.as_str()).collect();
+        if self.offline {
+            anyhow::bail!(
+                "Workspace doesn't match {} ({}), and --offline prevents fetching",
+                WEST_LOCK,
+                names.join(", ")
+            );
+        }
+
+        output::header(&format!("Checking out revisions from {}", WEST_LOCK));
+        let checkouts: Vec<Checkout> = drift
+            .iter()
+            .map(|(name, locked)| Checkout {
+                name: name.to_string(),
+                path: locked.path.clone(),
+                url: None,
+                revision: locked.revision.clone(),
+            })
+            .collect();
+        self.checkout(workspace, project, &checkouts)
+            .with_context(|| format!("Failed to apply {}", WEST_LOCK))?;
+
+        output::success(&format!(
+            "Checked out {} project(s) from {}",
+            drift.len(),
+            WEST_LOCK
+        ));
+        Ok(())
+    }
+
+    /// Check out the head of a ZMK pull request in the zmk project
+    pub fn checkout_pull_request(
+        &self,
+        workspace: &Path,
+        project: &Project,
+        number: u32,
+    ) -> Result<()> {
+        output::header(&format!("Checking out ZMK pull request #{}", number));
+        let path = zmk_path(project)?;
+        self.checkout(
+            workspace,
+            project,
+            &[Checkout {
+                name: "zmk".to_string(),
+                path: path.clone(),
+                url: Some(ZMK_REPO.to_string()),
+                revision: format!("pull/{}/head", number),
+            }],
+        )
+        .with_context(|| format!("Failed to check out ZMK pull request #{}", number))?;
+
+        // ZMK's west.yml pins Zephyr and the other modules, and the pull
+        // request may move them. West reads imports at `manifest-rev`, so
+        // point that at the pull request and update everything but zmk.
+        let runtime = Runtime::detect()?;
+        let status = self
+            .west_container(&runtime, workspace, project)?
+            .shell_command(format!(
+                "git -C /workspace/{} update-ref refs/heads/manifest-rev HEAD",
+                path
+            ))
+            .build()
+            .status()
+            .context("Failed to run container for checkout")?;
+        if !status.success() {
+            anyhow::bail!(
+                "Failed to point zmk's manifest-rev at pull request #{}",
+                number
+            );
+        }
+        let modules: Vec<String> = WestManifest::load(&project.config_dir.join("west.yml"))?
+            .resolved_projects(workspace)
+            .into_iter()
+            .map(|p| p.name)
+            .filter(|name| name != "zmk")
+            .collect();
+        if modules.is_empty() {
+            return Ok(());
+        }
+        self.update_workspace(workspace, project, &runtime, &modules)
+            .with_context(|| format!("Failed to update modules for ZMK pull request #{}", number))
+    }
+
+    /// Check out a commit of the zmk project
+    pub fn checkout_zmk(&self, workspace: &Path, project: &Project, revision: &str) -> Result<()> {
+        self.checkout(
+            workspace,
+            project,
+            &[Checkout {
+                name: "zmk".to_string(),
+                path: zmk_path(project)?,
+                url: None,
+                revision: revision.to_string(),
+            }],
+        )
+    }
+
+    /// ZMK commits after `good` up to and including `bad`, oldest first,
+    /// as (SHA, subject) pairs
+    ///
+    /// Fetches the full history of the zmk project, which is otherwise a
+    /// shallow clone.
+    pub fn zmk_commits(
+        &self,
+        workspace: &Path,
+        project: &Project,
+        good: &str,
+        bad: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let script = format!(
+            r#"set -e
+cd /workspace/{path}
+url="$(west list -f '{{url}}' zmk)"
+if [ -f .git/shallow ]; then unshallow=--unshallow; fi
+echo "Fetching ZMK history..." >&2
+git fetch -q $unshallow "$url" '+refs/heads/*:refs/remotes/upstream/*' '+refs/tags/*:refs/tags/*' >&2
+if ! git merge-base --is-ancestor '{good}' '{bad}'; then
+  echo "ERROR: {good} is not an ancestor of {bad}" >&2
+  exit 1
+fi
+git log --reverse --ancestry-path --format='%H %s' '{good}..{bad}'
+"#,
+            path = zmk_path(project)?,
+            good = good,
+            bad = bad,
+        );
+
+        let output = self
+            .west_container(&Runtime::detect()?, workspace, project)?
+            .shell_command(script)
+            .build()
+            .stderr(Stdio::inherit())
+            .output()
+            .context("Failed to run container for ZMK history")?;
+        if !output.status.success() {
+            anyhow::bail!("Failed to list ZMK commits between {} and {}", good, bad);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (sha, subject) = line.split_once(' ').unwrap_or((line, ""));
+                (!sha.is_empty()).then(|| (sha.to_string(), subject.to_string()))
+            })
+            .collect())
+    }
+
+    /// Move projects to other revisions, fetching them if needed
+    fn checkout(&self, workspace: &Path, project: &Project, checkouts: &[Checkout]) -> Result<()> {
+        let mut script = String::from("set -e\n");
+        for checkout in checkouts {
+            let dir = format!("/workspace/{}", checkout.path);
+            let url = match &checkout.url {
+                Some(url) => url.clone(),
+                None => format!("$(west list -f '{{url}}' {})", checkout.name),
+            };
+            script.push_str(&format!(
+                "echo \"=== {name} @ {rev}\"\n\
+                 [ -d {dir}/.git ] || {{ echo \"ERROR: {name} is not in the workspace\"; exit 1; }}\n\
+                 if git -C {dir} cat-file -e '{rev}^{{commit}}' 2>/dev/null; then\n\
+                 \x20 git -C {dir} checkout -q --detach '{rev}'\n\
+                 else\n\
+                 \x20 git -C {dir} fetch -q --depth 1 \"{url}\" '{rev}'\n\
+                 \x20 git -C {dir} checkout -q --detach FETCH_HEAD\n\
+                 fi\n",
+                name = checkout.name,
+                rev = checkout.revision,
+                dir = dir,
+                url = url,
+            ));
+        }
+
+        let status = self
+            .west_container(&Runtime::detect()?, workspace, project)?
+            .shell_command(script)
+            .build()
+            .status()
+            .context("Failed to run container for checkout")?;
+        if !status.success() {
+            let names: Vec<&str> = checkouts.iter().map(|c| c.name.as_str()).collect();
+            anyhow::bail!("Failed to check out {}", names.join(", "));
+        }
+        Ok(())
+    }
+
     /// Get the ccache directory path
     #[allow(dead_code)]
     pub fn ccache_dir(&self) -> &PathBuf {
@@ -276,24 +868,29 @@ echo "Workspace initialized successfully"
         Ok(())
     }
 
-    /// Run west update in an existing workspace
+    /// Run west update in an existing workspace, for only some projects if
+    /// `projects` isn't empty
     fn update_workspace(
         &self,
-        workspace: &PathBuf,
+        workspace: &Path,
         project: &Project,
         runtime: &Runtime,
+        projects: &[String],
     ) -> Result<()> {
         runtime.ensure_image(DEFAULT_IMAGE)?;
 
         // Run west update to sync modules with west.yml changes
         // Use shallow clones to save disk space and download time
         // Retry up to 3 times since network failures are common
-        let update_script = r#"
+        let update_script = format!(
+            r#"
 set -e
+set -- $LFZ_PROJECTS
+{west_jobs}
 echo "Updating west modules..."
 max_retries=3
 retry_count=0
-until west update --narrow --fetch-opt=--depth=1; do
+until west update --narrow --fetch-opt=--depth=1 --fetch-opt=--progress $jobs "$@"; do
     retry_count=$((retry_count + 1))
     if [ $retry_count -ge $max_retries ]; then
         echo "ERROR: west update failed after $max_retries attempts"
@@ -304,73 +901,36 @@ until west update --narrow --fetch-opt=--depth=1; do
 done
 
 echo "Workspace updated successfully"
-"#;
+"#,
+            west_jobs = WEST_JOBS_SCRIPT
+        );
 
-        let mut cmd = ContainerCommand::new(*runtime, DEFAULT_IMAGE)
-            .mount(workspace, "/workspace", false)
-            .mount(&project.config_dir, "/workspace/config", true)
-            .mount(&self.ccache_dir, "/root/.ccache", false)
-            .workdir("/workspace")
+        let cmd = self
+            .west_container(runtime, workspace, project)?
+            .env("LFZ_PROJECTS", projects.join(" "))
             .shell_command(update_script)
             .build();
 
-        output::command("west update --narrow --depth=1");
-        output::info("Syncing workspace with west.yml changes...");
-
-        // Stream output so user can see progress
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let mut child = cmd
-            .spawn()
-            .context("Failed to run container for workspace update")?;
-
-        let stdout = child.stdout.take().expect("Failed to capture stdout");
-        let stderr = child.stderr.take().expect("Failed to capture stderr");
-
-        // Stream stdout in a separate thread
-        let stdout_handle = std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            let mut last_lines: Vec<String> = Vec::new();
-            for line in reader.lines().map_while(Result::ok) {
-                // Show progress lines
-                if line.contains("Cloning")
-                    || line.contains("Fetching")
-                    || line.contains("Updating")
-                    || line.contains("=== ")
-                    || line.contains("updated")
-                    || line.contains("ERROR")
-                    || line.contains("error:")
-                {
-                    println!("  {}", line);
-                }
-                last_lines.push(line);
-                if last_lines.len() > 30 {
-                    last_lines.remove(0);
-                }
-            }
-            last_lines
-        });
-
-        // Capture stderr
-        let stderr_handle = std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            let mut error_output = String::new();
-            for line in reader.lines().map_while(Result::ok) {
-                if line.contains("error:") || line.contains("ERROR") || line.contains("fatal:") {
-                    eprintln!("  {}", line);
-                }
-                error_output.push_str(&line);
-                error_output.push('\n');
-            }
-            error_output
-        });
+        if projects.is_empty() {
+            output::command("west update --narrow --depth=1");
+            output::info("Syncing workspace with west.yml changes...");
+        } else {
+            output::command(&format!(
+                "west update --narrow --depth=1 {}",
+                projects.join(" ")
+            ));
+        }
 
-        let status = child
-            .wait()
-            .context("Failed to wait for workspace update")?;
-        let last_lines = stdout_handle.join().unwrap_or_default();
-        let stderr_output = stderr_handle.join().unwrap_or_default();
+        // The projects west goes through, as far as the workspace tells
+        let total = if projects.is_empty() {
+            WestManifest::load(&project.config_dir.join("west.yml"))
+                .map(|manifest| manifest.resolved_projects(workspace).len())
+                .ok()
+        } else {
+            Some(projects.len())
+        };
+        let (status, last_lines, stderr_output) =
+            run_west(cmd, total).context("Failed to run container for workspace update")?;
 
         if !status.success() {
             if !last_lines.is_empty() {
@@ -384,16 +944,119 @@ echo "Workspace updated successfully"
             }
 
             output::error("Workspace update failed");
-            output::info("Tip: Try running 'lfz update' to force a full workspace refresh.");
+            match credentials::auth_failure_hint(&stderr_output) {
+                Some(hint) => output::info(&format!("Tip: {}", hint)),
+                None => {
+                    output::info("Tip: Try running 'lfz update' to force a full workspace refresh.")
+                }
+            }
             anyhow::bail!("Workspace update failed");
         }
 
         output::success("Workspace updated successfully");
+        self.record_lock(workspace, project)?;
 
         Ok(())
     }
 }
 
+/// The workspace's projects with these names, all of them if `names` is
+/// empty
+fn find_projects(
+    workspace: &Path,
+    project: &Project,
+    names: &[String],
+) -> Result<Vec<WestProject>> {
+    let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+    let projects = manifest.resolved_projects(workspace);
+    if names.is_empty() {
+        return Ok(projects);
+    }
+    names
+        .iter()
+        .map(|name| match projects.iter().find(|p| &p.name == name) {
+            Some(found) => Ok(found.clone()),
+            None => {
+                let known: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+                anyhow::bail!(
+                    "Project '{}' is not in the workspace (projects: {})",
+                    name,
+                    known.join(", ")
+                );
+            }
+        })
+        .collect()
+}
+
+/// Run a west init/update command, showing which project west is at and
+/// how far git got with it. Returns the exit status, the last lines of
+/// output and the error output (without git's progress).
+fn run_west(mut cmd: Command, total: Option<usize>) -> Result<(ExitStatus, Vec<String>, String)> {
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    let mut child = cmd.spawn()?;
+    let stdout = child.stdout.take().expect("Failed to capture stdout");
+    let stderr = child.stderr.take().expect("Failed to capture stderr");
+    let progress = Arc::new(WorkspaceProgress::new(total));
+
+    let stdout_progress = Arc::clone(&progress);
+    let stdout_handle = std::thread::spawn(move || {
+        let mut last_lines: Vec<String> = Vec::new();
+        progress::for_each_line(stdout, |line| {
+            match progress::parse_line(line) {
+                Some(WestEvent::Project(name)) => stdout_progress.project(&name),
+                Some(WestEvent::Fetch { .. }) => {}
+                None if line.starts_with("=== ")
+                    || line.contains("ERROR")
+                    || line.contains("error:") =>
+                {
+                    stdout_progress.println(&format!("  {}", line));
+                }
+                None => {}
+            }
+            // Keep last lines for error context
+            last_lines.push(line.to_string());
+            if last_lines.len() > 30 {
+                last_lines.remove(0);
+            }
+        });
+        last_lines
+    });
+
+    let stderr_progress = Arc::clone(&progress);
+    let stderr_handle = std::thread::spawn(move || {
+        let mut error_output = String::new();
+        progress::for_each_line(stderr, |line| {
+            if let Some(WestEvent::Fetch { phase, percent }) = progress::parse_line(line) {
+                stderr_progress.fetch(&phase, percent);
+                return;
+            }
+            // Only print actual errors
+            if line.contains("error:") || line.contains("ERROR") || line.contains("fatal:") {
+                stderr_progress.eprintln(&format!("  {}", line));
+            }
+            error_output.push_str(line);
+            error_output.push('\n');
+        });
+        error_output
+    });
+
+    let status = child.wait()?;
+    let last_lines = stdout_handle.join().unwrap_or_default();
+    let stderr_output = stderr_handle.join().unwrap_or_default();
+    progress.finish();
+    Ok((status, last_lines, stderr_output))
+}
+
+/// Workspace path of the zmk project
+fn zmk_path(project: &Project) -> Result<String> {
+    let manifest = WestManifest::load(&project.config_dir.join("west.yml"))?;
+    match manifest.projects.iter().find(|p| p.name == "zmk") {
+        Some(zmk) => Ok(zmk.path().to_string()),
+        None => anyhow::bail!("west.yml has no project named 'zmk'"),
+    }
+}
+
 /// Calculate SHA256 hash of a file's contents
 fn hash_file_contents(path: &Path) -> Result<String> {
     let contents =
@@ -0,0 +1,184 @@
+//! What a workspace belongs to.
+//!
+//! Workspace directories are named after a hash of the config repository
+//! and branch; `.lfz_workspace.json` in each records them in readable form,
+//! along with the config directory and when the workspace was created.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::lfz_toml::{LfzConfig, WorkspaceKey};
+use crate::config::project::Project;
+use crate::config::west_yml;
+
+/// File in a workspace describing it
+const METADATA_FILE: &str = ".lfz_workspace.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceMetadata {
+    /// Remote URL of the config repository (or its path, without a remote)
+    pub repo: String,
+    /// Branch, or commit when the head was detached
+    pub branch: String,
+    /// Config directory last built with the workspace
+    pub config_dir: PathBuf,
+    /// Suffix of a scratch workspace (e.g. `zmk-pr-1234`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch: Option<String>,
+    /// When the workspace was created (seconds since the epoch)
+    pub created: u64,
+    /// Digest of the build image the workspace was last built with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// What the workspace is keyed by (workspaces.key when it was last
+    /// used); unrecorded before keys could be chosen, when it was the branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<WorkspaceKey>,
+}
+
+impl WorkspaceMetadata {
+    /// The metadata of a workspace, if recorded
+    pub fn load(workspace: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(workspace.join(METADATA_FILE)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Record what a workspace belongs to, keeping when it was created
+    pub fn record(workspace: &Path, project: &Project, scratch: Option<&str>) -> Result<()> {
+        let (repo, branch) = west_yml::get_git_info(&project.config_dir)?;
+        let existing = Self::load(workspace);
+        let created = existing.as_ref().map(|m| m.created).unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+        let metadata = Self {
+            repo,
+            branch,
+            config_dir: project.config_dir.clone(),
+            scratch: scratch.map(String::from),
+            created,
+            image: existing.as_ref().and_then(|m| m.image.clone()),
+            key: Some(LfzConfig::load(&project.root)?.workspaces.key),
+        };
+        if existing.as_ref() == Some(&metadata) {
+            return Ok(());
+        }
+
+        metadata.write(workspace)
+    }
+
+    /// Record the digest of the image a build used, so that `lfz image
+    /// prune` keeps it
+    pub fn record_image(workspace: &Path, image: &str) -> Result<()> {
+        let Some(mut metadata) = Self::load(workspace) else {
+            return Ok(());
+        };
+        if metadata.image.as_deref() == Some(image) {
+            return Ok(());
+        }
+        metadata.image = Some(image.to_string());
+        metadata.write(workspace)
+    }
+
+    fn write(&self, workspace: &Path) -> Result<()> {
+        let path = workspace.join(METADATA_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// `repo:branch`, as builds show the project
+    pub fn name(&self) -> String {
+        format!(
+            "{}:{}",
+            west_yml::extract_repo_name(&self.repo),
+            self.branch
+        )
+    }
+
+    /// Whether a query names this workspace's project: its repository (name
+    /// or URL), branch, or `repo:branch`
+    pub fn matches(&self, query: &str) -> bool {
+        let repo_name = west_yml::extract_repo_name(&self.repo);
+        query == self.repo || query == repo_name || query == self.branch || query == self.name()
+    }
+
+    pub fn key(&self) -> WorkspaceKey {
+        self.key.unwrap_or_default()
+    }
+
+    /// When the workspace was created
+    pub fn created(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_record_and_load() {
+        let project_dir = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let config_dir = project_dir.path().join("config");
+        fs::create_dir(&config_dir).unwrap();
+        fs::write(config_dir.join("west.yml"), "manifest:\n  projects: []").unwrap();
+        fs::write(project_dir.path().join("build.yaml"), "include: []").unwrap();
+        let project = Project::detect_from(project_dir.path()).unwrap();
+
+        assert_eq!(WorkspaceMetadata::load(workspace.path()), None);
+        WorkspaceMetadata::record(workspace.path(), &project, Some("zmk-pr-1")).unwrap();
+        let metadata = WorkspaceMetadata::load(workspace.path()).unwrap();
+        assert_eq!(metadata.config_dir, project.config_dir);
+        assert_eq!(metadata.scratch.as_deref(), Some("zmk-pr-1"));
+        assert_eq!(metadata.key(), WorkspaceKey::Branch);
+        // Not a git repository
+        assert_eq!(metadata.branch, "default");
+        assert_eq!(metadata.name(), "config:default");
+
+        // Recording again keeps the creation time
+        let mut older = metadata.clone();
+        older.created = 1000;
+        fs::write(
+            workspace.path().join(METADATA_FILE),
+            serde_json::to_string(&older).unwrap(),
+        )
+        .unwrap();
+        WorkspaceMetadata::record(workspace.path(), &project, None).unwrap();
+        let metadata = WorkspaceMetadata::load(workspace.path()).unwrap();
+        assert_eq!(metadata.created, 1000);
+        assert_eq!(metadata.scratch, None);
+
+        // The image a build used survives recording the project again
+        WorkspaceMetadata::record_image(workspace.path(), "sha256:abc").unwrap();
+        WorkspaceMetadata::record(workspace.path(), &project, None).unwrap();
+        let metadata = WorkspaceMetadata::load(workspace.path()).unwrap();
+        assert_eq!(metadata.image.as_deref(), Some("sha256:abc"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let metadata = WorkspaceMetadata {
+            repo: "git@github.com:me/corne-config.git".to_string(),
+            branch: "main".to_string(),
+            config_dir: PathBuf::from("/home/me/corne-config/config"),
+            scratch: None,
+            created: 0,
+            image: None,
+            key: None,
+        };
+
+        assert!(metadata.matches("corne-config:main"));
+        assert!(metadata.matches("corne-config"));
+        assert!(metadata.matches("main"));
+        assert!(metadata.matches("git@github.com:me/corne-config.git"));
+        assert!(!metadata.matches("corne-config:dev"));
+        assert!(!metadata.matches("corne"));
+    }
+}
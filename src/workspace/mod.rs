@@ -1,5 +1,19 @@
+pub mod archive;
+mod credentials;
+mod discovery;
+pub mod eviction;
+pub mod guard;
 mod hash_tracker;
+mod health;
+mod lock;
 mod manager;
+mod metadata;
+mod progress;
 
-pub use hash_tracker::{is_incremental_safe, BuildHashes};
+pub use discovery::{board_roots, discover_boards, discover_shields};
+pub(crate) use hash_tracker::{collect_files, hash_file};
+pub use hash_tracker::{is_incremental_safe, rebuild_only_changes, BuildHashes};
+pub use health::MissingModules;
+pub use lock::WestLock;
 pub use manager::WorkspaceManager;
+pub use metadata::WorkspaceMetadata;
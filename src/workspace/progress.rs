@@ -0,0 +1,117 @@
+//! Progress of `west update`, read from its output.
+//!
+//! west announces each project with a banner on stdout
+//! (`=== updating zmk (zmk):`), and git reports how far each fetch got on
+//! stderr (`Receiving objects:  45% (123/456)`), redrawing the line with
+//! carriage returns.
+
+use std::io::{BufRead, BufReader, Read};
+
+/// Something west or git reported
+#[derive(Debug, PartialEq, Eq)]
+pub enum WestEvent {
+    /// west started on a project
+    Project(String),
+    /// A phase of git's fetch of the current project got this far
+    Fetch { phase: String, percent: u8 },
+}
+
+/// The event a line of output reports, if any
+pub fn parse_line(line: &str) -> Option<WestEvent> {
+    if let Some(rest) = line.strip_prefix("=== updating ") {
+        // `name (path):`
+        let name = rest.split(" (").next()?.trim_end_matches(':');
+        return Some(WestEvent::Project(name.to_string()));
+    }
+
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(':')?;
+    let percent = rest.trim_start().split_once('%')?.0.parse().ok()?;
+    Some(WestEvent::Fetch {
+        phase: phase.trim().to_string(),
+        percent,
+    })
+}
+
+/// Call `f` with every line of a stream, splitting on carriage returns as
+/// well as newlines
+pub fn for_each_line(reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        let (consumed, done) = match reader.fill_buf() {
+            Ok([]) | Err(_) => break,
+            Ok(buf) => match buf.iter().position(|&b| b == b'\n' || b == b'\r') {
+                Some(end) => {
+                    line.extend_from_slice(&buf[..end]);
+                    (end + 1, true)
+                }
+                None => {
+                    line.extend_from_slice(buf);
+                    (buf.len(), false)
+                }
+            },
+        };
+        reader.consume(consumed);
+        if done {
+            if !line.is_empty() {
+                f(&String::from_utf8_lossy(&line));
+            }
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        f(&String::from_utf8_lossy(&line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("=== updating zephyr (zephyr):"),
+            Some(WestEvent::Project("zephyr".to_string()))
+        );
+        assert_eq!(
+            parse_line("=== updating hal_nordic (modules/hal/nordic):"),
+            Some(WestEvent::Project("hal_nordic".to_string()))
+        );
+        assert_eq!(
+            parse_line("Receiving objects:  45% (123/456), 1.20 MiB | 3.00 MiB/s"),
+            Some(WestEvent::Fetch {
+                phase: "Receiving objects".to_string(),
+                percent: 45
+            })
+        );
+        assert_eq!(
+            parse_line("remote: Compressing objects: 100% (80/80), done."),
+            Some(WestEvent::Fetch {
+                phase: "Compressing objects".to_string(),
+                percent: 100
+            })
+        );
+        assert_eq!(parse_line("=== Initializing west workspace..."), None);
+        assert_eq!(parse_line("fatal: unable to access 'https://x/'"), None);
+        assert_eq!(parse_line("HEAD is now at 2f0a3b1"), None);
+    }
+
+    #[test]
+    fn test_for_each_line() {
+        let output =
+            "Receiving objects:  10%\rReceiving objects:  90%\r\nHEAD is now at 2f0a3b1\nlast";
+        let mut lines = Vec::new();
+        for_each_line(output.as_bytes(), |line| lines.push(line.to_string()));
+        assert_eq!(
+            lines,
+            [
+                "Receiving objects:  10%",
+                "Receiving objects:  90%",
+                "HEAD is now at 2f0a3b1",
+                "last"
+            ]
+        );
+    }
+}